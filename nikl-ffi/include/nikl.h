@@ -0,0 +1,49 @@
+/*
+ * C API for embedding the NIKL interpreter, implemented by the nikl-ffi crate.
+ *
+ * Values cross the boundary as JSON text. Strings returned by the library are
+ * owned by the caller and released with nikl_string_free, strings passed in are
+ * only borrowed for the duration of the call.
+ */
+
+#ifndef NIKL_H
+#define NIKL_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NIKL_OK 0
+#define NIKL_ERROR 1
+
+typedef struct NiklInterpreter NiklInterpreter;
+typedef struct NiklResult NiklResult;
+
+/* Called with the script's arguments as a JSON array, reports through nikl_result_set/nikl_result_error */
+typedef void (*NiklCallback)(void *user_data, const char *args_json, NiklResult *result);
+
+/* base_path: directory file imports resolve from, NULL for the current directory. Returns NULL on failure. */
+NiklInterpreter *nikl_interpreter_new(const char *base_path);
+void nikl_interpreter_free(NiklInterpreter *interp);
+
+/* Runs a script, stores its result as JSON in *result_json when result_json isn't NULL */
+int nikl_run(NiklInterpreter *interp, const char *source, char **result_json);
+
+int nikl_set_global(NiklInterpreter *interp, const char *name, const char *json);
+/* NULL when undefined or not convertible to JSON */
+char *nikl_get_global(NiklInterpreter *interp, const char *name);
+
+/* callback and user_data must stay valid, and usable from any thread, while the interpreter lives */
+int nikl_register_callback(NiklInterpreter *interp, const char *name, NiklCallback callback, void *user_data);
+void nikl_result_set(NiklResult *result, const char *json);
+void nikl_result_error(NiklResult *result, const char *message);
+
+/* Message of the last failed call, owned by the interpreter, NULL if none */
+const char *nikl_last_error(const NiklInterpreter *interp);
+void nikl_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NIKL_H */
@@ -0,0 +1,270 @@
+//! C-compatible API for embedding NIKL from C, C++, Go and anything else with a C FFI.
+//!
+//! Values cross the boundary as JSON text, see `include/nikl.h` for the C declarations.
+//! Strings returned by the library are owned by the caller and released with `nikl_string_free`,
+//! strings passed in are only borrowed for the duration of the call.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::path::PathBuf;
+use std::ptr;
+
+use nikl::modules::convert::{from_json, to_json};
+use nikl::{Interpreter, Value};
+
+
+/// Status codes returned by the fallible functions
+pub const NIKL_OK: c_int = 0;
+pub const NIKL_ERROR: c_int = 1;
+
+
+/// An interpreter plus the message of its last failed call
+pub struct NiklInterpreter {
+    interp: Interpreter,
+    last_error: Option<CString>,
+}
+
+impl NiklInterpreter {
+    fn fail(&mut self, message: String) -> c_int {
+        self.last_error = Some(to_c_string(message));
+        NIKL_ERROR
+    }
+}
+
+
+/// Where a callback stores its result, set with `nikl_result_set` or `nikl_result_error`
+pub struct NiklResult {
+    value: Result<Value, String>,
+}
+
+/// A C function callable from scripts, it receives the arguments as a JSON array
+pub type NiklCallback = unsafe extern "C" fn(user_data: *mut c_void, args_json: *const c_char, result: *mut NiklResult);
+
+/// The callback and its user data, the host promises both can be used from any thread
+struct Callback {
+    func: NiklCallback,
+    user_data: *mut c_void,
+}
+
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+impl Callback {
+    fn call(&self, args: Vec<Value>) -> Result<Value, String> {
+        let args_json = to_json(&Value::Array(args))?.to_string();
+        let args_json = to_c_string(args_json);
+        let mut result = NiklResult { value: Ok(Value::Null) };
+        unsafe { (self.func)(self.user_data, args_json.as_ptr(), &mut result) };
+        result.value
+    }
+}
+
+
+/// Interior NUL bytes can't be represented in a C string, they are dropped
+fn to_c_string(text: String) -> CString {
+    CString::new(text).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|b| *b != 0);
+        CString::new(bytes).expect("NUL bytes were removed")
+    })
+}
+
+unsafe fn borrow_str<'a>(text: *const c_char, what: &str) -> Result<&'a str, String> {
+    if text.is_null() {
+        return Err(format!("{} is NULL", what));
+    }
+    unsafe { CStr::from_ptr(text) }
+        .to_str()
+        .map_err(|_| format!("{} is not valid UTF-8", what))
+}
+
+fn parse_json(text: &str) -> Result<Value, String> {
+    serde_json::from_str(text).map(from_json).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+fn value_to_json(value: &Value) -> Result<CString, String> {
+    to_json(value).map(|json| to_c_string(json.to_string()))
+}
+
+fn parse_program(source: &str) -> Result<Vec<nikl::Stmt>, String> {
+    let tokens = nikl::lexer::Lexer::new(source).tokenize().map_err(|e| e.to_string())?;
+    nikl::parser::Parser::new(tokens).parse()
+}
+
+
+/// Creates an interpreter whose file imports resolve relative to `base_path`,
+/// or the current directory when `base_path` is NULL. Returns NULL on failure.
+///
+/// # Safety
+/// `base_path` must be NULL or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_interpreter_new(base_path: *const c_char) -> *mut NiklInterpreter {
+    let base_path = if base_path.is_null() {
+        match std::env::current_dir() {
+            Ok(dir) => dir,
+            Err(_) => return ptr::null_mut(),
+        }
+    } else {
+        match unsafe { borrow_str(base_path, "base_path") } {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => return ptr::null_mut(),
+        }
+    };
+    let interp = Interpreter::new(base_path);
+    Box::into_raw(Box::new(NiklInterpreter { interp, last_error: None }))
+}
+
+/// Destroys an interpreter, NULL is ignored.
+///
+/// # Safety
+/// `interp` must be NULL or come from `nikl_interpreter_new` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_interpreter_free(interp: *mut NiklInterpreter) {
+    if !interp.is_null() {
+        drop(unsafe { Box::from_raw(interp) });
+    }
+}
+
+/// Runs a script. On success the script's result (see `eval_script`) is stored as JSON
+/// in `*result_json` when `result_json` isn't NULL, results that aren't data are stored as `null`.
+///
+/// # Safety
+/// `interp` must be a live interpreter, `source` a valid NUL-terminated string
+/// and `result_json` NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_run(interp: *mut NiklInterpreter, source: *const c_char, result_json: *mut *mut c_char) -> c_int {
+    let Some(interp) = (unsafe { interp.as_mut() }) else {
+        return NIKL_ERROR;
+    };
+    let outcome = unsafe { borrow_str(source, "source") }
+        .and_then(parse_program)
+        .and_then(|stmts| interp.interp.run_program(&stmts));
+    match outcome {
+        Ok(value) => {
+            if !result_json.is_null() {
+                let json = value_to_json(&value).unwrap_or_else(|_| to_c_string("null".to_string()));
+                unsafe { *result_json = json.into_raw() };
+            }
+            NIKL_OK
+        }
+        Err(e) => interp.fail(e),
+    }
+}
+
+/// Defines or replaces a global variable from JSON text.
+///
+/// # Safety
+/// `interp` must be a live interpreter, `name` and `json` valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_set_global(interp: *mut NiklInterpreter, name: *const c_char, json: *const c_char) -> c_int {
+    let Some(interp) = (unsafe { interp.as_mut() }) else {
+        return NIKL_ERROR;
+    };
+    let parsed = unsafe { borrow_str(name, "name") }
+        .and_then(|name| Ok((name, parse_json(unsafe { borrow_str(json, "json") }?)?)));
+    match parsed {
+        Ok((name, value)) => {
+            interp.interp.set_global(name, value);
+            NIKL_OK
+        }
+        Err(e) => interp.fail(e),
+    }
+}
+
+/// Returns a global variable as JSON, or NULL when it's undefined or can't be converted,
+/// in which case `nikl_last_error` says why. Free the result with `nikl_string_free`.
+///
+/// # Safety
+/// `interp` must be a live interpreter and `name` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_get_global(interp: *mut NiklInterpreter, name: *const c_char) -> *mut c_char {
+    let Some(interp) = (unsafe { interp.as_mut() }) else {
+        return ptr::null_mut();
+    };
+    let json = unsafe { borrow_str(name, "name") }.and_then(|name| {
+        let value = interp.interp.get_global(name).ok_or_else(|| format!("Undefined variable '{}'", name))?;
+        value_to_json(&value)
+    });
+    match json {
+        Ok(json) => json.into_raw(),
+        Err(e) => {
+            interp.fail(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Defines a global function `name` that calls `callback` with `user_data` and the
+/// arguments as a JSON array. The callback reports its result through `nikl_result_set`
+/// or `nikl_result_error`, a callback that sets neither returns None to the script.
+///
+/// # Safety
+/// `interp` must be a live interpreter and `name` a valid NUL-terminated string.
+/// `callback` and `user_data` must stay valid, and usable from any thread, for as long as
+/// the interpreter can call them.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_register_callback(
+    interp: *mut NiklInterpreter,
+    name: *const c_char,
+    callback: NiklCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    let Some(interp) = (unsafe { interp.as_mut() }) else {
+        return NIKL_ERROR;
+    };
+    let name = match unsafe { borrow_str(name, "name") } {
+        Ok(name) => name.to_string(),
+        Err(e) => return interp.fail(e),
+    };
+    let callback = Callback { func: callback, user_data };
+    match interp.interp.register_fn(&name, move |_, args| callback.call(args)) {
+        Ok(()) => NIKL_OK,
+        Err(e) => interp.fail(e),
+    }
+}
+
+/// Sets a callback's return value from JSON text. Invalid JSON turns into an error.
+///
+/// # Safety
+/// `result` must be the pointer passed to the running callback and `json` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_result_set(result: *mut NiklResult, json: *const c_char) {
+    if let Some(result) = unsafe { result.as_mut() } {
+        result.value = unsafe { borrow_str(json, "json") }.and_then(parse_json);
+    }
+}
+
+/// Makes a callback fail with `message`, the script sees it as a runtime error.
+///
+/// # Safety
+/// `result` must be the pointer passed to the running callback and `message` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_result_error(result: *mut NiklResult, message: *const c_char) {
+    if let Some(result) = unsafe { result.as_mut() } {
+        let message = unsafe { borrow_str(message, "message") }.map(str::to_string).unwrap_or_else(|e| e);
+        result.value = Err(message);
+    }
+}
+
+/// The message of the last call that failed on `interp`, or NULL. The string belongs to
+/// the interpreter and stays valid until its next failing call or until it's freed.
+///
+/// # Safety
+/// `interp` must be a live interpreter.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_last_error(interp: *const NiklInterpreter) -> *const c_char {
+    match unsafe { interp.as_ref() }.and_then(|interp| interp.last_error.as_ref()) {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Frees a string returned by the library, NULL is ignored.
+///
+/// # Safety
+/// `text` must be NULL or a string returned by `nikl_run` or `nikl_get_global`, freed only once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nikl_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(unsafe { CString::from_raw(text) });
+    }
+}
@@ -0,0 +1,66 @@
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+
+use nikl_ffi::*;
+
+
+fn c(text: &str) -> CString {
+    CString::new(text).unwrap()
+}
+
+unsafe fn take(text: *mut c_char) -> String {
+    let owned = unsafe { CStr::from_ptr(text) }.to_str().unwrap().to_string();
+    unsafe { nikl_string_free(text) };
+    owned
+}
+
+#[test]
+fn test_run_and_globals() {
+    unsafe {
+        let interp = nikl_interpreter_new(ptr::null());
+        assert!(!interp.is_null());
+
+        assert_eq!(nikl_set_global(interp, c("config").as_ptr(), c(r#"{"factor": 3, "tags": ["a"]}"#).as_ptr()), NIKL_OK);
+        let mut result = ptr::null_mut();
+        assert_eq!(nikl_run(interp, c("let out = config.factor * 14\n[out, config.tags]").as_ptr(), &mut result), NIKL_OK);
+        assert_eq!(take(result), r#"[42,["a"]]"#);
+        assert_eq!(take(nikl_get_global(interp, c("out").as_ptr())), "42");
+
+        assert!(nikl_get_global(interp, c("missing").as_ptr()).is_null());
+        assert_eq!(CStr::from_ptr(nikl_last_error(interp)).to_str().unwrap(), "Undefined variable 'missing'");
+        assert_eq!(nikl_run(interp, c("1 / 0").as_ptr(), ptr::null_mut()), NIKL_ERROR);
+        assert_eq!(CStr::from_ptr(nikl_last_error(interp)).to_str().unwrap(), "Division by zero");
+        assert_eq!(nikl_set_global(interp, c("x").as_ptr(), c("{oops").as_ptr()), NIKL_ERROR);
+
+        nikl_interpreter_free(interp);
+    }
+}
+
+unsafe extern "C" fn add_offset(user_data: *mut c_void, args_json: *const c_char, result: *mut NiklResult) {
+    let offset = unsafe { *(user_data as *const i64) };
+    let args: Vec<i64> = match serde_json::from_str(unsafe { CStr::from_ptr(args_json) }.to_str().unwrap()) {
+        Ok(args) => args,
+        Err(_) => return unsafe { nikl_result_error(result, c("add_offset expects integers").as_ptr()) },
+    };
+    let total = args.iter().sum::<i64>() + offset;
+    unsafe { nikl_result_set(result, c(&total.to_string()).as_ptr()) };
+}
+
+#[test]
+fn test_callbacks() {
+    unsafe {
+        let interp = nikl_interpreter_new(ptr::null());
+        let mut offset: i64 = 100;
+        let user_data = &mut offset as *mut i64 as *mut c_void;
+        assert_eq!(nikl_register_callback(interp, c("add_offset").as_ptr(), add_offset, user_data), NIKL_OK);
+
+        let mut result = ptr::null_mut();
+        assert_eq!(nikl_run(interp, c("add_offset(1, 2)").as_ptr(), &mut result), NIKL_OK);
+        assert_eq!(take(result), "103");
+
+        assert_eq!(nikl_run(interp, c("add_offset(\"x\")").as_ptr(), ptr::null_mut()), NIKL_ERROR);
+        assert_eq!(CStr::from_ptr(nikl_last_error(interp)).to_str().unwrap(), "add_offset expects integers");
+
+        nikl_interpreter_free(interp);
+    }
+}
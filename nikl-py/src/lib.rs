@@ -0,0 +1,218 @@
+//! Python bindings, build with `maturin develop` from this directory then `import nikl`
+//!
+//! Values are converted both ways: None, bool, int, float, str, list, tuple and dict map to
+//! the matching NIKL values, NIKL functions come back as opaque `Function` objects that can be
+//! passed back to `Interpreter.call` or called directly.
+
+use std::path::PathBuf;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+use nikl::{NiklError, Value};
+
+
+create_exception!(nikl, Error, PyException, "Raised when a NIKL script fails to lex, parse or run");
+
+
+fn nikl_err(message: impl Into<String>) -> PyErr {
+    Error::new_err(message.into())
+}
+
+fn to_value(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if obj.is_none() {
+        return Ok(Value::Null);
+    }
+    // bool is a subclass of int in Python, so it has to be checked first
+    if let Ok(b) = obj.cast::<PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if obj.is_instance_of::<PyInt>() {
+        return Ok(Value::Integer(obj.extract()?));
+    }
+    if let Ok(f) = obj.cast::<PyFloat>() {
+        return Ok(Value::Float(f.value()));
+    }
+    if let Ok(s) = obj.cast::<PyString>() {
+        return Ok(Value::String(s.to_str()?.to_string()));
+    }
+    if let Ok(list) = obj.cast::<PyList>() {
+        return list.iter().map(|item| to_value(&item)).collect::<PyResult<_>>().map(Value::Array);
+    }
+    if let Ok(tuple) = obj.cast::<PyTuple>() {
+        return tuple.iter().map(|item| to_value(&item)).collect::<PyResult<_>>().map(Value::Tuple);
+    }
+    if let Ok(dict) = obj.cast::<PyDict>() {
+        return dict
+            .iter()
+            .map(|(key, value)| Ok((to_value(&key)?, to_value(&value)?)))
+            .collect::<PyResult<_>>()
+            .map(Value::HashMap);
+    }
+    if let Ok(func) = obj.cast::<Function>() {
+        return Ok(func.get().value.clone());
+    }
+    Err(PyTypeError::new_err(format!("Cannot convert {} to a NIKL value", obj.get_type().name()?)))
+}
+
+fn to_py(py: Python<'_>, value: &Value) -> PyResult<Py<PyAny>> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any().unbind(),
+        Value::Integer(i) => i.into_pyobject(py)?.into_any().unbind(),
+        Value::Float(f) => f.into_pyobject(py)?.into_any().unbind(),
+        Value::String(s) => s.into_pyobject(py)?.into_any().unbind(),
+        Value::Array(items) => {
+            let items = items.iter().map(|item| to_py(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any().unbind()
+        }
+        Value::Tuple(items) => {
+            let items = items.iter().map(|item| to_py(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyTuple::new(py, items)?.into_any().unbind()
+        }
+        Value::HashMap(pairs) => {
+            let dict = PyDict::new(py);
+            for (key, value) in pairs {
+                dict.set_item(to_py(py, key)?, to_py(py, value)?)?;
+            }
+            dict.into_any().unbind()
+        }
+        Value::Function { .. } | Value::BuiltinFunction(_) | Value::HostFunction(_) => {
+            Py::new(py, Function { value: value.clone() })?.into_any()
+        }
+        other => return Err(nikl_err(format!("Cannot convert {} to a Python value", other.type_name()))),
+    })
+}
+
+fn to_values(args: &Bound<'_, PyTuple>) -> PyResult<Vec<Value>> {
+    args.iter().map(|arg| to_value(&arg)).collect()
+}
+
+fn parse(source: &str) -> PyResult<Vec<nikl::Stmt>> {
+    let tokens = nikl::lexer::Lexer::new(source)
+        .tokenize()
+        .map_err(|e| nikl_err(NiklError::from(e).to_string()))?;
+    nikl::parser::Parser::new(tokens)
+        .parse()
+        .map_err(|e| nikl_err(NiklError::Parse(e).to_string()))
+}
+
+
+/// A NIKL function returned to Python, callable with Python arguments
+#[pyclass(frozen, module = "nikl")]
+struct Function {
+    value: Value,
+}
+
+#[pymethods]
+impl Function {
+    #[pyo3(signature = (*args))]
+    fn __call__(&self, py: Python<'_>, args: &Bound<'_, PyTuple>) -> PyResult<Py<PyAny>> {
+        let mut interp = nikl::Interpreter::new(PathBuf::from("."));
+        let result = interp.call_function(self.value.clone(), to_values(args)?).map_err(nikl_err)?;
+        to_py(py, &result)
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.value {
+            Value::Function { name, .. } => format!("<nikl function {}>", name),
+            _ => "<nikl builtin function>".to_string(),
+        }
+    }
+}
+
+
+/// A NIKL interpreter whose globals persist between `run` calls
+#[pyclass(module = "nikl")]
+struct Interpreter {
+    interp: nikl::Interpreter,
+}
+
+#[pymethods]
+impl Interpreter {
+    /// `base_path` is the directory file imports resolve from, the current directory by default
+    #[new]
+    #[pyo3(signature = (base_path = None))]
+    fn new(base_path: Option<PathBuf>) -> PyResult<Self> {
+        let base_path = match base_path {
+            Some(path) => path,
+            None => std::env::current_dir()?,
+        };
+        Ok(Self { interp: nikl::Interpreter::new(base_path) })
+    }
+
+    /// Runs a script and returns its result, see `nikl.eval_script`
+    fn run(&mut self, py: Python<'_>, source: &str) -> PyResult<Py<PyAny>> {
+        let stmts = parse(source)?;
+        let result = self.interp.run_program(&stmts).map_err(nikl_err)?;
+        to_py(py, &result)
+    }
+
+    /// Evaluates a single expression against the globals
+    fn eval(&mut self, py: Python<'_>, source: &str) -> PyResult<Py<PyAny>> {
+        let result = self.interp.eval(source).map_err(|e| nikl_err(e.to_string()))?;
+        to_py(py, &result)
+    }
+
+    /// Returns a global variable, or None when it isn't defined
+    fn get(&self, py: Python<'_>, name: &str) -> PyResult<Py<PyAny>> {
+        match self.interp.get_global(name) {
+            Some(value) => to_py(py, &value),
+            None => Ok(py.None()),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.interp.set_global(name, to_value(value)?);
+        Ok(())
+    }
+
+    /// Calls a global function by name, or a `Function` value, with Python arguments
+    #[pyo3(signature = (func, *args))]
+    fn call(&mut self, py: Python<'_>, func: &Bound<'_, PyAny>, args: &Bound<'_, PyTuple>) -> PyResult<Py<PyAny>> {
+        let func = match func.cast::<PyString>() {
+            Ok(name) => {
+                let name = name.to_str()?;
+                self.interp.get_global(name).ok_or_else(|| nikl_err(format!("Undefined variable '{}'", name)))?
+            }
+            Err(_) => to_value(func)?,
+        };
+        let result = self.interp.call_function(func, to_values(args)?).map_err(nikl_err)?;
+        to_py(py, &result)
+    }
+
+    /// Defines a global function backed by a Python callable, exceptions it raises become script errors
+    fn register(&mut self, name: &str, callable: Py<PyAny>) -> PyResult<()> {
+        self.interp
+            .register_fn(name, move |_, args| {
+                Python::attach(|py| {
+                    let args = args.iter().map(|arg| to_py(py, arg)).collect::<PyResult<Vec<_>>>()?;
+                    let result = callable.call1(py, PyTuple::new(py, args)?)?;
+                    to_value(result.bind(py))
+                })
+                .map_err(|e| e.to_string())
+            })
+            .map_err(nikl_err)
+    }
+}
+
+
+/// Runs a script in a fresh interpreter and returns its result
+#[pyfunction]
+fn run_script(py: Python<'_>, source: &str) -> PyResult<Py<PyAny>> {
+    let result = nikl::eval_script(source).map_err(nikl_err)?;
+    to_py(py, &result)
+}
+
+
+#[pymodule]
+#[pyo3(name = "nikl")]
+fn nikl_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Interpreter>()?;
+    m.add_class::<Function>()?;
+    m.add("Error", m.py().get_type::<Error>())?;
+    m.add_function(wrap_pyfunction!(run_script, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+import pytest
+
+import nikl
+
+
+def test_run_script():
+    assert nikl.run_script("let x = 20\nx * 2 + 2") == 42
+
+
+def test_globals_round_trip():
+    interp = nikl.Interpreter()
+    interp.set("config", {"factor": 3, "tags": ["a", "b"], "pair": (1, None), "on": True})
+    assert interp.run("let out = config.factor * 14\nout") == 42
+    assert interp.get("out") == 42
+    assert interp.get("config") == {"factor": 3, "tags": ["a", "b"], "pair": (1, None), "on": True}
+    assert interp.get("missing") is None
+    assert interp.eval("out + 1") == 43
+
+
+def test_call_functions():
+    interp = nikl.Interpreter()
+    interp.run("fn add(a, b) { return a + b }")
+    assert interp.call("add", 40, 2) == 42
+    add = interp.get("add")
+    assert add(1.5, 2) == 3.5
+    assert interp.call(add, "a", "b") == "ab"
+
+
+def test_register_python_callable():
+    interp = nikl.Interpreter()
+    seen = []
+    interp.register("record", lambda *args: seen.append(args) or len(seen))
+    assert interp.run('record(1, "two")\nrecord([3])') == 2
+    assert seen == [(1, "two"), ([3],)]
+
+    def fail():
+        raise ValueError("boom")
+
+    interp.register("fail", fail)
+    with pytest.raises(nikl.Error, match="boom"):
+        interp.run("fail()")
+
+
+def test_errors():
+    with pytest.raises(nikl.Error, match="Division by zero"):
+        nikl.run_script("1 / 0")
+    with pytest.raises(nikl.Error, match="Parse error"):
+        nikl.Interpreter().run("let = 1")
+    with pytest.raises(TypeError):
+        nikl.Interpreter().set("x", object())
@@ -0,0 +1,197 @@
+//! The `nikl` command line, parsed with clap
+//! Without a subcommand `nikl` runs a script (or starts the REPL), the flags before the script
+//! choose how it runs. Everything after the script is passed to it as `sys.argv`
+
+use clap::{ArgGroup, Parser, Subcommand};
+
+
+#[derive(Debug, Parser)]
+#[command(name = "nikl", version, about)]
+#[command(override_usage = "nikl [OPTIONS] [SCRIPT [ARGS]...]\n       nikl [OPTIONS] <COMMAND>")]
+#[command(group(ArgGroup::new("mode").args(["eval", "interactive", "dump_tokens", "dump_ast", "profile", "coverage"])))]
+pub struct Cli {
+    /// Print more about what commands do, like every file they look at
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only print errors and results
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Don't use colors, also set by the NO_COLOR environment variable
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Run a one-line program, e.g. nikl -e 'print(1 + 2)', the arguments after it are passed to it,
+    /// after a `--` when the first one is a command name
+    #[arg(short, long, value_name = "CODE", allow_hyphen_values = true)]
+    pub eval: Option<String>,
+
+    /// Run the script, then open the REPL with its variables and functions
+    #[arg(short, long, requires = "script")]
+    pub interactive: bool,
+
+    /// Print the tokens of the script instead of running it
+    #[arg(long, group = "dump", requires = "script")]
+    pub dump_tokens: bool,
+
+    /// Print the syntax tree of the script instead of running it
+    #[arg(long, group = "dump", requires = "script")]
+    pub dump_ast: bool,
+
+    /// Print the dump as JSON
+    #[arg(long, requires = "dump")]
+    pub json: bool,
+
+    /// Run the script and report the calls and time of every function
+    #[arg(long, requires = "script")]
+    pub profile: bool,
+
+    /// With --profile, also write the stacks in the folded format flamegraph tools read
+    #[arg(long, value_name = "OUT", requires = "profile")]
+    pub folded: Option<String>,
+
+    /// Run the script and report the lines it ran, in it and its imports
+    #[arg(long, requires = "script")]
+    pub coverage: bool,
+
+    /// With --coverage, also write the lines as an lcov tracefile
+    #[arg(long, value_name = "OUT", requires = "coverage")]
+    pub lcov: Option<String>,
+
+    /// The script to run, `-` reads it from standard input, then the arguments passed to it as
+    /// sys.argv, a leading `--` is dropped. Without a script the REPL starts
+    #[arg(id = "script", value_name = "SCRIPT", trailing_var_arg = true, allow_hyphen_values = true)]
+    pub script: Vec<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Lex and parse scripts without running them
+    Check {
+        /// Also check that imported modules exist, and check the `.nk` ones
+        #[arg(long)]
+        imports: bool,
+        /// Files or directories, the current directory by default
+        paths: Vec<String>,
+    },
+    /// Format scripts in place
+    Fmt {
+        /// Only list the files that aren't formatted, failing when there are any
+        #[arg(long)]
+        check: bool,
+        /// Files or directories, the current directory by default
+        paths: Vec<String>,
+    },
+    /// Report likely mistakes, rules are configured under "lint" in config.json
+    Lint {
+        /// Files or directories, the current directory by default
+        paths: Vec<String>,
+    },
+    /// Run the tests registered with testing.test or named test_*
+    Test {
+        /// Report the lines the tests ran, in them and the modules they import
+        #[arg(long)]
+        coverage: bool,
+        /// Also write the covered lines as an lcov tracefile, implies --coverage
+        #[arg(long, value_name = "OUT")]
+        lcov: Option<String>,
+        /// Files or directories, `tests` by default, of every member at a workspace's root
+        paths: Vec<String>,
+    },
+    /// Run a script under the debugger, type 'help' at its prompt
+    Debug {
+        script: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Run a script, or `test` and its paths, again whenever the files or their imports change
+    Watch {
+        /// A script or `test`
+        #[arg(value_name = "SCRIPT")]
+        target: String,
+        /// The script's arguments, or the test paths
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Pack a script and the modules it imports into one `.nk` file, or an executable
+    Bundle {
+        script: String,
+        /// Where to write it, `<script>.bundle.nk` or with --exe `<script>` by default
+        #[arg(short, long, value_name = "OUT")]
+        output: Option<String>,
+        /// Write an executable that runs the script with its arguments, no `nikl` needed
+        #[arg(long)]
+        exe: bool,
+    },
+    /// Start a language server for editors on standard input and output
+    Lsp,
+    /// Initialize a new package
+    Init {
+        dir: String,
+    },
+    /// Build the current package, or every member at a workspace's root
+    Build,
+    /// Save a registry token, used to publish and to install private packages
+    Login {
+        /// The token, asked for when not given
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Forget the saved registry token
+    Logout,
+    /// Build the current package and upload it to the registry
+    Publish,
+    /// Install a package and its dependencies into .nikl/packages, from the registry, a local archive or a git repository
+    Install {
+        /// `name`, `name@version`, a `name-version.tar.gz` file or `git+<url>#<ref>`, the
+        /// dependencies in config.json when left out
+        package: Option<String>,
+    },
+    /// Find packages in the registry by name, description or keywords
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Show a package's description, versions, authors and dependencies from the registry
+    Info {
+        /// `name`, or `name@version` for the dependencies of that version
+        package: String,
+    },
+    /// Remove an installed package from .nikl/packages
+    Uninstall {
+        /// `name` or `name@version`
+        package: String,
+        /// Remove it even when other installed packages depend on it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add a package to the dependencies in config.json and install it
+    Add {
+        /// `name`, or `name@version` instead of the latest version
+        package: String,
+    },
+    /// Take a package out of the dependencies in config.json and uninstall it
+    Remove {
+        package: String,
+        /// Uninstall it even when other installed packages depend on it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Manage the downloaded package archives shared by all projects
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Print where the archives are kept
+    Dir,
+    /// Delete every cached archive
+    Clean,
+}
@@ -0,0 +1,236 @@
+//! `nikl bundle`, packs a script and every `.nk` module it imports into a single file
+//! The bundle is itself a script: the modules follow the header, each after a `//@module`
+//! line naming its import path and its length in bytes, and the entry script comes last after
+//! `//@main`. Sections are read by length, so sources may hold lines that look like markers
+//!
+//! ```text
+//! //@bundle nikl
+//! //@module lib/util.nk 30
+//! fn double(n) { return n * 2 }
+//! //@main main.nk 52
+//! import "lib/util.nk" as util
+//! print(util.double(21))
+//! ```
+//!
+//! Modules are keyed by their path from the entry script's directory, which is how a
+//! `ModuleBundle` finds them again. Executables are a copy of `nikl` with the bundle appended
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::interpreter::resolver::{normalize, BUNDLE_ROOT};
+use crate::interpreter::{FileResolver, ModuleBundle, ModuleResolver};
+use crate::modules::make_internal_module;
+use super::check::{collect_imports, parse};
+use super::files::{canonical, display_path};
+use super::output;
+use super::run_file::{read_file, run_with};
+
+
+const HEADER: &str = "//@bundle nikl";
+const MODULE: &str = "//@module ";
+const MAIN: &str = "//@main ";
+/// Ends an executable, after the bundle and its length as 8 little-endian bytes
+const MAGIC: &[u8; 8] = b"NIKLBNDL";
+
+
+/// `nikl bundle <file.nk> [-o <out>] [--exe]`, writes the script and its imports as one `.nk`
+/// file, `<name>.bundle.nk` by default, or with `--exe` as an executable named after the script
+/// Returns the process exit code, 1 when an import can't be bundled
+pub fn bundle(entry: &str, out: Option<&str>, exe: bool) -> i32 {
+    let Some(source) = read_file(entry) else {
+        eprintln!("Failed to read or validate the file '{}'", entry);
+        return 2;
+    };
+    // A bundle is taken as it is, e.g. to turn it into an executable
+    let bundle = match Bundle::parse(&source).map(Ok).unwrap_or_else(|| Bundle::collect(Path::new(entry))) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    let stem = Path::new(entry).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let out = match out {
+        Some(out) => out.to_string(),
+        None if exe => format!("{}{}", stem, std::env::consts::EXE_SUFFIX),
+        None => format!("{}.bundle.nk", stem),
+    };
+    let written = if exe { write_executable(&bundle.to_source(), &out) } else { std::fs::write(&out, bundle.to_source()).map_err(|e| e.to_string()) };
+    if let Err(e) = written {
+        eprintln!("Failed to write '{}': {}", out, e);
+        return 2;
+    }
+    if !output::quiet() {
+        println!("Bundled {} module(s) into {}", bundle.modules.len(), out);
+    }
+    0
+}
+
+/// Runs the bundle appended to this executable by `nikl bundle --exe`, with all the command-line
+/// arguments passed to it. `None` for a plain `nikl`
+pub fn run_embedded() -> Option<i32> {
+    let exe = std::env::current_exe().ok()?;
+    let bundle = Bundle::parse(&read_embedded(&exe).ok()??)?;
+    let mut args = std::env::args();
+    let argv = args.next().into_iter().chain(args).collect();
+    Some(bundle.run(PathBuf::from("."), argv))
+}
+
+
+pub(super) struct Bundle {
+    /// Key of the entry script
+    main: String,
+    /// Key and source of every module, in the order they were found
+    modules: Vec<(String, String)>,
+    main_source: String,
+}
+
+impl Bundle {
+    /// Follows the imports of `entry`, and of the modules it imports
+    fn collect(entry: &Path) -> Result<Self, String> {
+        let main = entry.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let mut bundle = Self { main: main.clone(), modules: Vec::new(), main_source: String::new() };
+        // Files by key, so two paths can't end up as the same module
+        let mut files: HashMap<String, String> = HashMap::from([(main.clone(), canonical(&entry.to_string_lossy()))]);
+        let mut queue = VecDeque::from([(main, PathBuf::from(canonical(&entry.to_string_lossy())))]);
+
+        while let Some((key, file)) = queue.pop_front() {
+            if output::verbose() {
+                println!("Adding {}", display_path(&file.to_string_lossy()));
+            }
+            let source = std::fs::read_to_string(&file).map_err(|e| format!("Failed to read '{}': {}", file.display(), e))?;
+            let stmts = parse(&source).map_err(|e| format!("{}: {}", display_path(&file.to_string_lossy()), e))?;
+            let mut imports = Vec::new();
+            collect_imports(&stmts, &mut imports);
+
+            let dir = Path::new(&key).parent().map(Path::to_path_buf).unwrap_or_default();
+            let base_path = file.parent().map(Path::to_path_buf).unwrap_or_default();
+            for path in imports {
+                if make_internal_module(path).is_some() {
+                    continue;
+                }
+                if !path.ends_with(".nk") {
+                    return Err(format!("Unknown module '{}' imported by '{}'", path, display_path(&file.to_string_lossy())));
+                }
+                let module = FileResolver.resolve(&base_path, path)?;
+                let module_key = normalize(&dir.join(path));
+                match files.get(&module_key) {
+                    Some(id) if *id == module.id => {}
+                    Some(id) => return Err(format!("'{}' and '{}' would both be bundled as '{}'", display_path(id), display_path(&module.id), module_key)),
+                    None => {
+                        files.insert(module_key.clone(), module.id.clone());
+                        queue.push_back((module_key, PathBuf::from(module.id)));
+                    }
+                }
+            }
+
+            if key == bundle.main {
+                bundle.main_source = source;
+            } else {
+                bundle.modules.push((key, source));
+            }
+        }
+        Ok(bundle)
+    }
+
+    /// The bundle written out, see the module docs for the layout
+    pub fn to_source(&self) -> String {
+        let section = |marker: &str, key: &str, source: &str| {
+            let source = with_newline(source);
+            format!("{}{} {}\n{}", marker, key, source.len(), source)
+        };
+        let mut out = format!("{}\n", HEADER);
+        for (key, source) in &self.modules {
+            out += &section(MODULE, key, source);
+        }
+        out + &section(MAIN, &self.main, &self.main_source)
+    }
+
+    /// Reads a bundle back, `None` when `source` is a plain script. The header may follow a
+    /// shebang line added to run the bundle as a command
+    pub fn parse(source: &str) -> Option<Self> {
+        let mut rest = source;
+        if rest.starts_with("#!") {
+            rest = rest.split_once('\n')?.1;
+        }
+        let (header, mut rest) = rest.split_once('\n')?;
+        if header.trim_end() != HEADER {
+            return None;
+        }
+        let mut modules = Vec::new();
+        loop {
+            let (line, after) = rest.split_once('\n')?;
+            let (marker, section) = match (line.strip_prefix(MODULE), line.strip_prefix(MAIN)) {
+                (Some(section), _) => (MODULE, section),
+                (_, Some(section)) => (MAIN, section),
+                _ => return None,
+            };
+            let (key, length) = section.trim_end().rsplit_once(' ')?;
+            let length: usize = length.parse().ok()?;
+            let source = after.get(..length)?.to_string();
+            rest = &after[length..];
+            if marker == MAIN {
+                return Some(Self { main: key.to_string(), modules, main_source: source });
+            }
+            modules.push((key.to_string(), source));
+        }
+    }
+
+    /// Runs the entry script, its imports are only served from the bundle
+    pub fn run(&self, base_path: PathBuf, argv: Vec<String>) -> i32 {
+        let resolver = self.modules.iter().fold(ModuleBundle::new().exclusive(), |bundle, (key, source)| bundle.module(key, source.clone()));
+        let mut interpreter = output::interpreter(base_path).argv(argv).resolver(resolver).build();
+        // Errors point into the bundled sources rather than the bundle file
+        let name = |key: &str| format!("{}/{}", BUNDLE_ROOT, key);
+        let mut sources: HashMap<String, String> = self.modules.iter().map(|(key, source)| (name(key), source.clone())).collect();
+        sources.insert(name(&self.main), self.main_source.clone());
+        run_with(&mut interpreter, &self.main_source, &name(&self.main), &sources)
+    }
+}
+
+fn with_newline(source: &str) -> String {
+    if source.ends_with('\n') { source.to_string() } else { format!("{}\n", source) }
+}
+
+/// A copy of the running `nikl` with the bundle appended, executable like it
+fn write_executable(bundle: &str, out: &str) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("can't find the nikl executable: {}", e))?;
+    let mut bytes = std::fs::read(&exe).map_err(|e| e.to_string())?;
+    // Bundling from a bundled executable replaces its bundle
+    if let Ok(Some(old)) = read_embedded(&exe) {
+        bytes.truncate(bytes.len() - old.len() - 16);
+    }
+    bytes.extend_from_slice(bundle.as_bytes());
+    bytes.extend_from_slice(&(bundle.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(MAGIC);
+    std::fs::write(out, bytes).map_err(|e| e.to_string())?;
+    let permissions = std::fs::metadata(&exe).map_err(|e| e.to_string())?.permissions();
+    std::fs::set_permissions(out, permissions).map_err(|e| e.to_string())
+}
+
+/// The bundle at the end of an executable, `None` when it has none
+fn read_embedded(exe: &Path) -> std::io::Result<Option<String>> {
+    let mut file = File::open(exe)?;
+    let size = file.metadata()?.len();
+    if size < 16 {
+        return Ok(None);
+    }
+    let mut trailer = [0; 16];
+    file.seek(SeekFrom::End(-16))?;
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let length = u64::from_le_bytes(trailer[..8].try_into().unwrap_or_default());
+    if length > size - 16 {
+        return Ok(None);
+    }
+    let mut bundle = vec![0; length as usize];
+    file.seek(SeekFrom::End(-16 - length as i64))?;
+    file.read_exact(&mut bundle)?;
+    Ok(String::from_utf8(bundle).ok())
+}
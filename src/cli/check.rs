@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::interpreter::{FileResolver, ModuleResolver, NiklError};
+use crate::lexer::Lexer;
+use crate::modules::make_internal_module;
+use crate::parser::{Parser, Stmt};
+use super::files::collect_scripts;
+use super::output;
+
+
+/// `nikl check [--imports] [paths...]`, lexes and parses scripts without running them
+/// With `--imports`, imported modules must exist and `.nk` imports are checked as well
+/// Returns the process exit code, 1 when any file has errors, 2 when the paths themselves are invalid
+pub fn check_files(paths: &[String], follow_imports: bool) -> i32 {
+    let scripts = match collect_scripts(paths) {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+
+    let mut checked = HashSet::new();
+    let mut errors = 0;
+    for path in &scripts {
+        if output::verbose() {
+            println!("Checking {}", path.display());
+        }
+        let diagnostics = match fs::read_to_string(path) {
+            Ok(source) => {
+                let base_path = path.parent().unwrap_or_else(|| Path::new("."));
+                check_source(&source, base_path, follow_imports, &mut checked)
+            }
+            Err(e) => vec![format!("Failed to read file: {}", e)],
+        };
+        for diagnostic in &diagnostics {
+            eprintln!("{}: {}", path.display(), diagnostic);
+        }
+        errors += diagnostics.len();
+    }
+
+    if !output::quiet() {
+        println!("Checked {} file(s), found {} error(s)", scripts.len(), errors);
+    }
+    i32::from(errors > 0)
+}
+
+/// Diagnostics for one script, `checked` holds the `.nk` modules already checked through imports
+fn check_source(source: &str, base_path: &Path, follow_imports: bool, checked: &mut HashSet<String>) -> Vec<String> {
+    let stmts = match parse(source) {
+        Ok(stmts) => stmts,
+        Err(e) => return vec![e.to_string()],
+    };
+    if !follow_imports {
+        return Vec::new();
+    }
+
+    let mut imports = Vec::new();
+    collect_imports(&stmts, &mut imports);
+
+    let mut diagnostics = Vec::new();
+    for path in imports {
+        if !path.ends_with(".nk") {
+            if make_internal_module(path).is_none() {
+                diagnostics.push(format!("Unknown module '{}'", path));
+            }
+            continue;
+        }
+        let module = match FileResolver.resolve(base_path, path) {
+            Ok(module) => module,
+            Err(e) => {
+                diagnostics.push(e);
+                continue;
+            }
+        };
+        if checked.insert(module.id.clone()) {
+            let nested = check_source(&module.source, &module.base_path, true, checked);
+            diagnostics.extend(nested.into_iter().map(|e| format!("in '{}': {}", module.id, e)));
+        }
+    }
+    diagnostics
+}
+
+pub(super) fn parse(source: &str) -> Result<Vec<Stmt>, NiklError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    Parser::new(tokens).parse().map_err(NiklError::Parse)
+}
+
+/// Import paths in `stmts`, including imports nested in functions and blocks
+pub(super) fn collect_imports<'a>(stmts: &'a [Stmt], imports: &mut Vec<&'a str>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Import { path, .. } => imports.push(path),
+            Stmt::If { body, else_if_branches, else_body, .. } => {
+                collect_imports(body, imports);
+                for (_, branch) in else_if_branches {
+                    collect_imports(branch, imports);
+                }
+                if let Some(else_body) = else_body {
+                    collect_imports(else_body, imports);
+                }
+            }
+            Stmt::Function { body, .. } | Stmt::Loop(body) | Stmt::While { body, .. } | Stmt::For { body, .. } => {
+                collect_imports(body, imports);
+            }
+            _ => {}
+        }
+    }
+}
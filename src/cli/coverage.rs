@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::interpreter::{ExecutionHook, Interpreter};
+use crate::lexer::Lexer;
+use crate::parser::{Parser, Stmt};
+use super::files::{canonical, display_path};
+use super::output;
+use super::run_file::{read_file, run_in, script_args};
+
+
+/// `nikl --coverage [--lcov <out>] <file.nk> [--] [args...]`, runs a script and prints to stderr
+/// how many lines of it and of its `.nk` imports ran, `--lcov` also writes an lcov tracefile
+/// Returns the process exit code like `run_file`
+pub fn coverage_file(filename: &str, args: &[String], lcov: Option<&str>) -> i32 {
+    let Some(source) = read_file(filename) else {
+        eprintln!("Failed to read or validate the file '{}'", filename);
+        return 2;
+    };
+
+    let coverage = Arc::new(Coverage::default());
+    let base_path = Path::new(filename).parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let mut interpreter = output::interpreter(base_path)
+        .argv(std::iter::once(filename.to_string()).chain(script_args(args).iter().cloned()).collect())
+        .hook(coverage.clone())
+        .build();
+    let code = run_in(&mut interpreter, &source, &canonical(filename));
+
+    eprint!("{}", coverage.summary());
+    match lcov.map(|out| coverage.write_lcov(out)) {
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            2
+        }
+        _ => code,
+    }
+}
+
+/// Counts how often every line runs, in the files parsed with `Parser::with_lines`
+#[derive(Default)]
+pub(super) struct Coverage {
+    /// Runs of every line with a statement, by file
+    lines: Mutex<BTreeMap<String, BTreeMap<usize, u64>>>,
+}
+
+impl Coverage {
+    fn lines(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, BTreeMap<usize, u64>>> {
+        self.lines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds the lines of a program that haven't run yet, so files report what they miss
+    fn add_program(&self, program: &[Stmt]) {
+        let mut markers = Vec::new();
+        collect_lines(program, &mut markers);
+        let mut lines = self.lines();
+        for (file, line) in markers {
+            lines.entry(file.to_string()).or_default().entry(line).or_insert(0);
+        }
+    }
+
+    /// Every file that ran, read again for the lines that didn't
+    fn files(&self) -> BTreeMap<String, BTreeMap<usize, u64>> {
+        let files: Vec<String> = self.lines().keys().cloned().collect();
+        for file in files {
+            let program = std::fs::read_to_string(&file)
+                .ok()
+                .and_then(|source| Lexer::new(&source).tokenize().ok())
+                .and_then(|tokens| Parser::with_lines(tokens, &file).parse().ok());
+            if let Some(program) = program {
+                self.add_program(&program);
+            }
+        }
+        self.lines().clone()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = String::from("\nCoverage:\n");
+        let (mut all_hit, mut all_total) = (0, 0);
+        for (file, lines) in self.files() {
+            let hit = lines.values().filter(|&&runs| runs > 0).count();
+            summary += &coverage_row(hit, lines.len(), &display_path(&file));
+            all_hit += hit;
+            all_total += lines.len();
+        }
+        summary + &coverage_row(all_hit, all_total, "total")
+    }
+
+    pub fn write_lcov(&self, out: &str) -> Result<(), String> {
+        let mut lcov = String::new();
+        for (file, lines) in self.files() {
+            lcov += &format!("TN:\nSF:{}\n", file);
+            for (line, runs) in &lines {
+                lcov += &format!("DA:{},{}\n", line, runs);
+            }
+            let hit = lines.values().filter(|&&runs| runs > 0).count();
+            lcov += &format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), hit);
+        }
+        std::fs::write(out, lcov).map_err(|e| format!("Failed to write '{}': {}", out, e))
+    }
+}
+
+impl ExecutionHook for Coverage {
+    fn on_line(&self, _interp: &mut Interpreter, file: &str, line: usize) -> Result<(), String> {
+        *self.lines().entry(file.to_string()).or_default().entry(line).or_insert(0) += 1;
+        Ok(())
+    }
+}
+
+fn coverage_row(hit: usize, total: usize, name: &str) -> String {
+    let percent = if total == 0 { 100.0 } else { hit as f64 * 100.0 / total as f64 };
+    format!("{:>7.1}%  {:>9}  {}\n", percent, format!("{}/{}", hit, total), name)
+}
+
+/// The `Stmt::Line` markers of a program, nested blocks included
+fn collect_lines<'a>(stmts: &'a [Stmt], lines: &mut Vec<(&'a str, usize)>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Line { file, line } => lines.push((file, *line)),
+            Stmt::If { body, else_if_branches, else_body, .. } => {
+                collect_lines(body, lines);
+                for (_, body) in else_if_branches {
+                    collect_lines(body, lines);
+                }
+                if let Some(body) = else_body {
+                    collect_lines(body, lines);
+                }
+            }
+            Stmt::Function { body, .. } | Stmt::Loop(body) | Stmt::While { body, .. } | Stmt::For { body, .. } => {
+                collect_lines(body, lines);
+            }
+            _ => {}
+        }
+    }
+}
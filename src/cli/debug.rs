@@ -0,0 +1,259 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::interpreter::{ExecutionHook, Interpreter, NiklError};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use super::files::{canonical, display_path};
+use super::output;
+use super::run_file::script_args;
+
+
+const HELP: &str = "\
+Commands:
+  s, step          Run to the next line, entering function calls
+  n, next          Run to the next line in this function or its caller
+  c, continue      Run to the next breakpoint
+  b, break <line>  Stop before running <line> of the script, or <file>:<line> of a module,
+                   without a line lists the breakpoints
+  d, delete <line> Remove the breakpoint on <line> or <file>:<line>
+  l, locals        Show the variables visible here
+  p, print <expr>  Evaluate an expression in the paused frame
+  w, where         Show the current file, line and call depth
+  q, quit          Stop the script
+  h, help          Show this help";
+
+
+/// `nikl debug <file.nk> [--] [args...]`, runs a script under a command-line debugger
+/// Stops before the first line, imported `.nk` modules can be stepped into and have breakpoints
+/// too. Returns the process exit code like `run_file`
+pub fn debug_file(filename: &str, args: &[String]) -> i32 {
+    let source = match std::fs::read_to_string(filename) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
+            return 2;
+        }
+    };
+    // Named like imported modules are so breakpoints can use either path
+    let file = canonical(filename);
+    let program = match Lexer::new(&source).tokenize() {
+        Ok(tokens) => Parser::with_lines(tokens, &file).parse().map_err(NiklError::Parse),
+        Err(e) => Err(NiklError::from(e)),
+    };
+    let program = match program {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let debugger = Arc::new(Debugger::new(file, &source));
+    let args = script_args(args);
+    let base_path = Path::new(filename).parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let mut interpreter = output::interpreter(base_path)
+        .argv(std::iter::once(filename.to_string()).chain(args.iter().cloned()).collect())
+        .hook(debugger.clone())
+        .build();
+
+    let result = interpreter.run(&program);
+    if let Some(code) = interpreter.exit_code() {
+        println!("Program exited with {}", code);
+        return code;
+    }
+    match result {
+        Ok(_) => {
+            println!("Program finished");
+            0
+        }
+        Err(_) if debugger.quit() => 1,
+        Err(e) => {
+            eprintln!("Error executing script: {}", e);
+            1
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Step,
+    /// Stop at the next line at most this many calls deep
+    Next(usize),
+    Continue,
+    Quit,
+}
+
+struct State {
+    mode: Mode,
+    /// File and line of every breakpoint
+    breakpoints: BTreeSet<(String, usize)>,
+    /// Waiting for commands, lines run meanwhile by `print` or other threads don't stop
+    paused: bool,
+}
+
+struct Debugger {
+    /// The script being debugged, breakpoints without a file are in it
+    main: String,
+    /// Lines of the script and of the modules shown so far, by file
+    sources: Mutex<HashMap<String, Vec<String>>>,
+    state: Mutex<State>,
+}
+
+impl Debugger {
+    fn new(main: String, source: &str) -> Self {
+        let sources = HashMap::from([(main.clone(), source.lines().map(str::to_string).collect())]);
+        Self {
+            main,
+            sources: Mutex::new(sources),
+            state: Mutex::new(State { mode: Mode::Step, breakpoints: BTreeSet::new(), paused: false }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn quit(&self) -> bool {
+        matches!(self.state().mode, Mode::Quit)
+    }
+
+    /// Sets how the script resumes
+    fn resume(&self, mode: Mode) {
+        let mut state = self.state();
+        state.mode = mode;
+        state.paused = false;
+    }
+
+    /// Lines of `file`, read on first use, empty when it isn't on disk
+    fn with_source<T>(&self, file: &str, f: impl FnOnce(&[String]) -> T) -> T {
+        let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+        let lines = sources.entry(file.to_string()).or_insert_with(|| {
+            std::fs::read_to_string(file).map(|source| source.lines().map(str::to_string).collect()).unwrap_or_default()
+        });
+        f(lines)
+    }
+
+    /// Just the line for the script, `<file>:<line>` for modules
+    fn location(&self, file: &str, line: usize) -> String {
+        if file == self.main {
+            return line.to_string();
+        }
+        format!("{}:{}", display_path(file), line)
+    }
+
+    fn show_line(&self, file: &str, line: usize) {
+        let text = self.with_source(file, |lines| lines.get(line - 1).map(|text| text.trim().to_string()).unwrap_or_default());
+        println!("{:>4} | {}", self.location(file, line), text);
+    }
+
+    /// Reads a breakpoint argument, `<line>` or `<file>:<line>`
+    fn breakpoint(&self, arg: &str) -> Result<(String, usize), String> {
+        let (file, line) = match arg.rsplit_once(':') {
+            Some((file, line)) => (canonical(file), line),
+            None => (self.main.clone(), arg),
+        };
+        let count = self.with_source(&file, |lines| lines.len());
+        match line.parse::<usize>() {
+            Ok(line) if line >= 1 && line <= count => Ok((file, line)),
+            _ if count == 0 => Err(format!("Can't read '{}'", file)),
+            _ => Err(format!("Expected a line between 1 and {}", count)),
+        }
+    }
+
+    /// Reads and runs commands until one resumes the script
+    fn pause(&self, interp: &mut Interpreter, file: &str, line: usize) -> Result<(), String> {
+        self.show_line(file, line);
+        let stdin = std::io::stdin();
+        loop {
+            print!("(debug) ");
+            let _ = std::io::stdout().flush();
+            let mut input = String::new();
+            if stdin.lock().read_line(&mut input).map_err(|e| e.to_string())? == 0 {
+                // End of input, nothing can resume the script later
+                self.resume(Mode::Quit);
+                return Err("Stopped by the debugger".to_string());
+            }
+
+            let input = input.trim();
+            let (command, arg) = input.split_once(' ').map_or((input, ""), |(command, arg)| (command, arg.trim()));
+            match command {
+                "" => {}
+                "s" | "step" => {
+                    self.resume(Mode::Step);
+                    return Ok(());
+                }
+                "n" | "next" => {
+                    self.resume(Mode::Next(interp.call_depth()));
+                    return Ok(());
+                }
+                "c" | "continue" => {
+                    self.resume(Mode::Continue);
+                    return Ok(());
+                }
+                "q" | "quit" => {
+                    self.resume(Mode::Quit);
+                    return Err("Stopped by the debugger".to_string());
+                }
+                "b" | "break" if arg.is_empty() => {
+                    let lines: Vec<String> =
+                        self.state().breakpoints.iter().map(|(file, line)| self.location(file, *line)).collect();
+                    println!("Breakpoints: {}", if lines.is_empty() { "none".to_string() } else { lines.join(", ") });
+                }
+                "b" | "break" => match self.breakpoint(arg) {
+                    Ok((file, line)) => {
+                        println!("Breakpoint set on line {}", self.location(&file, line));
+                        self.state().breakpoints.insert((file, line));
+                    }
+                    Err(e) => println!("{}", e),
+                },
+                "d" | "delete" => match self.breakpoint(arg) {
+                    Ok(breakpoint) if self.state().breakpoints.remove(&breakpoint) => println!("Breakpoint on line {} removed", arg),
+                    _ => println!("No breakpoint on line '{}'", arg),
+                },
+                "l" | "locals" => {
+                    for (name, value) in interp.variables() {
+                        println!("{} = {}", name, value.repr());
+                    }
+                }
+                "p" | "print" => match interp.eval(arg) {
+                    Ok(value) => println!("{}", value.repr()),
+                    Err(e) => println!("{}", e),
+                },
+                "w" | "where" => {
+                    println!("{}:{}, {} call(s) deep", display_path(file), line, interp.call_depth());
+                    self.show_line(file, line);
+                }
+                "h" | "help" => println!("{}", HELP),
+                other => println!("Unknown command '{}', type 'help' for the list", other),
+            }
+        }
+    }
+}
+
+impl ExecutionHook for Debugger {
+    fn on_line(&self, interp: &mut Interpreter, file: &str, line: usize) -> Result<(), String> {
+        let mut state = self.state();
+        if state.paused {
+            return Ok(());
+        }
+        let breakpoint = state.breakpoints.contains(&(file.to_string(), line));
+        let stop = match state.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => interp.call_depth() <= depth || breakpoint,
+            Mode::Continue => breakpoint,
+            // Threads still running after a quit stop too
+            Mode::Quit => return Err("Stopped by the debugger".to_string()),
+        };
+        if !stop {
+            return Ok(());
+        }
+        state.paused = true;
+        drop(state);
+        self.pause(interp, file, line)
+    }
+}
+
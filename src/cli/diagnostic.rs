@@ -0,0 +1,129 @@
+//! Errors printed with the source line they point at, for scripts run from the command line
+//! and the REPL
+//!
+//! ```text
+//! error[E0201]: Expected RightParen, found Eof
+//!  --> main.nk:1:10
+//!   |
+//! 1 | let x = (1
+//!   |          ^
+//! ```
+//!
+//! Codes: `E0101` unexpected character, `E0102` unterminated string, `E0103` invalid number,
+//! `E0201` syntax error, `E0301` runtime error
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use crate::lexer::LexError;
+use super::files::display_path;
+use super::output;
+
+
+const ERROR: &str = "\x1b[1;31m";
+const BOLD: &str = "\x1b[1m";
+const GUTTER: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Diagnostic {
+    code: &'static str,
+    message: String,
+    file: String,
+    /// Where the error is, runtime errors only know their line
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn lex(error: &LexError, file: &str) -> Self {
+        let (code, message, line, column) = match error {
+            LexError::UnexpectedChar(ch, line, column) => ("E0101", format!("Unexpected character '{}'", ch), line, column),
+            LexError::UnterminatedString(line, column) => ("E0102", "Unterminated string".to_string(), line, column),
+            LexError::InvalidNumber(number, line, column) => ("E0103", format!("Invalid number '{}'", number), line, column),
+        };
+        Self { code, message, file: file.to_string(), line: Some(*line), column: Some(*column) }
+    }
+
+    /// From a `Parser` error, which ends with the position it failed at
+    pub fn parse(error: &str, file: &str) -> Self {
+        let (message, line, column) = match split_position(error) {
+            (message, Some((line, column))) => (message, Some(line), Some(column)),
+            (message, None) => (message, None, None),
+        };
+        Self { code: "E0201", message: message.to_string(), file: file.to_string(), line, column }
+    }
+
+    /// `location` is the file and line from `Interpreter::error_line`
+    pub fn runtime(error: &str, location: Option<(String, usize)>) -> Self {
+        let (file, line) = match location {
+            Some((file, line)) => (file, Some(line)),
+            None => (String::new(), None),
+        };
+        Self { code: "E0301", message: error.to_string(), file, line, column: None }
+    }
+
+    /// The diagnostic with its source line, `sources` holds the programs that aren't files on
+    /// disk by name. Colored when colors are on and standard error is a terminal
+    pub fn render(&self, sources: &HashMap<String, String>) -> String {
+        let color = output::color() && std::io::stderr().is_terminal();
+        let paint = |style: &str, text: &str| if color { format!("{}{}{}", style, text, RESET) } else { text.to_string() };
+
+        let mut out = format!("{}{}\n", paint(ERROR, &format!("error[{}]", self.code)), paint(BOLD, &format!(": {}", self.message)));
+        let Some(line) = self.line else { return out };
+        let source = sources.get(&self.file).cloned().or_else(|| std::fs::read_to_string(&self.file).ok()).unwrap_or_default();
+        let lines: Vec<&str> = source.lines().collect();
+
+        // Errors at the end of the input point just past the last line
+        let (line, column, text) = match lines.get(line - 1) {
+            Some(text) => (line, self.column, *text),
+            None if !lines.is_empty() && self.column.is_some() => {
+                let text = lines[lines.len() - 1];
+                (lines.len(), Some(text.chars().count() + 1), text)
+            }
+            None => (line, None, ""),
+        };
+
+        let number = line.to_string();
+        let pad = " ".repeat(number.len());
+        let place = match column {
+            Some(column) => format!("{}:{}:{}", display_path(&self.file), line, column),
+            None => format!("{}:{}", display_path(&self.file), line),
+        };
+        out += &format!("{}{} {}\n", pad, paint(GUTTER, "-->"), place);
+        if text.is_empty() {
+            return out;
+        }
+        out += &format!("{} {}\n", pad, paint(GUTTER, "|"));
+        out += &format!("{} {}\n", paint(GUTTER, &format!("{} |", number)), text);
+        out += &format!("{} {} {}\n", pad, paint(GUTTER, "|"), paint(ERROR, &marker(text, column)));
+        out
+    }
+}
+
+/// Splits the ` at line <line>, column <column>` every `Parser` error ends with off the message
+pub(super) fn split_position(error: &str) -> (&str, Option<(usize, usize)>) {
+    let position = error.rsplit_once(" at line ").and_then(|(message, position)| {
+        let (line, column) = position.split_once(", column ")?;
+        Some((message, line.parse().ok()?, column.parse().ok()?))
+    });
+    match position {
+        Some((message, line, column)) => (message, Some((line, column))),
+        None => (error, None),
+    }
+}
+
+/// A caret under `column`, or the whole statement underlined when only the line is known
+fn marker(text: &str, column: Option<usize>) -> String {
+    // Tabs are kept so the marker lines up however wide they are shown
+    let indent = |count: usize| -> String { text.chars().take(count).map(|c| if c == '\t' { '\t' } else { ' ' }).collect() };
+    match column {
+        Some(column) => indent(column.saturating_sub(1)) + "^",
+        None => {
+            let start = text.chars().take_while(|c| c.is_whitespace()).count();
+            let width = text.trim().chars().count();
+            indent(start) + &"^".repeat(width.max(1))
+        }
+    }
+}
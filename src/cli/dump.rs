@@ -0,0 +1,79 @@
+use std::io::Read;
+
+use crate::interpreter::NiklError;
+use crate::lexer::{Lexer, Token};
+use crate::parser::{Parser, Stmt};
+
+
+/// `nikl --dump-tokens [--json] <file.nk | ->`, prints the lexer output, one token per line
+/// Returns the process exit code, 1 when the source doesn't lex, 2 when it can't be read
+pub fn dump_tokens(file: &str, json: bool) -> i32 {
+    let tokens = match read_input(file).and_then(|source| lex(&source)) {
+        Ok(tokens) => tokens,
+        Err(code) => return code,
+    };
+    if json {
+        return print_json(&tokens);
+    }
+    for token in &tokens {
+        println!("{}:{}\t{:?}", token.line, token.column, token.kind);
+    }
+    0
+}
+
+/// `nikl --dump-ast [--json] <file.nk | ->`, prints the statements the parser produces
+/// Returns the process exit code, 1 when the source doesn't parse, 2 when it can't be read
+pub fn dump_ast(file: &str, json: bool) -> i32 {
+    let tokens = match read_input(file).and_then(|source| lex(&source)) {
+        Ok(tokens) => tokens,
+        Err(code) => return code,
+    };
+    let stmts: Vec<Stmt> = match Parser::new(tokens).parse() {
+        Ok(stmts) => stmts,
+        Err(e) => {
+            eprintln!("{}", NiklError::Parse(e));
+            return 1;
+        }
+    };
+    if json {
+        return print_json(&stmts);
+    }
+    for stmt in &stmts {
+        println!("{:#?}", stmt);
+    }
+    0
+}
+
+/// The source of the file argument, `-` reads standard input
+fn read_input(file: &str) -> Result<String, i32> {
+    let source = if file == "-" {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        std::fs::read_to_string(file)
+    };
+    source.map_err(|e| {
+        eprintln!("Error reading '{}': {}", file, e);
+        2
+    })
+}
+
+fn lex(source: &str) -> Result<Vec<Token>, i32> {
+    Lexer::new(source).tokenize().map_err(|e| {
+        eprintln!("{}", NiklError::from(e));
+        1
+    })
+}
+
+fn print_json(value: &impl serde::Serialize) -> i32 {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            println!("{}", json);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error serializing the dump: {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+
+
+/// Expands command-line paths into `.nk` files, directories are searched recursively
+/// skipping hidden entries, no paths means the current directory
+pub(crate) fn collect_scripts(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let roots: Vec<&str> = if paths.is_empty() { vec!["."] } else { paths.iter().map(String::as_str).collect() };
+
+    let mut scripts = Vec::new();
+    for root in roots {
+        let root = Path::new(root);
+        if root.is_file() {
+            scripts.push(root.to_path_buf());
+            continue;
+        }
+        if !root.is_dir() {
+            return Err(format!("'{}' does not exist", root.display()));
+        }
+        let walker = walkdir::WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'));
+        for entry in walker {
+            let entry = entry.map_err(|e| e.to_string())?;
+            if entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "nk") {
+                scripts.push(entry.into_path());
+            }
+        }
+    }
+    Ok(scripts)
+}
+
+
+/// `path` the way the resolver names imported modules, so paths match however they are written
+pub(crate) fn canonical(path: &str) -> String {
+    std::fs::canonicalize(path).map(|path| path.to_string_lossy().to_string()).unwrap_or_else(|_| path.to_string())
+}
+
+/// `path` relative to the current directory when it is inside it
+pub(crate) fn display_path(path: &str) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    Path::new(path).strip_prefix(&cwd).map(|path| path.display().to_string()).unwrap_or_else(|_| path.to_string())
+}
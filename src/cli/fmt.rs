@@ -0,0 +1,56 @@
+use std::fs;
+
+use crate::formatter::format_source;
+use super::files::collect_scripts;
+use super::output;
+
+
+/// `nikl fmt [--check] [paths...]`, rewrites files in place or with `--check` only lists
+/// the ones that aren't formatted. Returns the process exit code, 1 when a file wasn't
+/// formatted or failed so CI can fail on them, 2 when the paths themselves are invalid
+pub fn format_files(paths: &[String], check: bool) -> i32 {
+    let scripts = match collect_scripts(paths) {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+
+    let mut failed = false;
+    for path in scripts {
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Error reading '{}': {}", path.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == source {
+            if output::verbose() {
+                println!("Unchanged {}", path.display());
+            }
+            continue;
+        }
+        if check {
+            println!("Would reformat {}", path.display());
+            failed = true;
+        } else if let Err(e) = fs::write(&path, formatted) {
+            eprintln!("Error writing '{}': {}", path.display(), e);
+            failed = true;
+        } else if !output::quiet() {
+            println!("Formatted {}", path.display());
+        }
+    }
+
+    i32::from(failed)
+}
@@ -0,0 +1,154 @@
+//! Syntax highlighting for the REPL prompt
+//! Lines are scanned leniently since they are usually incomplete while being typed,
+//! words are classified with the lexer's own keyword table
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::Helper;
+
+use crate::lexer::{keyword, TokenKind};
+
+
+const KEYWORD: &str = "\x1b[35m";
+const TYPE_NAME: &str = "\x1b[36m";
+const LITERAL: &str = "\x1b[33m";
+const STRING: &str = "\x1b[32m";
+const COMMENT: &str = "\x1b[90m";
+const MATCHING_BRACKET: &str = "\x1b[1;4m";
+const RESET: &str = "\x1b[0m";
+
+
+/// The rustyline helper behind the REPL, it only adds highlighting
+pub struct ReplHelper;
+
+impl Helper for ReplHelper {}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Validator for ReplHelper {}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight_line(line, Some(pos)))
+    }
+
+    /// Every edit or cursor move can change the colors or the bracket pair
+    fn highlight_char(&self, _line: &str, _pos: usize, _forced: bool) -> bool {
+        true
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Span {
+    Keyword,
+    TypeName,
+    Literal,
+    String,
+    Comment,
+    Bracket,
+}
+
+/// Byte ranges of the highlighted parts of `line`, a string missing its closing quote runs to the end
+fn scan(line: &str) -> Vec<(Range<usize>, Span)> {
+    let mut spans = Vec::new();
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        let span = match ch {
+            '"' => {
+                let end = chars.find(|&(_, c)| c == '"').map_or(line.len(), |(i, _)| i + 1);
+                spans.push((start..end, Span::String));
+                continue;
+            }
+            '/' if line[start + 1..].starts_with('/') => {
+                spans.push((start..line.len(), Span::Comment));
+                break;
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' => Span::Bracket,
+            '0'..='9' => {
+                while chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.').is_some() {}
+                Span::Literal
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_').is_some() {}
+                let end = chars.peek().map_or(line.len(), |&(i, _)| i);
+                match keyword(&line[start..end]) {
+                    Some(TokenKind::BooleanLiteral(_)) => spans.push((start..end, Span::Literal)),
+                    Some(kind) if kind.is_type_name() => spans.push((start..end, Span::TypeName)),
+                    Some(_) => spans.push((start..end, Span::Keyword)),
+                    None => {}
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let end = chars.peek().map_or(line.len(), |&(i, _)| i);
+        spans.push((start..end, span));
+    }
+    spans
+}
+
+/// Index into `spans` of the bracket paired with the one under or just before the cursor
+fn matching_brackets(line: &str, spans: &[(Range<usize>, Span)], pos: usize) -> Option<(usize, usize)> {
+    let brackets: Vec<usize> = (0..spans.len()).filter(|&i| spans[i].1 == Span::Bracket).collect();
+    let at_cursor = brackets
+        .iter()
+        .copied()
+        .find(|&i| spans[i].0.start == pos)
+        .or_else(|| brackets.iter().copied().find(|&i| spans[i].0.end == pos))?;
+
+    let mut stack = Vec::new();
+    for &i in &brackets {
+        match &line[spans[i].0.clone()] {
+            "(" | "[" | "{" => stack.push(i),
+            close => {
+                let open = stack.pop()?;
+                let pair = matches!((&line[spans[open].0.clone()], close), ("(", ")") | ("[", "]") | ("{", "}"));
+                if pair && (open == at_cursor || i == at_cursor) {
+                    return Some((open, i));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// `line` with ANSI colors, the bracket pair around `cursor` is underlined
+pub fn highlight_line(line: &str, cursor: Option<usize>) -> String {
+    let spans = scan(line);
+    let pair = cursor.and_then(|pos| matching_brackets(line, &spans, pos));
+
+    let mut out = String::with_capacity(line.len() * 2);
+    let mut last = 0;
+    for (index, (range, span)) in spans.iter().enumerate() {
+        let color = match span {
+            Span::Keyword => KEYWORD,
+            Span::TypeName => TYPE_NAME,
+            Span::Literal => LITERAL,
+            Span::String => STRING,
+            Span::Comment => COMMENT,
+            Span::Bracket if pair.is_some_and(|(open, close)| index == open || index == close) => MATCHING_BRACKET,
+            Span::Bracket => continue,
+        };
+        out.push_str(&line[last..range.start]);
+        out.push_str(color);
+        out.push_str(&line[range.clone()]);
+        out.push_str(RESET);
+        last = range.end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
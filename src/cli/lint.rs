@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::Path;
+
+use crate::linter::{lint_source, LintConfig};
+use super::files::collect_scripts;
+use super::output;
+
+
+/// `nikl lint [paths...]`, reports likely mistakes using the rules enabled in `./config.json`
+/// Returns the process exit code, 1 when anything is reported, 2 when the paths or the config are invalid
+pub fn lint_files(paths: &[String]) -> i32 {
+    let config = match fs::read_to_string(Path::new("config.json")) {
+        Ok(text) => LintConfig::from_package_config(&text),
+        Err(_) => Ok(LintConfig::default()),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+
+    let scripts = match collect_scripts(paths) {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+
+    let mut problems = 0;
+    for path in &scripts {
+        if output::verbose() {
+            println!("Linting {}", path.display());
+        }
+        // Findings read `path:line: [rule] message`, like compiler diagnostics
+        let reports = match fs::read_to_string(path) {
+            Ok(source) => match lint_source(&source, &config) {
+                Ok(lints) => lints.iter().map(|lint| format!("{}:{}", path.display(), lint)).collect(),
+                Err(e) => vec![format!("{}: {}", path.display(), e)],
+            },
+            Err(e) => vec![format!("{}: Failed to read file: {}", path.display(), e)],
+        };
+        for report in &reports {
+            eprintln!("{}", report);
+        }
+        problems += reports.len();
+    }
+
+    if !output::quiet() {
+        println!("Linted {} file(s), found {} problem(s)", scripts.len(), problems);
+    }
+    i32::from(problems > 0)
+}
@@ -0,0 +1,439 @@
+//! What the language server knows about one document
+//! The syntax tree has no positions, so definitions are found in the token stream, which also
+//! keeps working while the document doesn't parse. Positions are the lexer's 1-based lines and
+//! character columns, converted to the protocol's 0-based lines and UTF-16 offsets here
+
+use std::path::{Path, PathBuf};
+
+use crate::interpreter::environment::Environment;
+use crate::interpreter::{FileResolver, ModuleResolver, ResolvedModule};
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::modules::make_internal_module;
+use crate::parser::Parser;
+use crate::Value;
+use super::super::diagnostic::split_position;
+
+
+const KEYWORDS: &[&str] = &[
+    "let", "const", "fn", "return", "import", "as", "if", "elif", "else", "for", "in", "while", "loop",
+    "break", "continue", "spawn", "wait", "del", "pub", "and", "or", "not", "True", "False",
+];
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Kind {
+    Function,
+    Variable,
+    Constant,
+    Module,
+    Keyword,
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    name: String,
+    kind: Kind,
+    /// Index of the name token
+    token: usize,
+    /// The declaration as written, type annotations included, e.g. `fn add(a: Int) -> Int`
+    detail: String,
+}
+
+#[derive(Debug, Clone)]
+struct Import {
+    path: String,
+    alias: String,
+    /// Index of the path token
+    token: usize,
+}
+
+/// A module an import alias stands for
+enum Module {
+    Builtin(String, Value),
+    Script(ResolvedModule),
+}
+
+/// A range in protocol coordinates, `(line, character)` pairs
+pub(super) type Range = ((usize, usize), (usize, usize));
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Problem {
+    pub range: Range,
+    pub message: String,
+}
+
+/// Where a definition is, `file` is `None` in the analyzed document
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Target {
+    pub file: Option<String>,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Completion {
+    pub label: String,
+    pub kind: Kind,
+    pub detail: String,
+}
+
+
+pub(super) struct Analysis {
+    lines: Vec<Vec<char>>,
+    tokens: Vec<Token>,
+    /// Brace depth of every token, 0 at the top level
+    depths: Vec<usize>,
+    definitions: Vec<Definition>,
+    imports: Vec<Import>,
+    base_path: PathBuf,
+    pub problems: Vec<Problem>,
+}
+
+impl Analysis {
+    /// Lexes and parses `source`, imports resolve relative to `base_path`
+    pub fn new(source: &str, base_path: &Path) -> Self {
+        let mut analysis = Self {
+            lines: source.lines().map(|line| line.chars().collect()).collect(),
+            tokens: Vec::new(),
+            depths: Vec::new(),
+            definitions: Vec::new(),
+            imports: Vec::new(),
+            base_path: base_path.to_path_buf(),
+            problems: Vec::new(),
+        };
+        let error = match Lexer::new(source).tokenize() {
+            Ok(tokens) => {
+                analysis.tokens = tokens;
+                Parser::new(analysis.tokens.clone()).parse().err()
+            }
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(error) = error {
+            let (message, position) = split_position(&error);
+            let (mut line, mut column) = position.unwrap_or((1, 1));
+            // Errors at the end of the input point just past the last line
+            if line > analysis.lines.len() && !analysis.lines.is_empty() {
+                line = analysis.lines.len();
+                column = analysis.lines[line - 1].len() + 1;
+            }
+            let range = analysis.range(line, column, 1);
+            analysis.problems.push(Problem { range, message: message.to_string() });
+        }
+
+        analysis.collect_definitions();
+        analysis.check_imports();
+        analysis
+    }
+
+    fn collect_definitions(&mut self) {
+        let mut depth: usize = 0;
+        for token in &self.tokens {
+            if token.kind == TokenKind::RightBrace {
+                depth = depth.saturating_sub(1);
+            }
+            self.depths.push(depth);
+            if token.kind == TokenKind::LeftBrace {
+                depth += 1;
+            }
+        }
+
+        for i in 0..self.tokens.len() {
+            let next = |offset: usize| self.tokens.get(i + offset).map(|token| &token.kind);
+            match (&self.tokens[i].kind, next(1)) {
+                (TokenKind::Function, Some(TokenKind::Identifier(name))) => {
+                    let body = self.find(i, |kind| *kind == TokenKind::LeftBrace);
+                    let definition = Definition { name: name.clone(), kind: Kind::Function, token: i + 1, detail: self.text(i, body) };
+                    self.definitions.push(definition);
+                    let params_end = self.find(i, |kind| *kind == TokenKind::RightParen);
+                    self.collect_parameters(i + 2, params_end);
+                }
+                (TokenKind::Let | TokenKind::Const, Some(TokenKind::Identifier(name))) => {
+                    let kind = if self.tokens[i].kind == TokenKind::Let { Kind::Variable } else { Kind::Constant };
+                    let value = self.find(i, |kind| *kind == TokenKind::Assign);
+                    self.definitions.push(Definition { name: name.clone(), kind, token: i + 1, detail: self.text(i, value) });
+                }
+                (TokenKind::For, _) => {
+                    let body = self.find(i, |kind| *kind == TokenKind::LeftBrace);
+                    let detail = self.text(i, body);
+                    let names = (i + 1..body).take_while(|&j| matches!(self.tokens[j].kind, TokenKind::Identifier(_) | TokenKind::Comma));
+                    for j in names {
+                        if let TokenKind::Identifier(name) = &self.tokens[j].kind {
+                            self.definitions.push(Definition { name: name.clone(), kind: Kind::Variable, token: j, detail: detail.clone() });
+                        }
+                    }
+                }
+                (TokenKind::Import, Some(TokenKind::StringLiteral(path))) => {
+                    if let (Some(TokenKind::As), Some(TokenKind::Identifier(alias))) = (next(2), next(3)) {
+                        let detail = format!("import \"{}\" as {}", path, alias);
+                        self.imports.push(Import { path: path.clone(), alias: alias.clone(), token: i + 1 });
+                        self.definitions.push(Definition { name: alias.clone(), kind: Kind::Module, token: i + 3, detail });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The parameters between `(` and `)` of a signature, with their annotations
+    fn collect_parameters(&mut self, open: usize, close: usize) {
+        let mut start = open + 1;
+        while start < close {
+            let end = (start..close).find(|&j| self.tokens[j].kind == TokenKind::Comma).unwrap_or(close);
+            if let TokenKind::Identifier(name) = &self.tokens[start].kind {
+                let definition = Definition { name: name.clone(), kind: Kind::Variable, token: start, detail: self.text(start, end) };
+                self.definitions.push(definition);
+            }
+            start = end + 1;
+        }
+    }
+
+    fn check_imports(&mut self) {
+        for import in &self.imports {
+            let problem = if make_internal_module(&import.path).is_some() {
+                continue;
+            } else if !import.path.ends_with(".nk") {
+                format!("Unknown module '{}'", import.path)
+            } else {
+                match FileResolver.resolve(&self.base_path, &import.path) {
+                    Ok(_) => continue,
+                    Err(e) => e,
+                }
+            };
+            let range = self.token_range(import.token);
+            self.problems.push(Problem { range, message: problem });
+        }
+    }
+
+    /// The first token after `from` matching `is_end`, or the end of the tokens
+    fn find(&self, from: usize, is_end: impl Fn(&TokenKind) -> bool) -> usize {
+        (from..self.tokens.len()).find(|&j| is_end(&self.tokens[j].kind)).unwrap_or(self.tokens.len() - 1)
+    }
+
+    /// The source from the start of token `from` to the start of token `to`, on one line
+    fn text(&self, from: usize, to: usize) -> String {
+        let (start, end) = (&self.tokens[from], &self.tokens[to]);
+        let mut text = String::new();
+        for line in start.line..=end.line {
+            let Some(chars) = self.lines.get(line - 1) else { break };
+            let first = if line == start.line { start.column - 1 } else { 0 };
+            let last = if line == end.line { (end.column - 1).min(chars.len()) } else { chars.len() };
+            text.extend(chars.get(first..last).unwrap_or_default());
+            text.push(' ');
+        }
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// The token under the cursor, an identifier wins over the punctuation right after it
+    fn token_at(&self, line: usize, character: usize) -> Option<usize> {
+        let column = self.column(line + 1, character);
+        let covers = |token: &Token| token.line == line + 1 && token.column <= column && column <= token.column + token_len(token);
+        let mut candidates = (0..self.tokens.len()).filter(|&i| covers(&self.tokens[i]));
+        let first = candidates.next()?;
+        let named = |i: &usize| matches!(self.tokens[*i].kind, TokenKind::Identifier(_) | TokenKind::StringLiteral(_));
+        Some(std::iter::once(first).chain(candidates).find(named).unwrap_or(first))
+    }
+
+    /// The definition `name` refers to at token `at`: the closest one before it that is in scope,
+    /// otherwise the first, functions can be used above their definition
+    fn lookup(&self, name: &str, at: usize) -> Option<&Definition> {
+        let named = || self.definitions.iter().filter(|definition| definition.name == name);
+        named()
+            .filter(|definition| definition.token <= at && self.depths[definition.token] <= self.depths[at])
+            .max_by_key(|definition| definition.token)
+            .or_else(|| named().next())
+    }
+
+    fn top_level(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|definition| definition.name == name && self.depths[definition.token] == 0)
+    }
+
+    /// The alias before `.name` when token `i` is a member access like `alias.name`
+    fn member_of(&self, i: usize) -> Option<&str> {
+        if i < 2 || self.tokens[i - 1].kind != TokenKind::Dot {
+            return None;
+        }
+        match &self.tokens[i - 2].kind {
+            TokenKind::Identifier(alias) => Some(alias),
+            _ => None,
+        }
+    }
+
+    fn module(&self, alias: &str) -> Option<Module> {
+        let import = self.imports.iter().find(|import| import.alias == alias)?;
+        self.resolve(&import.path)
+    }
+
+    fn resolve(&self, path: &str) -> Option<Module> {
+        match make_internal_module(path) {
+            Some(module) => Some(Module::Builtin(path.to_string(), module)),
+            None => FileResolver.resolve(&self.base_path, path).ok().map(Module::Script),
+        }
+    }
+
+    pub fn definition(&self, line: usize, character: usize) -> Option<Target> {
+        let i = self.token_at(line, character)?;
+        let script = |module: ResolvedModule| Target { file: Some(module.id), range: ((0, 0), (0, 0)) };
+        match &self.tokens[i].kind {
+            TokenKind::StringLiteral(path) if self.imports.iter().any(|import| import.token == i) => match self.resolve(path)? {
+                Module::Script(module) => Some(script(module)),
+                Module::Builtin(..) => None,
+            },
+            TokenKind::Identifier(name) => {
+                if let Some(alias) = self.member_of(i) {
+                    let Module::Script(module) = self.module(alias)? else { return None };
+                    let analysis = Analysis::new(&module.source, &module.base_path);
+                    let range = analysis.token_range(analysis.top_level(name)?.token);
+                    return Some(Target { file: Some(module.id), range });
+                }
+                let definition = self.lookup(name, i)?;
+                // An import alias leads to the module it names
+                if let (Kind::Module, Some(Module::Script(module))) = (definition.kind, self.module(name)) {
+                    return Some(script(module));
+                }
+                Some(Target { file: None, range: self.token_range(definition.token) })
+            }
+            _ => None,
+        }
+    }
+
+    /// Markdown describing the name under the cursor
+    pub fn hover(&self, line: usize, character: usize) -> Option<String> {
+        let i = self.token_at(line, character)?;
+        let code = |text: &str| format!("```nikl\n{}\n```", text);
+        match &self.tokens[i].kind {
+            TokenKind::StringLiteral(path) if self.imports.iter().any(|import| import.token == i) => match self.resolve(path)? {
+                Module::Builtin(name, _) => Some(format!("Built-in module `{}`", name)),
+                Module::Script(module) => Some(format!("Module `{}`", module.id)),
+            },
+            TokenKind::Identifier(name) => {
+                if let Some(alias) = self.member_of(i) {
+                    return match self.module(alias)? {
+                        Module::Builtin(path, module) => {
+                            let member = module_members(&module).into_iter().find(|(member, _)| member == name)?.1;
+                            Some(format!("{}\n{} of the built-in `{}` module", code(&format!("{}.{}", alias, name)), describe(&member), path))
+                        }
+                        Module::Script(module) => {
+                            let analysis = Analysis::new(&module.source, &module.base_path);
+                            let definition = analysis.top_level(name)?;
+                            Some(format!("{}\nFrom `{}`", code(&definition.detail), module.id))
+                        }
+                    };
+                }
+                if let Some(definition) = self.lookup(name, i) {
+                    return Some(code(&definition.detail));
+                }
+                let builtin = Environment::new().get(name)?;
+                Some(format!("{}\nBuilt-in {}", code(name), describe(&builtin).to_lowercase()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Names that fit at the cursor: the members of a module after `alias.`, otherwise the
+    /// document's definitions, the builtins and the keywords
+    pub fn completion(&self, line: usize, character: usize) -> Vec<Completion> {
+        let column = self.column(line + 1, character);
+        let before: String = self.lines.get(line).map(|chars| chars[..(column - 1).min(chars.len())].iter().collect()).unwrap_or_default();
+        let before = before.trim_end_matches(is_name_char);
+        if let Some(object) = before.strip_suffix('.') {
+            let alias = &object[object.trim_end_matches(is_name_char).len()..];
+            return match self.module(alias) {
+                Some(Module::Builtin(_, module)) => module_members(&module)
+                    .into_iter()
+                    .map(|(name, value)| Completion { label: name, kind: value_kind(&value), detail: describe(&value) })
+                    .collect(),
+                Some(Module::Script(module)) => {
+                    let analysis = Analysis::new(&module.source, &module.base_path);
+                    let top_level = analysis.definitions.iter().filter(|definition| analysis.depths[definition.token] == 0);
+                    top_level.filter(|definition| definition.kind != Kind::Module).map(Completion::from).collect()
+                }
+                None => Vec::new(),
+            };
+        }
+
+        let mut completions: Vec<Completion> = Vec::new();
+        for definition in &self.definitions {
+            if !completions.iter().any(|completion| completion.label == definition.name) {
+                completions.push(definition.into());
+            }
+        }
+        let mut builtins: Vec<(String, Value)> = Environment::new().flatten().into_iter().map(|(name, entry)| (name, entry.value().clone())).collect();
+        builtins.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, value) in builtins {
+            completions.push(Completion { label: name, kind: value_kind(&value), detail: describe(&value) });
+        }
+        completions.extend(KEYWORDS.iter().map(|keyword| Completion { label: keyword.to_string(), kind: Kind::Keyword, detail: String::new() }));
+        completions
+    }
+
+    /// The lexer column of a protocol character offset on a 1-based line
+    fn column(&self, line: usize, character: usize) -> usize {
+        let Some(chars) = self.lines.get(line - 1) else { return character + 1 };
+        let mut units = 0;
+        for (index, ch) in chars.iter().enumerate() {
+            if units >= character {
+                return index + 1;
+            }
+            units += ch.len_utf16();
+        }
+        chars.len() + 1
+    }
+
+    /// The protocol character offset of a lexer column
+    fn character(&self, line: usize, column: usize) -> usize {
+        let Some(chars) = self.lines.get(line - 1) else { return column - 1 };
+        chars.iter().take(column - 1).map(|ch| ch.len_utf16()).sum::<usize>() + column.saturating_sub(chars.len() + 1)
+    }
+
+    fn range(&self, line: usize, column: usize, length: usize) -> Range {
+        let line = line.max(1);
+        let start = self.character(line, column);
+        let end = self.character(line, column + length);
+        ((line - 1, start), (line - 1, end))
+    }
+
+    fn token_range(&self, i: usize) -> Range {
+        let token = &self.tokens[i];
+        self.range(token.line, token.column, token_len(token))
+    }
+}
+
+impl From<&Definition> for Completion {
+    fn from(definition: &Definition) -> Self {
+        Completion { label: definition.name.clone(), kind: definition.kind, detail: definition.detail.clone() }
+    }
+}
+
+/// Characters the lexer reads as part of an identifier
+fn is_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Characters a token covers, only names and strings need to be exact
+fn token_len(token: &Token) -> usize {
+    match &token.kind {
+        TokenKind::Identifier(name) => name.chars().count(),
+        TokenKind::StringLiteral(value) => value.chars().count() + 2,
+        _ => 1,
+    }
+}
+
+fn module_members(module: &Value) -> Vec<(String, Value)> {
+    let Value::HashMap(pairs) = module else { return Vec::new() };
+    let mut members: Vec<(String, Value)> = pairs
+        .iter()
+        .filter_map(|(key, value)| match key {
+            Value::String(name) => Some((name.clone(), value.clone())),
+            _ => None,
+        })
+        .collect();
+    members.sort_by(|(a, _), (b, _)| a.cmp(b));
+    members
+}
+
+fn value_kind(value: &Value) -> Kind {
+    if value.is_callable() { Kind::Function } else { Kind::Constant }
+}
+
+fn describe(value: &Value) -> String {
+    if value.is_callable() { "Function".to_string() } else { value.type_name().to_string() }
+}
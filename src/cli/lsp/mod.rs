@@ -0,0 +1,233 @@
+//! `nikl lsp`, a language server speaking JSON-RPC over standard input and output
+//! Open documents are analyzed again on every change: lex and parse errors and imports that
+//! don't resolve are published as diagnostics, and requests for definitions, hovers and
+//! completions are answered from the latest analysis
+
+mod analysis;
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use analysis::{Analysis, Kind, Range};
+
+
+/// `nikl lsp`, serves one editor until it sends `exit`
+/// Returns 0 when the editor asked for a shutdown first, 1 otherwise
+pub fn run_lsp() -> i32 {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut server = Server::default();
+    while let Some(message) = read_message(&mut input) {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("nikl lsp: {}", e);
+                continue;
+            }
+        };
+        if message["method"] == "exit" {
+            return if server.shut_down { 0 } else { 1 };
+        }
+        if let Some(reply) = server.handle(&message) {
+            write_message(&reply);
+        }
+    }
+    1
+}
+
+
+#[derive(Default)]
+struct Server {
+    /// Text of the open documents by URI
+    documents: HashMap<String, String>,
+    shut_down: bool,
+}
+
+impl Server {
+    /// The message to send back for one from the editor, if any
+    fn handle(&mut self, message: &Value) -> Option<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                },
+                "serverInfo": { "name": "nikl", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => Ok(self.at(params, |analysis, line, character, uri| {
+                let target = analysis.definition(line, character)?;
+                let uri = target.file.map(|file| path_to_uri(Path::new(&file))).unwrap_or_else(|| uri.to_string());
+                Some(json!({ "uri": uri, "range": range(target.range) }))
+            })),
+            "textDocument/hover" => Ok(self.at(params, |analysis, line, character, _| {
+                let text = analysis.hover(line, character)?;
+                Some(json!({ "contents": { "kind": "markdown", "value": text } }))
+            })),
+            "textDocument/completion" => Ok(self.at(params, |analysis, line, character, _| {
+                let items: Vec<Value> = analysis
+                    .completion(line, character)
+                    .into_iter()
+                    .map(|item| json!({ "label": item.label, "kind": completion_kind(item.kind), "detail": item.detail }))
+                    .collect();
+                Some(Value::Array(items))
+            })),
+            _ => Err(format!("Unknown method '{}'", method)),
+        };
+        let reply = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": e } }),
+        };
+        Some(reply)
+    }
+
+    /// Notifications only get diagnostics back, unknown ones are ignored
+    fn notification(&mut self, method: &str, params: &Value) -> Option<Value> {
+        let uri = params["textDocument"]["uri"].as_str()?.to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str()?;
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            // Documents are synced in full, the last change holds the whole text
+            "textDocument/didChange" => {
+                let text = params["contentChanges"].as_array()?.last()?["text"].as_str()?;
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return Some(diagnostics(&uri, Vec::new()));
+            }
+            _ => return None,
+        }
+        let analysis = self.analyze(&uri)?;
+        let problems = analysis.problems.iter().map(|problem| {
+            json!({ "range": range(problem.range), "severity": 1, "source": "nikl", "message": problem.message })
+        });
+        Some(diagnostics(&uri, problems.collect()))
+    }
+
+    fn analyze(&self, uri: &str) -> Option<Analysis> {
+        let text = self.documents.get(uri)?;
+        let base_path = uri_to_path(uri).and_then(|path| path.parent().map(Path::to_path_buf)).unwrap_or_else(|| PathBuf::from("."));
+        Some(Analysis::new(text, &base_path))
+    }
+
+    /// Answers a request about a position in a document, `null` when there's nothing to say
+    fn at(&self, params: &Value, answer: impl Fn(&Analysis, usize, usize, &str) -> Option<Value>) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let position = &params["position"];
+        let (Some(line), Some(character)) = (position["line"].as_u64(), position["character"].as_u64()) else {
+            return Value::Null;
+        };
+        self.analyze(uri)
+            .and_then(|analysis| answer(&analysis, line as usize, character as usize, uri))
+            .unwrap_or(Value::Null)
+    }
+}
+
+
+fn diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn range(((start_line, start), (end_line, end)): Range) -> Value {
+    json!({
+        "start": { "line": start_line, "character": start },
+        "end": { "line": end_line, "character": end },
+    })
+}
+
+fn completion_kind(kind: Kind) -> u8 {
+    match kind {
+        Kind::Function => 3,
+        Kind::Variable => 6,
+        Kind::Module => 9,
+        Kind::Keyword => 14,
+        Kind::Constant => 21,
+    }
+}
+
+/// The next message, `None` once the input ends
+fn read_message(input: &mut impl BufRead) -> Option<Result<Value, String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Some(Err("Message without a Content-Length header".to_string()));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).ok()?;
+    Some(serde_json::from_slice(&body).map_err(|e| format!("Invalid message: {}", e)))
+}
+
+fn write_message(message: &Value) {
+    let body = message.to_string();
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = stdout.flush();
+}
+
+/// The path of a `file://` URI, percent-escapes decoded
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%').then(|| path.get(i + 1..i + 3)).flatten().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&decoded).to_string()))
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri += &format!("%{:02X}", byte);
+        }
+    }
+    uri
+}
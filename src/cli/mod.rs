@@ -1,48 +1,130 @@
+mod args;
+mod bundle;
+mod check;
+mod coverage;
+mod debug;
+mod diagnostic;
+mod dump;
+mod files;
+mod fmt;
+mod highlight;
+mod lint;
+mod lsp;
+mod output;
+mod profile;
 mod repl;
 mod run_file;
+mod test;
+mod watch;
 
-pub use repl::run_repl;
-pub use run_file::run_file;
-
-
-pub fn print_help() {
-    println!("Usage:");
-    println!("  nikl            # Start REPL");
-    println!("  nikl <file.nk>  # Run script file");
-    println!("  nikl run        # Run the current package");    // TODO: Not sure if really needed (not yet considered)
-    println!("  nikl init <dir> # Initialize a new package");
-    println!("  nikl build      # Build the current package");
-    println!("  nikl login      # Login to your account");
-    println!("  nikl logout     # Logout from the current user");
-    println!("  nikl publish    # Publish the current package");
-    println!("  nikl install <pkg>    # Install a package");
-    println!("  nikl uninstall <pkg>  # Uninstall a package");
-    println!("  nikl help       # Show this help message");
+pub use args::{CacheCommand, Cli, Command};
+pub use bundle::{bundle, run_embedded};
+pub use check::check_files;
+pub use coverage::coverage_file;
+pub use debug::debug_file;
+pub use dump::{dump_ast, dump_tokens};
+pub use fmt::format_files;
+pub use highlight::{highlight_line, ReplHelper};
+pub use lint::lint_files;
+pub use lsp::run_lsp;
+pub use profile::profile_file;
+pub use repl::{run_repl, run_repl_with};
+pub use run_file::{run_eval, run_file, run_interactive, run_stdin};
+pub use test::run_tests;
+pub use watch::watch;
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::packages::{build_archive, cache_dir, clean_cache, compare_versions, remove_token, save_token, Registry, Workspace};
+
+
+/// Runs what the command line asks for and returns the process exit code
+pub fn run(cli: Cli) -> i32 {
+    output::configure(cli.verbose, cli.quiet, cli.no_color);
+
+    let Some(command) = cli.command else {
+        return run_script(cli);
+    };
+    match command {
+        Command::Check { imports, paths } => check_files(&paths, imports),
+        Command::Fmt { check, paths } => format_files(&paths, check),
+        Command::Lint { paths } => lint_files(&paths),
+        Command::Test { coverage, lcov, paths } => run_tests(&paths, coverage, lcov.as_deref()),
+        Command::Debug { script, args } => debug_file(&script, &args),
+        Command::Watch { target, args } => watch(&target, &args),
+        Command::Lsp => run_lsp(),
+        Command::Bundle { script, output, exe } => bundle(&script, output.as_deref(), exe),
+        Command::Init { dir } => init_package(&dir),
+        Command::Build => build_package(),
+        Command::Login { token } => login(token),
+        Command::Logout => logout(),
+        Command::Publish => publish_package(),
+        Command::Install { package } => install_package(package.as_deref()),
+        Command::Uninstall { package, force } => uninstall_package(&package, force),
+        Command::Search { query } => search_packages(&query.join(" ")),
+        Command::Info { package } => package_info(&package),
+        Command::Add { package } => add_package(&package),
+        Command::Remove { package, force } => remove_package(&package, force),
+        Command::Cache { command } => package_cache(command),
+    }
 }
 
+/// `nikl [flags] [script] [args...]`, clap already checked the flags that need a script have one
+fn run_script(cli: Cli) -> i32 {
+    if let Some(code) = &cli.eval {
+        return run_eval(code, &cli.script);
+    }
+    let Some((script, args)) = cli.script.split_first() else {
+        return match run_repl() {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("REPL exited with error: {}", e);
+                1
+            }
+        };
+    };
 
-pub fn init_package(args: &[String]) {
-    if args.len() != 1 {
-        eprintln!("Usage: nikl init <dir>");
-        return;
+    if cli.dump_tokens {
+        dump_tokens(script, cli.json)
+    } else if cli.dump_ast {
+        dump_ast(script, cli.json)
+    } else if cli.profile {
+        profile_file(script, args, cli.folded.as_deref())
+    } else if cli.coverage {
+        coverage_file(script, args, cli.lcov.as_deref())
+    } else if cli.interactive {
+        run_interactive(script, args)
+    } else if script == "-" {
+        run_stdin(args)
+    } else {
+        run_file(script, args)
+    }
+}
+
+
+/// `nikl init <dir>`, creates a new package in an empty directory and returns the process exit code
+pub fn init_package(dir: &str) -> i32 {
+    if !output::quiet() {
+        println!("Initializing package in directory: {}", dir);
     }
-    let dir = &args[0];
-    println!("Initializing package in directory: {}", dir);
 
     // Validate directory
     let dir = std::path::Path::new(dir);
     if !dir.exists() {
-        println!("Directory does not exist. Creating it...");
+        if !output::quiet() {
+            println!("Directory does not exist. Creating it...");
+        }
         if let Err(e) = std::fs::create_dir_all(dir) {
             eprintln!("Failed to create directory: {}", e);
-            return;
+            return 1;
         }
     }
 
     // Check if the directory is empty
-    if dir.read_dir().map_or(false, |mut entries| entries.next().is_some()) {
+    if dir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
         eprintln!("Directory is not empty. Please choose an empty directory.");
-        return;
+        return 1;
     }
 
     // Get project name from the directory name
@@ -52,51 +134,281 @@ pub fn init_package(args: &[String]) {
         .unwrap_or("nikl_project");
 
     // Create the package structure
-    println!("Creating package structure...");
+    if !output::quiet() {
+        println!("Creating package structure...");
+    }
     match crate::packages::create_package_structure(dir, project_name) {
-        Ok(_) => println!("Package structure created successfully."),
-        Err(e) => eprintln!("Failed to create package structure: {}", e),
+        Ok(_) => {
+            if !output::quiet() {
+                println!("Package structure created successfully.");
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to create package structure: {}", e);
+            1
+        }
     }
 }
 
 
-pub fn build_package() {
-    println!("Building the current package...");
-    crate::packages::create_tar_gz().unwrap_or_else(|e| {
-        eprintln!("Failed to create package: {}", e);
+/// `nikl build`, archives the current package, or every member at the root of a workspace
+/// Returns the process exit code, 1 when any package failed to build
+pub fn build_package() -> i32 {
+    let dir = std::env::current_dir().unwrap_or_default();
+    let workspace = match Workspace::find(&dir) {
+        Ok(workspace) => workspace.filter(|workspace| workspace.is_root(&dir)),
+        Err(e) => {
+            eprintln!("Failed to create package: {}", e);
+            return 1;
+        }
+    };
+    let Some(workspace) = workspace else {
+        if !output::quiet() {
+            println!("Building the current package...");
+        }
+        return match crate::packages::create_tar_gz(Path::new("")) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("Failed to create package: {}", e);
+                1
+            }
+        };
+    };
+    // Every member is built even when one fails, so all the errors show at once
+    let mut failed = false;
+    for member in &workspace.members {
+        if !output::quiet() {
+            println!("Building {} in {}...", member.name, member.dir.display());
+        }
+        if let Err(e) = crate::packages::create_tar_gz(&member.dir) {
+            eprintln!("Failed to create package {}: {}", member.name, e);
+            failed = true;
+        }
+    }
+    i32::from(failed)
+}
+
+/// `nikl login [--token <token>]`, checks the token with the registry and saves it,
+/// asking for it on standard input when not given. Returns the process exit code
+pub fn login(token: Option<String>) -> i32 {
+    let registry = Registry::from_env();
+    let token = match token {
+        Some(token) => token,
+        None => {
+            if !output::quiet() {
+                print!("Paste your API token for {}: ", registry.url());
+                let _ = std::io::stdout().flush();
+            }
+            let mut line = String::new();
+            if let Err(e) = std::io::stdin().read_line(&mut line) {
+                eprintln!("Error reading the token: {}", e);
+                return 2;
+            }
+            line.trim().to_string()
+        }
+    };
+    if token.is_empty() {
+        eprintln!("Error: the token is empty");
+        return 2;
+    }
+
+    let registry = registry.with_token(&token);
+    let result = registry.whoami().and_then(|name| save_token(registry.url(), &token).map(|()| name));
+    match result {
+        Ok(name) => {
+            if !output::quiet() {
+                println!("Logged in to {} as {}", registry.url(), name);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+/// `nikl logout`, forgets the token saved for the registry
+pub fn logout() -> i32 {
+    let registry = Registry::from_env();
+    match remove_token(registry.url()) {
+        Ok(removed) => {
+            let message = if removed { "Logged out of" } else { "Not logged in to" };
+            if !output::quiet() {
+                println!("{} {}", message, registry.url());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+/// `nikl publish`, builds the package in the current directory and uploads it with the saved token
+pub fn publish_package() -> i32 {
+    let registry = Registry::from_env();
+    let result = std::env::current_dir().and_then(|dir| build_archive(&dir)).map_err(|e| e.to_string()).and_then(|(name, version, archive)| {
+        if !output::quiet() {
+            println!("Publishing {}@{} to {}", name, version, registry.url());
+        }
+        registry.publish(&name, &version, &archive).map(|()| (name, version))
     });
+    match result {
+        Ok((name, version)) => {
+            if !output::quiet() {
+                println!("Published {}@{}", name, version);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
 }
 
-pub fn login() {
-    println!("Logging in...");
-    todo!("Implement login logic");
+/// `nikl install [package]`, returns the process exit code
+pub fn install_package(pkg: Option<&str>) -> i32 {
+    let result = match pkg {
+        Some(pkg) => crate::packages::install_package(pkg, output::quiet()),
+        None => crate::packages::install_project(output::quiet()),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
 }
 
-pub fn logout() {
-    println!("Logging out...");
-    todo!("Implement logout logic");
+/// `nikl uninstall <package> [--force]`, returns the process exit code
+pub fn uninstall_package(pkg: &str, force: bool) -> i32 {
+    match crate::packages::uninstall_package(pkg, force, output::quiet()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
 }
 
-pub fn publish_package() {
-    println!("Publishing the current package...");
-    todo!("Implement package publishing logic");
+/// `nikl add <package>`, returns the process exit code
+pub fn add_package(pkg: &str) -> i32 {
+    match crate::packages::add_dependency(pkg, output::quiet()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
 }
 
-pub fn install_package(args: &[String]) {
-    if args.len() != 1 {
-        eprintln!("Usage: nikl install <pkg>");
-        return;
+/// `nikl remove <package> [--force]`, returns the process exit code
+pub fn remove_package(pkg: &str, force: bool) -> i32 {
+    match crate::packages::remove_dependency(pkg, force, output::quiet()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+/// `nikl search <query>`, lists matching packages with their latest version
+pub fn search_packages(query: &str) -> i32 {
+    let packages = match Registry::from_env().search(query) {
+        Ok(packages) => packages,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+    if packages.is_empty() {
+        if !output::quiet() {
+            println!("No packages found for '{}'", query);
+        }
+        return 0;
+    }
+    let width = packages.iter().map(|package| package.name.len() + package.version.len() + 1).max().unwrap_or(0);
+    for package in packages {
+        let name = format!("{}@{}", package.name, package.version);
+        println!("{}", format!("{:<width$}  {}", name, package.description, width = width).trim_end());
+    }
+    0
+}
+
+/// `nikl info <package>[@version]`, describes a package, the dependencies are those of the
+/// given version or the latest
+pub fn package_info(pkg: &str) -> i32 {
+    let (name, version) = pkg.split_once('@').unwrap_or((pkg, ""));
+    let info = match Registry::from_env().package(name) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+    let shown = match version {
+        "" => info.latest(),
+        version => info.versions.iter().find(|info| info.version == version),
+    };
+    let Some(shown) = shown else {
+        eprintln!("Error: {} has no version '{}'", name, version);
+        return 1;
+    };
+
+    println!("{}@{}", info.name, shown.version);
+    if !info.description.is_empty() {
+        println!("{}", info.description);
+    }
+    println!();
+    let authors: Vec<String> = info.authors.iter().map(|author| match &author.email {
+        Some(email) => format!("{} <{}>", author.name, email),
+        None => author.name.clone(),
+    }).collect();
+    let mut versions: Vec<&str> = info.versions.iter().map(|info| info.version.as_str()).collect();
+    versions.sort_by(|a, b| compare_versions(b, a));
+    let dependencies: Vec<String> = shown.dependencies.iter().map(|dependency| format!("{} {}", dependency.name, dependency.version).trim_end().to_string()).collect();
+    let fields = [
+        ("authors", authors.join(", ")),
+        ("license", info.license.clone().unwrap_or_default()),
+        ("keywords", info.keywords.join(", ")),
+        ("homepage", info.homepage.clone().unwrap_or_default()),
+        ("repository", info.repository.clone().unwrap_or_default()),
+        ("versions", versions.join(", ")),
+        ("dependencies", dependencies.join(", ")),
+    ];
+    for (field, value) in fields {
+        if !value.is_empty() {
+            println!("{:<13} {}", format!("{}:", field), value);
+        }
     }
-    let pkg = &args[0];
-    crate::packages::install_package(pkg);
+    0
 }
 
-pub fn uninstall_package(args: &[String]) {
-    if args.len() != 1 {
-        eprintln!("Usage: nikl uninstall <pkg>");
-        return;
+/// `nikl cache dir|clean`, returns the process exit code
+pub fn package_cache(command: CacheCommand) -> i32 {
+    let Some(dir) = cache_dir() else {
+        eprintln!("Error: Can't find a cache directory, set NIKL_CACHE_DIR");
+        return 1;
+    };
+    match command {
+        CacheCommand::Dir => println!("{}", dir.display()),
+        CacheCommand::Clean => match clean_cache() {
+            Ok(count) => {
+                if !output::quiet() {
+                    println!("Removed {} cached archive(s) from {}", count, dir.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return 1;
+            }
+        },
     }
-    let pkg = &args[0];
-    println!("Uninstalling package: {}", pkg);
-    todo!("Implement package uninstallation logic");
+    0
 }
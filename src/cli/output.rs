@@ -0,0 +1,59 @@
+//! The global `--verbose`, `--quiet` and `--no-color` flags, read by the commands as they print
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::interpreter::{Interpreter, InterpreterBuilder};
+
+
+const QUIET: u8 = 0;
+const NORMAL: u8 = 1;
+const VERBOSE: u8 = 2;
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(NORMAL);
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+
+
+/// Applies the global flags, `NO_COLOR` set to anything but an empty string also turns colors off
+pub(super) fn configure(verbose: bool, quiet: bool, no_color: bool) {
+    let verbosity = match (verbose, quiet) {
+        (_, true) => QUIET,
+        (true, false) => VERBOSE,
+        (false, false) => NORMAL,
+    };
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+    let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    NO_COLOR.store(no_color, Ordering::Relaxed);
+}
+
+/// Progress and summary lines are left out
+pub(super) fn quiet() -> bool {
+    VERBOSITY.load(Ordering::Relaxed) == QUIET
+}
+
+pub(super) fn verbose() -> bool {
+    VERBOSITY.load(Ordering::Relaxed) == VERBOSE
+}
+
+pub(super) fn color() -> bool {
+    !NO_COLOR.load(Ordering::Relaxed)
+}
+
+/// The global flags in effect, for commands starting `nikl` again
+pub(super) fn flags() -> Vec<String> {
+    let mut flags = Vec::new();
+    match VERBOSITY.load(Ordering::Relaxed) {
+        QUIET => flags.push("--quiet".to_string()),
+        VERBOSE => flags.push("--verbose".to_string()),
+        _ => {}
+    }
+    if !color() {
+        flags.push("--no-color".to_string());
+    }
+    flags
+}
+
+/// A builder for the interpreter of a script run by a command, `--no-color` reaches the `term` module
+pub(super) fn interpreter(base_path: PathBuf) -> InterpreterBuilder {
+    Interpreter::builder(base_path).no_color(!color())
+}
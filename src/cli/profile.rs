@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+use crate::interpreter::ExecutionHook;
+use super::files::canonical;
+use super::output;
+use super::run_file::{read_file, run_in, script_args};
+
+
+/// Name of the frame for the top level of the script
+const MAIN: &str = "<main>";
+
+
+/// `nikl --profile [--folded <out>] <file.nk> [--] [args...]`, runs a script and prints the
+/// calls and time of every function to stderr, `--folded` also writes the stacks in the folded
+/// format flamegraph tools read, weighted by self time in nanoseconds
+/// Returns the process exit code like `run_file`
+pub fn profile_file(filename: &str, args: &[String], folded: Option<&str>) -> i32 {
+    let Some(source) = read_file(filename) else {
+        eprintln!("Failed to read or validate the file '{}'", filename);
+        return 2;
+    };
+
+    let profiler = Arc::new(Profiler::default());
+    let base_path = Path::new(filename).parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let mut interpreter = output::interpreter(base_path)
+        .argv(std::iter::once(filename.to_string()).chain(script_args(args).iter().cloned()).collect())
+        .hook(profiler.clone())
+        .build();
+
+    profiler.on_call(MAIN);
+    let code = run_in(&mut interpreter, &source, &canonical(filename));
+    profiler.on_return(MAIN);
+
+    let state = profiler.state();
+    eprint!("{}", state.report());
+    if let Some(out) = folded {
+        if let Err(e) = std::fs::write(out, state.folded()) {
+            eprintln!("Failed to write '{}': {}", out, e);
+            return 2;
+        }
+    }
+    code
+}
+
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Stats {
+    calls: u64,
+    /// Time from call to return, counted once for recursive calls
+    total: Duration,
+    /// `total` minus the time spent in the functions it called
+    own: Duration,
+}
+
+struct Frame {
+    name: String,
+    start: Instant,
+    in_calls: Duration,
+}
+
+#[derive(Default)]
+struct State {
+    /// Call stack of every thread running script functions
+    stacks: HashMap<ThreadId, Vec<Frame>>,
+    functions: HashMap<String, Stats>,
+    /// Self time of every distinct stack, names joined by `;`
+    stacks_time: HashMap<String, Duration>,
+}
+
+impl State {
+    fn report(&self) -> String {
+        let mut functions: Vec<(&String, &Stats)> = self.functions.iter().collect();
+        functions.sort_by(|(a_name, a), (b_name, b)| b.total.cmp(&a.total).then_with(|| a_name.cmp(b_name)));
+
+        let mut report = format!("\n{:>8}  {:>12}  {:>12}  function\n", "calls", "total", "self");
+        for (name, stats) in functions {
+            report += &format!("{:>8}  {:>12}  {:>12}  {}\n", stats.calls, format!("{:.2?}", stats.total), format!("{:.2?}", stats.own), name);
+        }
+        report
+    }
+
+    fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks_time.iter().collect();
+        stacks.sort();
+        stacks.iter().map(|(stack, time)| format!("{} {}\n", stack, time.as_nanos())).collect()
+    }
+}
+
+#[derive(Default)]
+struct Profiler {
+    state: Mutex<State>,
+}
+
+impl Profiler {
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ExecutionHook for Profiler {
+    fn on_call(&self, function: &str) {
+        let mut state = self.state();
+        state.functions.entry(function.to_string()).or_default().calls += 1;
+        let frame = Frame { name: function.to_string(), start: Instant::now(), in_calls: Duration::ZERO };
+        state.stacks.entry(std::thread::current().id()).or_default().push(frame);
+    }
+
+    fn on_return(&self, function: &str) {
+        let mut state = self.state();
+        let state = &mut *state;
+        let Some(stack) = state.stacks.get_mut(&std::thread::current().id()) else { return };
+        let stack_key = stack.iter().map(|frame| frame.name.as_str()).collect::<Vec<_>>().join(";");
+        let Some(frame) = stack.pop() else { return };
+        debug_assert_eq!(frame.name, function);
+
+        let elapsed = frame.start.elapsed();
+        let own = elapsed.saturating_sub(frame.in_calls);
+        let recursive = stack.iter().any(|outer| outer.name == frame.name);
+        if let Some(caller) = stack.last_mut() {
+            caller.in_calls += elapsed;
+        }
+
+        let stats = state.functions.entry(frame.name).or_default();
+        stats.own += own;
+        if !recursive {
+            stats.total += elapsed;
+        }
+        *state.stacks_time.entry(stack_key).or_default() += own;
+    }
+}
@@ -1,8 +1,12 @@
 use rustyline::{Editor, history::FileHistory};
 use rustyline::error::ReadlineError;
+use std::collections::HashMap;
 use std::fs;
 
-use crate::{lexer::{Lexer, LexError, Token}, parser::Parser, interpreter::Interpreter};
+use crate::{lexer::Lexer, parser::Parser, interpreter::Interpreter, Value};
+use super::diagnostic::Diagnostic;
+use super::highlight::ReplHelper;
+use super::output;
 
 
 fn create_history_file_if_not_exists(filename: &str) -> std::io::Result<()> {
@@ -16,28 +20,30 @@ fn create_history_file_if_not_exists(filename: &str) -> std::io::Result<()> {
     Ok(())
 }
 
-fn tokenize_input(input: &str) -> Result<Vec<Token>, LexError> {
-    let lexer = Lexer::new(input);
-    lexer.tokenize()
-}
-
-fn parse_tokens(tokens: Vec<Token>) -> Result<Vec<crate::parser::Stmt>, String> {
-    let mut parser = Parser::new(tokens);
-    parser.parse()
+pub fn run_repl() -> rustyline::Result<()> {
+    let base_path = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+    run_repl_with(output::interpreter(base_path).build())
 }
 
-pub fn run_repl() -> rustyline::Result<()> {
-    println!("Welcome to Nikl REPL!");
-    println!("To exit, type 'exit' or press Ctrl+D");
+/// Runs the REPL in the global scope of `interpreter`, e.g. one that just ran a script
+pub fn run_repl_with(mut interpreter: Interpreter) -> rustyline::Result<()> {
+    if !output::quiet() {
+        println!("Welcome to Nikl REPL!");
+        println!("To exit, type 'exit' or press Ctrl+D");
+    }
 
-    let mut rl = Editor::<(), FileHistory>::new()?;
+    let mut rl = Editor::<ReplHelper, FileHistory>::new()?;
+    if output::color() {
+        rl.set_helper(Some(ReplHelper));
+    }
     create_history_file_if_not_exists("/tmp/.nikl_history")?;
-    if !rl.load_history("/tmp/.nikl_history").is_ok() {
+    if rl.load_history("/tmp/.nikl_history").is_err() {
         eprintln!("No previous history found");
     }
 
-    let base_path = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
-    let mut interpreter = Interpreter::new(base_path);
+    // Set when a line calls `exit()`, the REPL exits with it after saving the history
+    let mut exit_code = None;
+    let mut sources = HashMap::new();
 
     loop {
         let readline = rl.readline(">>> ");
@@ -53,32 +59,30 @@ pub fn run_repl() -> rustyline::Result<()> {
                 }
                 rl.add_history_entry(input)?;
 
-                match tokenize_input(input) {
-                    Ok(tokens) => {
-                        // If required, get the tokens for debugging
-                        // for token in &tokens {
-                        //     println!("{:?}", token);
-                        // }
-                        match parse_tokens(tokens.clone()) {
-                            Ok(stmts) => {
-                                match interpreter.run(&stmts) {
-                                    Ok(_) => (),
-                                    Err(e) => eprintln!("Runtime error: {}", e),
-                                }
-                            }
-                            Err(e) => eprintln!("Parse error: {}", e),
-                        }
+                // Every entry is its own source so errors in functions defined earlier show their line
+                let file = format!("<input {}>", sources.len() + 1);
+                sources.insert(file.clone(), input.to_string());
+                let stmts = Lexer::new(input)
+                    .tokenize()
+                    .map_err(|e| Diagnostic::lex(&e, &file))
+                    .and_then(|tokens| Parser::with_lines(tokens, &file).parse().map_err(|e| Diagnostic::parse(&e, &file)));
+                let stmts = match stmts {
+                    Ok(stmts) => stmts,
+                    Err(diagnostic) => {
+                        eprint!("{}", diagnostic.render(&sources));
+                        continue;
                     }
-                    Err(e) => match e {
-                        LexError::UnexpectedChar(ch, line, col) => {
-                            eprintln!("Unexpected character '{}' at line {}, column {}", ch, line, col);
-                        }
-                        LexError::UnterminatedString(line, col) => {
-                            eprintln!("Unterminated string starting at line {}, column {}", line, col);
-                        }
-                        LexError::InvalidNumber(num, line, col) => {
-                            eprintln!("Invalid number '{}' at line {}, column {}", num, line, col);
+                };
+                // A bare expression shows its value, like `1 + 2` printing `3`
+                match interpreter.run_program(&stmts) {
+                    Ok(Value::Null) => (),
+                    Ok(value) => println!("{}", value.repr()),
+                    Err(e) => match interpreter.exit_code() {
+                        Some(code) => {
+                            exit_code = Some(code);
+                            break;
                         }
+                        None => eprint!("{}", Diagnostic::runtime(&e, interpreter.error_line(&e)).render(&sources)),
                     },
                 }
             }
@@ -97,9 +101,12 @@ pub fn run_repl() -> rustyline::Result<()> {
         }
     }
 
-    if !rl.save_history("/tmp/.nikl_history").is_ok() {
+    if rl.save_history("/tmp/.nikl_history").is_err() {
         eprintln!("Failed to save history");
     }
+    if let Some(code) = exit_code {
+        std::process::exit(code);
+    }
 
     Ok(())
 }
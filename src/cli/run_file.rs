@@ -1,12 +1,19 @@
+use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use crate::{lexer::{Lexer, LexError, Token}, parser::Parser, interpreter::Interpreter};
+use crate::{lexer::Lexer, parser::Parser, interpreter::Interpreter};
+use super::bundle::Bundle;
+use super::diagnostic::Diagnostic;
+use super::files::canonical;
+use super::output;
 
 
+/// Scripts end with `.nk`, or start with a `#!` line when they are run as a command
 fn check_file_is_valid(filename: &str) -> bool {
     match fs::metadata(filename) {
-        Ok(metadata) if metadata.is_file() && filename.ends_with(".nk") => {
+        Ok(metadata) if metadata.is_file() && (filename.ends_with(".nk") || has_shebang(filename)) => {
             if metadata.len() > 0 {
                 true
             } else {
@@ -15,7 +22,7 @@ fn check_file_is_valid(filename: &str) -> bool {
             }
         }
         Ok(_) => {
-            eprintln!("Error: File '{}' is not a valid script, it should end with .nk", filename);
+            eprintln!("Error: File '{}' is not a valid script, it should end with .nk or start with a #! line", filename);
             false
         }
         Err(_) => {
@@ -25,7 +32,12 @@ fn check_file_is_valid(filename: &str) -> bool {
     }
 }
 
-fn read_file(filename: &str) -> Option<String> {
+fn has_shebang(filename: &str) -> bool {
+    let mut start = [0; 2];
+    fs::File::open(filename).and_then(|mut file| file.read_exact(&mut start)).is_ok() && &start == b"#!"
+}
+
+pub(super) fn read_file(filename: &str) -> Option<String> {
     if !check_file_is_valid(filename) {
         return None;
     }
@@ -39,64 +51,111 @@ fn read_file(filename: &str) -> Option<String> {
     }
 }
 
-fn tokenize_input(input: &str) -> Result<Vec<Token>, LexError> {
-    let lexer = Lexer::new(input);
-    lexer.tokenize()
+/// Lexes, parses and runs a program, `file` names it in the line markers runtime errors are
+/// located with, and in execution hooks
+fn execute(interpreter: &mut Interpreter, source: &str, file: &str) -> Result<(), Diagnostic> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| Diagnostic::lex(&e, file))?;
+    let stmts = Parser::with_lines(tokens, file).parse().map_err(|e| Diagnostic::parse(&e, file))?;
+    interpreter.run(&stmts).map(|_| ()).map_err(|e| Diagnostic::runtime(&e, interpreter.error_line(&e)))
 }
 
-fn parse_tokens(tokens: Vec<Token>) -> Result<Vec<crate::parser::Stmt>, String> {
-    let mut parser = Parser::new(tokens);
-    parser.parse()
+/// Runs a program and returns the process exit code: the value passed to `exit()`,
+/// 1 after printing the error when it fails, otherwise 0
+fn run_source(source: &str, file: &str, base_path: PathBuf, argv: Vec<String>) -> i32 {
+    let mut interpreter = output::interpreter(base_path).build();
+    interpreter.set_argv(argv);
+    run_in(&mut interpreter, source, file)
 }
 
-fn interpret_statements(stmts: &[crate::parser::Stmt], base_path: PathBuf) -> Result<(), String> {
-    let mut interpreter = Interpreter::new(base_path);
-    interpreter.run(stmts).map(|_| ())
+/// Like `run_source` with an interpreter the caller has set up
+pub(super) fn run_in(interpreter: &mut Interpreter, source: &str, file: &str) -> i32 {
+    run_with(interpreter, source, file, &HashMap::from([(file.to_string(), source.to_string())]))
 }
 
-pub fn run_file(filename: &str) {
-    if let Some(content) = read_file(filename) {
-        match tokenize_input(&content) {
-            Ok(tokens) => {
-                // If required, log the tokens
-                // for token in &tokens {
-                //     println!("{:?}", token);
-                // }
-                match parse_tokens(tokens.clone()) {
-                    Ok(stmts) => {
-                        // If required, log the parsed statements
-                        // for stmt in &stmts {
-                        //     println!("{:?}", stmt);
-                        // }
-
-                        // Extract the directory containing the file
-                        let base_path = Path::new(filename)
-                            .parent()
-                            .unwrap_or_else(|| Path::new("."))
-                            .to_path_buf();
-
-                        // Execute the statements
-                        match interpret_statements(&stmts, base_path) {
-                            Ok(_) => (),    // Successfully executed
-                            Err(e) => eprintln!("Error executing script: {}", e),
-                        }
-                    }
-                    Err(e) => eprintln!("Error parsing statements: {}", e),
-                }
-            }
-            Err(e) => match e {
-                LexError::UnexpectedChar(ch, line, col) => {
-                    eprintln!("Unexpected character '{}' at line {}, column {}", ch, line, col);
-                }
-                LexError::UnterminatedString(line, col) => {
-                    eprintln!("Unterminated string starting at line {}, column {}", line, col);
-                }
-                LexError::InvalidNumber(num, line, col) => {
-                    eprintln!("Invalid number '{}' at line {}, column {}", num, line, col);
-                }
-            },
+/// Like `run_in`, errors are shown from `sources` by file name
+pub(super) fn run_with(interpreter: &mut Interpreter, source: &str, file: &str, sources: &HashMap<String, String>) -> i32 {
+    let result = execute(interpreter, source, file);
+    if let Some(code) = interpreter.exit_code() {
+        return code;
+    }
+    match result {
+        Ok(()) => 0,
+        Err(diagnostic) => {
+            eprint!("{}", diagnostic.render(sources));
+            1
         }
-    } else {
+    }
+}
+
+/// The arguments after the script, a leading `--` is dropped so scripts can take
+/// arguments that look like `nikl` options
+pub(super) fn script_args(args: &[String]) -> &[String] {
+    match args.first() {
+        Some(first) if first == "--" => &args[1..],
+        _ => args,
+    }
+}
+
+/// Runs a script file, `args` are the extra command-line arguments passed to it
+/// Returns the process exit code, 2 when the file can't be read
+pub fn run_file(filename: &str, args: &[String]) -> i32 {
+    let args = script_args(args);
+    let Some(content) = read_file(filename) else {
+        eprintln!("Failed to read or validate the file '{}'", filename);
+        return 2;
+    };
+    // Extract the directory containing the file
+    let base_path = Path::new(filename)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+
+    let argv = std::iter::once(filename.to_string()).chain(args.iter().cloned()).collect();
+    if let Some(bundle) = Bundle::parse(&content) {
+        return bundle.run(base_path, argv);
+    }
+    run_source(&content, &canonical(filename), base_path, argv)
+}
+
+/// `nikl -e <code> [args...]`, runs a one-line program from the current directory
+/// `sys.argv` starts with `-e`, returns the process exit code like `run_file`
+pub fn run_eval(code: &str, args: &[String]) -> i32 {
+    let argv = std::iter::once("-e".to_string()).chain(args.iter().cloned()).collect();
+    run_source(code, "<eval>", PathBuf::from("."), argv)
+}
+
+/// `nikl - [args...]`, runs the program piped to standard input from the current directory
+/// `sys.argv` starts with `-`, returns the process exit code like `run_file`
+pub fn run_stdin(args: &[String]) -> i32 {
+    let mut source = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut source) {
+        eprintln!("Error reading standard input: {}", e);
+        return 2;
+    }
+    let argv = std::iter::once("-".to_string()).chain(script_args(args).iter().cloned()).collect();
+    run_source(&source, "<stdin>", PathBuf::from("."), argv)
+}
+
+/// `nikl -i <file.nk> [--] [args...]`, runs a script and then opens the REPL in its global scope,
+/// also when the script failed. Returns the process exit code, a script calling `exit()` skips the REPL
+pub fn run_interactive(filename: &str, args: &[String]) -> i32 {
+    let Some(content) = read_file(filename) else {
         eprintln!("Failed to read or validate the file '{}'", filename);
+        return 2;
+    };
+    let base_path = Path::new(filename).parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let mut interpreter = output::interpreter(base_path).build();
+    interpreter.set_argv(std::iter::once(filename.to_string()).chain(script_args(args).iter().cloned()).collect());
+
+    run_in(&mut interpreter, &content, &canonical(filename));
+    if let Some(code) = interpreter.exit_code() {
+        return code;
+    }
+    match super::run_repl_with(interpreter) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("REPL exited with error: {}", e);
+            1
+        }
     }
 }
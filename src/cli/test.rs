@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::interpreter::NiklError;
+use crate::lexer::Lexer;
+use crate::modules::testing::run_registered_tests;
+use crate::packages::Workspace;
+use crate::parser::{Parser, Stmt};
+use super::coverage::Coverage;
+use super::files::{canonical, collect_scripts};
+use super::output;
+
+
+/// `nikl test [--coverage] [--lcov <out>] [paths...]`, runs every `.nk` file under `tests/`, of
+/// every member at the root of a workspace, (or the given paths) in its own interpreter, then the tests it registered with `testing.test` and its
+/// top-level `test_*` functions
+/// `--coverage` reports the lines the tests ran, in them and the modules they import,
+/// `--lcov` also writes them as an lcov tracefile
+/// Returns the process exit code, 1 when a test or a file fails, 2 when the paths themselves are invalid
+pub fn run_tests(paths: &[String], coverage: bool, lcov: Option<&str>) -> i32 {
+    let coverage = (coverage || lcov.is_some()).then(|| Arc::new(Coverage::default()));
+
+    let default_paths = default_test_paths();
+    let paths = if paths.is_empty() { &default_paths[..] } else { paths };
+    let scripts = match collect_scripts(paths) {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    for path in &scripts {
+        if !output::quiet() {
+            println!("running {}", path.display());
+        }
+        match run_test_file(path, coverage.as_ref()) {
+            Ok(Some((file_passed, file_failed))) => {
+                passed += file_passed;
+                failed += file_failed;
+            }
+            Ok(None) if !output::quiet() => println!("no tests found"),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failed += 1;
+            }
+        }
+        if !output::quiet() {
+            println!();
+        }
+    }
+
+    let status = if failed == 0 { "ok" } else { "FAILED" };
+    println!("{}. {} passed; {} failed in {} file(s)", status, passed, failed, scripts.len());
+    if let Some(coverage) = coverage {
+        print!("{}", coverage.summary());
+        if let Some(Err(e)) = lcov.map(|out| coverage.write_lcov(out)) {
+            eprintln!("{}", e);
+            return 2;
+        }
+    }
+    i32::from(failed > 0)
+}
+
+/// `tests`, and at the root of a workspace the `tests` of its members that have them
+fn default_test_paths() -> Vec<String> {
+    let dir = std::env::current_dir().unwrap_or_default();
+    let Some(workspace) = Workspace::find(&dir).ok().flatten().filter(|workspace| workspace.is_root(&dir)) else {
+        return vec!["tests".to_string()];
+    };
+    let dirs = std::iter::once(PathBuf::from("tests")).chain(workspace.members.iter().map(|member| member.dir.join("tests")));
+    let paths: Vec<String> = dirs.filter(|dir| dir.is_dir()).map(|dir| dir.to_string_lossy().to_string()).collect();
+    if paths.is_empty() { vec!["tests".to_string()] } else { paths }
+}
+
+/// Loads one test file and runs its tests, `None` when it has none
+fn run_test_file(path: &Path, coverage: Option<&Arc<Coverage>>) -> Result<Option<(usize, usize)>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let tokens = Lexer::new(&source).tokenize().map_err(|e| NiklError::from(e).to_string())?;
+    let mut parser = match coverage {
+        Some(_) => Parser::with_lines(tokens, &canonical(&path.to_string_lossy())),
+        None => Parser::new(tokens),
+    };
+    let stmts = parser.parse().map_err(|e| NiklError::Parse(e).to_string())?;
+
+    let base_path = path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let mut builder = output::interpreter(base_path).argv(vec![path.display().to_string()]);
+    if let Some(coverage) = coverage {
+        builder = builder.hook(coverage.clone());
+    }
+    let mut interp = builder.build();
+    interp.run(&stmts)?;
+
+    // `test_*` functions run after the explicit registrations, unless registered under the same name
+    let registered = interp.take_registered_tests();
+    let mut names: Vec<String> = registered.iter().map(|test| test.name.clone()).collect();
+    for test in registered {
+        interp.register_test(test.name, test.func);
+    }
+    for stmt in &stmts {
+        let Stmt::Function { name, .. } = stmt else { continue };
+        if !name.starts_with("test_") || names.contains(name) {
+            continue;
+        }
+        if let Some(func) = interp.get_global(name) {
+            interp.register_test(name.clone(), func);
+            names.push(name.clone());
+        }
+    }
+    if names.is_empty() {
+        return Ok(None);
+    }
+    let summary = run_registered_tests(&mut interp);
+    Ok(Some((summary.passed, summary.failures.len())))
+}
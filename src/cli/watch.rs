@@ -0,0 +1,130 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, SystemTime};
+
+use crate::interpreter::{FileResolver, ModuleResolver};
+use super::check::{collect_imports, parse};
+use super::files::collect_scripts;
+use super::output;
+use super::run_file::script_args;
+
+
+/// How often files are checked for changes
+const POLL: Duration = Duration::from_millis(200);
+/// Changes are picked up once files have been still this long, so an editor saving several
+/// files at once causes a single run
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+
+/// `nikl watch <file.nk> [--] [args...]` or `nikl watch test [paths...]`, clears the screen and
+/// runs the script or the tests again whenever one of their files or `.nk` imports changes
+/// A run still going when files change is stopped first. Only returns on bad arguments
+pub fn watch(target: &str, args: &[String]) -> i32 {
+    let (run_args, roots): (Vec<String>, Vec<String>) = if target == "test" {
+        let paths = if args.is_empty() { vec!["tests".to_string()] } else { args.to_vec() };
+        (std::iter::once(target.to_string()).chain(args.iter().cloned()).collect(), paths)
+    } else if target.ends_with(".nk") {
+        let run_args = [target.to_string(), "--".to_string()].into_iter().chain(script_args(args).iter().cloned()).collect();
+        (run_args, vec![target.to_string()])
+    } else {
+        eprintln!("Error: expected a script ending with .nk or `test`, got '{}'", target);
+        return 2;
+    };
+    // The global flags go first, after the script they would be its arguments
+    let run_args: Vec<String> = output::flags().into_iter().chain(run_args).collect();
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Error: can't find the nikl executable: {}", e);
+            return 2;
+        }
+    };
+
+    loop {
+        let files = match watched_files(&roots) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return 2;
+            }
+        };
+        let stamps = stamps(&files);
+
+        // Clear the screen and move the cursor to the top
+        print!("\x1b[2J\x1b[H");
+        println!("[watch] nikl {}", run_args.join(" "));
+        let mut child = Command::new(&exe).args(&run_args).spawn().ok();
+        if child.is_none() {
+            eprintln!("[watch] failed to start nikl");
+        }
+
+        wait_for_change(&files, stamps, &mut child);
+        if let Some(mut child) = child {
+            // The run is stale, it may also never finish on its own
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Polls until a file changes and then stays unchanged for `DEBOUNCE`, reporting how the run ends
+fn wait_for_change(files: &BTreeSet<PathBuf>, mut last: HashMap<PathBuf, Option<(SystemTime, u64)>>, child: &mut Option<Child>) {
+    let mut changed_at = None;
+    loop {
+        std::thread::sleep(POLL);
+        if let Some(running) = child {
+            if let Ok(Some(status)) = running.try_wait() {
+                match status.code() {
+                    Some(code) => println!("[watch] exited with {}, waiting for changes", code),
+                    None => println!("[watch] stopped, waiting for changes"),
+                }
+                *child = None;
+            }
+        }
+
+        let current = stamps(files);
+        if current != last {
+            last = current;
+            changed_at = Some(std::time::Instant::now());
+        } else if changed_at.is_some_and(|at| at.elapsed() >= DEBOUNCE) {
+            return;
+        }
+    }
+}
+
+/// Modification time and size of every file, `None` for files that can't be read
+fn stamps(files: &BTreeSet<PathBuf>) -> HashMap<PathBuf, Option<(SystemTime, u64)>> {
+    files
+        .iter()
+        .map(|file| {
+            let stamp = std::fs::metadata(file).ok().and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+            (file.clone(), stamp)
+        })
+        .collect()
+}
+
+/// The scripts under `roots` and every `.nk` module they import, directly or not
+fn watched_files(roots: &[String]) -> Result<BTreeSet<PathBuf>, String> {
+    let mut files = BTreeSet::new();
+    let mut pending = collect_scripts(roots)?;
+    while let Some(file) = pending.pop() {
+        let file = std::fs::canonicalize(&file).unwrap_or(file);
+        if !files.insert(file.clone()) {
+            continue;
+        }
+        // Files that don't parse are still watched, fixing them starts the next run
+        let Some(stmts) = std::fs::read_to_string(&file).ok().and_then(|source| parse(&source).ok()) else {
+            continue;
+        };
+        let mut imports = Vec::new();
+        collect_imports(&stmts, &mut imports);
+        let base_path = file.parent().unwrap_or_else(|| Path::new("."));
+        for import in imports.into_iter().filter(|import| import.ends_with(".nk")) {
+            if let Ok(module) = FileResolver.resolve(base_path, import) {
+                pending.push(PathBuf::from(module.id));
+            }
+        }
+    }
+    Ok(files)
+}
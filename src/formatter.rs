@@ -0,0 +1,263 @@
+//! Canonical source formatting, used by `nikl fmt`
+//!
+//! Works on the token stream rather than the AST so comments and the author's line breaks
+//! survive: only indentation, spacing and blank lines are normalized. The result is parsed
+//! again and must give the same program, otherwise the source is left alone.
+
+use crate::interpreter::NiklError;
+use crate::lexer::{Comment, Lexer, Token, TokenKind};
+use crate::parser::Parser;
+
+
+const INDENT: &str = "    ";
+
+
+/// Returns `source` formatted, fails with the lex or parse error if it isn't a valid program
+///
+/// ```
+/// let formatted = nikl::formatter::format_source("fn add(a,b){\nreturn a+b // sum\n}").unwrap();
+/// assert_eq!(formatted, "fn add(a, b) {\n    return a + b // sum\n}\n");
+/// ```
+pub fn format_source(source: &str) -> Result<String, String> {
+    let (tokens, comments) = Lexer::new(source).tokenize_with_comments().map_err(|e| NiklError::from(e).to_string())?;
+    let program = Parser::new(tokens.clone()).parse().map_err(|e| NiklError::Parse(e).to_string())?;
+
+    let formatted = Formatter::new(source).format(&tokens, &comments);
+
+    let reparsed = Lexer::new(&formatted)
+        .tokenize()
+        .map_err(NiklError::from)
+        .and_then(|tokens| Parser::new(tokens).parse().map_err(NiklError::Parse));
+    if reparsed.as_ref() != Ok(&program) {
+        return Err("Formatting would change the program, the source was left as is".to_string());
+    }
+    Ok(formatted)
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Group {
+    Paren,
+    Bracket,
+    Block,
+    Map,
+}
+
+/// A token or comment in source order
+enum Item<'a> {
+    Token(&'a Token),
+    Comment(&'a Comment),
+}
+
+struct Formatter<'a> {
+    source_lines: Vec<&'a str>,
+    out: Vec<String>,
+    line: String,
+    groups: Vec<Group>,
+    /// The last token written is a `-` negating its operand rather than subtracting
+    negation: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { source_lines: source.lines().collect(), out: Vec::new(), line: String::new(), groups: Vec::new(), negation: false }
+    }
+
+    fn format(mut self, tokens: &[Token], comments: &[Comment]) -> String {
+        let mut items: Vec<(usize, usize, Item)> = tokens
+            .iter()
+            .filter(|token| token.kind != TokenKind::Eof)
+            .map(|token| (start_line(token), token.column, Item::Token(token)))
+            .chain(comments.iter().map(|comment| (comment.line, comment.column, Item::Comment(comment))))
+            .collect();
+        items.sort_by_key(|(line, column, _)| (*line, *column));
+
+        let mut last_line = None;
+        let mut prev: Option<&Token> = None;
+        let mut prev_operand = false;
+        for (line, _, item) in &items {
+            let new_line = last_line.is_some_and(|last| *line > last);
+            if new_line {
+                self.end_line(*line - last_line.unwrap_or(*line) > 1);
+            }
+            match item {
+                Item::Comment(comment) => {
+                    if !self.line.is_empty() {
+                        self.line.push(' ');
+                    } else {
+                        self.indent(0);
+                    }
+                    self.line.push_str(&comment.text);
+                    last_line = Some(*line);
+                }
+                Item::Token(token) => {
+                    let closes = matches!(token.kind, TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace);
+                    if self.line.is_empty() {
+                        self.indent(closes as usize);
+                    } else if self.space_between(prev, prev_operand, token) {
+                        self.line.push(' ');
+                    }
+                    self.line.push_str(&self.token_text(token));
+
+                    match token.kind {
+                        TokenKind::LeftParen => self.groups.push(Group::Paren),
+                        TokenKind::LeftBracket => self.groups.push(Group::Bracket),
+                        TokenKind::LeftBrace => self.groups.push(if prev_operand || is_block_keyword(prev) { Group::Block } else { Group::Map }),
+                        _ if closes => {
+                            self.groups.pop();
+                        }
+                        _ => {}
+                    }
+                    self.negation = token.kind == TokenKind::Subtract && !prev_operand;
+                    // `thread.spawn` uses a keyword as a member name
+                    let member = prev.is_some_and(|p| p.kind == TokenKind::Dot);
+                    prev_operand = member || ends_operand(&token.kind);
+                    prev = Some(token);
+                    last_line = Some(token.line);
+                }
+            }
+        }
+        if !self.line.is_empty() {
+            self.end_line(false);
+        }
+        while self.out.last().is_some_and(|line| line.is_empty()) {
+            self.out.pop();
+        }
+        if self.out.is_empty() {
+            return String::new();
+        }
+        self.out.join("\n") + "\n"
+    }
+
+    fn end_line(&mut self, blank_after: bool) {
+        let line = std::mem::take(&mut self.line);
+        self.out.push(line.trim_end().to_string());
+        if blank_after {
+            self.out.push(String::new());
+        }
+    }
+
+    fn indent(&mut self, dedent: usize) {
+        let depth = self.groups.len().saturating_sub(dedent);
+        self.line.push_str(&INDENT.repeat(depth));
+    }
+
+    fn space_between(&self, prev: Option<&Token>, prev_operand: bool, token: &Token) -> bool {
+        use TokenKind::*;
+        let Some(prev) = prev else { return false };
+        let inner_group = self.groups.last().copied();
+        match (&prev.kind, &token.kind) {
+            (LeftBrace, RightBrace) => false,
+            (LeftBrace, _) => inner_group == Some(Group::Block),
+            (_, RightBrace) => inner_group == Some(Group::Block),
+            (_, Comma | RightParen | RightBracket | Dot | Colon) => false,
+            (LeftParen | LeftBracket | Dot, _) => false,
+            (Subtract, _) if self.negation => false,
+            (_, LeftParen) => !prev_operand,
+            _ => true,
+        }
+    }
+
+    fn token_text(&self, token: &Token) -> String {
+        use TokenKind::*;
+        match &token.kind {
+            Identifier(name) => name.clone(),
+            StringLiteral(value) => format!("\"{}\"", value),
+            // Numbers keep their spelling, e.g. `1.50`
+            IntegerLiteral(_) | FloatLiteral(_) => self.number_text(token),
+            BooleanLiteral(true) => "True".to_string(),
+            BooleanLiteral(false) => "False".to_string(),
+            Let => "let".to_string(),
+            Const => "const".to_string(),
+            Function => "fn".to_string(),
+            Import => "import".to_string(),
+            Pub => "pub".to_string(),
+            As => "as".to_string(),
+            In => "in".to_string(),
+            For => "for".to_string(),
+            While => "while".to_string(),
+            Loop => "loop".to_string(),
+            Break => "break".to_string(),
+            Continue => "continue".to_string(),
+            Spawn => "spawn".to_string(),
+            Wait => "wait".to_string(),
+            If => "if".to_string(),
+            ElseIf => "elif".to_string(),
+            Else => "else".to_string(),
+            And => "and".to_string(),
+            Or => "or".to_string(),
+            Not => "not".to_string(),
+            Return => "return".to_string(),
+            Delete => "del".to_string(),
+            Integer => "Int".to_string(),
+            Float => "Float".to_string(),
+            String => "String".to_string(),
+            Boolean => "Bool".to_string(),
+            Array => "Array".to_string(),
+            Tuple => "Tuple".to_string(),
+            HashMap => "HashMap".to_string(),
+            Assign => "=".to_string(),
+            Equals => "==".to_string(),
+            NotEqual => "!=".to_string(),
+            Divide => "/".to_string(),
+            Multiply => "*".to_string(),
+            Subtract => "-".to_string(),
+            Add => "+".to_string(),
+            LessThan => "<".to_string(),
+            GreaterThan => ">".to_string(),
+            LessThanOrEqual => "<=".to_string(),
+            GreaterThanOrEqual => ">=".to_string(),
+            LeftParen => "(".to_string(),
+            RightParen => ")".to_string(),
+            LeftBrace => "{".to_string(),
+            RightBrace => "}".to_string(),
+            LeftBracket => "[".to_string(),
+            RightBracket => "]".to_string(),
+            Comma => ",".to_string(),
+            Colon => ":".to_string(),
+            Arrow => "->".to_string(),
+            Dot => ".".to_string(),
+            Eof => std::string::String::new(),
+        }
+    }
+
+    /// The literal as written, read back from the source like the lexer reads it
+    fn number_text(&self, token: &Token) -> String {
+        let line = self.source_lines.get(token.line - 1).copied().unwrap_or("");
+        let mut dots = 0;
+        line.chars()
+            .skip(token.column - 1)
+            .take_while(|&c| {
+                if c == '.' {
+                    dots += 1;
+                    dots == 1
+                } else {
+                    c.is_ascii_digit()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Tokens are recorded on the line they end on, only strings can span lines
+fn start_line(token: &Token) -> usize {
+    match &token.kind {
+        TokenKind::StringLiteral(value) => token.line - value.matches('\n').count(),
+        _ => token.line,
+    }
+}
+
+/// Whether an expression can end with this token, so a following `(` is a call and `-` is binary
+fn ends_operand(kind: &TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(
+        kind,
+        Identifier(_) | StringLiteral(_) | IntegerLiteral(_) | FloatLiteral(_) | BooleanLiteral(_)
+            | RightParen | RightBracket | RightBrace
+            | Integer | Float | String | Boolean | Array | Tuple | HashMap
+    )
+}
+
+fn is_block_keyword(prev: Option<&Token>) -> bool {
+    prev.is_some_and(|token| matches!(token.kind, TokenKind::Else | TokenKind::Loop))
+}
@@ -0,0 +1,295 @@
+//! Interpreter configuration: which builtins and internal modules scripts can use,
+//! where imports resolve from, and how much work a script may do
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::engine::Interpreter;
+use super::environment::Environment;
+use super::hooks::ExecutionHook;
+use super::resolver::{FileResolver, ModuleResolver};
+use super::stdio::{InputSource, OutputWriter, Stdio};
+use super::value::Value;
+
+
+/// Internal modules that touch the host system, removed by `InterpreterBuilder::sandboxed`
+pub const SYSTEM_MODULES: &[&str] = &[
+    "os", "subprocess", "network", "server", "db", "sqlite", "archive", "async", "thread", "timer", "sys",
+];
+
+/// Builtins that end or block the host process, removed by `InterpreterBuilder::sandboxed`
+pub const SYSTEM_BUILTINS: &[&str] = &["exit", "input", "sleep"];
+
+/// Module functions that do the same as a builtin, `(module, function, builtin)`,
+/// left out of the module when the builtin is disabled
+const BUILTIN_ALIASES: &[(&str, &str, &str)] = &[("time", "sleep", "sleep")];
+
+/// Internal modules whose results depend on the clock, the machine or thread scheduling,
+/// removed in deterministic mode along with `input`
+pub const NONDETERMINISTIC_MODULES: &[&str] = &[
+    "time", "datetime", "bench", "os", "subprocess", "network", "server", "db", "sys", "thread", "async", "timer",
+];
+
+
+/// Builds an `Interpreter`, `Interpreter::new` is the same as `InterpreterBuilder::new(path).build()`
+///
+/// ```
+/// use std::path::PathBuf;
+/// use nikl::InterpreterBuilder;
+///
+/// let mut interp = InterpreterBuilder::new(PathBuf::from("."))
+///     .sandboxed()
+///     .max_steps(10_000)
+///     .build();
+/// let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("import \"os\" as os").tokenize().unwrap()).parse().unwrap();
+/// assert!(interp.run(&stmts).is_err());
+/// ```
+pub struct InterpreterBuilder {
+    base_path: PathBuf,
+    argv: Vec<String>,
+    resolver: Arc<dyn ModuleResolver>,
+    disabled_builtins: HashSet<String>,
+    disabled_modules: HashSet<String>,
+    max_call_depth: Option<usize>,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+    deterministic: bool,
+    no_color: bool,
+    stdin: InputSource,
+    stdout: Option<OutputWriter>,
+    stderr: Option<OutputWriter>,
+    hook: Option<Arc<dyn ExecutionHook>>,
+}
+
+impl InterpreterBuilder {
+    /// File imports resolve relative to `base_path`
+    pub fn new(base_path: PathBuf) -> Self {
+        Self {
+            base_path,
+            argv: Vec::new(),
+            resolver: Arc::new(FileResolver),
+            disabled_builtins: HashSet::new(),
+            disabled_modules: HashSet::new(),
+            max_call_depth: None,
+            max_steps: None,
+            timeout: None,
+            deterministic: false,
+            no_color: false,
+            stdin: InputSource::Stdin,
+            stdout: None,
+            stderr: None,
+            hook: None,
+        }
+    }
+
+    pub fn base_path(mut self, base_path: PathBuf) -> Self {
+        self.base_path = base_path;
+        self
+    }
+
+    /// Arguments exposed as `sys.argv`, see `Interpreter::set_argv`
+    pub fn argv(mut self, argv: Vec<String>) -> Self {
+        self.argv = argv;
+        self
+    }
+
+    /// Loads `.nk` imports, `FileResolver` by default
+    pub fn resolver(mut self, resolver: impl ModuleResolver + 'static) -> Self {
+        self.resolver = Arc::new(resolver);
+        self
+    }
+
+    /// Removes a builtin function such as `exit`, scripts see it as undefined
+    pub fn disable_builtin(mut self, name: &str) -> Self {
+        self.disabled_builtins.insert(name.to_string());
+        self
+    }
+
+    /// Makes `import "<name>"` of an internal module fail
+    pub fn disable_module(mut self, name: &str) -> Self {
+        self.disabled_modules.insert(name.to_string());
+        self
+    }
+
+    /// Disables the modules in `SYSTEM_MODULES` and builtins in `SYSTEM_BUILTINS`,
+    /// leaving pure computation, data formats and host registered functions
+    pub fn sandboxed(mut self) -> Self {
+        self.disabled_modules.extend(SYSTEM_MODULES.iter().map(|m| m.to_string()));
+        self.disabled_builtins.extend(SYSTEM_BUILTINS.iter().map(|b| b.to_string()));
+        self
+    }
+
+    /// Fails a call that would nest user functions deeper than `depth`
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = Some(depth);
+        self
+    }
+
+    /// Fails the script after `steps` statements and loop iterations, counted across
+    /// every function, module and thread the interpreter runs
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Fails the script once `timeout` has passed since `build()`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Disables the modules in `NONDETERMINISTIC_MODULES` and `input`,
+    /// so the same script always produces the same result
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Turns `term` styling off like the `NO_COLOR` variable, e.g. for a `--no-color` flag
+    /// Scripts can still force colors with `term.set_colors(True)`
+    pub fn no_color(mut self, no_color: bool) -> Self {
+        self.no_color = no_color;
+        self
+    }
+
+    /// Where `input()` reads lines from, the process stdin by default
+    pub fn stdin(mut self, source: InputSource) -> Self {
+        self.stdin = source;
+        self
+    }
+
+    /// Where `print` and module reports write, the process stdout by default
+    pub fn stdout(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.stdout = Some(Box::new(writer));
+        self
+    }
+
+    /// Where `eprint` and background errors write, the process stderr by default
+    pub fn stderr(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.stderr = Some(Box::new(writer));
+        self
+    }
+
+    /// Calls `hook` as scripts run, see `ExecutionHook`
+    pub fn hook(mut self, hook: Arc<dyn ExecutionHook>) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let mut disabled_modules = self.disabled_modules;
+        let mut disabled_builtins = self.disabled_builtins;
+        if self.deterministic {
+            disabled_modules.extend(NONDETERMINISTIC_MODULES.iter().map(|m| m.to_string()));
+            disabled_builtins.insert("input".to_string());
+        }
+
+        let policy = Policy {
+            disabled_builtins,
+            disabled_modules,
+            max_call_depth: self.max_call_depth,
+            max_steps: self.max_steps,
+            timeout: self.timeout,
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+            steps: AtomicU64::new(0),
+            hook: self.hook,
+            no_color: self.no_color,
+        };
+        let stdio = Stdio::new(self.stdin, self.stdout, self.stderr);
+        Interpreter::from_parts(self.base_path, self.argv, self.resolver, policy, stdio)
+    }
+}
+
+
+/// What a built interpreter allows, shared by every interpreter it creates for
+/// functions, modules and threads
+#[derive(Debug)]
+pub(crate) struct Policy {
+    disabled_builtins: HashSet<String>,
+    disabled_modules: HashSet<String>,
+    max_call_depth: Option<usize>,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    steps: AtomicU64,
+    hook: Option<Arc<dyn ExecutionHook>>,
+    no_color: bool,
+}
+
+impl Policy {
+    /// The same restrictions with the step count and time limit starting over
+    pub fn restarted(&self) -> Self {
+        Self {
+            disabled_builtins: self.disabled_builtins.clone(),
+            disabled_modules: self.disabled_modules.clone(),
+            max_call_depth: self.max_call_depth,
+            max_steps: self.max_steps,
+            timeout: self.timeout,
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+            steps: AtomicU64::new(0),
+            hook: self.hook.clone(),
+            no_color: self.no_color,
+        }
+    }
+
+    pub fn hook(&self) -> Option<Arc<dyn ExecutionHook>> {
+        self.hook.clone()
+    }
+
+    pub fn no_color(&self) -> bool {
+        self.no_color
+    }
+
+    /// A global environment with the disabled builtins removed
+    pub fn global_env(&self) -> Environment {
+        let mut env = Environment::new();
+        for name in &self.disabled_builtins {
+            // Unknown names have nothing to remove
+            let _ = env.delete(name);
+        }
+        env
+    }
+
+    pub fn check_module(&self, name: &str) -> Result<(), String> {
+        if self.disabled_modules.contains(name) {
+            return Err(format!("Module '{}' is disabled", name));
+        }
+        Ok(())
+    }
+
+    /// Takes the functions standing in for disabled builtins out of an internal module
+    pub fn strip_module(&self, name: &str, module: &mut Value) {
+        let Value::HashMap(pairs) = module else {
+            return;
+        };
+        for (_, function, _) in BUILTIN_ALIASES.iter().filter(|(m, _, b)| *m == name && self.disabled_builtins.contains(*b)) {
+            pairs.retain(|(key, _)| !matches!(key, Value::String(key) if key == function));
+        }
+    }
+
+    pub fn check_call_depth(&self, depth: usize) -> Result<(), String> {
+        match self.max_call_depth {
+            Some(max) if depth > max => Err(format!("Maximum call depth of {} exceeded", max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts one step of work against the step and time limits
+    pub fn tick(&self) -> Result<(), String> {
+        if let Some(max) = self.max_steps {
+            if self.steps.fetch_add(1, Ordering::Relaxed) >= max {
+                return Err(format!("Step limit of {} exceeded", max));
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.timeout) {
+            if Instant::now() >= deadline {
+                return Err(format!("Time limit of {:?} exceeded", timeout));
+            }
+        }
+        Ok(())
+    }
+}
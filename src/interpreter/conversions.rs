@@ -0,0 +1,258 @@
+//! Conversions between NIKL values and Rust types for embedders
+//! `From` builds values from Rust data, `FromNikl` (or `TryFrom<Value>`) extracts typed data
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::value::Value;
+
+
+fn mismatch(expected: &str, value: &Value) -> String {
+    format!("Expected {}, got {}", expected, value.type_name())
+}
+
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::Integer(value as i64)
+            }
+        }
+    )*};
+}
+
+from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Null
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Entries are sorted by key, so the resulting hashmap is deterministic
+impl<K: Into<String>, V: Into<Value>> From<HashMap<K, V>> for Value {
+    fn from(map: HashMap<K, V>) -> Self {
+        let sorted: BTreeMap<String, Value> = map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        Value::from(sorted)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Value::HashMap(map.into_iter().map(|(k, v)| (Value::String(k.into()), v.into())).collect())
+    }
+}
+
+impl<A: Into<Value>, B: Into<Value>> From<(A, B)> for Value {
+    fn from((a, b): (A, B)) -> Self {
+        Value::Tuple(vec![a.into(), b.into()])
+    }
+}
+
+impl<A: Into<Value>, B: Into<Value>, C: Into<Value>> From<(A, B, C)> for Value {
+    fn from((a, b, c): (A, B, C)) -> Self {
+        Value::Tuple(vec![a.into(), b.into(), c.into()])
+    }
+}
+
+
+/// Extracts a Rust value out of a NIKL value, failing with a message naming both types
+///
+/// ```
+/// use std::collections::HashMap;
+/// use nikl::{FromNikl, Value};
+///
+/// let value = Value::from(vec![1, 2, 3]);
+/// let numbers = Vec::<i64>::from_nikl(value).unwrap();
+/// assert_eq!(numbers, vec![1, 2, 3]);
+///
+/// let scores: HashMap<String, f64> = Value::from(HashMap::from([("a", 1.5)])).extract().unwrap();
+/// assert_eq!(scores["a"], 1.5);
+/// ```
+pub trait FromNikl: Sized {
+    fn from_nikl(value: Value) -> Result<Self, String>;
+}
+
+impl Value {
+    /// Shorthand for `T::from_nikl(value)`
+    pub fn extract<T: FromNikl>(self) -> Result<T, String> {
+        T::from_nikl(self)
+    }
+}
+
+impl FromNikl for Value {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        Ok(value)
+    }
+}
+
+impl FromNikl for i64 {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Integer(i) => Ok(i),
+            other => Err(mismatch("Integer", &other)),
+        }
+    }
+}
+
+macro_rules! from_nikl_narrow_integer {
+    ($($ty:ty),*) => {$(
+        impl FromNikl for $ty {
+            fn from_nikl(value: Value) -> Result<Self, String> {
+                let i = i64::from_nikl(value)?;
+                <$ty>::try_from(i).map_err(|_| format!("Integer {} does not fit in {}", i, stringify!($ty)))
+            }
+        }
+    )*};
+}
+
+from_nikl_narrow_integer!(i8, i16, i32, u8, u16, u32, u64, usize);
+
+/// Integers are widened, like in NIKL arithmetic
+impl FromNikl for f64 {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Float(f) => Ok(f),
+            Value::Integer(i) => Ok(i as f64),
+            other => Err(mismatch("Float", &other)),
+        }
+    }
+}
+
+impl FromNikl for bool {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            other => Err(mismatch("Boolean", &other)),
+        }
+    }
+}
+
+impl FromNikl for String {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(mismatch("String", &other)),
+        }
+    }
+}
+
+impl FromNikl for () {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Null => Ok(()),
+            other => Err(mismatch("None", &other)),
+        }
+    }
+}
+
+/// None becomes `None`, anything else must convert to `T`
+impl<T: FromNikl> FromNikl for Option<T> {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_nikl(other).map(Some),
+        }
+    }
+}
+
+/// Accepts arrays and tuples
+impl<T: FromNikl> FromNikl for Vec<T> {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Array(items) | Value::Tuple(items) => items.into_iter().map(T::from_nikl).collect(),
+            other => Err(mismatch("Array", &other)),
+        }
+    }
+}
+
+fn map_entries<T: FromNikl>(value: Value) -> Result<Vec<(String, T)>, String> {
+    match value {
+        Value::HashMap(pairs) => pairs
+            .into_iter()
+            .map(|(k, v)| Ok((String::from_nikl(k)?, T::from_nikl(v)?)))
+            .collect(),
+        other => Err(mismatch("HashMap", &other)),
+    }
+}
+
+impl<T: FromNikl> FromNikl for HashMap<String, T> {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        map_entries(value).map(|entries| entries.into_iter().collect())
+    }
+}
+
+impl<T: FromNikl> FromNikl for BTreeMap<String, T> {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        map_entries(value).map(|entries| entries.into_iter().collect())
+    }
+}
+
+impl<A: FromNikl, B: FromNikl> FromNikl for (A, B) {
+    fn from_nikl(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Tuple(items) | Value::Array(items) if items.len() == 2 => {
+                let mut items = items.into_iter();
+                Ok((A::from_nikl(items.next().unwrap())?, B::from_nikl(items.next().unwrap())?))
+            }
+            other => Err(mismatch("Tuple of 2", &other)),
+        }
+    }
+}
+
+
+macro_rules! try_from_value {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = String;
+
+            fn try_from(value: Value) -> Result<Self, String> {
+                <$ty>::from_nikl(value)
+            }
+        }
+    )*};
+}
+
+try_from_value!(i64, i32, u32, u64, usize, f64, bool, String);
@@ -1,10 +1,21 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use crate::parser::{Expr, Stmt};
 use crate::lexer::TokenKind;
-use super::environment::Environment;
-use super::value::Value;
+use super::builder::{InterpreterBuilder, Policy};
+use super::environment::{Environment, VariableEntry};
+use super::error::NiklError;
+use super::host::HostModule;
+use super::isolate::Prelude;
+use super::resolver::ModuleResolver;
+use super::snapshot;
+use super::stdio::{InputSource, Stdio, Stream};
+use super::value::{HostFunction, Value};
+use super::methods;
+use super::tasks::{self, Task};
 use crate::modules;
 
 
@@ -12,6 +23,54 @@ pub struct Interpreter {
     env: Environment,
     loaded_modules: HashSet<String>,
     base_path: PathBuf,
+    argv: Arc<[String]>,
+    /// `testing.test` registrations, shared with module and function interpreters
+    tests: Arc<Mutex<Vec<RegisteredTest>>>,
+    /// Code passed to `exit()`, shared like `tests` so the exit reaches the host from any function
+    exit_code: Arc<Mutex<Option<i32>>>,
+    /// Modules registered by the embedder, importable from every file of the script
+    host_modules: Arc<HashMap<String, Value>>,
+    /// Loads the source of `.nk` imports
+    resolver: Arc<dyn ModuleResolver>,
+    /// Builtins, modules and limits set by `InterpreterBuilder`
+    policy: Arc<Policy>,
+    /// Standard input, output and error of the script
+    stdio: Arc<Stdio>,
+    /// Number of user function calls this interpreter is nested in
+    depth: usize,
+    /// The last `Stmt::Line` this interpreter ran, where its statements fail
+    line: Option<(Arc<str>, usize)>,
+    /// Message, file and line of the latest runtime error in a program with line markers,
+    /// shared like `exit_code` so the line inside a function reaches the host
+    error_line: Arc<Mutex<Option<ErrorLine>>>,
+}
+
+
+/// Where a runtime error came from, see `Interpreter::error_line`
+#[derive(Debug)]
+struct ErrorLine {
+    message: String,
+    file: Arc<str>,
+    line: usize,
+}
+
+
+/// Where a method receiver was read from, with the containers on the way already evaluated,
+/// so mutating methods can store the result back without evaluating the receiver again
+enum Place {
+    Variable(String),
+    /// An entry of a hashmap, read by `map.key` or `map.get(key)`, stored back into `parent`
+    Entry { container: Vec<(Value, Value)>, key: Value, parent: Box<Place> },
+    /// Temporaries such as `[1, 2].push(3)` have nowhere to be stored
+    Temporary,
+}
+
+
+/// A test registered by a script with `testing.test(name, fn)`
+#[derive(Debug, Clone)]
+pub struct RegisteredTest {
+    pub name: String,
+    pub func: Value,
 }
 
 
@@ -27,14 +86,303 @@ pub enum ControlFlow {
 
 
 impl Interpreter {
+    /// An interpreter with every builtin and module available and no limits
     pub fn new(base_path: PathBuf) -> Self {
+        InterpreterBuilder::new(base_path).build()
+    }
+
+    pub fn builder(base_path: PathBuf) -> InterpreterBuilder {
+        InterpreterBuilder::new(base_path)
+    }
+
+    pub(crate) fn from_parts(
+        base_path: PathBuf,
+        argv: Vec<String>,
+        resolver: Arc<dyn ModuleResolver>,
+        policy: Policy,
+        stdio: Stdio,
+    ) -> Self {
         Self {
-            env: Environment::new(),
+            env: policy.global_env(),
             loaded_modules: HashSet::new(),
             base_path,
+            argv: argv.into(),
+            tests: Arc::new(Mutex::new(Vec::new())),
+            exit_code: Arc::new(Mutex::new(None)),
+            host_modules: Arc::new(HashMap::new()),
+            resolver,
+            policy: Arc::new(policy),
+            stdio: Arc::new(stdio),
+            depth: 0,
+            line: None,
+            error_line: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Sets the command-line arguments exposed to scripts as `sys.argv`
+    /// By convention the first entry is the script path
+    pub fn set_argv(&mut self, argv: Vec<String>) {
+        self.argv = argv.into();
+    }
+
+    pub fn argv(&self) -> &[String] {
+        &self.argv
+    }
+
+    /// Defines or replaces a global variable, e.g. to seed configuration before running a script
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::{Interpreter, Value};
+    ///
+    /// let mut interp = Interpreter::new(PathBuf::from("."));
+    /// interp.set_global("limit", 10);
+    /// # let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("let doubled = limit * 2").tokenize().unwrap()).parse().unwrap();
+    /// # interp.run(&stmts).unwrap();
+    /// assert_eq!(interp.get_global("doubled"), Some(Value::Integer(20)));
+    /// ```
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        // Global definitions can't fail, `define` only returns a Result for future checks
+        let _ = self.env.define(name, value.into(), true);
+    }
+
+    /// Defines or replaces a global that scripts can read but not assign to
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::Interpreter;
+    ///
+    /// let mut interp = Interpreter::new(PathBuf::from("."));
+    /// interp.set_const("limit", 10);
+    /// assert!(interp.eval("limit = 20").is_err());
+    /// ```
+    pub fn set_const(&mut self, name: &str, value: impl Into<Value>) {
+        let _ = self.env.define(name, value.into(), false);
+    }
+
+    /// Reads a variable from the global scope, falling back to builtins like `get` does for scripts
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.env.get(name)
+    }
+
+    /// The variables visible where this interpreter runs, by name, builtins left out
+    /// Inside a function these are its locals and parameters plus the variables it closes over
+    pub fn variables(&self) -> BTreeMap<String, Value> {
+        self.env.user_variables().into_iter().map(|(name, entry)| (name, entry.value().clone())).collect()
+    }
+
+    /// How many function calls deep this interpreter runs, 0 at the top level of a script or module
+    pub fn call_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Saves the global variables and functions defined so far, see `restore`
+    /// Imported modules, native objects and host functions are left out
+    pub fn snapshot(&self) -> Result<Vec<u8>, String> {
+        snapshot::save(&self.env)
+    }
+
+    /// Defines the globals saved by `snapshot` in this interpreter, replacing variables with the same name
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::{Interpreter, Value};
+    ///
+    /// let mut session = Interpreter::new(PathBuf::from("."));
+    /// session.run_ast(&nikl::parser::Parser::new(nikl::lexer::Lexer::new("let count = 2\nfn bump(n) { return n + 1 }").tokenize().unwrap()).parse().unwrap()).unwrap();
+    /// let saved = session.snapshot().unwrap();
+    ///
+    /// let mut resumed = Interpreter::new(PathBuf::from("."));
+    /// resumed.restore(&saved).unwrap();
+    /// assert_eq!(resumed.eval("bump(count)").unwrap(), Value::Integer(3));
+    /// ```
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let policy = self.policy.clone();
+        snapshot::load(&mut self.env, bytes, &|| policy.global_env())
+    }
+
+    /// Defines a global function backed by a Rust closure, which can capture application state.
+    /// Its errors can be `NiklError` or anything converting into it, like `String`
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::{Interpreter, NiklError, Value};
+    ///
+    /// let mut interp = Interpreter::new(PathBuf::from("."));
+    /// let factor = 3;
+    /// interp.register_fn("scale", move |_, args| match args.as_slice() {
+    ///     [Value::Integer(n)] => Ok(Value::Integer(n * factor)),
+    ///     _ => Err(NiklError::Runtime("scale expects an integer".to_string())),
+    /// }).unwrap();
+    /// ```
+    pub fn register_fn<F, E>(&mut self, name: &str, func: F) -> Result<(), String>
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<NiklError>,
+    {
+        self.env.define(name, Value::HostFunction(HostFunction::new(name, func)), false)
+    }
+
+    /// Replaces how `.nk` imports are loaded, for this interpreter and the modules and functions it runs later
+    pub fn set_resolver(&mut self, resolver: impl ModuleResolver + 'static) {
+        self.resolver = Arc::new(resolver);
+    }
+
+    /// Makes `module` importable with `import "<name>" as alias`, ahead of internal modules
+    pub fn register_module(&mut self, name: &str, module: HostModule) {
+        Arc::make_mut(&mut self.host_modules).insert(name.to_string(), module.into_value());
+    }
+
+    /// Replaces the script's standard output, shared with every interpreter this one created
+    pub fn set_stdout(&self, writer: impl Write + Send + 'static) {
+        self.stdio.set_stdout(Box::new(writer));
+    }
+
+    pub fn set_stderr(&self, writer: impl Write + Send + 'static) {
+        self.stdio.set_stderr(Box::new(writer));
+    }
+
+    /// Runs `f` with the script's standard output, the process stdout unless the host replaced it
+    pub fn with_stdout<T>(&self, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+        self.stdio.with_stdout(f)
+    }
+
+    pub fn with_stderr<T>(&self, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+        self.stdio.with_stderr(f)
+    }
+
+    /// Whether the script's `stream` is a terminal, never for writers and inputs the host configured
+    pub(crate) fn is_terminal(&self, stream: Stream) -> bool {
+        self.stdio.is_terminal(stream)
+    }
+
+    /// Colors forced on or off by `term.set_colors`, kept with the script's streams so other
+    /// interpreters in the process keep their own
+    pub(crate) fn forced_colors(&self) -> Option<bool> {
+        self.stdio.forced_colors()
+    }
+
+    pub(crate) fn force_colors(&self, colors: Option<bool>) {
+        self.stdio.force_colors(colors);
+    }
+
+    /// Whether the host turned colors off with `InterpreterBuilder::no_color`
+    pub(crate) fn no_color(&self) -> bool {
+        self.policy.no_color()
+    }
+
+    /// Replaces where `input()` reads from, `InputSource::None` makes it fail for headless hosts
+    pub fn set_stdin(&self, source: InputSource) {
+        self.stdio.set_stdin(source);
+    }
+
+    /// Reads a line for `input()`, showing `prompt` the way the input source expects
+    pub fn read_input(&self, prompt: &str) -> Result<String, String> {
+        self.stdio.read_line(prompt)
+    }
+
+    pub fn register_test(&self, name: String, func: Value) {
+        self.tests.lock().unwrap_or_else(|e| e.into_inner()).push(RegisteredTest { name, func });
+    }
+
+    /// Records the code passed to `exit()`, the builtin then fails so the script unwinds to the host
+    pub(crate) fn request_exit(&self, code: i32) {
+        *self.exit_code.lock().unwrap_or_else(|e| e.into_inner()) = Some(code);
+    }
+
+    /// The code the script passed to `exit()`, if it called it
+    /// A script that exits fails with an `exit(<code>)` error, hosts check this to tell it apart
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::Interpreter;
+    ///
+    /// let mut interp = Interpreter::new(PathBuf::from("."));
+    /// let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("exit(3)\nprint(1)").tokenize().unwrap()).parse().unwrap();
+    /// assert_eq!(interp.run(&stmts).unwrap_err(), "exit(3)");
+    /// assert_eq!(interp.exit_code(), Some(3));
+    /// ```
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// File and line of the statement a runtime error came from, for programs parsed with
+    /// `Parser::with_lines` and the modules they import. `None` when `error` isn't the latest error
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::Interpreter;
+    ///
+    /// let tokens = nikl::lexer::Lexer::new("fn f() {\n    return missing\n}\nf()").tokenize().unwrap();
+    /// let program = nikl::parser::Parser::with_lines(tokens, "main.nk").parse().unwrap();
+    /// let mut interp = Interpreter::new(PathBuf::from("."));
+    /// let error = interp.run(&program).unwrap_err();
+    /// assert_eq!(interp.error_line(&error), Some(("main.nk".to_string(), 2)));
+    /// ```
+    pub fn error_line(&self, error: &str) -> Option<(String, usize)> {
+        let error_line = self.error_line.lock().unwrap_or_else(|e| e.into_inner());
+        match &*error_line {
+            Some(recorded) if recorded.message == error => Some((recorded.file.to_string(), recorded.line)),
+            _ => None,
+        }
+    }
+
+    /// Removes and returns the tests registered so far, in registration order
+    pub fn take_registered_tests(&self) -> Vec<RegisteredTest> {
+        std::mem::take(&mut *self.tests.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// An interpreter running `env` that shares this one's script-wide state: imports, argv,
+    /// tests, exit code, host modules, resolver, policy, streams and error line
+    /// Detached interpreters, imported modules, function calls and isolates start from it
+    fn child(&self, env: Environment) -> Self {
+        Self {
+            env,
+            loaded_modules: self.loaded_modules.clone(),
+            base_path: self.base_path.clone(),
+            argv: self.argv.clone(),
+            tests: self.tests.clone(),
+            exit_code: self.exit_code.clone(),
+            host_modules: self.host_modules.clone(),
+            resolver: self.resolver.clone(),
+            policy: self.policy.clone(),
+            stdio: self.stdio.clone(),
+            depth: self.depth,
+            line: None,
+            error_line: self.error_line.clone(),
+        }
+    }
+
+    /// A fresh interpreter with the same base path, imports and argv, used to run
+    /// functions on another thread without sharing this one's state
+    pub fn detached(&self) -> Self {
+        Self { depth: 0, ..self.child(self.policy.global_env()) }
+    }
+
+    /// Freezes the builtins and globals defined so far into a prelude for cheap isolates,
+    /// see `Prelude::isolate`
+    pub fn into_prelude(self) -> Prelude {
+        let scope = self.env.flatten();
+        Prelude::new(self, scope)
+    }
+
+    pub(crate) fn isolate(&self, shared: Arc<HashMap<String, VariableEntry>>) -> Self {
+        Self {
+            tests: Arc::new(Mutex::new(Vec::new())),
+            exit_code: Arc::new(Mutex::new(None)),
+            policy: Arc::new(self.policy.restarted()),
+            stdio: Arc::new(Stdio::new(InputSource::Stdin, None, None)),
+            depth: 0,
+            error_line: Arc::new(Mutex::new(None)),
+            ..self.child(Environment::with_shared(shared))
+        }
+    }
+
+    /// Wraps the interpreter so it can be driven from multiple threads or tokio tasks
+    pub fn into_shared(self) -> super::SharedInterpreter {
+        Arc::new(std::sync::Mutex::new(self))
+    }
+
     pub fn run(&mut self, stmts: &[Stmt]) -> Result<ControlFlow, String> {
         for stmt in stmts {
             match self.exec_stmt(stmt)? {
@@ -45,7 +393,76 @@ impl Interpreter {
         Ok(ControlFlow::Value)
     }
 
+    /// Evaluates a single expression in the current global scope, e.g. a config value or a REPL line
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use nikl::{Interpreter, NiklError, Value};
+    ///
+    /// let mut interp = Interpreter::new(PathBuf::from("."));
+    /// interp.set_global("price", 20);
+    /// assert_eq!(interp.eval("price * 2 + 2").unwrap(), Value::Integer(42));
+    /// assert!(matches!(interp.eval("price +"), Err(NiklError::Parse(_))));
+    /// assert!(matches!(interp.eval("missing"), Err(NiklError::Runtime(_))));
+    /// ```
+    pub fn eval(&mut self, source: &str) -> Result<Value, NiklError> {
+        let tokens = crate::lexer::Lexer::new(source).tokenize()?;
+        let expr = crate::parser::Parser::new(tokens).parse_expression().map_err(NiklError::Parse)?;
+        self.policy.tick().map_err(NiklError::Runtime)?;
+        self.eval_expr(&expr).map_err(NiklError::Runtime)
+    }
+
+    /// Runs a whole program and returns its result: the value of a top-level `return`,
+    /// otherwise the value of the final statement when it is an expression, otherwise None
+    pub fn run_program(&mut self, stmts: &[Stmt]) -> Result<Value, String> {
+        let mut last = Value::Null;
+        for stmt in stmts {
+            if let Stmt::Expr(expr) = stmt {
+                self.policy.tick()?;
+                last = self.eval_expr(expr).inspect_err(|e| self.record_error_line(e))?;
+                continue;
+            }
+            last = Value::Null;
+            match self.exec_stmt(stmt)? {
+                ControlFlow::Value => continue,
+                ControlFlow::Return(val) => return Ok(val),
+                ControlFlow::Break | ControlFlow::Continue => break,
+            }
+        }
+        Ok(last)
+    }
+
+    /// Runs a program built with the `Expr`/`Stmt` constructors or produced by `Parser::parse`,
+    /// returning its result like `run_program`
+    pub fn run_ast(&mut self, program: &[Stmt]) -> Result<Value, String> {
+        self.run_program(program)
+    }
+
     fn exec_stmt(&mut self, stmt: &Stmt) -> Result<ControlFlow, String> {
+        let result = self.exec_stmt_unlocated(stmt);
+        if let Err(e) = &result {
+            self.record_error_line(e);
+        }
+        result
+    }
+
+    /// Notes the line an error came from, the innermost statement records it first and the
+    /// outer ones see the same message
+    fn record_error_line(&self, error: &str) {
+        let Some((file, line)) = &self.line else { return };
+        let mut error_line = self.error_line.lock().unwrap_or_else(|e| e.into_inner());
+        if error_line.as_ref().is_none_or(|recorded| recorded.message != error) {
+            *error_line = Some(ErrorLine { message: error.to_string(), file: file.clone(), line: *line });
+        }
+    }
+
+    fn exec_stmt_unlocated(&mut self, stmt: &Stmt) -> Result<ControlFlow, String> {
+        // Line markers aren't statements of the program, they don't count against `max_steps`
+        if let Stmt::Line { file, line } = stmt {
+            self.line = Some((file.clone(), *line));
+            return self.handle_line(file, *line);
+        }
+        self.policy.tick()?;
         match stmt {
             Stmt::Let { name, value } => self.handle_let(name, value),
             Stmt::Const { name, value } => self.handle_const(name, value),
@@ -60,9 +477,17 @@ impl Interpreter {
             Stmt::If { condition, body, else_if_branches, else_body } => self.handle_if(condition, body, else_if_branches, else_body.as_ref()),
             Stmt::Import { path, alias } => self.handle_import(path, alias),
             Stmt::Return(expr) => self.handle_return(expr),
+            Stmt::Line { .. } => unreachable!("line markers are handled before the step count"),
         }
     }
 
+    fn handle_line(&mut self, file: &str, line: usize) -> Result<ControlFlow, String> {
+        if let Some(hook) = self.policy.hook() {
+            hook.on_line(self, file, line)?;
+        }
+        Ok(ControlFlow::Value)
+    }
+
     fn handle_let(&mut self, name: &str, value: &Expr) -> Result<ControlFlow, String> {
         if self.env.is_defined(name) {
             return Err(format!("Variable '{}' already defined in this scope", name));
@@ -81,16 +506,16 @@ impl Interpreter {
         Ok(ControlFlow::Value)
     }
 
-    fn handle_function(&mut self, name: &String, params: &Vec<String>, body: &Vec<Stmt>) -> Result<ControlFlow, String> {
+    fn handle_function(&mut self, name: &str, params: &[String], body: &[Stmt]) -> Result<ControlFlow, String> {
         if self.env.is_defined(name) {
             return Err(format!("Function '{}' already defined in this scope", name));
         }
         // TODO: Check if the function name is valid
         let func = Value::Function {
-            name: name.clone(),
-            params: params.clone(),
-            body: body.clone(),
-            closure: self.env.clone(),
+            name: name.to_string(),
+            params: params.to_vec(),
+            body: body.to_vec(),
+            closure: Arc::new(self.env.clone()),
         };
         self.env.define(name, func, true)?;
         Ok(ControlFlow::Value)
@@ -98,6 +523,7 @@ impl Interpreter {
 
     fn handle_loop(&mut self, body: &Vec<Stmt>) -> Result<ControlFlow, String> {
         loop {
+            self.policy.tick()?;
             for stmt in body {
                 match self.exec_stmt(stmt)? {
                     ControlFlow::Break => return Ok(ControlFlow::Value),
@@ -111,6 +537,7 @@ impl Interpreter {
 
     fn handle_while(&mut self, condition: &Expr, body: &Vec<Stmt>) -> Result<ControlFlow, String> {
         while let Value::Bool(true) = self.eval_expr(condition)? {
+            self.policy.tick()?;
             for stmt in body {
                 match self.exec_stmt(stmt)? {
                     ControlFlow::Break => return Ok(ControlFlow::Value),
@@ -147,15 +574,16 @@ impl Interpreter {
                 }
             }
             Value::Array(elements) => {
-                // There should be only one name in the names vector
-                if names.len() != 1 {
-                    return Err(format!("'for' loop requires exactly one name for type 'Array', got {:?}", names));
+                // With several names every element is unpacked, e.g. `for i, item in enumerate(arr)`
+                if names.is_empty() {
+                    return Err(format!("'for' loop requires at least one name for type 'Array', got {:?}", names));
+                }
+                for name in names {
+                    // For loop's variable will overwrite any existing variable/constant with the same name
+                    self.env.define(name, Value::Null, true)?; // mutable
                 }
-                let name = &names[0];
-                // For loop's variable will overwrite any existing variable/constant with the same name
-                self.env.define(name, Value::Null, true)?; // mutable
                 for elem in elements {
-                    self.env.assign(name, elem.clone())?;
+                    self.assign_loop_names(names, elem)?;
                     for stmt in body {
                         match self.exec_stmt(stmt)? {
                             ControlFlow::Break => return Ok(ControlFlow::Value),
@@ -210,11 +638,44 @@ impl Interpreter {
                     }
                 }
             }
+            Value::Native(object) => {
+                // Items are pulled one at a time, so e.g. a file is read line by line
+                for name in names {
+                    // For loop's variable will overwrite any existing variable/constant with the same name
+                    self.env.define(name, Value::Null, true)?; // mutable
+                }
+                while let Some(item) = object.next_item()? {
+                    self.assign_loop_names(names, item)?;
+                    for stmt in body {
+                        match self.exec_stmt(stmt)? {
+                            ControlFlow::Break => return Ok(ControlFlow::Value),
+                            ControlFlow::Continue => break, // Skip to next iteration
+                            ControlFlow::Value => continue,
+                            cf => return Ok(cf), // Return bubbles up
+                        }
+                    }
+                }
+            }
             _ => return Err(format!("'for' loop requires an iterable, got {:?}", iter_val)),
         }
         Ok(ControlFlow::Value)
     }
 
+    fn assign_loop_names(&mut self, names: &[String], elem: Value) -> Result<(), String> {
+        if names.len() == 1 {
+            return self.env.assign(&names[0], elem);
+        }
+        match elem {
+            Value::Tuple(parts) | Value::Array(parts) if parts.len() == names.len() => {
+                for (name, part) in names.iter().zip(parts) {
+                    self.env.assign(name, part)?;
+                }
+                Ok(())
+            }
+            other => Err(format!("Cannot unpack {:?} into {} names {:?}", other, names.len(), names)),
+        }
+    }
+
     fn handle_if(&mut self, condition: &Expr, body: &Vec<Stmt>, else_if_branches: &Vec<(Expr, Vec<Stmt>)>, else_body: Option<&Vec<Stmt>>) -> Result<ControlFlow, String> {
         // This "if" will update the variable in the current environment also
         let cond_val = self.eval_expr(condition)?;
@@ -266,57 +727,56 @@ impl Interpreter {
             return Err(format!("Module '{}' already loaded", path));
         }
 
+        if let Some(module) = self.host_modules.get(path) {
+            self.env.define(alias, module.clone(), false)?;
+            self.loaded_modules.insert(path.clone());
+            return Ok(ControlFlow::Value);
+        }
+
         // Add Internal modules like os, network, regex, etc.
-        match path.as_str() {
-            "os" => {
-                let module = modules::make_os_module();
-                self.env.define(alias, module, false)?;
-                self.loaded_modules.insert(path.clone()); // track internal
-                return Ok(ControlFlow::Value);
-            }
-            "regex" => {
-                let module = modules::make_regex_module();
-                self.env.define(alias, module, false)?;
-                self.loaded_modules.insert(path.clone()); // track internal
-                return Ok(ControlFlow::Value);
-            }
-            _ => {
-                // Check if the module has .nk extension before moving to filesystem
-                if !path.ends_with(".nk") {
-                    return Err(format!("Module '{}' must have .nk extension, if its not an internal module", path));
-                }
+        self.policy.check_module(path)?;
+        if let Some(mut module) = modules::make_internal_module(path) {
+            self.policy.strip_module(path, &mut module);
+            // `sys.argv` belongs to this interpreter, not the process
+            if let (Value::HashMap(pairs), "sys") = (&mut module, path.as_str()) {
+                let argv = self.argv.iter().map(|arg| Value::String(arg.clone())).collect();
+                pairs.push((Value::String("argv".to_string()), Value::Array(argv)));
             }
+            self.env.define(alias, module, false)?;
+            self.loaded_modules.insert(path.clone()); // track internal
+            return Ok(ControlFlow::Value);
         }
 
-        // Resolve relative to base_path of current interpreter
-        let mut final_path = self.base_path.clone();
-        final_path.push(path); // appends e.g., "os.nk"
-
-        // Normalize path to avoid duplicates
-        let canonical = std::fs::canonicalize(&final_path)
-            .map_err(|_| format!("Failed to read module '{}'", final_path.display()))?;
+        // Check if the module has .nk extension before moving to the resolver
+        if !path.ends_with(".nk") {
+            return Err(format!("Module '{}' must have .nk extension, if its not an internal module", path));
+        }
 
-        if self.loaded_modules.contains(canonical.to_str().unwrap()) {
+        // Resolve relative to base_path of current interpreter
+        let module = self.resolver.resolve(&self.base_path, path)?;
+        if self.loaded_modules.contains(&module.id) {
             return Ok(ControlFlow::Value);
         }
 
-        let module_code = std::fs::read_to_string(&canonical)
-            .map_err(|_| format!("Failed to read module '{}'", canonical.display()))?;
-
-        let lexer = crate::lexer::Lexer::new(&module_code);
+        let lexer = crate::lexer::Lexer::new(&module.source);
         let tokens = lexer
             .tokenize()
             .map_err(|_| format!("Failed to tokenize module '{}'", path))?;
 
-        let mut parser = crate::parser::Parser::new(tokens);
+        // Hooks and error lines follow imported modules too
+        let mut parser = if self.policy.hook().is_some() || self.line.is_some() {
+            crate::parser::Parser::with_lines(tokens, &module.id)
+        } else {
+            crate::parser::Parser::new(tokens)
+        };
         let module_stmts = parser.parse()?;
 
         let mut module_interp = Interpreter {
-            env: Environment::new(),
             loaded_modules: HashSet::new(),
-            base_path: canonical.parent().unwrap().to_path_buf(), // <- important
+            base_path: module.base_path, // <- important
+            ..self.child(self.policy.global_env())
         };
-        module_interp.loaded_modules.insert(canonical.to_string_lossy().to_string());
+        module_interp.loaded_modules.insert(module.id.clone());
         module_interp.run(&module_stmts)?;
 
         let exports: Vec<(Value, Value)> = module_interp.env
@@ -325,13 +785,13 @@ impl Interpreter {
             .map(|(k, v)| (Value::String(k), v.value().clone()))
             .collect();
 
-        self.env.define(&alias, Value::HashMap(exports), false)?;
-        self.loaded_modules.insert(canonical.to_string_lossy().to_string());
+        self.env.define(alias, Value::HashMap(exports), false)?;
+        self.loaded_modules.insert(module.id);
 
         Ok(ControlFlow::Value)
     }
 
-    fn handle_delete(&mut self, name: &String) -> Result<ControlFlow, String> {
+    fn handle_delete(&mut self, name: &str) -> Result<ControlFlow, String> {
         self.env.delete(name)?;
         Ok(ControlFlow::Value)
     }
@@ -394,41 +854,14 @@ impl Interpreter {
                 self.eval_unary_op(op, &val)
             }
             Expr::Call { function, args } => {
-                let func_val = self.eval_expr(function)?;
-                let arg_values: Result<Vec<Value>, String> = args.iter().map(|arg| self.eval_expr(arg)).collect();
-
-                match func_val {
-                    Value::Function { name, params, body, closure } => {
-                        if params.len() != args.len() {
-                            return Err(format!(
-                                "Function '{}' expects {} arguments, got {}",
-                                name,
-                                params.len(),
-                                args.len()
-                            ));
-                        }
-
-                        let mut local_env = Environment::with_parent(closure.clone());
-                        for (param, arg_expr) in params.iter().zip(args.iter()) {
-                            let arg_val = self.eval_expr(arg_expr)?;
-                            // Parameter names will overwrite any existing variable/constant with the same name
-                            local_env.define(param, arg_val, true)?;
-                        }
-
-                        let mut local_interpreter = Interpreter {
-                            env: local_env,
-                            loaded_modules: self.loaded_modules.clone(),
-                            base_path: self.base_path.clone(),
-                        };
-
-                        match local_interpreter.run(&body)? {
-                            ControlFlow::Return(val) => Ok(val),
-                            _ => Ok(Value::Null),
-                        }
-                    }
-                    Value::BuiltinFunction(f) => f(arg_values?),
-                    _ => Err("Tried to call non-function".into()),
+                // `value.method(...)` is either a module function or a method on the value
+                if let Expr::DotAccess { object, property } = function.as_ref() {
+                    return self.eval_method_call(object, property, args);
                 }
+
+                let func_val = self.eval_expr(function)?;
+                let arg_values = self.eval_args(args)?;
+                self.call_function(func_val, arg_values)
             }
             Expr::DotAccess { object, property } => {
                 let val = self.eval_expr(object)?;
@@ -443,9 +876,164 @@ impl Interpreter {
                         }
                         Err(format!("Property '{}' not found", property))
                     }
+                    Value::Native(object) => object
+                        .get_property(property)
+                        .ok_or_else(|| format!("Property '{}' not found on {}", property, object.type_name())),
                     _ => Err(format!("Dot access on non-object value: {:?}", val)),
                 }
             }
+            Expr::Spawn(call) => self.eval_spawn(call),
+            Expr::Wait(expr) => {
+                let val = self.eval_expr(expr)?;
+                tasks::wait_value(val)
+            }
+        }
+    }
+
+    /// Evaluates the callee and arguments here, then runs the call on a detached interpreter
+    fn eval_spawn(&mut self, call: &Expr) -> Result<Value, String> {
+        let Expr::Call { function, args } = call else {
+            return Err("spawn expects a function call".to_string());
+        };
+        let func_val = self.eval_expr(function)?;
+        let arg_values = self.eval_args(args)?;
+        let mut task_interp = self.detached();
+        Ok(Task::spawn_blocking(move || task_interp.call_function(func_val, arg_values)))
+    }
+
+    fn eval_args(&mut self, args: &[Expr]) -> Result<Vec<Value>, String> {
+        args.iter().map(|arg| self.eval_expr(arg)).collect()
+    }
+
+    /// Calls a user defined or builtin function with already evaluated arguments
+    pub fn call_function(&mut self, func: Value, args: Vec<Value>) -> Result<Value, String> {
+        match func {
+            Value::Function { name, params, body, closure } => {
+                if params.len() != args.len() {
+                    return Err(format!(
+                        "Function '{}' expects {} arguments, got {}",
+                        name,
+                        params.len(),
+                        args.len()
+                    ));
+                }
+
+                self.policy.check_call_depth(self.depth + 1)?;
+
+                let mut local_env = Environment::with_parent(Arc::unwrap_or_clone(closure));
+                for (param, arg_val) in params.iter().zip(args) {
+                    // Parameter names will overwrite any existing variable/constant with the same name
+                    local_env.define(param, arg_val, true)?;
+                }
+
+                let mut local_interpreter = Interpreter { depth: self.depth + 1, ..self.child(local_env) };
+
+                // Returns are reported for failed calls too so hooks can keep a call stack
+                let hook = self.policy.hook();
+                if let Some(hook) = &hook {
+                    hook.on_call(&name);
+                }
+                let result = local_interpreter.run(&body);
+                if let Some(hook) = &hook {
+                    hook.on_return(&name);
+                }
+                match result? {
+                    ControlFlow::Return(val) => Ok(val),
+                    _ => Ok(Value::Null),
+                }
+            }
+            Value::BuiltinFunction(f) => f(self, args),
+            Value::HostFunction(f) => f.call(self, args),
+            _ => Err("Tried to call non-function".into()),
+        }
+    }
+
+    fn eval_method_call(&mut self, object: &Expr, method: &str, args: &[Expr]) -> Result<Value, String> {
+        let (receiver, place) = self.eval_place(object)?;
+        self.call_method(receiver, place, method, args)
+    }
+
+    fn call_method(&mut self, receiver: Value, place: Place, method: &str, args: &[Expr]) -> Result<Value, String> {
+        let arg_values = self.eval_args(args)?;
+
+        match receiver {
+            // Modules are hashmaps of functions, so a stored function wins over map methods,
+            // while data stored under a method's name leaves the method usable
+            Value::HashMap(mut pairs) => {
+                if let Some(func) = stored_function(&pairs, method) {
+                    return self.call_function(func, arg_values);
+                }
+                let result = methods::call_hashmap_method(&mut pairs, method, arg_values)?;
+                if methods::HASHMAP_MUTATING_METHODS.contains(&method) {
+                    self.write_back(place, Value::HashMap(pairs))?;
+                }
+                Ok(result)
+            }
+            Value::String(s) => methods::call_string_method(&s, method, arg_values),
+            Value::Array(mut items) => {
+                let result = methods::call_array_method(&mut items, method, arg_values)?;
+                if methods::ARRAY_MUTATING_METHODS.contains(&method) {
+                    self.write_back(place, Value::Array(items))?;
+                }
+                Ok(result)
+            }
+            Value::Native(object) => object.call_method(method, arg_values),
+            other => Err(format!("Dot access on non-object value: {:?}", other)),
+        }
+    }
+
+    /// Evaluates a method receiver once, noting where it came from for `write_back`
+    fn eval_place(&mut self, expr: &Expr) -> Result<(Value, Place), String> {
+        match expr {
+            Expr::Identifier(name) => Ok((self.eval_expr(expr)?, Place::Variable(name.clone()))),
+            Expr::DotAccess { object, property } => match self.eval_place(object)? {
+                (Value::HashMap(pairs), parent) => {
+                    let key = Value::String(property.clone());
+                    let value = pairs
+                        .iter()
+                        .find(|(k, _)| *k == key)
+                        .map(|(_, v)| v.clone())
+                        .ok_or_else(|| format!("Property '{}' not found", property))?;
+                    Ok((value, Place::Entry { container: pairs, key, parent: Box::new(parent) }))
+                }
+                (Value::Native(object), _) => object
+                    .get_property(property)
+                    .map(|value| (value, Place::Temporary))
+                    .ok_or_else(|| format!("Property '{}' not found on {}", property, object.type_name())),
+                (other, _) => Err(format!("Dot access on non-object value: {:?}", other)),
+            },
+            Expr::Call { function, args } => match function.as_ref() {
+                Expr::DotAccess { object, property } => {
+                    let (receiver, parent) = self.eval_place(object)?;
+                    match receiver {
+                        // `map.get(key)` of a present key is that entry, the arguments are evaluated once
+                        Value::HashMap(pairs) if property == "get" && (1..=2).contains(&args.len()) && stored_function(&pairs, property).is_none() => {
+                            let mut arg_values = self.eval_args(args)?.into_iter();
+                            let key = arg_values.next().unwrap_or(Value::Null);
+                            match pairs.iter().find(|(k, _)| *k == key).map(|(_, v)| v.clone()) {
+                                Some(value) => Ok((value, Place::Entry { container: pairs, key, parent: Box::new(parent) })),
+                                None => Ok((arg_values.next().unwrap_or(Value::Null), Place::Temporary)),
+                            }
+                        }
+                        receiver => Ok((self.call_method(receiver, parent, property, args)?, Place::Temporary)),
+                    }
+                }
+                _ => Ok((self.eval_expr(expr)?, Place::Temporary)),
+            },
+            _ => Ok((self.eval_expr(expr)?, Place::Temporary)),
+        }
+    }
+
+    /// Stores an updated value back into the variable or entry it was read from,
+    /// so methods like `arr.push(x)` behave as in-place mutation
+    fn write_back(&mut self, place: Place, value: Value) -> Result<(), String> {
+        match place {
+            Place::Variable(name) => self.env.assign(&name, value),
+            Place::Entry { mut container, key, parent } => {
+                methods::set_pair(&mut container, key, value);
+                self.write_back(*parent, Value::HashMap(container))
+            }
+            Place::Temporary => Ok(()),
         }
     }
 
@@ -567,6 +1155,12 @@ impl Interpreter {
                 TokenKind::Add => Ok(Value::String(format!("{}{}", if *l { "True" } else { "False" }, r))),
                 _ => Err(format!("Unsupported operator: {:?}", op)),
             },
+            // native, native: identity, see `PartialEq for Value`
+            (Value::Native(_), Value::Native(_)) => match op {
+                TokenKind::Equals => Ok(Value::Bool(left == right)),
+                TokenKind::NotEqual => Ok(Value::Bool(left != right)),
+                _ => Err(format!("Unsupported operator: {:?}", op)),
+            },
             _ => Err(format!("Type error: {:?} {:?} {:?}", left, op, right)),
         }
     }
@@ -574,8 +1168,18 @@ impl Interpreter {
     fn eval_unary_op(&self, op: &TokenKind, val: &Value) -> Result<Value, String> {
         match (op, val) {
             (TokenKind::Subtract, Value::Integer(i)) => Ok(Value::Integer(-i)),
+            (TokenKind::Subtract, Value::Float(f)) => Ok(Value::Float(-f)),
             (TokenKind::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
             _ => Err(format!("Unsupported unary operation: {:?} {:?}", op, val)),
         }
     }
 }
+
+
+/// A function stored in a hashmap under a method's name, which wins over the hashmap method
+fn stored_function(pairs: &[(Value, Value)], method: &str) -> Option<Value> {
+    pairs
+        .iter()
+        .find(|(k, v)| matches!(k, Value::String(s) if s == method) && v.is_callable())
+        .map(|(_, func)| func.clone())
+}
@@ -1,16 +1,38 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use super::value::Value;
 use crate::modules::builtin_core::{
     builtin_print,
+    builtin_eprint,
     builtin_len,
     builtin_str,
+    builtin_repr,
+    builtin_format,
+    builtin_hash,
+    builtin_copy,
+    builtin_deepcopy,
     builtin_int,
     builtin_float,
     builtin_bool,
     builtin_exit,
     builtin_type,
-    builtin_input
+    builtin_input,
+    builtin_sleep,
+    builtin_min,
+    builtin_max,
+    builtin_sum,
+    builtin_abs,
+    builtin_round,
+    builtin_pow,
+    builtin_ord,
+    builtin_chr,
+    builtin_enumerate,
+    builtin_zip,
+    builtin_sorted,
+    builtin_map,
+    builtin_filter,
+    builtin_reduce
 };
 
 
@@ -24,6 +46,8 @@ pub struct VariableEntry {
 pub struct Environment {
     values: HashMap<String, VariableEntry>,
     parent: Option<Box<Environment>>,
+    /// Read-only variables below the outermost scope, shared between isolates of a `Prelude`
+    shared: Option<Arc<HashMap<String, VariableEntry>>>,
 }
 
 
@@ -31,47 +55,94 @@ impl VariableEntry {
     pub fn value(&self) -> &Value {
         &self.value
     }
+
+    pub fn is_mutable(&self) -> bool {
+        self.mutable
+    }
 }
 
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
+    /// Creates a global environment, builtins live in a parent scope so scripts can shadow them
     pub fn new() -> Self {
         let mut env = Self {
             values: HashMap::new(),
             parent: None,
+            shared: None,
         };
 
         env.define("print", Value::BuiltinFunction(builtin_print), false).unwrap();
+        env.define("eprint", Value::BuiltinFunction(builtin_eprint), false).unwrap();
         env.define("len", Value::BuiltinFunction(builtin_len), false).unwrap();
         env.define("str", Value::BuiltinFunction(builtin_str), false).unwrap();
+        env.define("repr", Value::BuiltinFunction(builtin_repr), false).unwrap();
+        env.define("format", Value::BuiltinFunction(builtin_format), false).unwrap();
+        env.define("hash", Value::BuiltinFunction(builtin_hash), false).unwrap();
+        env.define("copy", Value::BuiltinFunction(builtin_copy), false).unwrap();
+        env.define("deepcopy", Value::BuiltinFunction(builtin_deepcopy), false).unwrap();
         env.define("int", Value::BuiltinFunction(builtin_int), false).unwrap();
         env.define("float", Value::BuiltinFunction(builtin_float), false).unwrap();
         env.define("bool", Value::BuiltinFunction(builtin_bool), false).unwrap();
         env.define("exit", Value::BuiltinFunction(builtin_exit), false).unwrap();
         env.define("type", Value::BuiltinFunction(builtin_type), false).unwrap();
         env.define("input", Value::BuiltinFunction(builtin_input), false).unwrap();
-        env
+        env.define("sleep", Value::BuiltinFunction(builtin_sleep), false).unwrap();
+        env.define("min", Value::BuiltinFunction(builtin_min), false).unwrap();
+        env.define("max", Value::BuiltinFunction(builtin_max), false).unwrap();
+        env.define("sum", Value::BuiltinFunction(builtin_sum), false).unwrap();
+        env.define("abs", Value::BuiltinFunction(builtin_abs), false).unwrap();
+        env.define("round", Value::BuiltinFunction(builtin_round), false).unwrap();
+        env.define("pow", Value::BuiltinFunction(builtin_pow), false).unwrap();
+        env.define("ord", Value::BuiltinFunction(builtin_ord), false).unwrap();
+        env.define("chr", Value::BuiltinFunction(builtin_chr), false).unwrap();
+        env.define("enumerate", Value::BuiltinFunction(builtin_enumerate), false).unwrap();
+        env.define("zip", Value::BuiltinFunction(builtin_zip), false).unwrap();
+        env.define("sorted", Value::BuiltinFunction(builtin_sorted), false).unwrap();
+        env.define("map", Value::BuiltinFunction(builtin_map), false).unwrap();
+        env.define("filter", Value::BuiltinFunction(builtin_filter), false).unwrap();
+        env.define("reduce", Value::BuiltinFunction(builtin_reduce), false).unwrap();
+        Self::with_parent(env)
     }
 
     pub fn with_parent(parent: Environment) -> Self {
         Self {
             values: HashMap::new(),
             parent: Some(Box::new(parent)),
+            shared: None,
         }
     }
 
+    /// A global environment over read-only `shared` variables, which replace the builtins
+    pub fn with_shared(shared: Arc<HashMap<String, VariableEntry>>) -> Self {
+        let root = Self {
+            values: HashMap::new(),
+            parent: None,
+            shared: Some(shared),
+        };
+        Self::with_parent(root)
+    }
+
     pub fn get(&self, name: &str) -> Option<Value> {
         if let Some(entry) = self.values.get(name) {
             Some(entry.value.clone())
         } else if let Some(parent) = &self.parent {
             parent.get(name)
         } else {
-            None
+            self.shared.as_ref().and_then(|shared| shared.get(name)).map(|entry| entry.value.clone())
         }
     }
 
     pub fn flatten(&self) -> HashMap<String, VariableEntry> {
         let mut map = HashMap::new();
+        if let Some(shared) = &self.shared {
+            map.extend(shared.iter().map(|(name, entry)| (name.clone(), entry.clone())));
+        }
         if let Some(parent) = &self.parent {
             map.extend(parent.flatten());
         }
@@ -79,6 +150,16 @@ impl Environment {
         map
     }
 
+    /// Like `flatten`, but without the outermost scope holding the builtins
+    pub fn user_variables(&self) -> HashMap<String, VariableEntry> {
+        let Some(parent) = &self.parent else {
+            return HashMap::new();
+        };
+        let mut map = parent.user_variables();
+        map.extend(self.values.clone());
+        map
+    }
+
     pub fn is_defined(&self, name: &str) -> bool {
         if self.values.contains_key(name) {
             true
@@ -104,6 +185,8 @@ impl Environment {
             Ok(())
         } else if let Some(parent) = self.parent.as_mut() {
             parent.assign(name, value)
+        } else if self.is_shared(name) {
+            Err(format!("Cannot assign to '{}' from the shared prelude", name))
         } else {
             Err(format!("Variable '{}' is not defined", name))
         }
@@ -114,8 +197,14 @@ impl Environment {
             Ok(())
         } else if let Some(parent) = self.parent.as_mut() {
             parent.delete(name)
+        } else if self.is_shared(name) {
+            Err(format!("Cannot delete '{}' from the shared prelude", name))
         } else {
             Err(format!("Variable '{}' is not defined", name))
         }
     }
+
+    fn is_shared(&self, name: &str) -> bool {
+        self.shared.as_ref().is_some_and(|shared| shared.contains_key(name))
+    }
 }
@@ -0,0 +1,52 @@
+//! Error returned by the embedding APIs that go from source text to a value,
+//! telling hosts which stage failed
+
+use std::fmt;
+
+use crate::lexer::LexError;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NiklError {
+    Lex(LexError),
+    Parse(String),
+    Runtime(String),
+}
+
+impl fmt::Display for NiklError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NiklError::Lex(e) => write!(f, "{}", e),
+            NiklError::Parse(e) => write!(f, "Parse error: {}", e),
+            NiklError::Runtime(e) => write!(f, "Runtime error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for NiklError {}
+
+impl From<LexError> for NiklError {
+    fn from(e: LexError) -> Self {
+        NiklError::Lex(e)
+    }
+}
+
+/// Plain string errors, like the ones builtins return, are runtime errors
+impl From<String> for NiklError {
+    fn from(e: String) -> Self {
+        NiklError::Runtime(e)
+    }
+}
+
+impl From<&str> for NiklError {
+    fn from(e: &str) -> Self {
+        NiklError::Runtime(e.to_string())
+    }
+}
+
+/// For callers that use the interpreter's plain string errors
+impl From<NiklError> for String {
+    fn from(e: NiklError) -> Self {
+        e.to_string()
+    }
+}
@@ -0,0 +1,61 @@
+//! Callbacks into a running script, for debuggers, profilers and coverage tools
+//!
+//! A hook is installed with `InterpreterBuilder::hook` and shared by every interpreter the
+//! built one creates for functions, modules and threads.
+
+use std::fmt;
+
+use super::engine::Interpreter;
+
+
+/// Called by the interpreter as it runs, every method does nothing by default
+///
+/// ```
+/// use std::path::PathBuf;
+/// use std::sync::{Arc, Mutex};
+/// use nikl::interpreter::ExecutionHook;
+/// use nikl::{Interpreter, Value};
+///
+/// #[derive(Default)]
+/// struct Lines(Mutex<Vec<usize>>);
+///
+/// impl ExecutionHook for Lines {
+///     fn on_line(&self, _interp: &mut Interpreter, _file: &str, line: usize) -> Result<(), String> {
+///         self.0.lock().unwrap().push(line);
+///         Ok(())
+///     }
+/// }
+///
+/// let lines = Arc::new(Lines::default());
+/// let mut interp = Interpreter::builder(PathBuf::from(".")).hook(lines.clone()).build();
+/// let tokens = nikl::lexer::Lexer::new("let x = 1\n\nx + 1").tokenize().unwrap();
+/// let program = nikl::parser::Parser::with_lines(tokens, "main.nk").parse().unwrap();
+/// assert_eq!(interp.run_program(&program).unwrap(), Value::Integer(2));
+/// assert_eq!(*lines.0.lock().unwrap(), vec![1, 3]);
+/// ```
+pub trait ExecutionHook: Send + Sync {
+    /// Before each statement of a program parsed with `Parser::with_lines`, and of the `.nk`
+    /// modules it imports, which are named by their resolved id
+    /// `interp` is the interpreter of the running frame so its variables can be read and
+    /// expressions evaluated, an error stops the script with that error
+    fn on_line(&self, interp: &mut Interpreter, file: &str, line: usize) -> Result<(), String> {
+        let _ = (interp, file, line);
+        Ok(())
+    }
+
+    /// When a script function starts running, on the thread that runs it
+    fn on_call(&self, function: &str) {
+        let _ = function;
+    }
+
+    /// When the function from the matching `on_call` finishes, whether or not it failed
+    fn on_return(&self, function: &str) {
+        let _ = function;
+    }
+}
+
+impl fmt::Debug for dyn ExecutionHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExecutionHook(..)")
+    }
+}
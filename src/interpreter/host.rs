@@ -0,0 +1,58 @@
+//! Host modules, groups of embedder functions and values that scripts import by name
+
+use super::engine::Interpreter;
+use super::error::NiklError;
+use super::value::{HostFunction, Value};
+
+
+/// Builder for a module registered with `Interpreter::register_module`
+///
+/// ```
+/// use std::path::PathBuf;
+/// use std::sync::{Arc, Mutex};
+/// use nikl::{HostModule, Interpreter, NiklError, Value};
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let sink = log.clone();
+/// let app = HostModule::new()
+///     .value("version", Value::String("1.2".to_string()))
+///     .function("log", move |_, args| {
+///         sink.lock().unwrap().extend(args);
+///         Ok::<_, NiklError>(Value::Null)
+///     });
+///
+/// let mut interp = Interpreter::new(PathBuf::from("."));
+/// interp.register_module("app", app);
+/// # let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("import \"app\" as app\napp.log(app.version)").tokenize().unwrap()).parse().unwrap();
+/// # interp.run(&stmts).unwrap();
+/// # assert_eq!(log.lock().unwrap().len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HostModule {
+    items: Vec<(Value, Value)>,
+}
+
+impl HostModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn function<F, E>(mut self, name: &str, func: F) -> Self
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<NiklError>,
+    {
+        self.items.push((Value::String(name.to_string()), Value::HostFunction(HostFunction::new(name, func))));
+        self
+    }
+
+    pub fn value(mut self, name: &str, value: Value) -> Self {
+        self.items.push((Value::String(name.to_string()), value));
+        self
+    }
+
+    /// The module as scripts see it, a hashmap like the internal modules
+    pub fn into_value(self) -> Value {
+        Value::HashMap(self.items)
+    }
+}
@@ -0,0 +1,44 @@
+//! Preludes and isolates for hosting many scripts at once: the prelude (builtins, imported
+//! modules and shared helpers) is set up once, every isolate gets its own globals on top of it
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::engine::Interpreter;
+use super::environment::VariableEntry;
+
+
+/// Frozen globals of a set-up interpreter, shared read-only by the isolates created from it
+///
+/// ```
+/// use std::path::PathBuf;
+/// use nikl::{Interpreter, Value};
+///
+/// let mut setup = Interpreter::new(PathBuf::from("."));
+/// # setup.run_ast(&nikl::parser::Parser::new(nikl::lexer::Lexer::new("import \"regex\" as re\nfn is_id(s) { return re.is_match(\"^[0-9]+$\", s) }").tokenize().unwrap()).parse().unwrap()).unwrap();
+/// let prelude = setup.into_prelude();
+///
+/// let mut tenant_a = prelude.isolate();
+/// let mut tenant_b = prelude.isolate();
+/// tenant_a.set_global("id", "42");
+/// assert_eq!(tenant_a.eval("is_id(id)").unwrap(), Value::Bool(true));
+/// assert_eq!(tenant_b.get_global("id"), None);
+/// ```
+pub struct Prelude {
+    /// Supplies the configuration isolates are created with
+    template: Interpreter,
+    scope: Arc<HashMap<String, VariableEntry>>,
+}
+
+impl Prelude {
+    pub(crate) fn new(template: Interpreter, scope: HashMap<String, VariableEntry>) -> Self {
+        Self { template, scope: Arc::new(scope) }
+    }
+
+    /// A fresh interpreter seeing the prelude's variables, which it can shadow but not change
+    /// Output, input, registered tests and step and time limits are its own, the rest of the
+    /// configuration comes from the interpreter the prelude was made from
+    pub fn isolate(&self) -> Interpreter {
+        self.template.isolate(self.scope.clone())
+    }
+}
@@ -0,0 +1,270 @@
+//! Methods callable on values with the dot syntax, e.g. `name.upper()`
+//! These are dispatched by the interpreter when the receiver is not a module
+
+use super::value::Value;
+
+
+/// Returns the string argument at `index`, or an error naming the method
+fn expect_string<'a>(method: &str, args: &'a [Value], index: usize) -> Result<&'a str, String> {
+    match args.get(index) {
+        Some(Value::String(s)) => Ok(s),
+        Some(other) => Err(format!("{}() expects a string argument, but got {:?}", method, other)),
+        None => Err(format!("{}() is missing argument {}", method, index + 1)),
+    }
+}
+
+fn expect_integer(method: &str, args: &[Value], index: usize) -> Result<i64, String> {
+    match args.get(index) {
+        Some(Value::Integer(i)) => Ok(*i),
+        Some(other) => Err(format!("{}() expects an integer argument, but got {:?}", method, other)),
+        None => Err(format!("{}() is missing argument {}", method, index + 1)),
+    }
+}
+
+/// Converts a possibly negative index into a position within `0..=len`
+fn resolve_index(index: i64, len: usize) -> usize {
+    if index < 0 {
+        len.saturating_sub(index.unsigned_abs() as usize)
+    } else {
+        (index as usize).min(len)
+    }
+}
+
+fn expect_arity(method: &str, args: &[Value], count: usize) -> Result<(), String> {
+    if args.len() != count {
+        return Err(format!("{}() takes exactly {} argument(s), but got {}", method, count, args.len()));
+    }
+    Ok(())
+}
+
+
+/// Calls a method on a string value
+/// Positions returned by `find` are in characters, same as string iteration
+pub fn call_string_method(s: &str, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match method {
+        "split" => {
+            let parts: Vec<Value> = match args.len() {
+                0 => s.split_whitespace().map(|p| Value::String(p.to_string())).collect(),
+                1 => s.split(expect_string(method, &args, 0)?).map(|p| Value::String(p.to_string())).collect(),
+                n => return Err(format!("split() takes at most one argument, but got {}", n)),
+            };
+            Ok(Value::Array(parts))
+        }
+        "trim" => {
+            expect_arity(method, &args, 0)?;
+            Ok(Value::String(s.trim().to_string()))
+        }
+        "upper" => {
+            expect_arity(method, &args, 0)?;
+            Ok(Value::String(s.to_uppercase()))
+        }
+        "lower" => {
+            expect_arity(method, &args, 0)?;
+            Ok(Value::String(s.to_lowercase()))
+        }
+        "replace" => {
+            expect_arity(method, &args, 2)?;
+            let from = expect_string(method, &args, 0)?;
+            let to = expect_string(method, &args, 1)?;
+            Ok(Value::String(s.replace(from, to)))
+        }
+        "starts_with" => {
+            expect_arity(method, &args, 1)?;
+            Ok(Value::Bool(s.starts_with(expect_string(method, &args, 0)?)))
+        }
+        "ends_with" => {
+            expect_arity(method, &args, 1)?;
+            Ok(Value::Bool(s.ends_with(expect_string(method, &args, 0)?)))
+        }
+        "contains" => {
+            expect_arity(method, &args, 1)?;
+            Ok(Value::Bool(s.contains(expect_string(method, &args, 0)?)))
+        }
+        "find" => {
+            expect_arity(method, &args, 1)?;
+            let needle = expect_string(method, &args, 0)?;
+            let index = s
+                .find(needle)
+                .map(|byte_idx| s[..byte_idx].chars().count() as i64)
+                .unwrap_or(-1);
+            Ok(Value::Integer(index))
+        }
+        "join" => {
+            expect_arity(method, &args, 1)?;
+            let items = match &args[0] {
+                Value::Array(items) | Value::Tuple(items) => items,
+                other => return Err(format!("join() expects an array or tuple, but got {:?}", other)),
+            };
+            let parts: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+            Ok(Value::String(parts.join(s)))
+        }
+        _ => Err(format!("String has no method '{}'", method)),
+    }
+}
+
+
+/// Array methods that modify the array they are called on
+/// The interpreter writes the updated array back to the receiver after calling these
+pub const ARRAY_MUTATING_METHODS: &[&str] = &["push", "pop", "insert", "remove", "reverse", "sort"];
+
+
+/// Calls a method on an array value, mutating `items` in place where applicable
+pub fn call_array_method(items: &mut Vec<Value>, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match method {
+        "push" => {
+            expect_arity(method, &args, 1)?;
+            items.extend(args);
+            Ok(Value::Null)
+        }
+        "pop" => {
+            expect_arity(method, &args, 0)?;
+            items.pop().ok_or_else(|| "pop() called on an empty array".to_string())
+        }
+        "insert" => {
+            expect_arity(method, &args, 2)?;
+            let index = expect_integer(method, &args, 0)?;
+            if index < 0 || index as usize > items.len() {
+                return Err(format!("insert() index {} out of range for array of length {}", index, items.len()));
+            }
+            items.insert(index as usize, args[1].clone());
+            Ok(Value::Null)
+        }
+        "remove" => {
+            expect_arity(method, &args, 1)?;
+            let index = expect_integer(method, &args, 0)?;
+            if index < 0 || index as usize >= items.len() {
+                return Err(format!("remove() index {} out of range for array of length {}", index, items.len()));
+            }
+            Ok(items.remove(index as usize))
+        }
+        "index_of" => {
+            expect_arity(method, &args, 1)?;
+            let index = items.iter().position(|v| *v == args[0]).map(|i| i as i64).unwrap_or(-1);
+            Ok(Value::Integer(index))
+        }
+        "contains" => {
+            expect_arity(method, &args, 1)?;
+            Ok(Value::Bool(items.contains(&args[0])))
+        }
+        "reverse" => {
+            expect_arity(method, &args, 0)?;
+            items.reverse();
+            Ok(Value::Null)
+        }
+        "sort" => {
+            expect_arity(method, &args, 0)?;
+            sort_values(items)?;
+            Ok(Value::Null)
+        }
+        "slice" => {
+            let start = resolve_index(expect_integer(method, &args, 0)?, items.len());
+            let end = match args.len() {
+                1 => items.len(),
+                2 => resolve_index(expect_integer(method, &args, 1)?, items.len()),
+                n => return Err(format!("slice() takes one or two arguments, but got {}", n)),
+            };
+            Ok(Value::Array(items[start..end.max(start)].to_vec()))
+        }
+        "join" => {
+            expect_arity(method, &args, 1)?;
+            let sep = expect_string(method, &args, 0)?;
+            let parts: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+            Ok(Value::String(parts.join(sep)))
+        }
+        _ => Err(format!("Array has no method '{}'", method)),
+    }
+}
+
+
+/// HashMap methods that modify the map they are called on
+pub const HASHMAP_MUTATING_METHODS: &[&str] = &["set", "remove", "merge"];
+
+
+/// Calls a method on a hashmap value, mutating `pairs` in place where applicable
+/// Keys keep their insertion order, `set` on an existing key replaces its value
+pub fn call_hashmap_method(pairs: &mut Vec<(Value, Value)>, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match method {
+        "keys" => {
+            expect_arity(method, &args, 0)?;
+            Ok(Value::Array(pairs.iter().map(|(k, _)| k.clone()).collect()))
+        }
+        "values" => {
+            expect_arity(method, &args, 0)?;
+            Ok(Value::Array(pairs.iter().map(|(_, v)| v.clone()).collect()))
+        }
+        "items" => {
+            expect_arity(method, &args, 0)?;
+            let items = pairs
+                .iter()
+                .map(|(k, v)| Value::Tuple(vec![k.clone(), v.clone()]))
+                .collect();
+            Ok(Value::Array(items))
+        }
+        "get" => {
+            let default = match args.len() {
+                1 => Value::Null,
+                2 => args[1].clone(),
+                n => return Err(format!("get() takes one or two arguments, but got {}", n)),
+            };
+            Ok(pairs
+                .iter()
+                .find(|(k, _)| *k == args[0])
+                .map_or(default, |(_, v)| v.clone()))
+        }
+        "set" => {
+            expect_arity(method, &args, 2)?;
+            let mut args = args.into_iter();
+            let (key, value) = (args.next().unwrap(), args.next().unwrap());
+            set_pair(pairs, key, value);
+            Ok(Value::Null)
+        }
+        "has" => {
+            expect_arity(method, &args, 1)?;
+            Ok(Value::Bool(pairs.iter().any(|(k, _)| *k == args[0])))
+        }
+        "remove" => {
+            expect_arity(method, &args, 1)?;
+            match pairs.iter().position(|(k, _)| *k == args[0]) {
+                Some(index) => Ok(pairs.remove(index).1),
+                None => Err(format!("remove() key {} not found", args[0])),
+            }
+        }
+        "merge" => {
+            expect_arity(method, &args, 1)?;
+            match args.into_iter().next().unwrap() {
+                Value::HashMap(other) => {
+                    for (key, value) in other {
+                        set_pair(pairs, key, value);
+                    }
+                    Ok(Value::Null)
+                }
+                other => Err(format!("merge() expects a hashmap, but got {:?}", other)),
+            }
+        }
+        "len" => {
+            expect_arity(method, &args, 0)?;
+            Ok(Value::Integer(pairs.len() as i64))
+        }
+        _ => Err(format!("Property '{}' not found", method)),
+    }
+}
+
+pub fn set_pair(pairs: &mut Vec<(Value, Value)>, key: Value, value: Value) {
+    match pairs.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, v)) => *v = value,
+        None => pairs.push((key, value)),
+    }
+}
+
+
+/// Sorts values in ascending order, failing if any two elements can't be compared
+pub fn sort_values(items: &mut [Value]) -> Result<(), String> {
+    let mut error = None;
+    items.sort_by(|a, b| {
+        a.compare(b).unwrap_or_else(|e| {
+            error.get_or_insert(e);
+            std::cmp::Ordering::Equal
+        })
+    });
+    error.map_or(Ok(()), Err)
+}
@@ -1,5 +1,42 @@
+pub mod builder;
+mod conversions;
 pub mod engine;
 pub mod environment;
+pub mod error;
+pub mod host;
+pub mod hooks;
+pub mod isolate;
+pub mod methods;
+pub mod resolver;
+mod snapshot;
+pub mod stdio;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod tasks;
 pub mod value;
 
+use std::sync::{Arc, Mutex};
+
+pub use builder::InterpreterBuilder;
+pub use conversions::FromNikl;
 pub use engine::Interpreter;
+pub use hooks::ExecutionHook;
+pub use isolate::Prelude;
+pub use error::NiklError;
+pub use resolver::{FileResolver, ModuleBundle, ModuleResolver, ResolvedModule};
+pub use stdio::{CaptureBuffer, InputSource};
+
+
+/// An interpreter that can be shared between threads or tokio tasks,
+/// e.g. a single script runtime behind a web server
+pub type SharedInterpreter = Arc<Mutex<Interpreter>>;
+
+
+// Values, environments and the interpreter itself must stay `Send + Sync`,
+// so builtins and closures stored in a `Value` can never hold `Rc`/`RefCell`
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<value::Value>();
+    assert_send_sync::<environment::Environment>();
+    assert_send_sync::<Interpreter>();
+};
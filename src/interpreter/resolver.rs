@@ -0,0 +1,159 @@
+//! Loading the source of `.nk` imports, the filesystem by default,
+//! hosts can serve modules from memory, a database or modules bundled into the binary
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+
+/// A module located by a `ModuleResolver`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedModule {
+    /// Identifies the module, a module is only run once per interpreter however it's imported
+    pub id: String,
+    pub source: String,
+    /// Imports made by the module resolve relative to this
+    pub base_path: PathBuf,
+}
+
+/// Turns an import path such as `"lib/utils.nk"` into module source
+pub trait ModuleResolver: Send + Sync {
+    /// `base_path` belongs to the importing script or module
+    fn resolve(&self, base_path: &Path, path: &str) -> Result<ResolvedModule, String>;
+}
+
+
+/// Reads modules from disk relative to the importing file, the default resolver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileResolver;
+
+impl ModuleResolver for FileResolver {
+    fn resolve(&self, base_path: &Path, path: &str) -> Result<ResolvedModule, String> {
+        let final_path = base_path.join(path);
+
+        // Normalize path to avoid duplicates
+        let canonical = std::fs::canonicalize(&final_path)
+            .map_err(|_| format!("Failed to read module '{}'", final_path.display()))?;
+        let source = std::fs::read_to_string(&canonical)
+            .map_err(|_| format!("Failed to read module '{}'", canonical.display()))?;
+
+        Ok(ResolvedModule {
+            id: canonical.to_string_lossy().to_string(),
+            source,
+            base_path: canonical.parent().map(Path::to_path_buf).unwrap_or_default(),
+        })
+    }
+}
+
+
+/// Base path of modules served from a `ModuleBundle`, keeps their relative imports inside the bundle
+pub(crate) const BUNDLE_ROOT: &str = "<bundle>";
+
+/// Modules compiled into the host binary, keyed by import path, e.g. a standard library
+/// shipped with `include_str!`, anything not in the bundle falls back to another resolver
+///
+/// ```
+/// use std::path::PathBuf;
+/// use nikl::{module_bundle, InterpreterBuilder, Value};
+///
+/// let bundle = module_bundle! {
+///     "std/greet.nk" => "import \"text.nk\" as text\nfn hello(name) { return text.prefix + name }",
+///     "std/text.nk" => "let prefix = \"Hello, \"",
+/// };
+/// let mut interp = InterpreterBuilder::new(PathBuf::from(".")).resolver(bundle).build();
+/// # let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("import \"std/greet.nk\" as greet\ngreet.hello(\"NIKL\")").tokenize().unwrap()).parse().unwrap();
+/// assert_eq!(interp.run_program(&stmts), Ok(Value::from("Hello, NIKL")));
+/// ```
+#[derive(Clone)]
+pub struct ModuleBundle {
+    modules: HashMap<String, Cow<'static, str>>,
+    fallback: Option<Arc<dyn ModuleResolver>>,
+}
+
+impl Default for ModuleBundle {
+    fn default() -> Self {
+        Self { modules: HashMap::new(), fallback: Some(Arc::new(FileResolver)) }
+    }
+}
+
+impl fmt::Debug for ModuleBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.modules.keys().collect();
+        names.sort();
+        f.debug_struct("ModuleBundle").field("modules", &names).finish_non_exhaustive()
+    }
+}
+
+impl ModuleBundle {
+    /// An empty bundle falling back to `FileResolver`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a module importable as `name`, e.g. `"std/list.nk"`
+    pub fn module(mut self, name: &str, source: impl Into<Cow<'static, str>>) -> Self {
+        self.modules.insert(normalize(Path::new(name)), source.into());
+        self
+    }
+
+    /// Resolves imports missing from the bundle with `resolver` instead of the filesystem
+    pub fn fallback(mut self, resolver: impl ModuleResolver + 'static) -> Self {
+        self.fallback = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Only serves bundled modules, other imports fail
+    pub fn exclusive(mut self) -> Self {
+        self.fallback = None;
+        self
+    }
+
+    fn get(&self, key: String) -> Option<ResolvedModule> {
+        let source = self.modules.get(&key)?;
+        let parent = Path::new(&key).parent().map(Path::to_path_buf).unwrap_or_default();
+        Some(ResolvedModule {
+            id: format!("{}/{}", BUNDLE_ROOT, key),
+            source: source.to_string(),
+            base_path: Path::new(BUNDLE_ROOT).join(parent),
+        })
+    }
+}
+
+impl ModuleResolver for ModuleBundle {
+    fn resolve(&self, base_path: &Path, path: &str) -> Result<ResolvedModule, String> {
+        // Bundled modules import their siblings relatively, scripts import bundled modules by name
+        let relative = base_path.strip_prefix(BUNDLE_ROOT).ok().and_then(|dir| self.get(normalize(&dir.join(path))));
+        if let Some(module) = relative.or_else(|| self.get(normalize(Path::new(path)))) {
+            return Ok(module);
+        }
+        match &self.fallback {
+            Some(resolver) if !base_path.starts_with(BUNDLE_ROOT) => resolver.resolve(base_path, path),
+            _ => Err(format!("Module '{}' is not in the bundle", path)),
+        }
+    }
+}
+
+/// Joins the path components with `/`, resolving `.` and `..` without touching the filesystem
+pub(crate) fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// Builds a `ModuleBundle` from `"import/path.nk" => source` pairs, usually `include_str!`s
+#[macro_export]
+macro_rules! module_bundle {
+    ($($name:expr => $source:expr),* $(,)?) => {
+        $crate::ModuleBundle::new()$(.module($name, $source))*
+    };
+}
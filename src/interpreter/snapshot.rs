@@ -0,0 +1,187 @@
+//! Saving the global variables and functions of an interpreter to bytes and loading them
+//! into another one, e.g. to persist a REPL session or resume a job
+//! Imported modules, native objects and host or builtin functions can't be saved, globals
+//! holding them are left out and have to be recreated after restoring, e.g. by importing again
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::Stmt;
+use super::environment::Environment;
+use super::value::Value;
+
+
+/// Bumped whenever the layout changes, older snapshots are rejected instead of misread
+const FORMAT_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    /// Every user function reachable from the globals, each written once
+    functions: Vec<Function>,
+    globals: Vec<Global>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Global {
+    name: String,
+    mutable: bool,
+    value: Data,
+}
+
+/// A user function, closures may only hold functions written before it
+#[derive(Serialize, Deserialize)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+    /// The variables the function closed over, minus builtins
+    closure: Vec<Captured>,
+}
+
+/// A variable in a closure, `value` is `None` when it's the same as the saved global with its name
+#[derive(Serialize, Deserialize)]
+struct Captured {
+    name: String,
+    mutable: bool,
+    value: Option<Data>,
+}
+
+/// The saveable subset of `Value`
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+enum Data {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Data>),
+    HashMap(Vec<(Data, Data)>),
+    Tuple(Vec<Data>),
+    /// Index in `Snapshot::functions`
+    Function(usize),
+    Null,
+}
+
+
+/// Collects the functions while converting values, a function is known by its closure
+/// since every definition creates a new one and copies of the function share it
+#[derive(Default)]
+struct Saver {
+    functions: Vec<Function>,
+    written: HashMap<*const Environment, usize>,
+}
+
+impl Saver {
+    fn data(&mut self, value: &Value) -> Option<Data> {
+        Some(match value {
+            Value::Integer(i) => Data::Integer(*i),
+            Value::Float(f) => Data::Float(*f),
+            Value::Bool(b) => Data::Bool(*b),
+            Value::String(s) => Data::String(s.clone()),
+            Value::Null => Data::Null,
+            Value::Array(items) => Data::Array(items.iter().map(|item| self.data(item)).collect::<Option<_>>()?),
+            Value::Tuple(items) => Data::Tuple(items.iter().map(|item| self.data(item)).collect::<Option<_>>()?),
+            Value::HashMap(pairs) => Data::HashMap(
+                pairs.iter().map(|(k, v)| Some((self.data(k)?, self.data(v)?))).collect::<Option<_>>()?,
+            ),
+            Value::Function { name, params, body, closure } => {
+                if let Some(&index) = self.written.get(&Arc::as_ptr(closure)) {
+                    return Some(Data::Function(index));
+                }
+                let captured = self
+                    .globals(closure)
+                    .into_iter()
+                    .map(|global| Captured { name: global.name, mutable: global.mutable, value: Some(global.value) })
+                    .collect();
+                self.functions.push(Function { name: name.clone(), params: params.clone(), body: body.clone(), closure: captured });
+                self.written.insert(Arc::as_ptr(closure), self.functions.len() - 1);
+                Data::Function(self.functions.len() - 1)
+            }
+            Value::BuiltinFunction(_) | Value::HostFunction(_) | Value::Native(_) => return None,
+        })
+    }
+
+    /// Every saveable variable visible in `env`, except builtins, sorted by name
+    fn globals(&mut self, env: &Environment) -> Vec<Global> {
+        let mut globals: Vec<Global> = env
+            .user_variables()
+            .into_iter()
+            .filter_map(|(name, entry)| {
+                let value = self.data(entry.value())?;
+                Some(Global { name, mutable: entry.is_mutable(), value })
+            })
+            .collect();
+        globals.sort_by(|a, b| a.name.cmp(&b.name));
+        globals
+    }
+}
+
+fn from_data(data: Data, functions: &[Value]) -> Result<Value, String> {
+    let list = |items: Vec<Data>| items.into_iter().map(|d| from_data(d, functions)).collect::<Result<Vec<_>, _>>();
+    Ok(match data {
+        Data::Integer(i) => Value::Integer(i),
+        Data::Float(f) => Value::Float(f),
+        Data::Bool(b) => Value::Bool(b),
+        Data::String(s) => Value::String(s),
+        Data::Null => Value::Null,
+        Data::Array(items) => Value::Array(list(items)?),
+        Data::Tuple(items) => Value::Tuple(list(items)?),
+        Data::HashMap(pairs) => Value::HashMap(
+            pairs
+                .into_iter()
+                .map(|(k, v)| Ok((from_data(k, functions)?, from_data(v, functions)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        Data::Function(index) => functions.get(index).cloned().ok_or("Invalid snapshot: function refers to a later one")?,
+    })
+}
+
+
+/// Serializes the saveable variables of `env`
+pub(crate) fn save(env: &Environment) -> Result<Vec<u8>, String> {
+    let mut saver = Saver::default();
+    let globals = saver.globals(env);
+    // Closures hold the globals as they were when each function was defined, mostly unchanged since
+    let saved: HashMap<&str, &Data> = globals.iter().map(|global| (global.name.as_str(), &global.value)).collect();
+    for function in &mut saver.functions {
+        for captured in &mut function.closure {
+            if captured.value.as_ref() == saved.get(captured.name.as_str()).copied() {
+                captured.value = None;
+            }
+        }
+    }
+    let snapshot = Snapshot { version: FORMAT_VERSION, functions: saver.functions, globals };
+    serde_json::to_vec(&snapshot).map_err(|e| format!("snapshot error: {}", e))
+}
+
+/// Defines the variables of a snapshot in `env`, function closures are rebuilt on top of `global_env()`
+pub(crate) fn load(env: &mut Environment, bytes: &[u8], global_env: &dyn Fn() -> Environment) -> Result<(), String> {
+    let snapshot: Snapshot = serde_json::from_slice(bytes).map_err(|e| format!("Invalid snapshot: {}", e))?;
+    if snapshot.version != FORMAT_VERSION {
+        return Err(format!("Unsupported snapshot version {}, expected {}", snapshot.version, FORMAT_VERSION));
+    }
+    let saved: HashMap<&str, &Data> = snapshot.globals.iter().map(|global| (global.name.as_str(), &global.value)).collect();
+
+    let mut functions = Vec::with_capacity(snapshot.functions.len());
+    for function in snapshot.functions {
+        let mut closure = global_env();
+        for captured in function.closure {
+            let data = match captured.value {
+                Some(data) => data,
+                None => saved
+                    .get(captured.name.as_str())
+                    .map(|&data| data.clone())
+                    .ok_or_else(|| format!("Invalid snapshot: no global '{}'", captured.name))?,
+            };
+            closure.define(&captured.name, from_data(data, &functions)?, captured.mutable)?;
+        }
+        functions.push(Value::Function { name: function.name, params: function.params, body: function.body, closure: Arc::new(closure) });
+    }
+
+    for global in snapshot.globals {
+        env.define(&global.name, from_data(global.value, &functions)?, global.mutable)?;
+    }
+    Ok(())
+}
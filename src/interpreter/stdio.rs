@@ -0,0 +1,198 @@
+//! Standard streams of a script, `input()`, `print`/`eprint` and module reports go through
+//! these so hosts and tests can supply input and capture output instead of using the process stdio
+
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+
+/// A destination for script output
+pub type OutputWriter = Box<dyn Write + Send>;
+
+/// Host callback answering `input()`, called with the prompt
+pub type InputFn = dyn FnMut(&str) -> Result<String, String> + Send;
+
+/// Where `input()` gets its lines from
+pub enum InputSource {
+    /// The process stdin, the prompt is written to the script's standard output
+    Stdin,
+    /// Lines read from a reader, e.g. a `Cursor` of canned answers, the prompt is written like for `Stdin`
+    Reader(Box<dyn BufRead + Send>),
+    /// A host callback receiving the prompt and returning the line, e.g. a GUI dialog
+    Callback(Box<InputFn>),
+    /// No input available, `input()` fails instead of blocking a headless host
+    None,
+}
+
+impl InputSource {
+    pub fn reader(reader: impl BufRead + Send + 'static) -> Self {
+        InputSource::Reader(Box::new(reader))
+    }
+
+    pub fn callback(f: impl FnMut(&str) -> Result<String, String> + Send + 'static) -> Self {
+        InputSource::Callback(Box::new(f))
+    }
+}
+
+impl fmt::Debug for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSource::Stdin => write!(f, "Stdin"),
+            InputSource::Reader(_) => write!(f, "Reader(..)"),
+            InputSource::Callback(_) => write!(f, "Callback(..)"),
+            InputSource::None => write!(f, "None"),
+        }
+    }
+}
+
+
+/// One of the standard streams, see `Stdio::is_terminal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+
+/// The standard streams of one script, shared by every interpreter it creates
+pub(crate) struct Stdio {
+    stdin: Mutex<InputSource>,
+    stdout: Mutex<OutputWriter>,
+    stderr: Mutex<OutputWriter>,
+    /// Whether output still goes to the process streams, a host writer is never a terminal
+    process_stdout: AtomicBool,
+    process_stderr: AtomicBool,
+    /// Colors forced on or off by the script with `term.set_colors`, `None` detects them
+    forced_colors: Mutex<Option<bool>>,
+}
+
+impl Stdio {
+    pub fn new(stdin: InputSource, stdout: Option<OutputWriter>, stderr: Option<OutputWriter>) -> Self {
+        Self {
+            stdin: Mutex::new(stdin),
+            process_stdout: AtomicBool::new(stdout.is_none()),
+            process_stderr: AtomicBool::new(stderr.is_none()),
+            stdout: Mutex::new(stdout.unwrap_or_else(|| Box::new(io::stdout()))),
+            stderr: Mutex::new(stderr.unwrap_or_else(|| Box::new(io::stderr()))),
+            forced_colors: Mutex::new(None),
+        }
+    }
+
+    pub fn forced_colors(&self) -> Option<bool> {
+        *self.forced_colors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn force_colors(&self, colors: Option<bool>) {
+        *self.forced_colors.lock().unwrap_or_else(|e| e.into_inner()) = colors;
+    }
+
+    /// Whether the configured stream is a terminal, only the process streams can be
+    pub fn is_terminal(&self, stream: Stream) -> bool {
+        match stream {
+            Stream::Stdin => {
+                matches!(*self.stdin.lock().unwrap_or_else(|e| e.into_inner()), InputSource::Stdin)
+                    && io::stdin().is_terminal()
+            }
+            Stream::Stdout => self.process_stdout.load(Ordering::Relaxed) && io::stdout().is_terminal(),
+            Stream::Stderr => self.process_stderr.load(Ordering::Relaxed) && io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn set_stdin(&self, source: InputSource) {
+        *self.stdin.lock().unwrap_or_else(|e| e.into_inner()) = source;
+    }
+
+    /// Reads one line, without its line ending, an empty string at the end of input
+    pub fn read_line(&self, prompt: &str) -> Result<String, String> {
+        let mut source = self.stdin.lock().unwrap_or_else(|e| e.into_inner());
+        let mut line = String::new();
+        match &mut *source {
+            InputSource::Stdin => {
+                self.write_prompt(prompt)?;
+                io::stdin().lock().read_line(&mut line)
+            }
+            InputSource::Reader(reader) => {
+                self.write_prompt(prompt)?;
+                reader.read_line(&mut line)
+            }
+            InputSource::Callback(f) => return f(prompt),
+            InputSource::None => return Err("input() is not available, no input source is configured".to_string()),
+        }
+        .map_err(|e| format!("Failed to read input: {}", e))?;
+
+        let trimmed = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(trimmed);
+        Ok(line)
+    }
+
+    fn write_prompt(&self, prompt: &str) -> Result<(), String> {
+        self.with_stdout(|out| out.write_all(prompt.as_bytes()).and_then(|_| out.flush()))
+            .map_err(|e| format!("Failed to flush stdout: {}", e))
+    }
+
+    pub fn set_stdout(&self, writer: OutputWriter) {
+        *self.stdout.lock().unwrap_or_else(|e| e.into_inner()) = writer;
+        self.process_stdout.store(false, Ordering::Relaxed);
+    }
+
+    pub fn set_stderr(&self, writer: OutputWriter) {
+        *self.stderr.lock().unwrap_or_else(|e| e.into_inner()) = writer;
+        self.process_stderr.store(false, Ordering::Relaxed);
+    }
+
+    pub fn with_stdout<T>(&self, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+        f(&mut **self.stdout.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn with_stderr<T>(&self, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+        f(&mut **self.stderr.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+
+/// In-memory writer for capturing output, clones share the same buffer
+///
+/// ```
+/// use std::path::PathBuf;
+/// use nikl::{CaptureBuffer, InterpreterBuilder};
+///
+/// let out = CaptureBuffer::new();
+/// let mut interp = InterpreterBuilder::new(PathBuf::from(".")).stdout(out.clone()).build();
+/// # let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("print(\"hi\")").tokenize().unwrap()).parse().unwrap();
+/// interp.run(&stmts).unwrap();
+/// assert_eq!(out.contents(), "hi\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CaptureBuffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CaptureBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, invalid UTF-8 is replaced
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.lock().unwrap_or_else(|e| e.into_inner())).to_string()
+    }
+
+    /// Returns everything written so far and empties the buffer
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.bytes.lock().unwrap_or_else(|e| e.into_inner()));
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+}
+
+impl Write for CaptureBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
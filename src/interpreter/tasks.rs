@@ -0,0 +1,154 @@
+//! Background tasks behind the `spawn` and `wait` keywords
+//! The interpreter itself stays synchronous: spawned work and async builtins run on the
+//! tokio runtime, and `wait` only parks the calling interpreter until a result arrives
+//! Without the `native` feature there is no runtime and spawned functions run to completion on the spot
+
+#[cfg(feature = "native")]
+use std::future::Future;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+#[cfg(feature = "native")]
+use std::sync::OnceLock;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "native")]
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
+
+use super::value::{NativeObject, Value};
+
+
+/// The multi-threaded runtime `main` runs under, or a shared one when there is none
+/// (embedders, tests, single threaded runtimes that `wait` would otherwise stall)
+#[cfg(feature = "native")]
+pub fn runtime() -> Handle {
+    static FALLBACK: OnceLock<Runtime> = OnceLock::new();
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => handle,
+        _ => FALLBACK
+            .get_or_init(|| {
+                Builder::new_multi_thread()
+                    .enable_all()
+                    .thread_name("nikl-runtime")
+                    .build()
+                    .expect("Failed to start the tokio runtime")
+            })
+            .handle()
+            .clone(),
+    }
+}
+
+
+#[derive(Debug)]
+enum TaskState {
+    Pending(Receiver<Result<Value, String>>),
+    Done(Result<Value, String>),
+}
+
+/// Handle to a running future, blocking job or thread, shown to scripts as a `Task` object
+#[derive(Debug)]
+pub struct Task {
+    state: Mutex<TaskState>,
+}
+
+impl Task {
+    fn new(receiver: Receiver<Result<Value, String>>) -> Self {
+        Task { state: Mutex::new(TaskState::Pending(receiver)) }
+    }
+
+    fn pending(receiver: Receiver<Result<Value, String>>) -> Value {
+        Value::Native(Arc::new(Task::new(receiver)))
+    }
+
+    /// Runs an async builtin on the runtime
+    #[cfg(feature = "native")]
+    pub fn spawn<F>(future: F) -> Value
+    where
+        F: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        runtime().spawn(async move {
+            let _ = sender.send(future.await);
+        });
+        Task::pending(receiver)
+    }
+
+    /// Runs synchronous work, such as a NIKL function call, on the runtime's blocking pool
+    pub fn spawn_blocking<F>(job: F) -> Value
+    where
+        F: FnOnce() -> Result<Value, String> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        #[cfg(feature = "native")]
+        runtime().spawn_blocking(move || {
+            let _ = sender.send(job());
+        });
+        #[cfg(not(feature = "native"))]
+        let _ = sender.send(job());
+        Task::pending(receiver)
+    }
+
+    /// Runs synchronous work on an OS thread of its own, made by `builder`, for work that
+    /// shouldn't take a thread of the runtime's pool, like `thread.spawn`
+    pub fn spawn_thread<F>(builder: std::thread::Builder, job: F) -> std::io::Result<Task>
+    where
+        F: FnOnce() -> Result<Value, String> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        builder.spawn(move || {
+            let _ = sender.send(job());
+        })?;
+        Ok(Task::new(receiver))
+    }
+
+    /// Blocks until the task has finished, the result is kept so waiting again is cheap
+    pub fn wait(&self) -> Result<Value, String> {
+        let mut state = self.state.lock().map_err(|_| "task handle is poisoned".to_string())?;
+        if let TaskState::Pending(receiver) = &*state {
+            let result = receiver.recv().unwrap_or_else(|_| Err("task panicked".to_string()));
+            *state = TaskState::Done(result);
+        }
+        match &*state {
+            TaskState::Done(result) => result.clone(),
+            TaskState::Pending(_) => unreachable!(),
+        }
+    }
+
+    pub fn is_done(&self) -> Result<bool, String> {
+        let mut state = self.state.lock().map_err(|_| "task handle is poisoned".to_string())?;
+        if let TaskState::Pending(receiver) = &*state {
+            match receiver.try_recv() {
+                Ok(result) => *state = TaskState::Done(result),
+                Err(TryRecvError::Disconnected) => *state = TaskState::Done(Err("task panicked".to_string())),
+                Err(TryRecvError::Empty) => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl NativeObject for Task {
+    fn type_name(&self) -> &'static str {
+        "Task"
+    }
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        if !args.is_empty() {
+            return Err(format!("{}() expects no arguments", method));
+        }
+        match method {
+            "wait" => self.wait(),
+            "is_done" => self.is_done().map(Value::Bool),
+            _ => Err(format!("Task has no method '{}'", method)),
+        }
+    }
+}
+
+
+/// `wait` on a task gives its result, on an array it waits for every element in order,
+/// anything else is already a value and is returned as is
+pub fn wait_value(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Native(object) if object.type_name() == "Task" => object.call_method("wait", Vec::new()),
+        Value::Array(items) => items.into_iter().map(wait_value).collect::<Result<_, _>>().map(Value::Array),
+        other => Ok(other),
+    }
+}
@@ -1,6 +1,11 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 use crate::parser::Stmt;
 use super::environment::Environment;
+use super::engine::Interpreter;
+use super::error::NiklError;
 
 
 #[derive(Debug, Clone)]
@@ -16,13 +21,92 @@ pub enum Value {
         name: String,
         params: Vec<String>,
         body: Vec<Stmt>,
-        closure: Environment,
+        /// The variables when the function was defined, shared by its copies
+        closure: Arc<Environment>,
     },
-    BuiltinFunction(fn(Vec<Value>) -> Result<Value, String>),
+    /// Builtin from the standard library, see `BuiltinFn`
+    BuiltinFunction(BuiltinFn),
+    /// Rust closure registered by an embedder, see `Interpreter::register_fn`
+    HostFunction(HostFunction),
+    /// Rust object handed to scripts, e.g. a database connection, shared rather than copied
+    Native(Arc<dyn NativeObject>),
     Null,
 }
 
 
+/// Signature of builtins, they get the running interpreter as context to call back into
+/// user functions, write to the script's output or read its host state
+pub type BuiltinFn = fn(&mut Interpreter, Vec<Value>) -> Result<Value, String>;
+
+/// Signature of closures registered with `Interpreter::register_fn`
+pub type HostFn = dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, String> + Send + Sync;
+
+/// A named Rust closure callable from scripts, clones share the closure and its captured state
+#[derive(Clone)]
+pub struct HostFunction {
+    name: String,
+    func: Arc<HostFn>,
+}
+
+impl HostFunction {
+    /// Wraps a closure whose errors are `NiklError` or convert into it, like `String`
+    pub fn new<F, E>(name: &str, func: F) -> Self
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<NiklError>,
+    {
+        let func = move |interp: &mut Interpreter, args: Vec<Value>| {
+            // Runtime errors keep their bare message, the interpreter adds the prefix when it reports them
+            func(interp, args).map_err(|e| match e.into() {
+                NiklError::Runtime(message) => message,
+                e => e.to_string(),
+            })
+        };
+        Self { name: name.to_string(), func: Arc::new(func) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+        (self.func)(interp, args)
+    }
+}
+
+impl fmt::Debug for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostFunction").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+
+/// Object implemented in Rust whose methods scripts call with the dot syntax
+/// Implementations that mutate state use interior mutability, since clones share the object
+pub trait NativeObject: fmt::Debug + Send + Sync {
+    /// Name reported by `type()` and used when printing the object
+    fn type_name(&self) -> &'static str;
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String>;
+
+    /// Value of `object.name` without a call, `None` when the object has no such property
+    fn get_property(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Next item when the object is used in a `for` loop, `None` ends the loop
+    fn next_item(&self) -> Result<Option<Value>, String> {
+        Err(format!("'{}' object is not iterable", self.type_name()))
+    }
+
+    /// Independent copy for `deepcopy()`, `None` for handles to something outside the script
+    /// like a file or a connection, which can't be duplicated
+    fn duplicate(&self) -> Option<Arc<dyn NativeObject>> {
+        None
+    }
+}
+
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -31,20 +115,201 @@ impl fmt::Display for Value {
             Value::Bool(b) => write!(f, "{}", if *b { "True" } else { "False" }),
             Value::String(s) => write!(f, "{}", s),
             Value::Null => write!(f, "None"),
+            // Elements use `repr` so `["a, b"]` and `["a", "b"]` print differently
             Value::Array(arr) => {
-                let items: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
+                let items: Vec<String> = arr.iter().map(|v| v.repr()).collect();
                 write!(f, "[{}]", items.join(", "))
             }
             Value::Tuple(items) => {
-                let elements: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+                let elements: Vec<String> = items.iter().map(|v| v.repr()).collect();
                 write!(f, "({})", elements.join(", "))
             }
             Value::HashMap(pairs) => {
-                let formatted: Vec<String> = pairs.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                let formatted: Vec<String> = pairs.iter().map(|(k, v)| format!("{}: {}", k.repr(), v.repr())).collect();
                 write!(f, "{{{}}}", formatted.join(", "))
             }
             Value::Function { name, .. } => write!(f, "<function {}>", name),
             Value::BuiltinFunction(_) => write!(f, "<builtin function>"),
+            Value::HostFunction(func) => write!(f, "<builtin function {}>", func.name()),
+            Value::Native(object) => write!(f, "<{} object>", object.type_name()),
+        }
+    }
+}
+
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Integer(a), Value::Float(b)) | (Value::Float(b), Value::Integer(a)) => *a as f64 == *b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::HashMap(a), Value::HashMap(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            // Native objects are only equal to themselves
+            (Value::Native(a), Value::Native(b)) => Arc::ptr_eq(a, b),
+            // Functions are never considered equal
+            _ => false,
+        }
+    }
+}
+
+
+/// Hashes the hashable variants (see `Value::is_hashable`) by writing explicit bytes,
+/// so the result only depends on the hasher and not on std's `Hash` impls
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Value::Integer(i) => {
+                state.write_u8(0);
+                state.write(&i.to_le_bytes());
+            }
+            Value::Bool(b) => {
+                state.write_u8(1);
+                state.write_u8(*b as u8);
+            }
+            Value::String(s) => {
+                state.write_u8(2);
+                state.write(&(s.len() as u64).to_le_bytes());
+                state.write(s.as_bytes());
+            }
+            Value::Tuple(items) => {
+                state.write_u8(3);
+                state.write(&(items.len() as u64).to_le_bytes());
+                for item in items {
+                    item.hash(state);
+                }
+            }
+            Value::Null => state.write_u8(4),
+            // Not hashable, callers check `is_hashable` first
+            _ => state.write_u8(u8::MAX),
+        }
+    }
+}
+
+
+/// FNV-1a hasher, its output never changes between runs or Rust versions
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
         }
     }
 }
+
+
+impl Value {
+    /// Wraps a Rust object so scripts can use its properties and methods
+    pub fn native(object: impl NativeObject + 'static) -> Value {
+        Value::Native(Arc::new(object))
+    }
+
+    /// Whether the value can be hashed: strings, integers, booleans, None and tuples of those
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Value::Integer(_) | Value::Bool(_) | Value::String(_) | Value::Null => true,
+            Value::Tuple(items) => items.iter().all(Value::is_hashable),
+            _ => false,
+        }
+    }
+
+    /// Stable hash of a hashable value, `None` for values that can't be hashed
+    pub fn stable_hash(&self) -> Option<u64> {
+        if !self.is_hashable() {
+            return None;
+        }
+        let mut hasher = StableHasher::default();
+        self.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    /// Whether the value can be called like a function
+    pub fn is_callable(&self) -> bool {
+        matches!(
+            self,
+            Value::Function { .. } | Value::BuiltinFunction(_) | Value::HostFunction(_)
+        )
+    }
+
+    /// Name of the value's type as reported by `type()`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "Integer",
+            Value::Float(_) => "Float",
+            Value::Bool(_) => "Boolean",
+            Value::String(_) => "String",
+            Value::Array(_) => "Array",
+            Value::HashMap(_) => "HashMap",
+            Value::Tuple(_) => "Tuple",
+            Value::Function { .. } => "Function",
+            Value::BuiltinFunction(_) | Value::HostFunction(_) => "BuiltinFunction",
+            Value::Native(object) => object.type_name(),
+            Value::Null => "None",
+        }
+    }
+
+    /// Unambiguous representation of the value, as written in source code
+    /// Strings are quoted and escaped, floats always keep their decimal point
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(s) => {
+                let mut out = String::with_capacity(s.len() + 2);
+                out.push('"');
+                for c in s.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        '\t' => out.push_str("\\t"),
+                        '\r' => out.push_str("\\r"),
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+                out
+            }
+            Value::Float(fl) => format!("{:?}", fl),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Orders two values of compatible types, used by sorting and comparisons
+    /// Integers and floats compare numerically, strings lexicographically
+    pub fn compare(&self, other: &Value) -> Result<Ordering, String> {
+        let ordering = match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+            (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+                for (x, y) in a.iter().zip(b.iter()) {
+                    match x.compare(y)? {
+                        Ordering::Equal => continue,
+                        ord => return Ok(ord),
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            _ => None,
+        };
+        ordering.ok_or_else(|| format!("Cannot compare {:?} with {:?}", self, other))
+    }
+}
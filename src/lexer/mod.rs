@@ -1,3 +1,3 @@
 pub mod token;
 
-pub use token::{Lexer, LexError, Token, TokenKind};
+pub use token::{keyword, Comment, Lexer, LexError, Token, TokenKind};
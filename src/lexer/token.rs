@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenKind {
     // Diclaration keywords
     Let,
@@ -71,20 +73,108 @@ pub enum TokenKind {
     Eof,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The keyword, boolean or type name token `ident` stands for, `None` for plain identifiers
+pub fn keyword(ident: &str) -> Option<TokenKind> {
+    match ident {
+        "import" => Some(TokenKind::Import),
+        "pub" => Some(TokenKind::Pub),
+        "as" => Some(TokenKind::As),
+
+        "let" => Some(TokenKind::Let),
+        "const" => Some(TokenKind::Const),
+        "fn" => Some(TokenKind::Function),
+        "spawn" => Some(TokenKind::Spawn),
+        "wait" => Some(TokenKind::Wait),
+        "return" => Some(TokenKind::Return),
+        "del" => Some(TokenKind::Delete),
+        "in" => Some(TokenKind::In),
+
+        "if" => Some(TokenKind::If),
+        "elif" => Some(TokenKind::ElseIf),
+        "else" => Some(TokenKind::Else),
+        "for" => Some(TokenKind::For),
+        "while" => Some(TokenKind::While),
+        "loop" => Some(TokenKind::Loop),
+        "break" => Some(TokenKind::Break),
+        "continue" => Some(TokenKind::Continue),
+
+        "and" => Some(TokenKind::And),
+        "or" => Some(TokenKind::Or),
+        "not" => Some(TokenKind::Not),
+
+        "True" => Some(TokenKind::BooleanLiteral(true)),
+        "False" => Some(TokenKind::BooleanLiteral(false)),
+
+        "Int" => Some(TokenKind::Integer),
+        "Float" => Some(TokenKind::Float),
+        "String" => Some(TokenKind::String),
+        "Bool" => Some(TokenKind::Boolean),
+        "Array" => Some(TokenKind::Array),
+        "Tuple" => Some(TokenKind::Tuple),
+        "HashMap" => Some(TokenKind::HashMap),
+
+        _ => None,
+    }
+}
+
+impl TokenKind {
+    /// Words the language reserves for statements and operators, e.g. `let`, `if` or `and`
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Let | TokenKind::Const | TokenKind::Function | TokenKind::Import | TokenKind::Pub
+                | TokenKind::As | TokenKind::In | TokenKind::For | TokenKind::While | TokenKind::Loop
+                | TokenKind::Break | TokenKind::Continue | TokenKind::Spawn | TokenKind::Wait
+                | TokenKind::If | TokenKind::ElseIf | TokenKind::Else | TokenKind::And | TokenKind::Or
+                | TokenKind::Not | TokenKind::Return | TokenKind::Delete
+        )
+    }
+
+    /// Type names used in annotations, e.g. `Int` or `HashMap`
+    pub fn is_type_name(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Integer | TokenKind::Float | TokenKind::String | TokenKind::Boolean
+                | TokenKind::Array | TokenKind::Tuple | TokenKind::HashMap
+        )
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenKind,
     pub line: usize,
     pub column: usize,
 }
 
-#[derive(Debug)]
+/// A `//` comment with its position, `text` includes the slashes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LexError {
     UnexpectedChar(char, usize, usize),
     UnterminatedString(usize, usize),
     InvalidNumber(String, usize, usize),
 }
 
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnexpectedChar(ch, line, col) => write!(f, "Unexpected character '{}' at line {}, column {}", ch, line, col),
+            LexError::UnterminatedString(line, col) => write!(f, "Unterminated string starting at line {}, column {}", line, col),
+            LexError::InvalidNumber(num, line, col) => write!(f, "Invalid number '{}' at line {}, column {}", num, line, col),
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
 
 pub struct Lexer<'a> {
     input: &'a str,
@@ -125,8 +215,24 @@ impl<'a> Lexer<'a> {
         });
     }
 
-    pub fn tokenize(mut self) -> Result<Vec<Token>, LexError> {
+    /// A `#!` line at the very start, as in `#!/usr/bin/env nikl`, is skipped
+    pub fn tokenize(self) -> Result<Vec<Token>, LexError> {
+        self.tokenize_with_comments().map(|(tokens, _)| tokens)
+    }
+
+    /// Like `tokenize` but also returns the `//` comments the parser never sees, for tools like the formatter
+    /// A leading shebang line is returned as a comment so it's kept
+    pub fn tokenize_with_comments(mut self) -> Result<(Vec<Token>, Vec<Comment>), LexError> {
         let mut tokens = Vec::new();
+        let mut comments = Vec::new();
+
+        if self.input.starts_with("#!") {
+            let end = self.input.find('\n').unwrap_or(self.input.len());
+            comments.push(Comment { text: self.input[..end].trim_end().to_string(), line: 1, column: 1 });
+            while self.chars.next_if(|&(i, _)| i < end).is_some() {
+                self.column += 1;
+            }
+        }
 
         while let Some(&(idx, ch)) = self.chars.peek() {
             match ch {
@@ -137,15 +243,19 @@ impl<'a> Lexer<'a> {
 
                 // Comments: //
                 '/' => {
+                    let col = self.column;
                     self.advance();
                     if let Some(&(_, '/')) = self.chars.peek() {
                         // Consume till newline
-                        while let Some(&(_, c)) = self.chars.peek() {
+                        let mut end = self.input.len();
+                        while let Some(&(i, c)) = self.chars.peek() {
                             if c == '\n' {
+                                end = i;
                                 break;
                             }
                             self.advance();
                         }
+                        comments.push(Comment { text: self.input[idx..end].trim_end().to_string(), line: self.line, column: col });
                     } else {
                         self.add_token(&mut tokens, TokenKind::Divide, self.column);
                     }
@@ -289,47 +399,7 @@ impl<'a> Lexer<'a> {
                         }
                     }
 
-                    let kind = match ident.as_str() {
-                        "import" => TokenKind::Import,
-                        "pub" => TokenKind::Pub,
-                        "as" => TokenKind::As,
-
-                        "let" => TokenKind::Let,
-                        "const" => TokenKind::Const,
-                        "fn" => TokenKind::Function,
-                        "spawn" => TokenKind::Spawn,
-                        "wait" => TokenKind::Wait,
-                        "return" => TokenKind::Return,
-                        "del" => TokenKind::Delete,
-                        "in" => TokenKind::In,
-
-                        "if" => TokenKind::If,
-                        "elif" => TokenKind::ElseIf,
-                        "else" => TokenKind::Else,
-                        "for" => TokenKind::For,
-                        "while" => TokenKind::While,
-                        "loop" => TokenKind::Loop,
-                        "break" => TokenKind::Break,
-                        "continue" => TokenKind::Continue,
-
-                        "and" => TokenKind::And,
-                        "or" => TokenKind::Or,
-                        "not" => TokenKind::Not,
-
-                        "True" => TokenKind::BooleanLiteral(true),
-                        "False" => TokenKind::BooleanLiteral(false),
-
-                        "Int" => TokenKind::Integer,
-                        "Float" => TokenKind::Float,
-                        "String" => TokenKind::String,
-                        "Bool" => TokenKind::Boolean,
-                        "Array" => TokenKind::Array,
-                        "Tuple" => TokenKind::Tuple,
-                        "HashMap" => TokenKind::HashMap,
-
-                        _ => TokenKind::Identifier(ident),
-                    };
-
+                    let kind = keyword(&ident).unwrap_or(TokenKind::Identifier(ident));
                     self.add_token(&mut tokens, kind, start_col);
                 }
 
@@ -340,6 +410,6 @@ impl<'a> Lexer<'a> {
         }
 
         self.add_token(&mut tokens, TokenKind::Eof, self.column);
-        Ok(tokens)
+        Ok((tokens, comments))
     }
 }
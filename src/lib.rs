@@ -5,16 +5,28 @@
 
 // #![warn(missing_docs)]
 
+#[cfg(feature = "repl")]
 pub mod cli;
+pub mod formatter;
+pub mod linter;
 pub mod lexer;
 pub mod parser;
 pub mod modules;
+#[cfg(feature = "native")]
 pub mod packages;
 pub mod interpreter;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 
 pub use interpreter::engine::Interpreter;
+pub use interpreter::{
+    CaptureBuffer, FileResolver, FromNikl, InputSource, InterpreterBuilder, ModuleBundle, ModuleResolver,
+    NiklError, Prelude, ResolvedModule, SharedInterpreter,
+};
 pub use interpreter::environment::Environment;
+pub use interpreter::host::HostModule;
+pub use interpreter::value::Value;
 pub use lexer::token::{Token, TokenKind};
 pub use parser::ast::{Expr, Stmt};
 
@@ -30,6 +42,20 @@ pub use parser::ast::{Expr, Stmt};
 /// run_script("print(\"Hello from NIKL!\")");
 /// ```
 pub fn run_script(source: &str) -> Result<(), String> {
+    eval_script(source).map(|_| ())
+}
+
+/// Run a script string and return its result, the value of a top-level `return`
+/// or of the final expression statement, `Value::Null` otherwise.
+///
+/// # Example
+/// ```
+/// use nikl::{eval_script, Value};
+///
+/// let value = eval_script("let x = 20\nx * 2 + 2").unwrap();
+/// assert_eq!(value, Value::Integer(42));
+/// ```
+pub fn eval_script(source: &str) -> Result<Value, String> {
     let lexer = lexer::Lexer::new(source);
     match lexer.tokenize() {
         Ok(tokens) => {
@@ -37,8 +63,8 @@ pub fn run_script(source: &str) -> Result<(), String> {
             let stmts = parser.parse().map_err(|e| e.to_string())?;
             let base_path = std::env::current_dir().map_err(|e| e.to_string())?;
             let mut interpreter = Interpreter::new(base_path);
-            interpreter.run(&stmts).map(|_| ())
+            interpreter.run_program(&stmts)
         },
-        Err(_) => Err(format!("Lexer error")),
+        Err(_) => Err("Lexer error".to_string()),
     }
 }
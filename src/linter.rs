@@ -0,0 +1,407 @@
+//! Static checks for likely mistakes, used by `nikl lint`
+//!
+//! Rules can be turned off per package in `config.json`:
+//!
+//! ```json
+//! "lint": { "shadowed-builtin": false }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::interpreter::environment::Environment;
+use crate::interpreter::NiklError;
+use crate::lexer::{Lexer, TokenKind};
+use crate::parser::{Expr, Parser, Stmt};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// A `let` or `const` that is never read, in a function or at the top level
+    UnusedVariable,
+    /// A variable, parameter, function or import alias named like a builtin, e.g. `let len = 3`
+    ShadowedBuiltin,
+    /// An `if`, `elif` or `while` condition made only of literals
+    ConstantCondition,
+    /// Statements after `return`, `break` or `continue` in the same block
+    UnreachableCode,
+    /// `==` or `!=` against a float literal, which rounding makes unreliable
+    FloatEquality,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::UnusedVariable,
+        Rule::ShadowedBuiltin,
+        Rule::ConstantCondition,
+        Rule::UnreachableCode,
+        Rule::FloatEquality,
+    ];
+
+    /// The name used in `config.json` and in reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::ShadowedBuiltin => "shadowed-builtin",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::FloatEquality => "float-equality",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+
+/// Which rules run, all of them by default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+}
+
+impl LintConfig {
+    pub fn disable(mut self, rule: Rule) -> Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    pub fn enable(mut self, rule: Rule) -> Self {
+        self.disabled.remove(&rule);
+        self
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+
+    /// Reads the `lint` section of a package's `config.json`, `//` comments are allowed
+    pub fn from_package_config(text: &str) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct Package {
+            #[serde(default)]
+            lint: HashMap<String, bool>,
+        }
+
+        let package: Package = serde_json::from_str(&strip_line_comments(text))
+            .map_err(|e| format!("Invalid config.json: {}", e))?;
+        let mut config = LintConfig::default();
+        for (name, enabled) in package.lint {
+            let rule = Rule::from_name(&name).ok_or_else(|| format!("Unknown lint rule '{}'", name))?;
+            config = if enabled { config.enable(rule) } else { config.disable(rule) };
+        }
+        Ok(config)
+    }
+}
+
+/// Drops `//` comments that aren't inside strings
+fn strip_line_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut end = line.len();
+        for (i, ch) in line.char_indices() {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '/' if !in_string && line[i..].starts_with("//") => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        out.push_str(&line[..end]);
+        out.push('\n');
+    }
+    out
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub rule: Rule,
+    pub message: String,
+    /// Line of the statement it's about, when the program was parsed with `Parser::with_lines`
+    pub line: Option<usize>,
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "{}: ", line)?;
+        }
+        write!(f, "[{}] {}", self.rule.name(), self.message)
+    }
+}
+
+
+/// Lints a script, fails with the lex or parse error if it isn't a valid program
+///
+/// ```
+/// use nikl::linter::{lint_source, LintConfig, Rule};
+///
+/// let lints = lint_source("fn f() {\n    let unused = 1\n    return 2\n}", &LintConfig::default()).unwrap();
+/// assert_eq!(lints[0].rule, Rule::UnusedVariable);
+/// assert_eq!(lints[0].message, "in function 'f': variable 'unused' is never used");
+/// assert_eq!(lints[0].line, Some(2));
+/// ```
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Lint>, String> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| NiklError::from(e).to_string())?;
+    let stmts = Parser::with_lines(tokens, "").parse().map_err(|e| NiklError::Parse(e).to_string())?;
+    Ok(lint(&stmts, config))
+}
+
+/// Lints a parsed program, in the order of the lines the findings are on
+pub fn lint(stmts: &[Stmt], config: &LintConfig) -> Vec<Lint> {
+    let mut linter = Linter { config, builtins: Environment::new(), lints: Vec::new(), function: None, line: None };
+    linter.unused_variables(stmts);
+    linter.block(stmts);
+    linter.lints.sort_by_key(|lint| lint.line);
+    linter.lints
+}
+
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    builtins: Environment,
+    lints: Vec<Lint>,
+    /// Name of the function being linted, `None` at the top level
+    function: Option<String>,
+    /// Line of the statement being linted, from the last `Stmt::Line`
+    line: Option<usize>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, message: String) {
+        self.report_at(self.line, rule, message);
+    }
+
+    fn report_at(&mut self, line: Option<usize>, rule: Rule, message: String) {
+        if !self.config.is_enabled(rule) {
+            return;
+        }
+        let message = match &self.function {
+            Some(function) => format!("in function '{}': {}", function, message),
+            None => message,
+        };
+        self.lints.push(Lint { rule, message, line });
+    }
+
+    fn declare(&mut self, kind: &str, name: &str) {
+        if self.builtins.get(name).is_some() {
+            self.report(Rule::ShadowedBuiltin, format!("{} '{}' shadows the builtin of the same name", kind, name));
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        for (i, stmt) in stmts.iter().enumerate() {
+            self.stmt(stmt);
+            let keyword = match stmt {
+                Stmt::Return(_) => "return",
+                Stmt::Break => "break",
+                Stmt::Continue => "continue",
+                _ => continue,
+            };
+            // The first dead statement is reported, the rest are still linted for other rules
+            if i + 1 < stmts.len() {
+                if let Stmt::Line { line, .. } = &stmts[i + 1] {
+                    self.line = Some(*line);
+                }
+                self.report(Rule::UnreachableCode, format!("code after '{}' is never run", keyword));
+                stmts[i + 1..].iter().for_each(|stmt| self.stmt(stmt));
+            }
+            return;
+        }
+    }
+
+    fn condition(&mut self, keyword: &str, condition: &Expr) {
+        if is_constant(condition) {
+            self.report(Rule::ConstantCondition, format!("'{}' condition is always the same", keyword));
+        }
+        self.expr(condition);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, value } | Stmt::Const { name, value } => {
+                self.declare("variable", name);
+                self.expr(value);
+            }
+            Stmt::Expr(expr) | Stmt::Return(expr) => self.expr(expr),
+            Stmt::If { condition, body, else_if_branches, else_body } => {
+                self.condition("if", condition);
+                self.block(body);
+                for (condition, body) in else_if_branches {
+                    self.condition("elif", condition);
+                    self.block(body);
+                }
+                if let Some(body) = else_body {
+                    self.block(body);
+                }
+            }
+            Stmt::Function { name, params, body } => {
+                self.declare("function", name);
+                let outer = self.function.replace(name.clone());
+                for param in params {
+                    self.declare("parameter", param);
+                }
+                self.unused_variables(body);
+                self.block(body);
+                self.function = outer;
+            }
+            Stmt::Loop(body) => self.block(body),
+            Stmt::While { condition, body } => {
+                self.condition("while", condition);
+                self.block(body);
+            }
+            Stmt::For { names, iterable, body } => {
+                for name in names {
+                    self.declare("loop variable", name);
+                }
+                self.expr(iterable);
+                self.block(body);
+            }
+            Stmt::Import { alias, .. } => self.declare("import alias", alias),
+            Stmt::Line { line, .. } => self.line = Some(*line),
+            Stmt::Delete(_) | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BinaryOp { left, op, right } => {
+                if matches!(op, TokenKind::Equals | TokenKind::NotEqual) && (is_float(left) || is_float(right)) {
+                    let op = if *op == TokenKind::Equals { "==" } else { "!=" };
+                    self.report(Rule::FloatEquality, format!("'{}' against a float is unreliable, compare the difference to a tolerance instead", op));
+                }
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Array(items) | Expr::Tuple(items) => items.iter().for_each(|item| self.expr(item)),
+            Expr::HashMap(pairs) => {
+                for (key, value) in pairs {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::Assign { value, .. } => self.expr(value),
+            Expr::UnaryOp { expr, .. } | Expr::Spawn(expr) | Expr::Wait(expr) => self.expr(expr),
+            Expr::DotAccess { object, .. } => self.expr(object),
+            Expr::Call { function, args } => {
+                self.expr(function);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            Expr::Identifier(_) | Expr::Integer(_) | Expr::Float(_) | Expr::Bool(_) | Expr::String(_) => {}
+        }
+    }
+
+    /// Variables declared in `body`, a function's or the program's, that nothing in it reads,
+    /// nested functions included since they can capture them, names starting with `_` are left alone
+    fn unused_variables(&mut self, body: &[Stmt]) {
+        let mut declared = Vec::new();
+        let mut read = HashSet::new();
+        collect_locals(body, &mut declared, &mut read);
+        for (name, line) in declared {
+            if !name.starts_with('_') && !read.contains(name) {
+                self.report_at(line, Rule::UnusedVariable, format!("variable '{}' is never used", name));
+            }
+        }
+    }
+}
+
+
+/// `let`/`const` names declared directly in this function or program with the line of their first
+/// declaration, and every name read anywhere in it
+fn collect_locals<'a>(stmts: &'a [Stmt], declared: &mut Vec<(&'a str, Option<usize>)>, read: &mut HashSet<&'a str>) {
+    let mut line = None;
+    for stmt in stmts {
+        match stmt {
+            Stmt::Let { name, value } | Stmt::Const { name, value } => {
+                if !declared.iter().any(|(declared, _)| declared == name) {
+                    declared.push((name, line));
+                }
+                collect_reads(value, read);
+            }
+            Stmt::Expr(expr) | Stmt::Return(expr) => collect_reads(expr, read),
+            Stmt::If { condition, body, else_if_branches, else_body } => {
+                collect_reads(condition, read);
+                collect_locals(body, declared, read);
+                for (condition, body) in else_if_branches {
+                    collect_reads(condition, read);
+                    collect_locals(body, declared, read);
+                }
+                if let Some(body) = else_body {
+                    collect_locals(body, declared, read);
+                }
+            }
+            Stmt::Loop(body) => collect_locals(body, declared, read),
+            Stmt::While { condition, body } => {
+                collect_reads(condition, read);
+                collect_locals(body, declared, read);
+            }
+            Stmt::For { iterable, body, .. } => {
+                collect_reads(iterable, read);
+                collect_locals(body, declared, read);
+            }
+            // Nested functions report their own locals, but their reads count for ours
+            Stmt::Function { body, .. } => collect_locals(body, &mut Vec::new(), read),
+            Stmt::Delete(name) => {
+                read.insert(name);
+            }
+            Stmt::Line { line: next, .. } => line = Some(*next),
+            Stmt::Import { .. } | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+}
+
+fn collect_reads<'a>(expr: &'a Expr, read: &mut HashSet<&'a str>) {
+    match expr {
+        Expr::Identifier(name) => {
+            read.insert(name);
+        }
+        Expr::Array(items) | Expr::Tuple(items) => items.iter().for_each(|item| collect_reads(item, read)),
+        Expr::HashMap(pairs) => {
+            for (key, value) in pairs {
+                collect_reads(key, read);
+                collect_reads(value, read);
+            }
+        }
+        Expr::Assign { value, .. } => collect_reads(value, read),
+        Expr::BinaryOp { left, right, .. } => {
+            collect_reads(left, read);
+            collect_reads(right, read);
+        }
+        Expr::UnaryOp { expr, .. } | Expr::Spawn(expr) | Expr::Wait(expr) => collect_reads(expr, read),
+        Expr::DotAccess { object, .. } => collect_reads(object, read),
+        Expr::Call { function, args } => {
+            collect_reads(function, read);
+            args.iter().for_each(|arg| collect_reads(arg, read));
+        }
+        Expr::Integer(_) | Expr::Float(_) | Expr::Bool(_) | Expr::String(_) => {}
+    }
+}
+
+/// Literals and operators over literals, which always evaluate the same way
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::Bool(_) | Expr::String(_) => true,
+        Expr::Array(items) | Expr::Tuple(items) => items.iter().all(is_constant),
+        Expr::HashMap(pairs) => pairs.iter().all(|(key, value)| is_constant(key) && is_constant(value)),
+        Expr::BinaryOp { left, right, .. } => is_constant(left) && is_constant(right),
+        Expr::UnaryOp { expr, .. } => is_constant(expr),
+        _ => false,
+    }
+}
+
+fn is_float(expr: &Expr) -> bool {
+    match expr {
+        Expr::Float(_) => true,
+        Expr::UnaryOp { op: TokenKind::Subtract, expr } => is_float(expr),
+        _ => false,
+    }
+}
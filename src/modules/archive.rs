@@ -0,0 +1,158 @@
+//! `archive` internal module for tar, tar.gz and zip files
+//! The format is picked from the file extension: `.tar`, `.tar.gz`/`.tgz` or `.zip`
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use tar::Archive;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::interpreter::value::Value;
+use crate::packages::{open_tar_gz, tar_gz_builder};
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("create_tar_gz".to_string()), Value::BuiltinFunction(create_tar_gz)),
+        (Value::String("create_zip".to_string()), Value::BuiltinFunction(create_zip)),
+        (Value::String("extract".to_string()), Value::BuiltinFunction(extract)),
+        (Value::String("list".to_string()), Value::BuiltinFunction(list)),
+    ];
+    Value::HashMap(items)
+}
+
+
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+fn format_of(path: &str) -> Result<Format, String> {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        Ok(Format::TarGz)
+    } else if lower.ends_with(".tar") {
+        Ok(Format::Tar)
+    } else if lower.ends_with(".zip") {
+        Ok(Format::Zip)
+    } else {
+        Err(format!("Unknown archive format for '{}', expected .tar, .tar.gz, .tgz or .zip", path))
+    }
+}
+
+fn expect_two_paths<'a>(name: &str, args: &'a [Value]) -> Result<(&'a str, &'a str), String> {
+    match args {
+        [Value::String(a), Value::String(b)] => Ok((a, b)),
+        _ => Err(format!("{} expects 2 string arguments", name)),
+    }
+}
+
+/// Files and directories under `dir`, with their path relative to it using `/` separators
+fn dir_entries(dir: &Path) -> io::Result<Vec<(walkdir::DirEntry, String)>> {
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+        let entry = entry.map_err(io::Error::other)?;
+        let relative = entry.path().strip_prefix(dir).map_err(io::Error::other)?;
+        let name = relative.to_string_lossy().replace('\\', "/");
+        entries.push((entry, name));
+    }
+    Ok(entries)
+}
+
+fn entry_value(path: String, size: u64, is_dir: bool) -> Value {
+    Value::HashMap(vec![
+        (Value::String("path".to_string()), Value::String(path)),
+        (Value::String("size".to_string()), Value::Integer(size as i64)),
+        (Value::String("is_dir".to_string()), Value::Bool(is_dir)),
+    ])
+}
+
+
+/// Packs the contents of `dir` into `out`, paths in the archive are relative to `dir`
+fn create_tar_gz(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (dir, out) = expect_two_paths("create_tar_gz", &args)?;
+    let write = || -> io::Result<()> {
+        let mut builder = tar_gz_builder(Path::new(out))?;
+        for (entry, name) in dir_entries(Path::new(dir))? {
+            if entry.file_type().is_dir() {
+                builder.append_dir(&name, entry.path())?;
+            } else {
+                builder.append_path_with_name(entry.path(), &name)?;
+            }
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    };
+    write().map(|_| Value::Null).map_err(|e| format!("archive.create_tar_gz error: {}", e))
+}
+
+fn create_zip(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (dir, out) = expect_two_paths("create_zip", &args)?;
+    let write = || -> zip::result::ZipResult<()> {
+        let mut writer = ZipWriter::new(File::create(out)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (entry, name) in dir_entries(Path::new(dir))? {
+            if entry.file_type().is_dir() {
+                writer.add_directory(name, options)?;
+            } else {
+                writer.start_file(name, options)?;
+                io::copy(&mut File::open(entry.path())?, &mut writer)?;
+            }
+        }
+        writer.finish()?;
+        Ok(())
+    };
+    write().map(|_| Value::Null).map_err(|e| format!("archive.create_zip error: {}", e))
+}
+
+/// Unpacks into `dest`, creating it if needed, entries that would land outside of it are refused
+fn extract(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (path, dest) = expect_two_paths("extract", &args)?;
+    let result = match format_of(path)? {
+        Format::Tar => File::open(path).and_then(|file| Archive::new(file).unpack(dest)),
+        Format::TarGz => open_tar_gz(Path::new(path)).and_then(|mut archive| archive.unpack(dest)),
+        Format::Zip => File::open(path)
+            .and_then(|file| ZipArchive::new(file).map_err(io::Error::other))
+            .and_then(|mut archive| archive.extract(dest).map_err(io::Error::other)),
+    };
+    result.map(|_| Value::Null).map_err(|e| format!("archive.extract error: {}", e))
+}
+
+fn list_tar<R: io::Read>(mut archive: Archive<R>) -> io::Result<Vec<Value>> {
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        entries.push(entry_value(path, entry.size(), entry.header().entry_type().is_dir()));
+    }
+    Ok(entries)
+}
+
+fn list_zip(file: File) -> zip::result::ZipResult<Vec<Value>> {
+    let mut archive = ZipArchive::new(file)?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index)?;
+        entries.push(entry_value(entry.name().to_string(), entry.size(), entry.is_dir()));
+    }
+    Ok(entries)
+}
+
+/// Entries as `{"path", "size", "is_dir"}` hashmaps, in archive order
+fn list(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path,
+        _ => return Err("list expects a string path".to_string()),
+    };
+    let result = match format_of(path)? {
+        Format::Tar => File::open(path).and_then(|file| list_tar(Archive::new(file))),
+        Format::TarGz => open_tar_gz(Path::new(path)).and_then(list_tar),
+        Format::Zip => File::open(path).and_then(|file| list_zip(file).map_err(io::Error::other)),
+    };
+    result.map(Value::Array).map_err(|e| format!("archive.list error: {}", e))
+}
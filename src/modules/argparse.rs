@@ -0,0 +1,299 @@
+//! `argparse` internal module, parses command-line arguments from a declarative spec
+//!
+//! ```text
+//! let opts = argparse.parse({
+//!     "prog": "greet",
+//!     "description": "Greets people",
+//!     "args": [
+//!         {"name": "who", "help": "Person to greet"},
+//!         {"name": "--times", "short": "-t", "type": "int", "default": 1},
+//!         {"name": "--loud", "type": "bool", "help": "Shout the greeting"}
+//!     ]
+//! })
+//! ```
+//! Names starting with `-` are options, the others are positionals in order.
+//! The result maps each name, without dashes and with `-` turned into `_`, to its value.
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("parse".to_string()), Value::BuiltinFunction(parse)),
+        (Value::String("help".to_string()), Value::BuiltinFunction(help)),
+    ];
+    Value::HashMap(items)
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum ArgType {
+    Str,
+    Int,
+    Float,
+    Bool,
+}
+
+struct ArgSpec {
+    name: String,
+    short: Option<String>,
+    kind: ArgType,
+    help: String,
+    default: Option<Value>,
+    required: bool,
+    multiple: bool,
+}
+
+struct ParserSpec {
+    prog: String,
+    description: String,
+    args: Vec<ArgSpec>,
+}
+
+impl ArgSpec {
+    fn is_option(&self) -> bool {
+        self.name.starts_with('-')
+    }
+
+    /// Key of the parsed value in the result hashmap
+    fn key(&self) -> String {
+        self.name.trim_start_matches('-').replace('-', "_")
+    }
+
+    fn metavar(&self) -> String {
+        self.key().to_uppercase()
+    }
+}
+
+
+fn field<'a>(pairs: &'a [(Value, Value)], name: &str) -> Option<&'a Value> {
+    pairs
+        .iter()
+        .find(|(k, _)| matches!(k, Value::String(k) if k == name))
+        .map(|(_, v)| v)
+}
+
+fn string_field(pairs: &[(Value, Value)], name: &str, context: &str) -> Result<Option<String>, String> {
+    match field(pairs, name) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(other) => Err(format!("argparse {} '{}' must be a string, got {:?}", context, name, other)),
+    }
+}
+
+fn bool_field(pairs: &[(Value, Value)], name: &str, context: &str) -> Result<bool, String> {
+    match field(pairs, name) {
+        None => Ok(false),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(other) => Err(format!("argparse {} '{}' must be a boolean, got {:?}", context, name, other)),
+    }
+}
+
+fn parse_spec(value: Option<&Value>) -> Result<ParserSpec, String> {
+    let pairs = match value {
+        Some(Value::HashMap(pairs)) => pairs,
+        _ => return Err("argparse expects a spec hashmap as its first argument".to_string()),
+    };
+    let mut spec = ParserSpec {
+        prog: string_field(pairs, "prog", "spec")?.unwrap_or_else(|| "script".to_string()),
+        description: string_field(pairs, "description", "spec")?.unwrap_or_default(),
+        args: Vec::new(),
+    };
+    let args = match field(pairs, "args") {
+        None => return Ok(spec),
+        Some(Value::Array(args)) => args,
+        Some(other) => return Err(format!("argparse spec 'args' must be an array, got {:?}", other)),
+    };
+    for arg in args {
+        let Value::HashMap(arg) = arg else {
+            return Err(format!("argparse argument must be a hashmap, got {:?}", arg));
+        };
+        let name = string_field(arg, "name", "argument")?
+            .ok_or_else(|| "argparse argument is missing its 'name'".to_string())?;
+        let kind = match string_field(arg, "type", "argument")?.as_deref() {
+            None | Some("str") => ArgType::Str,
+            Some("int") => ArgType::Int,
+            Some("float") => ArgType::Float,
+            Some("bool") => ArgType::Bool,
+            Some(other) => return Err(format!("argparse argument '{}' has unknown type '{}'", name, other)),
+        };
+        let arg_spec = ArgSpec {
+            short: string_field(arg, "short", "argument")?,
+            help: string_field(arg, "help", "argument")?.unwrap_or_default(),
+            default: field(arg, "default").cloned(),
+            required: bool_field(arg, "required", "argument")?,
+            multiple: bool_field(arg, "multiple", "argument")?,
+            kind,
+            name,
+        };
+        if !arg_spec.is_option() && (arg_spec.kind == ArgType::Bool || arg_spec.multiple) {
+            return Err(format!("argparse positional '{}' can't be a bool or multiple", arg_spec.name));
+        }
+        spec.args.push(arg_spec);
+    }
+    Ok(spec)
+}
+
+
+fn convert(arg: &ArgSpec, raw: &str) -> Result<Value, String> {
+    match arg.kind {
+        ArgType::Str => Ok(Value::String(raw.to_string())),
+        ArgType::Int => raw
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("argument {}: invalid int value '{}'", arg.name, raw)),
+        ArgType::Float => raw
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| format!("argument {}: invalid float value '{}'", arg.name, raw)),
+        ArgType::Bool => Ok(Value::Bool(true)),
+    }
+}
+
+fn usage(spec: &ParserSpec) -> String {
+    let mut parts = vec![format!("usage: {}", spec.prog)];
+    for arg in spec.args.iter().filter(|a| a.is_option()) {
+        let flag = match arg.kind {
+            ArgType::Bool => arg.name.clone(),
+            _ => format!("{} {}", arg.name, arg.metavar()),
+        };
+        parts.push(if arg.required { flag } else { format!("[{}]", flag) });
+    }
+    for arg in spec.args.iter().filter(|a| !a.is_option()) {
+        parts.push(if arg.default.is_some() { format!("[{}]", arg.name) } else { arg.name.clone() });
+    }
+    parts.join(" ")
+}
+
+fn help_text(spec: &ParserSpec) -> String {
+    let mut out = usage(spec);
+    out.push('\n');
+    if !spec.description.is_empty() {
+        out.push_str(&format!("\n{}\n", spec.description));
+    }
+
+    let describe = |arg: &ArgSpec| match &arg.default {
+        Some(default) if arg.kind != ArgType::Bool => format!("{} (default: {})", arg.help, default).trim().to_string(),
+        _ => arg.help.clone(),
+    };
+    let positionals: Vec<&ArgSpec> = spec.args.iter().filter(|a| !a.is_option()).collect();
+    if !positionals.is_empty() {
+        out.push_str("\npositional arguments:\n");
+        for arg in positionals {
+            out.push_str(&format!("  {:<24}{}\n", arg.name, describe(arg)));
+        }
+    }
+    out.push_str("\noptions:\n");
+    out.push_str(&format!("  {:<24}{}\n", "-h, --help", "Show this help message and exit"));
+    for arg in spec.args.iter().filter(|a| a.is_option()) {
+        let mut flags = arg.short.iter().cloned().chain([arg.name.clone()]).collect::<Vec<_>>().join(", ");
+        if arg.kind != ArgType::Bool {
+            flags = format!("{} {}", flags, arg.metavar());
+        }
+        out.push_str(&format!("  {:<24}{}\n", flags, describe(arg)));
+    }
+    out
+}
+
+
+/// Parses `argv` (the script arguments after the script path by default)
+/// Prints the help text and requests exit code 0 when `-h`/`--help` is given, like `exit(0)`
+fn parse(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let spec = parse_spec(args.first())?;
+    let argv: Vec<String> = match args.get(1) {
+        None => interp.argv().iter().skip(1).cloned().collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.clone()),
+                other => Err(format!("argparse argv must contain strings, got {:?}", other)),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(other) => return Err(format!("argparse argv must be an array, got {:?}", other)),
+    };
+
+    if argv.iter().any(|a| a == "-h" || a == "--help") {
+        let _ = interp.with_stdout(|out| out.write_all(help_text(&spec).as_bytes()).and_then(|_| out.flush()));
+        interp.request_exit(0);
+        return Err("exit(0)".to_string());
+    }
+    parse_argv(&spec, &argv).map_err(|e| format!("{}\n{}: error: {}", usage(&spec), spec.prog, e))
+}
+
+fn parse_argv(spec: &ParserSpec, argv: &[String]) -> Result<Value, String> {
+    let mut values: Vec<Option<Value>> = vec![None; spec.args.len()];
+    let mut positionals = spec.args.iter().enumerate().filter(|(_, a)| !a.is_option());
+    let mut only_positionals = false;
+
+    let mut iter = argv.iter();
+    while let Some(token) = iter.next() {
+        if token == "--" && !only_positionals {
+            only_positionals = true;
+            continue;
+        }
+        if only_positionals || !token.starts_with('-') || token == "-" || token.parse::<f64>().is_ok() {
+            let (index, arg) = positionals
+                .next()
+                .ok_or_else(|| format!("unrecognized argument '{}'", token))?;
+            values[index] = Some(convert(arg, token)?);
+            continue;
+        }
+
+        let (flag, inline) = match token.split_once('=') {
+            Some((flag, value)) if token.starts_with("--") => (flag, Some(value)),
+            _ => (token.as_str(), None),
+        };
+        let (index, arg) = spec
+            .args
+            .iter()
+            .enumerate()
+            .find(|(_, a)| a.is_option() && (a.name == flag || a.short.as_deref() == Some(flag)))
+            .ok_or_else(|| format!("unrecognized argument '{}'", flag))?;
+
+        let value = if arg.kind == ArgType::Bool {
+            if inline.is_some() {
+                return Err(format!("argument {} doesn't take a value", arg.name));
+            }
+            Value::Bool(true)
+        } else {
+            let raw = match inline {
+                Some(raw) => raw,
+                None => iter.next().ok_or_else(|| format!("argument {} expects a value", arg.name))?,
+            };
+            convert(arg, raw)?
+        };
+        values[index] = Some(match (arg.multiple, values[index].take()) {
+            (true, Some(Value::Array(mut items))) => {
+                items.push(value);
+                Value::Array(items)
+            }
+            (true, _) => Value::Array(vec![value]),
+            (false, _) => value,
+        });
+    }
+
+    let mut result = Vec::new();
+    for (arg, value) in spec.args.iter().zip(values) {
+        let value = match value {
+            Some(value) => value,
+            None if arg.required || (!arg.is_option() && arg.default.is_none()) => {
+                return Err(format!("the following argument is required: {}", arg.name));
+            }
+            None => match (&arg.default, arg.kind, arg.multiple) {
+                (Some(default), _, _) => default.clone(),
+                (None, _, true) => Value::Array(Vec::new()),
+                (None, ArgType::Bool, _) => Value::Bool(false),
+                (None, _, _) => Value::Null,
+            },
+        };
+        result.push((Value::String(arg.key()), value));
+    }
+    Ok(Value::HashMap(result))
+}
+
+/// Returns the help text for a spec, as printed by `--help`
+fn help(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let spec = parse_spec(args.first())?;
+    Ok(Value::String(help_text(&spec)))
+}
@@ -0,0 +1,183 @@
+//! `async` internal module, non-blocking builtins that return `Task` objects
+//! Start several and `wait` for them together, e.g. `wait [aio.sleep(1), aio.http("GET", url)]`
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::interpreter::tasks::Task;
+use crate::interpreter::value::Value;
+use super::subprocess::{apply_options, build_command, output_value};
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("sleep".to_string()), Value::BuiltinFunction(sleep)),
+        (Value::String("read_file".to_string()), Value::BuiltinFunction(read_file)),
+        (Value::String("write_file".to_string()), Value::BuiltinFunction(write_file)),
+        (Value::String("run".to_string()), Value::BuiltinFunction(run)),
+        (Value::String("http".to_string()), Value::BuiltinFunction(http)),
+        (Value::String("tcp".to_string()), Value::BuiltinFunction(tcp)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Task that finishes with None after the given number of seconds
+fn sleep(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let seconds = match args.first() {
+        Some(Value::Integer(i)) => *i as f64,
+        Some(Value::Float(f)) => *f,
+        _ => return Err("sleep expects a non-negative number of seconds".to_string()),
+    };
+    let duration = Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("sleep duration must be a non-negative number of seconds, got {}", seconds))?;
+    Ok(Task::spawn(async move {
+        tokio::time::sleep(duration).await;
+        Ok(Value::Null)
+    }))
+}
+
+fn read_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path.clone(),
+        _ => return Err("read_file expects a string path".to_string()),
+    };
+    Ok(Task::spawn(async move {
+        tokio::fs::read_to_string(&path)
+            .await
+            .map(Value::String)
+            .map_err(|e| format!("async.read_file error: {}", e))
+    }))
+}
+
+fn write_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (path, content) = match args.as_slice() {
+        [Value::String(path), Value::String(content)] => (path.clone(), content.clone()),
+        _ => return Err("write_file expects 2 string arguments: path, content".to_string()),
+    };
+    Ok(Task::spawn(async move {
+        tokio::fs::write(&path, content)
+            .await
+            .map(|_| Value::Null)
+            .map_err(|e| format!("async.write_file error: {}", e))
+    }))
+}
+
+/// Same arguments and result as `subprocess.run`, as a task
+fn run(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let mut command = build_command("async.run", args.first(), args.get(1))?;
+    let stdin = apply_options("async.run", &mut command, args.get(2))?;
+    let mut command = tokio::process::Command::from(command);
+    command
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    Ok(Task::spawn(async move {
+        let error = |e: std::io::Error| format!("async.run error: {}", e);
+        let mut child = command.spawn().map_err(error)?;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes()).await.map_err(error)?;
+        }
+        child.wait_with_output().await.map(output_value).map_err(error)
+    }))
+}
+
+
+/// Seconds a request may take when no timeout is given
+const DEFAULT_TIMEOUT: f64 = 30.0;
+
+
+/// `http(method, url[, options])` sends a request, `options` may set `headers` (a hashmap of
+/// strings), `body` (a string) and `timeout` in seconds. The task gives `{"status", "headers", "body"}`
+/// for any response, error statuses included, and fails when there is none
+fn http(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (method, url) = match (args.first(), args.get(1)) {
+        (Some(Value::String(method)), Some(Value::String(url))) => (method.to_uppercase(), url.clone()),
+        _ => return Err("http expects a method and a URL string".to_string()),
+    };
+    let mut headers = Vec::new();
+    let mut body = String::new();
+    let mut timeout = super::timeout_arg("async.http", None, DEFAULT_TIMEOUT)?;
+    match args.get(2) {
+        None => {}
+        Some(Value::HashMap(pairs)) => {
+            for (key, value) in pairs {
+                match (key, value) {
+                    (Value::String(k), Value::HashMap(extra)) if k == "headers" => {
+                        for (name, value) in extra {
+                            match (name, value) {
+                                (Value::String(name), Value::String(value)) => headers.push((name.clone(), value.clone())),
+                                _ => return Err("async.http headers must be strings".to_string()),
+                            }
+                        }
+                    }
+                    (Value::String(k), Value::String(text)) if k == "body" => body = text.clone(),
+                    (Value::String(k), value) if k == "timeout" => timeout = super::timeout_arg("async.http", Some(value), DEFAULT_TIMEOUT)?,
+                    (k, v) => return Err(format!("async.http option {} has invalid value {:?}", k, v)),
+                }
+            }
+        }
+        Some(other) => return Err(format!("async.http options must be a hashmap, got {:?}", other)),
+    }
+
+    // The HTTP client blocks, so the request takes a thread of the blocking pool, not the runtime's
+    Ok(Task::spawn_blocking(move || send_request(&method, &url, headers, body, timeout)))
+}
+
+fn send_request(method: &str, url: &str, headers: Vec<(String, String)>, body: String, timeout: Duration) -> Result<Value, String> {
+    let mut request = ureq::http::Request::builder().method(method).uri(url);
+    for (name, value) in &headers {
+        request = request.header(name, value);
+    }
+    let request = request.body(body).map_err(|e| format!("async.http error: {}", e))?;
+    let config = ureq::Agent::config_builder().http_status_as_error(false).timeout_global(Some(timeout)).build();
+    let error = |e: ureq::Error| format!("async.http error: {}", e);
+    let mut response = ureq::Agent::new_with_config(config).run(request).map_err(error)?;
+
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| (Value::String(name.to_string()), Value::String(String::from_utf8_lossy(value.as_bytes()).to_string())))
+        .collect();
+    let status = Value::Integer(response.status().as_u16() as i64);
+    let body = response.body_mut().read_to_string().map_err(error)?;
+    Ok(Value::HashMap(vec![
+        (Value::String("status".to_string()), status),
+        (Value::String("headers".to_string()), Value::HashMap(headers)),
+        (Value::String("body".to_string()), Value::String(body)),
+    ]))
+}
+
+/// `tcp(host, port, data[, timeout])` connects, sends `data` and closes its side of the
+/// connection, the task gives everything the peer sent back until it closed its side too
+fn tcp(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (host, port, data) = match args.as_slice() {
+        [Value::String(host), Value::Integer(port), Value::String(data), ..] if (0..=65535).contains(port) => {
+            (host.clone(), *port as u16, data.clone())
+        }
+        _ => return Err("tcp expects a host, a port between 0 and 65535 and a string to send".to_string()),
+    };
+    let timeout = super::timeout_arg("async.tcp", args.get(3), DEFAULT_TIMEOUT)?;
+
+    Ok(Task::spawn(async move {
+        let exchange = async {
+            let mut stream = TcpStream::connect((host.as_str(), port)).await?;
+            stream.write_all(data.as_bytes()).await?;
+            stream.shutdown().await?;
+            let mut reply = Vec::new();
+            stream.read_to_end(&mut reply).await?;
+            Ok::<_, std::io::Error>(reply)
+        };
+        let reply = tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| "async.tcp error: timed out".to_string())?
+            .map_err(|e| format!("async.tcp error: {}", e))?;
+        Ok(Value::String(String::from_utf8_lossy(&reply).to_string()))
+    }))
+}
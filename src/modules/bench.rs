@@ -0,0 +1,138 @@
+//! `bench` internal module for timing NIKL functions
+//! Durations are floats in seconds, like `time.measure`
+
+use std::time::Instant;
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+const DEFAULT_ITERATIONS: i64 = 100;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("time".to_string()), Value::BuiltinFunction(time)),
+        (Value::String("compare".to_string()), Value::BuiltinFunction(compare)),
+    ];
+    Value::HashMap(items)
+}
+
+
+struct Timing {
+    iterations: i64,
+    min: f64,
+    mean: f64,
+    max: f64,
+    total: f64,
+}
+
+impl Timing {
+    fn to_value(&self, name: Option<&str>) -> Value {
+        let mut pairs = Vec::new();
+        if let Some(name) = name {
+            pairs.push((Value::String("name".to_string()), Value::String(name.to_string())));
+        }
+        pairs.extend([
+            (Value::String("iterations".to_string()), Value::Integer(self.iterations)),
+            (Value::String("min".to_string()), Value::Float(self.min)),
+            (Value::String("mean".to_string()), Value::Float(self.mean)),
+            (Value::String("max".to_string()), Value::Float(self.max)),
+            (Value::String("total".to_string()), Value::Float(self.total)),
+        ]);
+        Value::HashMap(pairs)
+    }
+}
+
+/// Calls `func()` once untimed to warm up, then `iterations` timed times
+fn measure(interp: &mut Interpreter, func: &Value, iterations: i64) -> Result<Timing, String> {
+    interp.call_function(func.clone(), Vec::new())?;
+    let (mut min, mut max, mut total) = (f64::INFINITY, 0.0_f64, 0.0);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        interp.call_function(func.clone(), Vec::new())?;
+        let elapsed = start.elapsed().as_secs_f64();
+        min = min.min(elapsed);
+        max = max.max(elapsed);
+        total += elapsed;
+    }
+    Ok(Timing { iterations, min, mean: total / iterations as f64, max, total })
+}
+
+fn iterations_arg(name: &str, value: Option<&Value>) -> Result<i64, String> {
+    match value {
+        None => Ok(DEFAULT_ITERATIONS),
+        Some(Value::Integer(n)) if *n > 0 => Ok(*n),
+        Some(other) => Err(format!("{} iterations must be a positive integer, got {:?}", name, other)),
+    }
+}
+
+/// Human friendly duration for the comparison table
+fn format_duration(seconds: f64) -> String {
+    if seconds >= 1.0 {
+        format!("{:.3} s", seconds)
+    } else if seconds >= 1e-3 {
+        format!("{:.3} ms", seconds * 1e3)
+    } else if seconds >= 1e-6 {
+        format!("{:.3} µs", seconds * 1e6)
+    } else {
+        format!("{:.0} ns", seconds * 1e9)
+    }
+}
+
+
+/// `time(fn[, iterations])` returns `{"iterations", "min", "mean", "max", "total"}`
+fn time(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let func = match args.first() {
+        Some(func) if func.is_callable() && args.len() <= 2 => func,
+        _ => return Err("time expects a function and an optional iteration count".to_string()),
+    };
+    let iterations = iterations_arg("time", args.get(1))?;
+    measure(interp, func, iterations).map(|timing| timing.to_value(None))
+}
+
+/// `compare([fn_a, ("label", fn_b)][, iterations])` times every candidate, prints a table
+/// from fastest to slowest and returns the timings in that order, each with its `name`
+fn compare(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let candidates = match args.first() {
+        Some(Value::Array(items)) if !items.is_empty() && args.len() <= 2 => items,
+        _ => return Err("compare expects a non-empty array of functions and an optional iteration count".to_string()),
+    };
+    let iterations = iterations_arg("compare", args.get(1))?;
+
+    let mut results = Vec::new();
+    for candidate in candidates {
+        let (name, func) = match candidate {
+            Value::Function { name, .. } => (name.clone(), candidate),
+            Value::Tuple(pair) | Value::Array(pair) => match pair.as_slice() {
+                [Value::String(name), func] if func.is_callable() => (name.clone(), func),
+                _ => return Err(format!("compare expects (name, function) pairs, got {}", candidate.repr())),
+            },
+            func if func.is_callable() => (format!("#{}", results.len() + 1), func),
+            other => return Err(format!("compare expects functions, got {}", other.repr())),
+        };
+        results.push((name, measure(interp, func, iterations)?));
+    }
+    results.sort_by(|(_, a), (_, b)| a.mean.total_cmp(&b.mean));
+
+    let width = results.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0).max(4);
+    let fastest = results[0].1.mean;
+    let mut table = format!("{:<width$}  {:>12}  {:>12}  {:>12}  relative\n", "name", "mean", "min", "max", width = width);
+    for (name, timing) in &results {
+        let relative = if fastest > 0.0 { timing.mean / fastest } else { 1.0 };
+        table += &format!(
+            "{:<width$}  {:>12}  {:>12}  {:>12}  x{:.2}\n",
+            name,
+            format_duration(timing.mean),
+            format_duration(timing.min),
+            format_duration(timing.max),
+            relative,
+            width = width
+        );
+    }
+    interp
+        .with_stdout(|out| out.write_all(table.as_bytes()))
+        .map_err(|e| format!("bench.compare error: {}", e))?;
+
+    Ok(Value::Array(results.iter().map(|(name, timing)| timing.to_value(Some(name))).collect()))
+}
@@ -2,9 +2,12 @@
 //! These functions are available in the interpreter environment
 //! and can be called directly from the user code
 
-use std::io::{self, Write};
+use std::io::Write;
+use std::sync::Arc;
 use regex::Regex;
-use crate::interpreter::value::Value;
+use crate::interpreter::value::{NativeObject, Value};
+use crate::interpreter::methods::sort_values;
+use crate::interpreter::Interpreter;
 
 
 /// Unescapes a string by replacing escape sequences with their corresponding characters
@@ -27,9 +30,41 @@ fn unescape_string(s: &str) -> String {
 }
 
 
-/// Built-in function to print values to the console
-/// It accepts any number of arguments and prints them in a single line
-pub fn builtin_print(args: Vec<Value>) -> Result<Value, String> {
+/// Options accepted by `print` as a trailing hashmap, e.g. `print("a", "b", {"sep": ", ", "end": ""})`
+struct PrintOptions {
+    sep: String,
+    end: String,
+    flush: bool,
+}
+
+/// Splits off a trailing options map, only a non-empty map after at least one value whose keys
+/// are all `sep`, `end` or `flush` counts as options, any other map is printed normally
+fn split_print_options(mut args: Vec<Value>) -> Result<(Vec<Value>, PrintOptions), String> {
+    let mut options = PrintOptions { sep: " ".to_string(), end: "\n".to_string(), flush: false };
+
+    let is_options = args.len() > 1 && matches!(args.last(), Some(Value::HashMap(pairs)) if !pairs.is_empty()
+        && pairs.iter().all(|(k, _)| matches!(k, Value::String(k) if k == "sep" || k == "end" || k == "flush")));
+    if !is_options {
+        return Ok((args, options));
+    }
+
+    if let Some(Value::HashMap(pairs)) = args.pop() {
+        for (key, value) in pairs {
+            match (key, value) {
+                (Value::String(k), Value::String(v)) if k == "sep" => options.sep = unescape_string(&v),
+                (Value::String(k), Value::String(v)) if k == "end" => options.end = unescape_string(&v),
+                (Value::String(k), Value::Bool(b)) if k == "flush" => options.flush = b,
+                (k, v) => return Err(format!("print() option {} has invalid value {:?}", k, v)),
+            }
+        }
+    }
+    Ok((args, options))
+}
+
+
+/// Writes the arguments of `print`/`eprint` to the given stream
+fn write_values(out: &mut dyn Write, args: Vec<Value>) -> Result<Value, String> {
+    let (args, options) = split_print_options(args)?;
     let output: Vec<String> = args.into_iter().map(|v| {
         match v {
             Value::String(s) => unescape_string(&s),
@@ -37,20 +72,39 @@ pub fn builtin_print(args: Vec<Value>) -> Result<Value, String> {
         }
     }).collect();
 
-    println!("{}", output.join(" "));
+    write!(out, "{}{}", output.join(&options.sep), options.end)
+        .map_err(|e| format!("Failed to write output: {}", e))?;
+    if options.flush {
+        out.flush().map_err(|e| format!("Failed to flush output: {}", e))?;
+    }
     Ok(Value::Null)
 }
 
 
-/// Built-in function to get the length of any possible type
-/// Currently only works on strings
-pub fn builtin_len(args: Vec<Value>) -> Result<Value, String> {
+/// Built-in function to print values to the script's standard output
+/// It accepts any number of arguments and prints them in a single line
+/// A trailing map can set the separator, line ending and flushing: `{"sep": ",", "end": "", "flush": True}`
+pub fn builtin_print(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    interp.with_stdout(|out| write_values(out, args))
+}
+
+
+/// Built-in function to print values to stderr, same arguments and options as `print`
+/// Keeps diagnostics separate from data when a script's output is piped
+pub fn builtin_eprint(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    interp.with_stderr(|out| write_values(out, args))
+}
+
+
+/// Built-in function to get the length of a string, array, tuple or hashmap
+/// Strings are measured in characters, the same units string iteration yields
+pub fn builtin_len(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("len() takes exactly one argument".to_string());
     }
 
     match &args[0] {
-        Value::String(s) => Ok(Value::Integer(s.len() as i64)),
+        Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
         Value::Array(a) => Ok(Value::Integer(a.len() as i64)),
         Value::Tuple(t) => Ok(Value::Integer(t.len() as i64)),
         Value::HashMap(h) => Ok(Value::Integer(h.len() as i64)),
@@ -61,7 +115,7 @@ pub fn builtin_len(args: Vec<Value>) -> Result<Value, String> {
 
 /// Built-in function to convert a value to a string
 /// Currently only works on strings, integers, floats, and booleans
-pub fn builtin_str(args: Vec<Value>) -> Result<Value, String> {
+pub fn builtin_str(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("str() takes exactly one argument".to_string());
     }
@@ -72,19 +126,246 @@ pub fn builtin_str(args: Vec<Value>) -> Result<Value, String> {
         Value::Float(f) => Ok(Value::String(f.to_string())),
         Value::Bool(b) => Ok(Value::String(b.to_string())),
         Value::Null => Ok(Value::String("None".to_string())),
-        Value::Array(a) => Ok(Value::String(format!("{:?}", a))),
-        Value::Tuple(t) => Ok(Value::String(format!("{:?}", t))),
-        Value::HashMap(h) => Ok(Value::String(format!("{:?}", h))),
+        Value::Array(_) | Value::Tuple(_) | Value::HashMap(_) => Ok(Value::String(args[0].to_string())),
         _ => Err(format!("str() expects a string, integer, float, boolean, array, tuple, or hashmap, but got {:?}", args[0])),
     }
 }
 
 
+/// Built-in function to get the unambiguous representation of a value
+/// Strings are quoted and escaped, so the output can be pasted back into a script
+pub fn builtin_repr(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("repr() takes exactly one argument".to_string());
+    }
+
+    Ok(Value::String(args[0].repr()))
+}
+
+
+/// A parsed `{index:spec}` placeholder of `format()`
+struct FormatSpec {
+    fill: char,
+    align: Option<char>,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+fn parse_format_spec(spec: &str) -> Result<FormatSpec, String> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut i = 0;
+    let mut fill = ' ';
+    let mut align = None;
+
+    // Optional fill character followed by an alignment, or just an alignment
+    if chars.len() >= 2 && matches!(chars[1], '<' | '>' | '^') {
+        fill = chars[0];
+        align = Some(chars[1]);
+        i = 2;
+    } else if !chars.is_empty() && matches!(chars[0], '<' | '>' | '^') {
+        align = Some(chars[0]);
+        i = 1;
+    }
+
+    let zero_pad = align.is_none() && chars.get(i) == Some(&'0');
+    if zero_pad {
+        i += 1;
+    }
+
+    let width_start = i;
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    let width = chars[width_start..i].iter().collect::<String>().parse().unwrap_or(0);
+
+    let mut precision = None;
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        let precision_start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        if precision_start == i {
+            return Err(format!("format() missing precision in '{{:{}}}'", spec));
+        }
+        precision = chars[precision_start..i].iter().collect::<String>().parse().ok();
+    }
+
+    if i != chars.len() {
+        return Err(format!("format() invalid format specifier '{{:{}}}'", spec));
+    }
+    Ok(FormatSpec { fill, align, zero_pad, width, precision })
+}
+
+fn apply_format_spec(value: &Value, spec: &FormatSpec) -> Result<String, String> {
+    let text = match (value, spec.precision) {
+        (Value::Float(f), Some(p)) => format!("{:.*}", p, f),
+        (Value::Integer(i), Some(p)) => format!("{:.*}", p, *i as f64),
+        (Value::String(s), Some(p)) => s.chars().take(p).collect(),
+        (other, Some(_)) => return Err(format!("format() precision is not supported for {}", other.type_name())),
+        (other, None) => other.to_string(),
+    };
+
+    let len = text.chars().count();
+    if len >= spec.width {
+        return Ok(text);
+    }
+    let padding = spec.width - len;
+
+    if spec.zero_pad {
+        // Zero padding goes after the sign, e.g. -0042
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text.as_str()),
+        };
+        return Ok(format!("{}{}{}", sign, "0".repeat(padding), digits));
+    }
+
+    let pad = |n: usize| spec.fill.to_string().repeat(n);
+    // Numbers are right aligned by default, everything else left aligned
+    let default_align = if matches!(value, Value::Integer(_) | Value::Float(_)) { '>' } else { '<' };
+    Ok(match spec.align.unwrap_or(default_align) {
+        '>' => format!("{}{}", pad(padding), text),
+        '^' => format!("{}{}{}", pad(padding / 2), text, pad(padding - padding / 2)),
+        _ => format!("{}{}", text, pad(padding)),
+    })
+}
+
+
+/// Built-in function to build a string from a template and arguments
+/// Placeholders are `{}` (next argument) or `{0}` (by position), optionally with a spec after a colon:
+/// `{:.2}` precision, `{:>8}` / `{:<8}` / `{:^8}` alignment and width, `{:*^8}` fill character, `{:05}` zero padding
+/// Use `{{` and `}}` for literal braces
+pub fn builtin_format(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (template, values) = match args.split_first() {
+        Some((Value::String(t), rest)) => (t, rest),
+        Some((other, _)) => return Err(format!("format() expects a string template, but got {:?}", other)),
+        None => return Err("format() takes at least one argument".to_string()),
+    };
+
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next_index = 0;
+    let mut used = vec![false; values.len()];
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(ch) => placeholder.push(ch),
+                        None => return Err("format() unclosed '{' in template".to_string()),
+                    }
+                }
+                let (index_part, spec_part) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+                let index = if index_part.is_empty() {
+                    next_index += 1;
+                    next_index - 1
+                } else {
+                    index_part
+                        .parse::<usize>()
+                        .map_err(|_| format!("format() invalid placeholder '{{{}}}'", placeholder))?
+                };
+                let value = values.get(index).ok_or_else(|| format!(
+                    "format() placeholder {} is out of range, only {} argument(s) given",
+                    index,
+                    values.len()
+                ))?;
+                used[index] = true;
+                out.push_str(&apply_format_spec(value, &parse_format_spec(spec_part)?)?);
+            }
+            '}' => return Err("format() single '}' in template, use '}}' for a literal brace".to_string()),
+            c => out.push(c),
+        }
+    }
+
+    if let Some(unused) = used.iter().position(|u| !u) {
+        return Err(format!(
+            "format() got {} argument(s) but argument {} is never used in the template",
+            values.len(),
+            unused
+        ));
+    }
+    Ok(Value::String(out))
+}
+
+/// Built-in function to get a stable hash of a value
+/// Only strings, integers, booleans, None and tuples of those can be hashed
+/// The same value always gives the same hash, across runs and machines
+pub fn builtin_hash(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("hash() takes exactly one argument".to_string());
+    }
+
+    args[0]
+        .stable_hash()
+        .map(|h| Value::Integer(h as i64))
+        .ok_or_else(|| format!("hash() unhashable type: {}", args[0].type_name()))
+}
+
+/// Built-in function to copy a value
+/// Only the top level is new, native objects inside it like files or connections are
+/// shared with the original, use `deepcopy` to get copies of those too
+pub fn builtin_copy(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("copy() takes exactly one argument".to_string());
+    }
+
+    Ok(args[0].clone())
+}
+
+
+/// Built-in function to copy a value and all values nested inside it
+/// Native objects are duplicated as well, it fails on handles that can't be, like files or
+/// connections. An object found several times is copied once, so the copy shares it the same way
+pub fn builtin_deepcopy(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("deepcopy() takes exactly one argument".to_string());
+    }
+
+    deep_copy(&args[0], &mut Vec::new())
+}
+
+/// Each native object `deepcopy` met so far, with its copy
+type Copies = Vec<(Arc<dyn NativeObject>, Arc<dyn NativeObject>)>;
+
+fn deep_copy(value: &Value, copies: &mut Copies) -> Result<Value, String> {
+    match value {
+        Value::Array(items) => Ok(Value::Array(items.iter().map(|item| deep_copy(item, copies)).collect::<Result<_, _>>()?)),
+        Value::Tuple(items) => Ok(Value::Tuple(items.iter().map(|item| deep_copy(item, copies)).collect::<Result<_, _>>()?)),
+        Value::HashMap(pairs) => Ok(Value::HashMap(
+            pairs.iter().map(|(k, v)| Ok((deep_copy(k, copies)?, deep_copy(v, copies)?))).collect::<Result<_, String>>()?,
+        )),
+        Value::Native(object) => {
+            if let Some((_, copy)) = copies.iter().find(|(original, _)| Arc::ptr_eq(original, object)) {
+                return Ok(Value::Native(copy.clone()));
+            }
+            let copy = object
+                .duplicate()
+                .ok_or_else(|| format!("deepcopy() can't copy a {} object, it is a handle shared by every copy", object.type_name()))?;
+            copies.push((object.clone(), copy.clone()));
+            Ok(Value::Native(copy))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
 /// Built-in function to convert a value to an integer
 /// Currently only works on strings, integers, and floats
 /// Strings are converted to integers if they are valid integer representations
 /// Floats are truncated to integers
-pub fn builtin_int(args: Vec<Value>) -> Result<Value, String> {
+pub fn builtin_int(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("int() takes exactly one argument".to_string());
     }
@@ -104,7 +385,7 @@ pub fn builtin_int(args: Vec<Value>) -> Result<Value, String> {
 /// Currently only works on strings, integers, and floats
 /// Strings are converted to floats if they are valid float representations
 /// Integers are converted to floats by adding .0
-pub fn builtin_float(args: Vec<Value>) -> Result<Value, String> {
+pub fn builtin_float(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("float() takes exactly one argument".to_string());
     }
@@ -124,7 +405,7 @@ pub fn builtin_float(args: Vec<Value>) -> Result<Value, String> {
 /// Currently only works on strings, integers, and floats
 /// Strings are converted to booleans if there is even one character
 /// Integers, Floats are converted to booleans if they are not 0
-pub fn builtin_bool(args: Vec<Value>) -> Result<Value, String> {
+pub fn builtin_bool(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("bool() takes exactly one argument".to_string());
     }
@@ -139,16 +420,20 @@ pub fn builtin_bool(args: Vec<Value>) -> Result<Value, String> {
 
 
 /// Built-in function to exit the interpreter
-/// Only works with single argument and of type integer
-/// The integer is the exit code
-pub fn builtin_exit(args: Vec<Value>) -> Result<Value, String> {
+/// Only works with single argument and of type integer, which must fit an `i32`
+/// The integer is the exit code, it is recorded on the interpreter and the script
+/// unwinds with an error so the host decides how to exit
+pub fn builtin_exit(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("exit() takes exactly one argument".to_string());
     }
 
     match &args[0] {
         Value::Integer(i) => {
-            std::process::exit(*i as i32);
+            let code = i32::try_from(*i)
+                .map_err(|_| format!("exit() code must be between {} and {}, got {}", i32::MIN, i32::MAX, i))?;
+            interp.request_exit(code);
+            Err(format!("exit({})", code))
         }
         _ => Err(format!("exit() only works with integer argument, got {:?}", args[0])),
     }
@@ -156,31 +441,20 @@ pub fn builtin_exit(args: Vec<Value>) -> Result<Value, String> {
 
 
 /// Built-in function to get the type of a value
-/// Currently only works on strings, integers, floats, and booleans
-pub fn builtin_type(args: Vec<Value>) -> Result<Value, String> {
+/// Works on every value, the returned names are stable so scripts can branch on them
+pub fn builtin_type(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err("type() takes exactly one argument".to_string());
     }
 
-    match &args[0] {
-        Value::String(_) => Ok(Value::String("String".to_string())),
-        Value::Integer(_) => Ok(Value::String("Integer".to_string())),
-        Value::Float(_) => Ok(Value::String("Float".to_string())),
-        Value::Bool(_) => Ok(Value::String("Boolean".to_string())),
-        Value::Null => Ok(Value::String("None".to_string())),
-        Value::Array(_) => Ok(Value::String("Array".to_string())),
-        Value::Tuple(_) => Ok(Value::String("Tuple".to_string())),
-        Value::HashMap(_) => Ok(Value::String("HashMap".to_string())),
-        // _ => Err(format!("type() does not support this type: {:?}", args[0])),
-        _ => Err(format!("type() only works with strings, integers, floats, booleans, none, arrays, tuples, and hashmaps, but got {:?}", args[0])),
-    }
+    Ok(Value::String(args[0].type_name().to_string()))
 }
 
 
 /// Built-in function to get input from the user
-/// Currently only works with strings
+/// Reads a line from the interpreter's input source, stdin unless the host configured another
 /// Returns the input as a string
-pub fn builtin_input(args: Vec<Value>) -> Result<Value, String> {
+pub fn builtin_input(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     let prompt = match args.len() {
         0 => "> ",
         1 => {
@@ -193,13 +467,394 @@ pub fn builtin_input(args: Vec<Value>) -> Result<Value, String> {
         _ => return Err(format!("input() takes at most one argument, but got {}", args.len())),
     };
 
-    print!("{}", prompt);
-    io::stdout().flush().map_err(|e| format!("Failed to flush stdout: {}", e))?;
+    let input = interp.read_input(prompt)?;
+    Ok(Value::String(input.trim().to_string()))
+}
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .map_err(|e| format!("Failed to read input: {}", e))?;
 
-    Ok(Value::String(input.trim().to_string()))
+/// Arguments for min/max/sum: either a single array/tuple or the values themselves
+fn numeric_operands(name: &str, args: Vec<Value>) -> Result<Vec<Value>, String> {
+    let values = match args.len() {
+        0 => return Err(format!("{}() takes at least one argument", name)),
+        1 => match args.into_iter().next().unwrap() {
+            Value::Array(items) | Value::Tuple(items) => items,
+            other => vec![other],
+        },
+        _ => args,
+    };
+    if values.is_empty() {
+        return Err(format!("{}() of an empty sequence", name));
+    }
+    Ok(values)
+}
+
+
+/// Picks the smallest or largest value, `keep` decides if the candidate replaces the current best
+fn extreme(name: &str, args: Vec<Value>, keep: std::cmp::Ordering) -> Result<Value, String> {
+    let mut values = numeric_operands(name, args)?.into_iter();
+    let mut best = values.next().unwrap();
+    for value in values {
+        let ordering = value.compare(&best).map_err(|e| format!("{}(): {}", name, e))?;
+        if ordering == keep {
+            best = value;
+        }
+    }
+    Ok(best)
+}
+
+
+/// Built-in function to get the smallest of several values or of an array
+/// Works on numbers, strings and any other comparable values, e.g. `min(3, 1)` or `min([3, 1])`
+pub fn builtin_min(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    extreme("min", args, std::cmp::Ordering::Less)
+}
+
+
+/// Built-in function to get the largest of several values or of an array
+pub fn builtin_max(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    extreme("max", args, std::cmp::Ordering::Greater)
+}
+
+
+/// Built-in function to add up numbers, given as an array or as separate arguments
+/// The result is an integer unless any of the numbers is a float
+pub fn builtin_sum(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let values = match args.len() {
+        1 => match &args[0] {
+            Value::Array(items) | Value::Tuple(items) => items.clone(),
+            _ => args,
+        },
+        _ => args,
+    };
+
+    let mut total = Value::Integer(0);
+    for value in values {
+        total = match (total, &value) {
+            (Value::Integer(a), Value::Integer(b)) => a
+                .checked_add(*b)
+                .map(Value::Integer)
+                .ok_or_else(|| "sum() integer overflow".to_string())?,
+            (Value::Integer(a), Value::Float(b)) => Value::Float(a as f64 + b),
+            (Value::Float(a), Value::Integer(b)) => Value::Float(a + *b as f64),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+            _ => return Err(format!("sum() only works with integers and floats, but got {:?}", value)),
+        };
+    }
+    Ok(total)
+}
+
+
+/// Applies `f` to every number of an array or tuple, nested ones included, or to a single value
+fn element_wise(value: &Value, f: &dyn Fn(&Value) -> Result<Value, String>) -> Result<Value, String> {
+    match value {
+        Value::Array(items) => items.iter().map(|item| element_wise(item, f)).collect::<Result<_, _>>().map(Value::Array),
+        Value::Tuple(items) => items.iter().map(|item| element_wise(item, f)).collect::<Result<_, _>>().map(Value::Tuple),
+        other => f(other),
+    }
+}
+
+
+/// Built-in function to get the absolute value of an integer or float, or of each one in an array or tuple
+pub fn builtin_abs(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("abs() takes exactly one argument".to_string());
+    }
+
+    element_wise(&args[0], &|value| match value {
+        Value::Integer(i) => i
+            .checked_abs()
+            .map(Value::Integer)
+            .ok_or_else(|| "abs() integer overflow".to_string()),
+        Value::Float(f) => Ok(Value::Float(f.abs())),
+        other => Err(format!("abs() expects an integer or float, but got {:?}", other)),
+    })
+}
+
+
+/// Built-in function to round a number, or each one in an array or tuple
+/// `round(x)` returns the nearest integer (halfway cases away from zero), NaN, infinities and
+/// floats beyond the integer range have none and are an error
+/// `round(x, digits)` returns a float rounded to that many decimal places, digits past what a
+/// float can hold leave it unchanged and negative ones beyond its range round it to 0
+pub fn builtin_round(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let digits = match args.as_slice() {
+        [_] => None,
+        [_, Value::Integer(digits)] => Some(*digits),
+        [_, other] => return Err(format!("round() digits must be an integer, but got {:?}", other)),
+        _ => return Err(format!("round() takes one or two arguments, but got {}", args.len())),
+    };
+
+    element_wise(&args[0], &|value| match (value, digits) {
+        (Value::Integer(i), None) => Ok(Value::Integer(*i)),
+        (Value::Float(f), None) => {
+            let rounded = f.round();
+            // `as` would quietly turn NaN into 0 and saturate the rest
+            if rounded.is_nan() || rounded < i64::MIN as f64 || rounded >= i64::MAX as f64 {
+                return Err(format!("round() can't turn {} into an integer", f));
+            }
+            Ok(Value::Integer(rounded as i64))
+        }
+        (Value::Integer(i), Some(_)) => Ok(Value::Float(*i as f64)),
+        (Value::Float(f), Some(digits)) => {
+            // 10^400 is already infinite, the clamp only keeps the exponent in i32
+            let factor = 10f64.powi(digits.unsigned_abs().min(400) as i32);
+            if digits >= 0 {
+                let scaled = f * factor;
+                Ok(Value::Float(if scaled.is_finite() { scaled.round() / factor } else { *f }))
+            } else if factor.is_finite() {
+                Ok(Value::Float((f / factor).round() * factor))
+            } else {
+                Ok(Value::Float(0f64.copysign(*f)))
+            }
+        }
+        (other, _) => Err(format!("round() expects an integer or float, but got {:?}", other)),
+    })
+}
+
+
+/// Built-in function to raise a number to a power
+/// Integer base with a non-negative integer exponent stays an integer, anything else is a float
+/// Either side may be an array to raise each element, two arrays go element by element
+pub fn builtin_pow(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("pow() takes exactly two arguments: base, exponent".to_string());
+    }
+
+    pow(&args[0], &args[1])
+}
+
+fn pow(base: &Value, exp: &Value) -> Result<Value, String> {
+    match (base, exp) {
+        (Value::Array(bases), Value::Array(exps)) => {
+            if bases.len() != exps.len() {
+                return Err(format!("pow() arrays must have the same length, got {} and {}", bases.len(), exps.len()));
+            }
+            bases.iter().zip(exps).map(|(base, exp)| pow(base, exp)).collect::<Result<_, _>>().map(Value::Array)
+        }
+        (Value::Array(bases), exp) => bases.iter().map(|base| pow(base, exp)).collect::<Result<_, _>>().map(Value::Array),
+        (base, Value::Array(exps)) => exps.iter().map(|exp| pow(base, exp)).collect::<Result<_, _>>().map(Value::Array),
+        (Value::Integer(base), Value::Integer(exp)) if *exp >= 0 => u32::try_from(*exp)
+            .ok()
+            .and_then(|exp| base.checked_pow(exp))
+            .map(Value::Integer)
+            .ok_or_else(|| format!("pow() integer overflow for {} ** {}", base, exp)),
+        (Value::Integer(base), Value::Integer(exp)) => Ok(Value::Float((*base as f64).powf(*exp as f64))),
+        (Value::Integer(base), Value::Float(exp)) => Ok(Value::Float((*base as f64).powf(*exp))),
+        (Value::Float(base), Value::Integer(exp)) => Ok(Value::Float(base.powf(*exp as f64))),
+        (Value::Float(base), Value::Float(exp)) => Ok(Value::Float(base.powf(*exp))),
+        (base, exp) => Err(format!("pow() expects integers or floats, but got {:?} and {:?}", base, exp)),
+    }
+}
+
+/// Built-in function to get the Unicode code point of a single character
+/// The string must contain exactly one character, as yielded by string iteration
+pub fn builtin_ord(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("ord() takes exactly one argument".to_string());
+    }
+
+    match &args[0] {
+        Value::String(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Value::Integer(c as i64)),
+                _ => Err(format!("ord() expects a single character, but got a string of length {}", s.chars().count())),
+            }
+        }
+        _ => Err(format!("ord() expects a string, but got {:?}", args[0])),
+    }
+}
+
+
+/// Built-in function to get the character for a Unicode code point
+pub fn builtin_chr(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("chr() takes exactly one argument".to_string());
+    }
+
+    match &args[0] {
+        Value::Integer(i) => u32::try_from(*i)
+            .ok()
+            .and_then(char::from_u32)
+            .map(|c| Value::String(c.to_string()))
+            .ok_or_else(|| format!("chr() argument {} is not a valid Unicode code point", i)),
+        _ => Err(format!("chr() expects an integer, but got {:?}", args[0])),
+    }
+}
+
+/// Built-in function to pause the script
+/// Takes the duration in seconds, as an integer or a float for fractions of a second
+pub fn builtin_sleep(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("sleep() takes exactly one argument".to_string());
+    }
+
+    let seconds = match &args[0] {
+        Value::Integer(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => return Err(format!("sleep() expects an integer or float number of seconds, but got {:?}", args[0])),
+    };
+    let duration = std::time::Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("sleep() duration must be a non-negative number of seconds, got {}", seconds))?;
+
+    std::thread::sleep(duration);
+    Ok(Value::Null)
+}
+
+/// Collects the elements of any iterable value, strings yield their characters
+fn iterable_items(name: &str, value: &Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Array(items) | Value::Tuple(items) => Ok(items.clone()),
+        Value::String(s) => Ok(s.chars().map(|c| Value::String(c.to_string())).collect()),
+        other => Err(format!("{}() expects an array, tuple or string, but got {:?}", name, other)),
+    }
+}
+
+
+/// Built-in function to pair each element with its index
+/// Returns an array of (index, element) tuples, usable as `for i, item in enumerate(arr)`
+pub fn builtin_enumerate(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("enumerate() takes exactly one argument".to_string());
+    }
+
+    let pairs = iterable_items("enumerate", &args[0])?
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| Value::Tuple(vec![Value::Integer(i as i64), v]))
+        .collect();
+    Ok(Value::Array(pairs))
+}
+
+
+/// Built-in function to combine two or more sequences element by element
+/// Returns an array of tuples, as long as the shortest input
+pub fn builtin_zip(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() < 2 {
+        return Err(format!("zip() takes at least two arguments, but got {}", args.len()));
+    }
+
+    let sequences = args
+        .iter()
+        .map(|arg| iterable_items("zip", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let shortest = sequences.iter().map(Vec::len).min().unwrap_or(0);
+
+    let tuples = (0..shortest)
+        .map(|i| Value::Tuple(sequences.iter().map(|seq| seq[i].clone()).collect()))
+        .collect();
+    Ok(Value::Array(tuples))
+}
+
+/// Built-in function to get a sorted copy of an array or tuple
+/// An optional key function is called on each element and the elements are ordered by its results
+/// The sort is stable, so elements with equal keys keep their original order
+pub fn builtin_sorted(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("sorted() takes one or two arguments, but got {}", args.len()));
+    }
+
+    let mut args = args.into_iter();
+    let mut items = expect_sequence("sorted", args.next().unwrap())?;
+
+    match args.next() {
+        None => sort_values(&mut items)?,
+        Some(key_fn) => {
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                let key = interp.call_function(key_fn.clone(), vec![item.clone()])?;
+                keyed.push((key, item));
+            }
+
+            let mut error = None;
+            keyed.sort_by(|(a, _), (b, _)| {
+                a.compare(b).unwrap_or_else(|e| {
+                    error.get_or_insert(e);
+                    std::cmp::Ordering::Equal
+                })
+            });
+            if let Some(e) = error {
+                return Err(e);
+            }
+            items = keyed.into_iter().map(|(_, item)| item).collect();
+        }
+    }
+
+    Ok(Value::Array(items))
+}
+
+
+/// Extracts the elements of an array or tuple argument for the higher-order builtins
+fn expect_sequence(name: &str, value: Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Array(items) | Value::Tuple(items) => Ok(items),
+        other => Err(format!("{}() expects an array or tuple, but got {:?}", name, other)),
+    }
+}
+
+
+/// Built-in function to apply a function to every element of an array or tuple
+/// Returns a new array with the results, e.g. `map(double, [1, 2])`
+pub fn builtin_map(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("map() takes exactly two arguments: function, array".to_string());
+    }
+
+    let mut args = args.into_iter();
+    let func = args.next().unwrap();
+    let items = expect_sequence("map", args.next().unwrap())?;
+
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        results.push(interp.call_function(func.clone(), vec![item])?);
+    }
+    Ok(Value::Array(results))
+}
+
+
+/// Built-in function to keep the elements for which a function returns True
+/// The function must return a boolean for every element
+pub fn builtin_filter(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("filter() takes exactly two arguments: function, array".to_string());
+    }
+
+    let mut args = args.into_iter();
+    let func = args.next().unwrap();
+    let items = expect_sequence("filter", args.next().unwrap())?;
+
+    let mut results = Vec::new();
+    for item in items {
+        match interp.call_function(func.clone(), vec![item.clone()])? {
+            Value::Bool(true) => results.push(item),
+            Value::Bool(false) => {}
+            other => return Err(format!("filter() function must return a boolean, but got {:?}", other)),
+        }
+    }
+    Ok(Value::Array(results))
+}
+
+
+/// Built-in function to fold an array or tuple into a single value
+/// Called as `reduce(fn, array)` or `reduce(fn, array, initial)`, the function receives (accumulator, element)
+/// Without an initial value the first element is used, so the array must not be empty
+pub fn builtin_reduce(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!("reduce() takes two or three arguments, but got {}", args.len()));
+    }
+
+    let mut args = args.into_iter();
+    let func = args.next().unwrap();
+    let mut items = expect_sequence("reduce", args.next().unwrap())?.into_iter();
+
+    let mut acc = match args.next() {
+        Some(initial) => initial,
+        None => items
+            .next()
+            .ok_or_else(|| "reduce() of empty array with no initial value".to_string())?,
+    };
+    for item in items {
+        acc = interp.call_function(func.clone(), vec![acc, item])?;
+    }
+    Ok(acc)
 }
@@ -0,0 +1,97 @@
+//! `compress` internal module for gzip, zlib and zstd data
+//! Compressing takes a string or byte array and returns a byte array, decompressing returns
+//! a byte array too unless `{"text": True}` is passed
+
+use std::io::{Read, Write};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+use crate::interpreter::value::Value;
+use super::encode::{bytes_to_value, value_to_bytes};
+use super::encoding::bytes_or_text;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("gzip".to_string()), Value::BuiltinFunction(gzip)),
+        (Value::String("gunzip".to_string()), Value::BuiltinFunction(gunzip)),
+        (Value::String("zlib".to_string()), Value::BuiltinFunction(zlib)),
+        (Value::String("unzlib".to_string()), Value::BuiltinFunction(unzlib)),
+        (Value::String("zstd".to_string()), Value::BuiltinFunction(zstd_compress)),
+        (Value::String("unzstd".to_string()), Value::BuiltinFunction(zstd_decompress)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Optional compression level, `range` is what the format accepts
+fn level(name: &str, value: Option<&Value>, range: std::ops::RangeInclusive<i64>) -> Result<Option<i64>, String> {
+    match value {
+        None => Ok(None),
+        Some(Value::Integer(level)) if range.contains(level) => Ok(Some(*level)),
+        Some(other) => Err(format!(
+            "{} level must be an integer in {}..={}, got {:?}",
+            name, range.start(), range.end(), other
+        )),
+    }
+}
+
+fn flate_level(name: &str, value: Option<&Value>) -> Result<Compression, String> {
+    Ok(level(name, value, 0..=9)?.map_or(Compression::default(), |l| Compression::new(l as u32)))
+}
+
+fn read_all(name: &str, mut reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).map_err(|e| format!("compress.{} error: {}", name, e))?;
+    Ok(out)
+}
+
+
+fn gzip(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let data = value_to_bytes("gzip", args.first())?;
+    let mut encoder = GzEncoder::new(Vec::new(), flate_level("gzip", args.get(1))?);
+    encoder
+        .write_all(&data)
+        .and_then(|_| encoder.finish())
+        .map(bytes_to_value)
+        .map_err(|e| format!("compress.gzip error: {}", e))
+}
+
+fn gunzip(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let data = value_to_bytes("gunzip", args.first())?;
+    let out = read_all("gunzip", GzDecoder::new(data.as_slice()))?;
+    bytes_or_text("gunzip", out, args.get(1))
+}
+
+fn zlib(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let data = value_to_bytes("zlib", args.first())?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate_level("zlib", args.get(1))?);
+    encoder
+        .write_all(&data)
+        .and_then(|_| encoder.finish())
+        .map(bytes_to_value)
+        .map_err(|e| format!("compress.zlib error: {}", e))
+}
+
+fn unzlib(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let data = value_to_bytes("unzlib", args.first())?;
+    let out = read_all("unzlib", ZlibDecoder::new(data.as_slice()))?;
+    bytes_or_text("unzlib", out, args.get(1))
+}
+
+fn zstd_compress(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let data = value_to_bytes("zstd", args.first())?;
+    let level = level("zstd", args.get(1), 1..=22)?.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL as i64);
+    zstd::encode_all(data.as_slice(), level as i32)
+        .map(bytes_to_value)
+        .map_err(|e| format!("compress.zstd error: {}", e))
+}
+
+fn zstd_decompress(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let data = value_to_bytes("unzstd", args.first())?;
+    let out = zstd::decode_all(data.as_slice()).map_err(|e| format!("compress.unzstd error: {}", e))?;
+    bytes_or_text("unzstd", out, args.get(1))
+}
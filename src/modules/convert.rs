@@ -0,0 +1,54 @@
+//! Conversion between NIKL values and `serde_json::Value`
+//! Shared by the data format modules, every format is parsed into and serialized from this tree
+
+use serde_json::{Map, Number, Value as Json};
+use crate::interpreter::value::Value;
+
+
+/// Converts parsed data into a NIKL value, objects become hashmaps keeping their key order
+pub fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => Value::String(s),
+        Json::Array(items) => Value::Array(items.into_iter().map(from_json).collect()),
+        Json::Object(map) => Value::HashMap(
+            map.into_iter()
+                .map(|(k, v)| (Value::String(k), from_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+
+/// Converts a NIKL value into serializable data
+/// Tuples become arrays, hashmap keys must be strings and functions can't be converted
+pub fn to_json(value: &Value) -> Result<Json, String> {
+    match value {
+        Value::Null => Ok(Json::Null),
+        Value::Bool(b) => Ok(Json::Bool(*b)),
+        Value::Integer(i) => Ok(Json::Number((*i).into())),
+        Value::Float(f) => Number::from_f64(*f)
+            .map(Json::Number)
+            .ok_or_else(|| format!("Cannot convert non-finite float {} to data", f)),
+        Value::String(s) => Ok(Json::String(s.clone())),
+        Value::Array(items) | Value::Tuple(items) => {
+            items.iter().map(to_json).collect::<Result<Vec<_>, _>>().map(Json::Array)
+        }
+        Value::HashMap(pairs) => {
+            let mut map = Map::with_capacity(pairs.len());
+            for (key, value) in pairs {
+                match key {
+                    Value::String(k) => map.insert(k.clone(), to_json(value)?),
+                    other => return Err(format!("Cannot convert hashmap key {} to data, keys must be strings", other.repr())),
+                };
+            }
+            Ok(Json::Object(map))
+        }
+        other => Err(format!("Cannot convert {} to data", other.type_name())),
+    }
+}
@@ -0,0 +1,54 @@
+//! `crypto` internal module: digests and HMAC, all returned as lowercase hex strings
+//! Data can be a string (hashed as UTF-8) or an array of byte values
+
+use hmac::{Hmac, KeyInit, Mac};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use super::encode::{to_hex, value_to_bytes};
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("sha256".to_string()), Value::BuiltinFunction(crypto_sha256)),
+        (Value::String("sha1".to_string()), Value::BuiltinFunction(crypto_sha1)),
+        (Value::String("md5".to_string()), Value::BuiltinFunction(crypto_md5)),
+        (Value::String("hmac_sha256".to_string()), Value::BuiltinFunction(crypto_hmac_sha256)),
+    ];
+    Value::HashMap(items)
+}
+
+
+fn hex_digest<D: Digest>(name: &str, args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("{} expects exactly one argument, got {}", name, args.len()));
+    }
+    let data = value_to_bytes(name, args.first())?;
+    Ok(Value::String(to_hex(&D::digest(&data))))
+}
+
+fn crypto_sha256(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    hex_digest::<Sha256>("crypto.sha256", &args)
+}
+
+fn crypto_sha1(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    hex_digest::<Sha1>("crypto.sha1", &args)
+}
+
+fn crypto_md5(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    hex_digest::<Md5>("crypto.md5", &args)
+}
+
+fn crypto_hmac_sha256(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!("crypto.hmac_sha256 expects a key and data, got {} argument(s)", args.len()));
+    }
+    let key = value_to_bytes("crypto.hmac_sha256", args.first())?;
+    let data = value_to_bytes("crypto.hmac_sha256", args.get(1))?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+        .map_err(|e| format!("crypto.hmac_sha256 error: {}", e))?;
+    mac.update(&data);
+    Ok(Value::String(to_hex(&mac.finalize().into_bytes())))
+}
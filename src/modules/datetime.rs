@@ -0,0 +1,197 @@
+//! `datetime` internal module, formats and parses the float timestamps returned by `time.now()`
+//! Functions take an optional zone: "utc" (the default), "local", or a fixed offset such as
+//! "+05:30", "-0800" or a number of seconds east of UTC
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike, Utc};
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("format".to_string()), Value::BuiltinFunction(format)),
+        (Value::String("parse".to_string()), Value::BuiltinFunction(parse)),
+        (Value::String("iso".to_string()), Value::BuiltinFunction(iso)),
+        (Value::String("parse_iso".to_string()), Value::BuiltinFunction(parse_iso)),
+        (Value::String("components".to_string()), Value::BuiltinFunction(components)),
+        (Value::String("utc_offset".to_string()), Value::BuiltinFunction(utc_offset)),
+    ];
+    Value::HashMap(items)
+}
+
+
+enum Zone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    fn from_value(name: &str, value: Option<&Value>) -> Result<Zone, String> {
+        let invalid = || format!("{} expects a zone of \"utc\", \"local\" or an offset like \"+05:30\"", name);
+        match value {
+            None => Ok(Zone::Utc),
+            Some(Value::Integer(seconds)) => i32::try_from(*seconds)
+                .ok()
+                .and_then(FixedOffset::east_opt)
+                .map(Zone::Fixed)
+                .ok_or_else(invalid),
+            Some(Value::String(zone)) => match zone.to_lowercase().as_str() {
+                "utc" | "z" => Ok(Zone::Utc),
+                "local" => Ok(Zone::Local),
+                offset => parse_offset(offset).map(Zone::Fixed).ok_or_else(invalid),
+            },
+            Some(_) => Err(invalid()),
+        }
+    }
+
+    fn convert(&self, dt: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Utc => dt.fixed_offset(),
+            Zone::Local => dt.with_timezone(&Local).fixed_offset(),
+            Zone::Fixed(offset) => dt.with_timezone(offset),
+        }
+    }
+
+    /// Places a wall clock time in this zone, the earlier instant wins when the time is ambiguous
+    fn localize(&self, naive: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Zone::Utc => Some(naive.and_utc().fixed_offset()),
+            Zone::Local => Local.from_local_datetime(&naive).earliest().map(|dt| dt.fixed_offset()),
+            Zone::Fixed(offset) => offset.from_local_datetime(&naive).earliest(),
+        }
+    }
+}
+
+/// Parses "+05:30", "+0530" or "+05"
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = text[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?),
+        _ => return None,
+    };
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn from_timestamp(name: &str, value: Option<&Value>) -> Result<DateTime<Utc>, String> {
+    let seconds = match value {
+        Some(Value::Integer(i)) => *i as f64,
+        Some(Value::Float(f)) => *f,
+        _ => return Err(format!("{} expects a timestamp in seconds", name)),
+    };
+    // `as` would quietly turn NaN into 0 and saturate the infinities
+    if !seconds.is_finite() {
+        return Err(format!("{} timestamp {} is not a finite number", name, seconds));
+    }
+    let whole = seconds.floor();
+    let nanos = ((seconds - whole) * 1e9).round().min(999_999_999.0) as u32;
+    DateTime::from_timestamp(whole as i64, nanos).ok_or_else(|| format!("{} timestamp {} is out of range", name, seconds))
+}
+
+fn to_timestamp<Tz: TimeZone>(dt: DateTime<Tz>) -> Value {
+    Value::Float(dt.timestamp() as f64 + dt.timestamp_subsec_nanos() as f64 / 1e9)
+}
+
+fn expect_string<'a>(name: &str, what: &str, value: Option<&'a Value>) -> Result<&'a str, String> {
+    match value {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("{} expects {} as a string", name, what)),
+    }
+}
+
+
+/// `format(ts, fmt[, zone])` with strftime style specifiers, e.g. "%Y-%m-%d %H:%M"
+fn format(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let dt = from_timestamp("format", args.first())?;
+    let fmt = expect_string("format", "the format", args.get(1))?;
+    let zone = Zone::from_value("format", args.get(2))?;
+
+    let mut out = String::new();
+    write!(out, "{}", zone.convert(dt).format(fmt))
+        .map_err(|_| format!("datetime.format error: invalid format string '{}'", fmt))?;
+    Ok(Value::String(out))
+}
+
+/// `parse(text, fmt[, zone])`, the zone is only used when the format has no `%z` offset
+/// Date only formats give midnight
+fn parse(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let text = expect_string("parse", "the text", args.first())?;
+    let fmt = expect_string("parse", "the format", args.get(1))?;
+    let zone = Zone::from_value("parse", args.get(2))?;
+
+    if let Ok(dt) = DateTime::parse_from_str(text, fmt) {
+        return Ok(to_timestamp(dt));
+    }
+    let naive = NaiveDateTime::parse_from_str(text, fmt)
+        .or_else(|e| {
+            NaiveDate::parse_from_str(text, fmt)
+                .map(|date| date.and_time(Default::default()))
+                .map_err(|_| e)
+        })
+        .map_err(|e| format!("datetime.parse error: '{}' does not match '{}': {}", text, fmt, e))?;
+    zone.localize(naive)
+        .map(to_timestamp)
+        .ok_or_else(|| format!("datetime.parse error: '{}' does not exist in the given zone", text))
+}
+
+/// RFC 3339 text such as "2024-05-01T12:30:00+00:00"
+fn iso(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let dt = from_timestamp("iso", args.first())?;
+    let zone = Zone::from_value("iso", args.get(1))?;
+    Ok(Value::String(zone.convert(dt).to_rfc3339()))
+}
+
+fn parse_iso(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let text = expect_string("parse_iso", "the text", args.first())?;
+    DateTime::parse_from_rfc3339(text.trim())
+        .map(to_timestamp)
+        .map_err(|e| format!("datetime.parse_iso error: {}", e))
+}
+
+/// Calendar fields of a timestamp, `weekday` counts from 0 for Monday and `offset` is in seconds
+fn components(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let dt = zone_time("components", &args)?;
+    let field = |name: &str, value: i64| (Value::String(name.to_string()), Value::Integer(value));
+    Ok(Value::HashMap(vec![
+        field("year", dt.year() as i64),
+        field("month", dt.month() as i64),
+        field("day", dt.day() as i64),
+        field("hour", dt.hour() as i64),
+        field("minute", dt.minute() as i64),
+        field("second", dt.second() as i64),
+        field("microsecond", (dt.nanosecond() / 1000) as i64),
+        field("weekday", dt.weekday().num_days_from_monday() as i64),
+        field("yearday", dt.ordinal() as i64),
+        field("offset", dt.offset().fix().local_minus_utc() as i64),
+    ]))
+}
+
+/// `utc_offset(zone[, ts])` is the zone's offset in seconds at `ts`, or now
+fn utc_offset(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let zone = Zone::from_value("utc_offset", args.first())?;
+    let dt = match args.get(1) {
+        Some(_) => from_timestamp("utc_offset", args.get(1))?,
+        None => Utc::now(),
+    };
+    Ok(Value::Integer(zone.convert(dt).offset().local_minus_utc() as i64))
+}
+
+fn zone_time(name: &str, args: &[Value]) -> Result<DateTime<FixedOffset>, String> {
+    let dt = from_timestamp(name, args.first())?;
+    Ok(Zone::from_value(name, args.get(1))?.convert(dt))
+}
@@ -0,0 +1,130 @@
+//! `encode` and `decode` internal modules for base64 and hex
+//! Binary data is passed around as arrays of integers in 0..=255
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_encode_module() -> Value {
+    let items = vec![
+        (Value::String("base64".to_string()), Value::BuiltinFunction(encode_base64)),
+        (Value::String("base64_url".to_string()), Value::BuiltinFunction(encode_base64_url)),
+        (Value::String("hex".to_string()), Value::BuiltinFunction(encode_hex)),
+    ];
+    Value::HashMap(items)
+}
+
+pub fn make_decode_module() -> Value {
+    let items = vec![
+        (Value::String("base64".to_string()), Value::BuiltinFunction(decode_base64)),
+        (Value::String("base64_bytes".to_string()), Value::BuiltinFunction(decode_base64_bytes)),
+        (Value::String("base64_url".to_string()), Value::BuiltinFunction(decode_base64_url)),
+        (Value::String("hex".to_string()), Value::BuiltinFunction(decode_hex)),
+        (Value::String("hex_bytes".to_string()), Value::BuiltinFunction(decode_hex_bytes)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Gets the raw bytes of a string (UTF-8) or of an array of byte values
+pub fn value_to_bytes(name: &str, value: Option<&Value>) -> Result<Vec<u8>, String> {
+    match value {
+        Some(Value::String(s)) => Ok(s.as_bytes().to_vec()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Integer(b) => u8::try_from(*b).map_err(|_| format!("{} expects bytes in 0..=255, got {}", name, b)),
+                other => Err(format!("{} expects an array of integers, got {:?}", name, other)),
+            })
+            .collect(),
+        _ => Err(format!("{} expects a string or an array of bytes", name)),
+    }
+}
+
+pub fn bytes_to_value(bytes: Vec<u8>) -> Value {
+    Value::Array(bytes.into_iter().map(|b| Value::Integer(b as i64)).collect())
+}
+
+pub fn bytes_to_string(name: &str, bytes: Vec<u8>) -> Result<Value, String> {
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| format!("{} result is not valid UTF-8, use the _bytes variant for binary data", name))
+}
+
+fn expect_text<'a>(name: &str, args: &'a [Value]) -> Result<&'a str, String> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(s.trim()),
+        _ => Err(format!("{} expects an encoded string", name)),
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if text.len() % 2 != 0 {
+        return Err("hex string must have an even number of digits".to_string());
+    }
+    // Per nibble, `u8::from_str_radix` would take a leading `+` as a sign
+    let digit = |b: u8| (b as char).to_digit(16);
+    text.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| match (digit(pair[0]), digit(pair[1])) {
+            (Some(high), Some(low)) => Ok((high * 16 + low) as u8),
+            _ => Err(format!("invalid hex digits at position {}", i * 2)),
+        })
+        .collect()
+}
+
+
+fn encode_base64(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = value_to_bytes("encode.base64", args.first())?;
+    Ok(Value::String(STANDARD.encode(bytes)))
+}
+
+fn encode_base64_url(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = value_to_bytes("encode.base64_url", args.first())?;
+    Ok(Value::String(URL_SAFE_NO_PAD.encode(bytes)))
+}
+
+fn encode_hex(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = value_to_bytes("encode.hex", args.first())?;
+    Ok(Value::String(to_hex(&bytes)))
+}
+
+fn decode_base64(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = STANDARD
+        .decode(expect_text("decode.base64", &args)?)
+        .map_err(|e| format!("decode.base64 error: {}", e))?;
+    bytes_to_string("decode.base64", bytes)
+}
+
+fn decode_base64_bytes(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    STANDARD
+        .decode(expect_text("decode.base64_bytes", &args)?)
+        .map(bytes_to_value)
+        .map_err(|e| format!("decode.base64_bytes error: {}", e))
+}
+
+fn decode_base64_url(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(expect_text("decode.base64_url", &args)?.trim_end_matches('='))
+        .map_err(|e| format!("decode.base64_url error: {}", e))?;
+    bytes_to_string("decode.base64_url", bytes)
+}
+
+fn decode_hex(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = from_hex(expect_text("decode.hex", &args)?)
+        .map_err(|e| format!("decode.hex error: {}", e))?;
+    bytes_to_string("decode.hex", bytes)
+}
+
+fn decode_hex_bytes(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    from_hex(expect_text("decode.hex_bytes", &args)?)
+        .map(bytes_to_value)
+        .map_err(|e| format!("decode.hex_bytes error: {}", e))
+}
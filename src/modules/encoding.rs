@@ -0,0 +1,106 @@
+//! `encoding` internal module, converts between text and byte arrays
+//! Byte arrays use the same form as the `encode`/`decode` modules, integers in 0..=255
+
+use crate::interpreter::value::Value;
+use super::encode::{bytes_to_value, from_hex, to_hex, value_to_bytes};
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("to_utf8_bytes".to_string()), Value::BuiltinFunction(to_utf8_bytes)),
+        (Value::String("from_utf8".to_string()), Value::BuiltinFunction(from_utf8)),
+        (Value::String("is_utf8".to_string()), Value::BuiltinFunction(is_utf8)),
+        (Value::String("to_hex".to_string()), Value::BuiltinFunction(encoding_to_hex)),
+        (Value::String("from_hex".to_string()), Value::BuiltinFunction(encoding_from_hex)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Reads the `{"lossy": bool}` option of the decoding functions, strict by default
+fn lossy_option(name: &str, value: Option<&Value>) -> Result<bool, String> {
+    let mut lossy = false;
+    match value {
+        None => {}
+        Some(Value::HashMap(options)) => {
+            for (key, value) in options {
+                match (key, value) {
+                    (Value::String(k), Value::Bool(b)) if k == "lossy" => lossy = *b,
+                    (k, v) => return Err(format!("{} option {} has invalid value {:?}", name, k, v)),
+                }
+            }
+        }
+        Some(other) => return Err(format!("{} expects options as a hashmap, got {:?}", name, other)),
+    }
+    Ok(lossy)
+}
+
+/// Decoded binary data, as a byte array or as text when the options hold `{"text": True}`
+/// Text decoding is strict unless `{"lossy": True}` is also given
+pub fn bytes_or_text(name: &str, bytes: Vec<u8>, options: Option<&Value>) -> Result<Value, String> {
+    let (mut as_text, mut lossy) = (false, false);
+    match options {
+        None => {}
+        Some(Value::HashMap(options)) => {
+            for (key, value) in options {
+                match (key, value) {
+                    (Value::String(k), Value::Bool(b)) if k == "text" => as_text = *b,
+                    (Value::String(k), Value::Bool(b)) if k == "lossy" => lossy = *b,
+                    (k, v) => return Err(format!("{} option {} has invalid value {:?}", name, k, v)),
+                }
+            }
+        }
+        Some(other) => return Err(format!("{} expects options as a hashmap, got {:?}", name, other)),
+    }
+
+    if as_text {
+        decode_utf8(name, bytes, lossy)
+    } else {
+        Ok(bytes_to_value(bytes))
+    }
+}
+
+/// Strict decoding fails on invalid UTF-8, lossy decoding replaces it with U+FFFD
+fn decode_utf8(name: &str, bytes: Vec<u8>, lossy: bool) -> Result<Value, String> {
+    if lossy {
+        return Ok(Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    }
+    String::from_utf8(bytes).map(Value::String).map_err(|e| {
+        format!("{} error: invalid UTF-8 at byte {}, pass {{\"lossy\": True}} to replace it", name, e.utf8_error().valid_up_to())
+    })
+}
+
+
+fn to_utf8_bytes(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(bytes_to_value(s.as_bytes().to_vec())),
+        _ => Err("to_utf8_bytes expects a string".to_string()),
+    }
+}
+
+fn from_utf8(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = value_to_bytes("from_utf8", args.first())?;
+    let lossy = lossy_option("from_utf8", args.get(1))?;
+    decode_utf8("from_utf8", bytes, lossy)
+}
+
+fn is_utf8(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = value_to_bytes("is_utf8", args.first())?;
+    Ok(Value::Bool(std::str::from_utf8(&bytes).is_ok()))
+}
+
+fn encoding_to_hex(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let bytes = value_to_bytes("to_hex", args.first())?;
+    Ok(Value::String(to_hex(&bytes)))
+}
+
+/// Returns a byte array, or text when called with `{"text": True}` (which also takes `lossy`)
+fn encoding_from_hex(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let text = match args.first() {
+        Some(Value::String(s)) => s.trim(),
+        _ => return Err("from_hex expects a hex string".to_string()),
+    };
+    let bytes = from_hex(text).map_err(|e| format!("from_hex error: {}", e))?;
+    bytes_or_text("from_hex", bytes, args.get(1))
+}
@@ -0,0 +1,210 @@
+//! File objects returned by `os.open(path, mode)`
+//! Modes follow C/Python: "r", "w", "a", "x", with "+" for reading and writing and "b" for
+//! binary files, whose reads return byte arrays and whose writes also accept them
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::interpreter::value::{NativeObject, Value};
+use super::encode::{bytes_to_value, value_to_bytes};
+use crate::interpreter::Interpreter;
+
+
+pub fn open(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (path, mode) = match args.as_slice() {
+        [Value::String(path)] => (path, "r"),
+        [Value::String(path), Value::String(mode)] => (path, mode.as_str()),
+        _ => return Err("open expects a path and an optional mode string".to_string()),
+    };
+    let (options, binary) = parse_mode(mode)?;
+    let file = options.open(path).map_err(|e| format!("os.open error: {}: {}", path, e))?;
+    Ok(Value::Native(Arc::new(FileHandle {
+        path: path.clone(),
+        binary,
+        file: Mutex::new(Some(BufReader::new(file))),
+    })))
+}
+
+fn parse_mode(mode: &str) -> Result<(OpenOptions, bool), String> {
+    let invalid = || format!("Invalid file mode '{}', expected r, w, a or x with optional + and b", mode);
+    let mut chars: Vec<char> = mode.chars().collect();
+    let binary = chars.contains(&'b');
+    let update = chars.contains(&'+');
+    chars.retain(|c| *c != 'b' && *c != '+');
+    if chars.len() != 1 || mode.chars().count() != 1 + binary as usize + update as usize {
+        return Err(invalid());
+    }
+
+    let mut options = OpenOptions::new();
+    match chars[0] {
+        'r' => options.read(true).write(update),
+        'w' => options.write(true).create(true).truncate(true).read(update),
+        'a' => options.append(true).create(true).read(update),
+        'x' => options.write(true).create_new(true).read(update),
+        _ => return Err(invalid()),
+    };
+    Ok((options, binary))
+}
+
+
+#[derive(Debug)]
+struct FileHandle {
+    path: String,
+    binary: bool,
+    /// `None` once closed, reads go through the buffer and writes bypass it
+    file: Mutex<Option<BufReader<File>>>,
+}
+
+impl FileHandle {
+    fn with_file<T>(&self, f: impl FnOnce(&mut BufReader<File>) -> std::io::Result<T>) -> Result<T, String> {
+        let mut guard = self.file.lock().map_err(|_| "file handle is poisoned".to_string())?;
+        let file = guard.as_mut().ok_or_else(|| format!("file '{}' is closed", self.path))?;
+        f(file).map_err(|e| format!("file error: {}: {}", self.path, e))
+    }
+
+    /// The next line without its line ending, `None` at the end of the file
+    fn read_line(&self) -> Result<Option<Value>, String> {
+        let mut line = Vec::new();
+        if self.with_file(|file| file.read_until(b'\n', &mut line))? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        self.decode(line).map(Some)
+    }
+
+    /// Reads `n` characters (bytes in binary mode), or everything left when `n` is absent
+    fn read(&self, limit: Option<usize>) -> Result<Value, String> {
+        let mut bytes = Vec::new();
+        match limit {
+            None => {
+                self.with_file(|file| file.read_to_end(&mut bytes))?;
+            }
+            Some(n) if self.binary => {
+                self.with_file(|file| file.take(n as u64).read_to_end(&mut bytes))?;
+            }
+            Some(n) => {
+                self.with_file(|file| {
+                    for _ in 0..n {
+                        let mut first = [0u8; 1];
+                        if file.read(&mut first)? == 0 {
+                            break;
+                        }
+                        // Pull in the rest of a multi-byte character
+                        let width = match first[0] {
+                            0xF0..=0xFF => 4,
+                            0xE0..=0xEF => 3,
+                            0xC0..=0xDF => 2,
+                            _ => 1,
+                        };
+                        bytes.push(first[0]);
+                        let mut rest = [0u8; 3];
+                        file.read_exact(&mut rest[..width - 1])?;
+                        bytes.extend_from_slice(&rest[..width - 1]);
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        self.decode(bytes)
+    }
+
+    fn decode(&self, bytes: Vec<u8>) -> Result<Value, String> {
+        if self.binary {
+            return Ok(bytes_to_value(bytes));
+        }
+        String::from_utf8(bytes)
+            .map(Value::String)
+            .map_err(|_| format!("file '{}' is not valid UTF-8, open it in binary mode", self.path))
+    }
+
+    fn write(&self, data: Option<&Value>) -> Result<Value, String> {
+        let bytes = match data {
+            Some(Value::String(s)) => s.as_bytes().to_vec(),
+            Some(value @ Value::Array(_)) if self.binary => value_to_bytes("write", Some(value))?,
+            _ if self.binary => return Err("write() expects a string or an array of bytes".to_string()),
+            _ => return Err("write() expects a string".to_string()),
+        };
+        self.with_file(|file| {
+            // Drops read-ahead data so the write lands at the logical position
+            let position = file.stream_position()?;
+            file.seek(SeekFrom::Start(position))?;
+            file.get_mut().write_all(&bytes)
+        })?;
+        Ok(Value::Integer(bytes.len() as i64))
+    }
+
+    /// `seek(offset[, whence])` where whence is "start" (default), "current" or "end"
+    fn seek(&self, args: &[Value]) -> Result<Value, String> {
+        let position = match args {
+            [Value::Integer(offset)] => SeekFrom::Start(u64::try_from(*offset).map_err(|_| "seek() offset from the start must be positive".to_string())?),
+            [Value::Integer(offset), Value::String(whence)] => match whence.as_str() {
+                "start" => SeekFrom::Start(u64::try_from(*offset).map_err(|_| "seek() offset from the start must be positive".to_string())?),
+                "current" => SeekFrom::Current(*offset),
+                "end" => SeekFrom::End(*offset),
+                other => return Err(format!("seek() whence must be \"start\", \"current\" or \"end\", got '{}'", other)),
+            },
+            _ => return Err("seek() expects an offset and an optional whence".to_string()),
+        };
+        self.with_file(|file| file.seek(position)).map(|pos| Value::Integer(pos as i64))
+    }
+}
+
+impl NativeObject for FileHandle {
+    fn type_name(&self) -> &'static str {
+        "File"
+    }
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        match (method, args.as_slice()) {
+            ("read_line", []) => self.read_line().map(|line| line.unwrap_or(Value::Null)),
+            ("read_lines", []) => {
+                let mut lines = Vec::new();
+                while let Some(line) = self.read_line()? {
+                    lines.push(line);
+                }
+                Ok(Value::Array(lines))
+            }
+            ("read", []) => self.read(None),
+            ("read", [Value::Integer(n)]) if *n >= 0 => self.read(Some(*n as usize)),
+            ("read", _) => Err("read() expects an optional non-negative count".to_string()),
+            ("write", [data]) => self.write(Some(data)),
+            ("write", _) => self.write(None),
+            ("seek", _) => self.seek(&args),
+            ("tell", []) => self.with_file(|file| file.stream_position()).map(|pos| Value::Integer(pos as i64)),
+            ("flush", []) => self.with_file(|file| file.get_mut().flush()).map(|_| Value::Null),
+            ("close", []) => {
+                let mut guard = self.file.lock().map_err(|_| "file handle is poisoned".to_string())?;
+                guard.take();
+                Ok(Value::Null)
+            }
+            ("is_closed", []) => {
+                let guard = self.file.lock().map_err(|_| "file handle is poisoned".to_string())?;
+                Ok(Value::Bool(guard.is_none()))
+            }
+            ("path", []) => Ok(Value::String(self.path.clone())),
+            ("read_line" | "read_lines" | "tell" | "flush" | "close" | "is_closed" | "path", _) => {
+                Err(format!("{}() expects no arguments", method))
+            }
+            _ => Err(format!("File has no method '{}'", method)),
+        }
+    }
+
+    fn get_property(&self, name: &str) -> Option<Value> {
+        match name {
+            "path" => Some(Value::String(self.path.clone())),
+            "binary" => Some(Value::Bool(self.binary)),
+            "closed" => self.file.lock().ok().map(|guard| Value::Bool(guard.is_none())),
+            _ => None,
+        }
+    }
+
+    fn next_item(&self) -> Result<Option<Value>, String> {
+        self.read_line()
+    }
+}
@@ -1,6 +1,155 @@
+#[cfg(feature = "native")]
+mod archive;
+mod argparse;
+#[cfg(feature = "native")]
+mod async_tasks;
+#[cfg(feature = "native")]
+mod bench;
 pub mod builtin_core;
+pub mod convert;
+#[cfg(feature = "native")]
+mod compress;
+mod crypto;
+#[cfg(feature = "native")]
+mod datetime;
+#[cfg(feature = "native")]
+mod db;
+mod encode;
+mod encoding;
+#[cfg(feature = "native")]
+mod file;
+#[cfg(feature = "native")]
+mod network;
+#[cfg(feature = "native")]
 mod os;
+mod path;
 mod regex;
+#[cfg(feature = "native")]
+mod server;
+#[cfg(feature = "native")]
+mod sql;
+#[cfg(feature = "native")]
+mod sqlite;
+#[cfg(feature = "native")]
+mod subprocess;
+#[cfg(feature = "native")]
+mod sys;
+mod term;
+pub mod testing;
+#[cfg(feature = "native")]
+mod thread;
+#[cfg(feature = "native")]
+mod time;
+#[cfg(feature = "native")]
+mod timer;
+mod toml;
+mod yaml;
 
+#[cfg(feature = "native")]
+use std::time::Duration;
+
+use crate::interpreter::value::Value;
+
+#[cfg(feature = "native")]
+pub use archive::make_module as make_archive_module;
+pub use argparse::make_module as make_argparse_module;
+#[cfg(feature = "native")]
+pub use async_tasks::make_module as make_async_module;
+#[cfg(feature = "native")]
+pub use bench::make_module as make_bench_module;
+#[cfg(feature = "native")]
+pub use compress::make_module as make_compress_module;
+pub use crypto::make_module as make_crypto_module;
+#[cfg(feature = "native")]
+pub use datetime::make_module as make_datetime_module;
+#[cfg(feature = "native")]
+pub use db::make_module as make_db_module;
+pub use encode::{make_encode_module, make_decode_module};
+pub use encoding::make_module as make_encoding_module;
+#[cfg(feature = "native")]
+pub use network::make_module as make_network_module;
+#[cfg(feature = "native")]
 pub use os::make_module as make_os_module;
+pub use path::make_module as make_path_module;
 pub use regex::make_module as make_regex_module;
+#[cfg(feature = "native")]
+pub use server::make_module as make_server_module;
+#[cfg(feature = "native")]
+pub use sqlite::make_module as make_sqlite_module;
+#[cfg(feature = "native")]
+pub use subprocess::make_module as make_subprocess_module;
+#[cfg(feature = "native")]
+pub use sys::make_module as make_sys_module;
+pub use term::make_module as make_term_module;
+pub use testing::make_module as make_testing_module;
+#[cfg(feature = "native")]
+pub use thread::make_module as make_thread_module;
+#[cfg(feature = "native")]
+pub use time::make_module as make_time_module;
+#[cfg(feature = "native")]
+pub use timer::make_module as make_timer_module;
+pub use toml::make_module as make_toml_module;
+pub use yaml::make_module as make_yaml_module;
+
+
+/// Builds the internal module imported as `import "<name>" as alias`, if one exists
+/// Modules that need an operating system only exist with the `native` feature
+pub fn make_internal_module(name: &str) -> Option<Value> {
+    match name {
+        #[cfg(feature = "native")]
+        "os" => Some(make_os_module()),
+        #[cfg(feature = "native")]
+        "network" => Some(make_network_module()),
+        "argparse" => Some(make_argparse_module()),
+        #[cfg(feature = "native")]
+        "archive" => Some(make_archive_module()),
+        #[cfg(feature = "native")]
+        "async" => Some(make_async_module()),
+        #[cfg(feature = "native")]
+        "bench" => Some(make_bench_module()),
+        "encode" => Some(make_encode_module()),
+        "decode" => Some(make_decode_module()),
+        "encoding" => Some(make_encoding_module()),
+        #[cfg(feature = "native")]
+        "compress" => Some(make_compress_module()),
+        "crypto" => Some(make_crypto_module()),
+        "path" => Some(make_path_module()),
+        "regex" => Some(make_regex_module()),
+        #[cfg(feature = "native")]
+        "server" => Some(make_server_module()),
+        #[cfg(feature = "native")]
+        "sqlite" => Some(make_sqlite_module()),
+        #[cfg(feature = "native")]
+        "db" => Some(make_db_module()),
+        #[cfg(feature = "native")]
+        "subprocess" => Some(make_subprocess_module()),
+        #[cfg(feature = "native")]
+        "sys" => Some(make_sys_module()),
+        "term" => Some(make_term_module()),
+        "testing" => Some(make_testing_module()),
+        #[cfg(feature = "native")]
+        "thread" => Some(make_thread_module()),
+        #[cfg(feature = "native")]
+        "time" => Some(make_time_module()),
+        #[cfg(feature = "native")]
+        "timer" => Some(make_timer_module()),
+        #[cfg(feature = "native")]
+        "datetime" => Some(make_datetime_module()),
+        "toml" => Some(make_toml_module()),
+        "yaml" => Some(make_yaml_module()),
+        _ => None,
+    }
+}
+
+
+/// A timeout argument in seconds, `default` when it's missing. Positive and small enough for a `Duration`
+#[cfg(feature = "native")]
+pub(crate) fn timeout_arg(name: &str, value: Option<&Value>, default: f64) -> Result<Duration, String> {
+    let seconds = match value {
+        None => default,
+        Some(Value::Integer(i)) if *i > 0 => *i as f64,
+        Some(Value::Float(f)) if *f > 0.0 => *f,
+        Some(other) => return Err(format!("{} timeout must be a positive number of seconds, got {:?}", name, other)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("{} timeout is too large, got {} seconds", name, seconds))
+}
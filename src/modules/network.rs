@@ -0,0 +1,143 @@
+//! `network` internal module for DNS lookups, reachability checks and local interfaces
+//! Timeouts are in seconds and default to 2
+
+use std::collections::HashSet;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use if_addrs::IfAddr;
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+const DEFAULT_TIMEOUT: f64 = 2.0;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("lookup".to_string()), Value::BuiltinFunction(lookup)),
+        (Value::String("port_open".to_string()), Value::BuiltinFunction(port_open)),
+        (Value::String("ping".to_string()), Value::BuiltinFunction(ping)),
+        (Value::String("interfaces".to_string()), Value::BuiltinFunction(interfaces)),
+        (Value::String("local_ip".to_string()), Value::BuiltinFunction(local_ip)),
+    ];
+    Value::HashMap(items)
+}
+
+
+fn expect_host<'a>(name: &str, value: Option<&'a Value>) -> Result<&'a str, String> {
+    match value {
+        Some(Value::String(host)) if !host.is_empty() => Ok(host),
+        _ => Err(format!("{} expects a host name or IP address", name)),
+    }
+}
+
+/// Resolves a host, keeping the resolver's order and dropping duplicates
+fn resolve(name: &str, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let mut seen = HashSet::new();
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("network.{} error: {}: {}", name, host, e))?
+        .filter(|addr| seen.insert(addr.ip()))
+        .collect();
+    Ok(addrs)
+}
+
+
+/// `lookup(host)` returns the host's IP addresses as strings
+fn lookup(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let host = expect_host("lookup", args.first())?;
+    let addrs = resolve("lookup", host, 0)?;
+    Ok(Value::Array(addrs.into_iter().map(|addr| Value::String(addr.ip().to_string())).collect()))
+}
+
+/// `port_open(host, port[, timeout])` is True when a TCP connection succeeds
+fn port_open(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let host = expect_host("port_open", args.first())?;
+    let port = match args.get(1) {
+        Some(Value::Integer(port)) => u16::try_from(*port).map_err(|_| format!("port_open port {} is out of range", port))?,
+        _ => return Err("port_open expects a host and a port number".to_string()),
+    };
+    let timeout = super::timeout_arg("port_open", args.get(2), DEFAULT_TIMEOUT)?;
+
+    let open = resolve("port_open", host, port)?
+        .iter()
+        .any(|addr| TcpStream::connect_timeout(addr, timeout).is_ok());
+    Ok(Value::Bool(open))
+}
+
+/// `ping(host[, timeout])` sends one echo request with the system `ping` command, since raw
+/// ICMP sockets need privileges, and returns `{"reachable", "time"}` with the time in seconds
+fn ping(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let host = expect_host("ping", args.first())?;
+    if host.starts_with('-') {
+        return Err(format!("ping expects a host name, got '{}'", host));
+    }
+    let timeout = super::timeout_arg("ping", args.get(1), DEFAULT_TIMEOUT)?;
+
+    let mut command = Command::new("ping");
+    if cfg!(windows) {
+        command.args(["-n", "1", "-w", &timeout.as_millis().to_string(), host]);
+    } else if cfg!(target_os = "macos") {
+        command.args(["-c", "1", "-t", &timeout.as_secs().max(1).to_string(), host]);
+    } else {
+        command.args(["-c", "1", "-W", &timeout.as_secs().max(1).to_string(), host]);
+    }
+    let start = Instant::now();
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("network.ping error: {}", e))?;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    Ok(Value::HashMap(vec![
+        (Value::String("reachable".to_string()), Value::Bool(status.success())),
+        (Value::String("time".to_string()), if status.success() { Value::Float(elapsed) } else { Value::Null }),
+    ]))
+}
+
+/// Every address of every interface as `{"name", "ip", "netmask", "prefix", "is_ipv6", "is_loopback"}`
+fn interfaces(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("interfaces expects no arguments".to_string());
+    }
+    let interfaces = if_addrs::get_if_addrs().map_err(|e| format!("network.interfaces error: {}", e))?;
+    let entries = interfaces
+        .into_iter()
+        .map(|interface| {
+            let (netmask, prefix) = match &interface.addr {
+                IfAddr::V4(addr) => (addr.netmask.to_string(), addr.prefixlen),
+                IfAddr::V6(addr) => (addr.netmask.to_string(), addr.prefixlen),
+            };
+            let ip = interface.ip();
+            Value::HashMap(vec![
+                (Value::String("name".to_string()), Value::String(interface.name.clone())),
+                (Value::String("ip".to_string()), Value::String(ip.to_string())),
+                (Value::String("netmask".to_string()), Value::String(netmask)),
+                (Value::String("prefix".to_string()), Value::Integer(prefix as i64)),
+                (Value::String("is_ipv6".to_string()), Value::Bool(ip.is_ipv6())),
+                (Value::String("is_loopback".to_string()), Value::Bool(interface.is_loopback())),
+            ])
+        })
+        .collect();
+    Ok(Value::Array(entries))
+}
+
+/// The address outgoing traffic would use, or None without a route
+/// A UDP "connect" only picks a route, nothing is sent
+fn local_ip(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("local_ip expects no arguments".to_string());
+    }
+    let route = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.connect("192.0.2.1:9").map(|_| socket))
+        .and_then(|socket| socket.local_addr());
+    match route.map(|addr| addr.ip()) {
+        Ok(ip) if !ip.is_unspecified() => Ok(Value::String(ip.to_string())),
+        _ => Ok(Value::Null),
+    }
+}
+
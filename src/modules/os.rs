@@ -1,6 +1,7 @@
-use std::{env, fs, path::Path};
+use std::{env, fs, path::{Component, Path, PathBuf}};
 
 use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
 
 
 pub fn make_module() -> Value {
@@ -17,21 +18,30 @@ pub fn make_module() -> Value {
         (Value::String("is_dir".to_string()), Value::BuiltinFunction(is_dir)),
         (Value::String("read_file".to_string()), Value::BuiltinFunction(read_file)),
         (Value::String("write_file".to_string()), Value::BuiltinFunction(write_file)),
+        (Value::String("open".to_string()), Value::BuiltinFunction(super::file::open)),
         (Value::String("env_get".to_string()), Value::BuiltinFunction(env_get)),
         (Value::String("env_set".to_string()), Value::BuiltinFunction(env_set)),
+        (Value::String("load_env".to_string()), Value::BuiltinFunction(load_env)),
+        (Value::String("glob".to_string()), Value::BuiltinFunction(glob)),
+        (Value::String("fnmatch".to_string()), Value::BuiltinFunction(fnmatch)),
+        (Value::String("walk".to_string()), Value::BuiltinFunction(walk)),
+        (Value::String("stat".to_string()), Value::BuiltinFunction(stat)),
+        (Value::String("chmod".to_string()), Value::BuiltinFunction(chmod)),
+        (Value::String("copy_file".to_string()), Value::BuiltinFunction(copy_file)),
+        (Value::String("copy_dir".to_string()), Value::BuiltinFunction(copy_dir)),
     ];
     Value::HashMap(items)
 }
 
 
-fn get_cwd(_: Vec<Value>) -> Result<Value, String> {
+fn get_cwd(_interp: &mut Interpreter, _: Vec<Value>) -> Result<Value, String> {
     env::current_dir()
         .map(|p| Value::String(p.to_string_lossy().to_string()))
         .map_err(|e| format!("os.getcwd error: {}", e))
 }
 
-fn set_cwd(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn set_cwd(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         env::set_current_dir(path)
             .map(|_| Value::Null)
             .map_err(|e| format!("os.set_cwd error: {}", e))
@@ -40,8 +50,8 @@ fn set_cwd(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn list_dir(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn list_dir(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         let entries = fs::read_dir(path)
             .map_err(|e| format!("os.listdir error: {}", e))?;
 
@@ -56,8 +66,8 @@ fn list_dir(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn make_dir(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn make_dir(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         fs::create_dir_all(path)
             .map(|_| Value::Null)
             .map_err(|e| format!("os.mkdir error: {}", e))
@@ -66,8 +76,8 @@ fn make_dir(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn remove_dir(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn remove_dir(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         fs::remove_dir_all(path)
             .map(|_| Value::Null)
             .map_err(|e| format!("os.rmdir error: {}", e))
@@ -76,8 +86,8 @@ fn remove_dir(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn remove_file(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn remove_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         fs::remove_file(path)
             .map(|_| Value::Null)
             .map_err(|e| format!("os.remove_file error: {}", e))
@@ -86,7 +96,7 @@ fn remove_file(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn rename(args: Vec<Value>) -> Result<Value, String> {
+fn rename(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err("rename expects 2 arguments: old_path, new_path".to_string());
     }
@@ -99,32 +109,32 @@ fn rename(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn exists(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn exists(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         Ok(Value::Bool(Path::new(path).exists()))
     } else {
         Err("exists expects a string path".to_string())
     }
 }
 
-fn is_file(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn is_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         Ok(Value::Bool(Path::new(path).is_file()))
     } else {
         Err("is_file expects a string path".to_string())
     }
 }
 
-fn is_dir(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn is_dir(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         Ok(Value::Bool(Path::new(path).is_dir()))
     } else {
         Err("is_dir expects a string path".to_string())
     }
 }
 
-fn read_file(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(path)) = args.get(0) {
+fn read_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(path)) = args.first() {
         fs::read_to_string(path)
             .map(Value::String)
             .map_err(|e| format!("os.read_file error: {}", e))
@@ -133,7 +143,7 @@ fn read_file(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn write_file(args: Vec<Value>) -> Result<Value, String> {
+fn write_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err("write_file expects 2 arguments: path, content".to_string());
     }
@@ -146,15 +156,15 @@ fn write_file(args: Vec<Value>) -> Result<Value, String> {
     }
 }
 
-fn env_get(args: Vec<Value>) -> Result<Value, String> {
-    if let Some(Value::String(key)) = args.get(0) {
+fn env_get(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(key)) = args.first() {
         Ok(env::var(key).map_or(Value::Null, Value::String))
     } else {
         Err("env_get expects a string key".to_string())
     }
 }
 
-fn env_set(args: Vec<Value>) -> Result<Value, String> {
+fn env_set(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err("env_set expects 2 arguments: key, value".to_string());
     }
@@ -167,3 +177,332 @@ fn env_set(args: Vec<Value>) -> Result<Value, String> {
         Err("env_set expects 2 string arguments".to_string())
     }
 }
+
+/// Parses the lines of a dotenv file: `KEY=value`, optionally prefixed with `export`
+/// Single quoted values are literal, double quoted ones understand `\n`, `\t`, `\"` and `\\`,
+/// and unquoted values end at a ` #` comment
+fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, raw) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected KEY=value", number + 1))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("line {}: invalid variable name '{}'", number + 1, key));
+        }
+        let raw = raw.trim();
+        let value = if let Some(rest) = raw.strip_prefix('\'') {
+            rest.split_once('\'')
+                .map(|(value, _)| value.to_string())
+                .ok_or_else(|| format!("line {}: unterminated single quote", number + 1))?
+        } else if let Some(rest) = raw.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = rest.chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some(c) => value.push(c),
+                        None => return Err(format!("line {}: unterminated double quote", number + 1)),
+                    },
+                    Some(c) => value.push(c),
+                    None => return Err(format!("line {}: unterminated double quote", number + 1)),
+                }
+            }
+            value
+        } else {
+            raw.split(" #").next().unwrap_or("").trim_end().to_string()
+        };
+        // Names are already limited to letters, digits and `_`, values can't hold what the OS can't store
+        if value.contains('\0') {
+            return Err(format!("line {}: value of '{}' contains a NUL byte", number + 1, key));
+        }
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// Loads a dotenv file and returns its variables as a hashmap
+/// Options: `apply` (default True) sets them in the process environment,
+/// `override` (default False) also replaces variables that are already set.
+/// Applying changes the environment of the whole process, so it belongs at the start of a
+/// script, before any `thread`, `timer` or `async` work that could be reading it
+fn load_env(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path,
+        _ => return Err("load_env expects a string path".to_string()),
+    };
+    let (mut apply, mut override_existing) = (true, false);
+    match args.get(1) {
+        None => {}
+        Some(Value::HashMap(pairs)) => {
+            for (key, value) in pairs {
+                match (key, value) {
+                    (Value::String(k), Value::Bool(b)) if k == "apply" => apply = *b,
+                    (Value::String(k), Value::Bool(b)) if k == "override" => override_existing = *b,
+                    (k, v) => return Err(format!("load_env option {} has invalid value {:?}", k, v)),
+                }
+            }
+        }
+        Some(other) => return Err(format!("load_env options must be a hashmap, got {:?}", other)),
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| format!("os.load_env error: {}", e))?;
+    let vars = parse_dotenv(&content).map_err(|e| format!("os.load_env error: {}", e))?;
+    if apply {
+        for (key, value) in &vars {
+            if override_existing || env::var_os(key).is_none() {
+                // SAFETY: `set_var` races with threads reading the environment at the same time,
+                // scripts have to load it before starting any, see the note above. `parse_dotenv`
+                // rejected names with `=` or NUL and values with NUL, which would make it panic
+                unsafe {
+                    env::set_var(key, value);
+                }
+            }
+        }
+    }
+    Ok(Value::HashMap(
+        vars.into_iter()
+            .map(|(k, v)| (Value::String(k), Value::String(v)))
+            .collect(),
+    ))
+}
+
+/// Paths matching a pattern such as `src/**/*.nk`, sorted alphabetically
+fn glob(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(pattern)) = args.first() {
+        let paths = glob::glob(pattern).map_err(|e| format!("os.glob error: {}", e))?;
+        let mut matches = Vec::new();
+        for entry in paths {
+            let path = entry.map_err(|e| format!("os.glob error: {}", e))?;
+            matches.push(Value::String(path.to_string_lossy().to_string()));
+        }
+        Ok(Value::Array(matches))
+    } else {
+        Err("glob expects a string pattern".to_string())
+    }
+}
+
+/// Whether a name matches a shell-style pattern (`*`, `?`, `[a-z]`), without touching the filesystem
+fn fnmatch(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("fnmatch expects 2 arguments: pattern, name".to_string());
+    }
+    if let (Value::String(pattern), Value::String(name)) = (&args[0], &args[1]) {
+        let pattern = glob::Pattern::new(pattern).map_err(|e| format!("os.fnmatch error: {}", e))?;
+        Ok(Value::Bool(pattern.matches(name)))
+    } else {
+        Err("fnmatch expects 2 string arguments".to_string())
+    }
+}
+
+/// Walks a directory tree top-down, returning a `(dir, subdirs, files)` tuple per directory
+/// Options: `follow_symlinks` (default False) and `max_depth` (0 only lists `path` itself)
+/// The whole tree is collected up front since the language has no lazy iterators yet
+fn walk(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let root = match args.first() {
+        Some(Value::String(path)) => path,
+        _ => return Err("walk expects a string path".to_string()),
+    };
+    let mut follow_links = false;
+    let mut max_depth = usize::MAX;
+    match args.get(1) {
+        None => {}
+        Some(Value::HashMap(pairs)) => {
+            for (key, value) in pairs {
+                match (key, value) {
+                    (Value::String(k), Value::Bool(follow)) if k == "follow_symlinks" => follow_links = *follow,
+                    (Value::String(k), Value::Integer(depth)) if k == "max_depth" && *depth >= 0 => {
+                        max_depth = *depth as usize
+                    }
+                    (k, v) => return Err(format!("walk option {} has invalid value {:?}", k, v)),
+                }
+            }
+        }
+        Some(other) => return Err(format!("walk options must be a hashmap, got {:?}", other)),
+    }
+    let walker = walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .follow_links(follow_links)
+        .max_depth(max_depth);
+
+    let mut result = Vec::new();
+    for entry in walker {
+        let entry = entry.map_err(|e| format!("os.walk error: {}", e))?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let mut subdirs = Vec::new();
+        let mut files = Vec::new();
+        for child in fs::read_dir(entry.path()).map_err(|e| format!("os.walk error: {}", e))? {
+            let child = child.map_err(|e| format!("os.walk error: {}", e))?;
+            let is_dir = if follow_links {
+                child.path().is_dir()
+            } else {
+                child.file_type().is_ok_and(|t| t.is_dir())
+            };
+            let name = child.file_name().to_string_lossy().to_string();
+            if is_dir { subdirs.push(name) } else { files.push(name) }
+        }
+        subdirs.sort();
+        files.sort();
+        result.push(Value::Tuple(vec![
+            Value::String(entry.path().to_string_lossy().to_string()),
+            Value::Array(subdirs.into_iter().map(Value::String).collect()),
+            Value::Array(files.into_iter().map(Value::String).collect()),
+        ]));
+    }
+    Ok(Value::Array(result))
+}
+
+fn timestamp(time: std::io::Result<std::time::SystemTime>) -> Value {
+    time.ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(Value::Null, |d| Value::Float(d.as_secs_f64()))
+}
+
+/// Metadata of a path as a hashmap, timestamps are seconds since the epoch
+/// or None when the platform doesn't record them, `mode` is the octal permission string
+fn stat(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path,
+        _ => return Err("stat expects a string path".to_string()),
+    };
+    let link = fs::symlink_metadata(path).map_err(|e| format!("os.stat error: {}", e))?;
+    // Describe what a symlink points to, falling back to the link itself when it's dangling
+    let meta = fs::metadata(path).unwrap_or_else(|_| link.clone());
+
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Value::String(format!("{:o}", meta.permissions().mode() & 0o7777))
+    };
+    #[cfg(not(unix))]
+    let mode = Value::Null;
+
+    let field = |name: &str, value: Value| (Value::String(name.to_string()), value);
+    Ok(Value::HashMap(vec![
+        field("size", Value::Integer(meta.len() as i64)),
+        field("modified", timestamp(meta.modified())),
+        field("accessed", timestamp(meta.accessed())),
+        field("created", timestamp(meta.created())),
+        field("mode", mode),
+        field("readonly", Value::Bool(meta.permissions().readonly())),
+        field("is_file", Value::Bool(meta.is_file())),
+        field("is_dir", Value::Bool(meta.is_dir())),
+        field("is_symlink", Value::Bool(link.file_type().is_symlink())),
+    ]))
+}
+
+/// Changes permissions from an octal string like "755" (or the equivalent integer, e.g. 493)
+/// Outside unix only the write bit is honoured, by toggling the read-only flag
+fn chmod(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("chmod expects 2 arguments: path, mode".to_string());
+    }
+    let path = match &args[0] {
+        Value::String(path) => path,
+        _ => return Err("chmod expects a string path".to_string()),
+    };
+    let mode = match &args[1] {
+        Value::String(octal) => u32::from_str_radix(octal, 8).map_err(|_| format!("chmod invalid octal mode '{}'", octal))?,
+        Value::Integer(mode) => u32::try_from(*mode).map_err(|_| format!("chmod invalid mode {}", mode))?,
+        _ => return Err("chmod expects an octal string or integer mode".to_string()),
+    };
+    if mode > 0o7777 {
+        return Err(format!("chmod invalid mode {:o}", mode));
+    }
+    let mut permissions = fs::metadata(path).map_err(|e| format!("os.chmod error: {}", e))?.permissions();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(mode);
+    }
+    #[cfg(not(unix))]
+    permissions.set_readonly(mode & 0o200 == 0);
+
+    fs::set_permissions(path, permissions)
+        .map(|_| Value::Null)
+        .map_err(|e| format!("os.chmod error: {}", e))
+}
+
+/// Copies a file byte for byte, replacing `dst` if it exists
+fn copy_file(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("copy_file expects 2 arguments: src, dst".to_string());
+    }
+    if let (Value::String(src), Value::String(dst)) = (&args[0], &args[1]) {
+        fs::copy(src, dst)
+            .map(|_| Value::Null)
+            .map_err(|e| format!("os.copy_file error: {}", e))
+    } else {
+        Err("copy_file expects 2 string arguments".to_string())
+    }
+}
+
+/// Recursively copies a directory into `dst`, creating it as needed
+/// Existing files are only replaced when `overwrite` is True, otherwise the copy stops with an error
+fn copy_dir(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (src, dst, overwrite) = match args.as_slice() {
+        [Value::String(src), Value::String(dst)] => (src, dst, false),
+        [Value::String(src), Value::String(dst), Value::Bool(overwrite)] => (src, dst, *overwrite),
+        _ => return Err("copy_dir expects 2 string paths and an optional overwrite boolean".to_string()),
+    };
+    if !Path::new(src).is_dir() {
+        return Err(format!("os.copy_dir error: '{}' is not a directory", src));
+    }
+    let src_real = fs::canonicalize(src).map_err(|e| format!("os.copy_dir error: {}", e))?;
+    if resolve_missing(Path::new(dst))?.starts_with(&src_real) {
+        return Err(format!("os.copy_dir error: can't copy '{}' into itself at '{}'", src, dst));
+    }
+    for entry in walkdir::WalkDir::new(src) {
+        let entry = entry.map_err(|e| format!("os.copy_dir error: {}", e))?;
+        let relative = entry.path().strip_prefix(src).map_err(|e| format!("os.copy_dir error: {}", e))?;
+        let target = Path::new(dst).join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).map_err(|e| format!("os.copy_dir error: {}", e))?;
+        } else {
+            if !overwrite && target.exists() {
+                return Err(format!("os.copy_dir error: '{}' already exists", target.display()));
+            }
+            fs::copy(entry.path(), &target).map_err(|e| format!("os.copy_dir error: {}", e))?;
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// Canonical form of a path that may not exist yet, components are resolved one at a time so
+/// symlinks are followed before a `..` after them, and once a component is missing the rest,
+/// `..` included, is applied lexically like `create_dir_all` would
+fn resolve_missing(path: &Path) -> Result<PathBuf, String> {
+    let absolute = std::path::absolute(path).map_err(|e| format!("os.copy_dir error: {}", e))?;
+    let mut resolved = PathBuf::new();
+    let mut exists = true;
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => {
+                resolved.push(other);
+                if exists {
+                    match fs::canonicalize(&resolved) {
+                        Ok(real) => resolved = real,
+                        Err(_) => exists = false,
+                    }
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
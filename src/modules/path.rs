@@ -0,0 +1,112 @@
+//! `path` internal module, path manipulation using the platform's separator
+//! Everything is lexical except `absolute`, which also looks at the current directory
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("sep".to_string()), Value::String(std::path::MAIN_SEPARATOR.to_string())),
+        (Value::String("join".to_string()), Value::BuiltinFunction(join)),
+        (Value::String("basename".to_string()), Value::BuiltinFunction(basename)),
+        (Value::String("dirname".to_string()), Value::BuiltinFunction(dirname)),
+        (Value::String("extension".to_string()), Value::BuiltinFunction(extension)),
+        (Value::String("absolute".to_string()), Value::BuiltinFunction(absolute)),
+        (Value::String("normalize".to_string()), Value::BuiltinFunction(normalize)),
+        (Value::String("split".to_string()), Value::BuiltinFunction(split)),
+    ];
+    Value::HashMap(items)
+}
+
+
+fn expect_path<'a>(name: &str, args: &'a [Value]) -> Result<&'a Path, String> {
+    match args {
+        [Value::String(path)] => Ok(Path::new(path)),
+        _ => Err(format!("{} expects a single string path", name)),
+    }
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().to_string())
+}
+
+/// Resolves `.` and `..` components without touching the filesystem
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                // `..` can't go above the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => out.push(".."),
+            },
+            other => out.push(other),
+        }
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
+
+
+/// Joins any number of parts, an absolute part replaces everything before it
+fn join(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.is_empty() {
+        return Err("join expects at least one path".to_string());
+    }
+    let mut out = PathBuf::new();
+    for arg in &args {
+        match arg {
+            Value::String(part) => out.push(part),
+            other => return Err(format!("join expects string paths, got {:?}", other)),
+        }
+    }
+    Ok(path_value(&out))
+}
+
+/// Last component of the path, `""` when there is none (e.g. `/`)
+fn basename(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = expect_path("basename", &args)?;
+    Ok(Value::String(path.file_name().map_or(String::new(), |n| n.to_string_lossy().to_string())))
+}
+
+/// Everything but the last component, `""` for a bare file name
+fn dirname(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = expect_path("dirname", &args)?;
+    Ok(path.parent().map_or(Value::String(String::new()), path_value))
+}
+
+/// Extension without the dot, `""` when there is none
+fn extension(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = expect_path("extension", &args)?;
+    Ok(Value::String(path.extension().map_or(String::new(), |e| e.to_string_lossy().to_string())))
+}
+
+/// Makes the path absolute against the current directory and normalizes it
+/// Symlinks are not resolved and the path does not have to exist
+fn absolute(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = expect_path("absolute", &args)?;
+    std::path::absolute(path)
+        .map(|p| path_value(&normalize_path(&p)))
+        .map_err(|e| format!("path.absolute error: {}", e))
+}
+
+fn normalize(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let path = expect_path("normalize", &args)?;
+    Ok(path_value(&normalize_path(path)))
+}
+
+/// Splits into a `(dirname, basename)` tuple
+fn split(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let dir = dirname(interp, args.clone())?;
+    let base = basename(interp, args)?;
+    Ok(Value::Tuple(vec![dir, base]))
+}
@@ -1,5 +1,8 @@
-use regex::Regex;
-use crate::interpreter::value::Value;
+use std::sync::Arc;
+
+use regex::{Captures, Regex};
+use crate::interpreter::value::{NativeObject, Value};
+use crate::interpreter::Interpreter;
 
 
 pub fn make_module() -> Value {
@@ -8,81 +11,222 @@ pub fn make_module() -> Value {
         (Value::String("is_match".to_string()), Value::BuiltinFunction(re_is_match)),
         (Value::String("find_all".to_string()), Value::BuiltinFunction(re_findall)),
         (Value::String("replace".to_string()), Value::BuiltinFunction(re_replace)),
+        (Value::String("captures".to_string()), Value::BuiltinFunction(re_captures)),
+        (Value::String("split".to_string()), Value::BuiltinFunction(re_split)),
+        (Value::String("compile".to_string()), Value::BuiltinFunction(re_compile)),
+        (Value::String("replace_fn".to_string()), Value::BuiltinFunction(re_replace_fn)),
     ];
     Value::HashMap(items)
 }
 
-fn re_is_match(args: Vec<Value>) -> Result<Value, String> {
+
+fn compile(pat: &str) -> Result<Regex, String> {
+    Regex::new(pat).map_err(|e| format!("regex error: {}", e))
+}
+
+/// Converts a byte offset into `text` to a character offset, like string `find()`
+fn char_offset(text: &str, byte_offset: usize) -> Value {
+    Value::Integer(text[..byte_offset].chars().count() as i64)
+}
+
+fn first_match(re: &Regex, text: &str) -> Value {
+    if let Some(caps) = re.captures(text) {
+        let matches = caps
+            .iter()
+            .map(|m| match m {
+                Some(m) => Value::String(m.as_str().to_string()),
+                None => Value::Null,
+            })
+            .collect();
+        Value::Array(matches)
+    } else {
+        Value::Null
+    }
+}
+
+fn find_all(re: &Regex, text: &str) -> Value {
+    let matches = re
+        .find_iter(text)
+        .map(|m| Value::String(m.as_str().to_string()))
+        .collect();
+    Value::Array(matches)
+}
+
+/// First match as `{"text", "start", "end", "groups"}`, or None when nothing matches
+/// `groups` is keyed by group index and also by name for named groups, unmatched groups are None
+fn captures(re: &Regex, text: &str) -> Value {
+    re.captures(text).map_or(Value::Null, |caps| match_value(re, &caps, text))
+}
+
+fn match_value(re: &Regex, caps: &Captures, text: &str) -> Value {
+    let whole = caps.get(0).expect("group 0 is the whole match");
+
+    let mut groups = Vec::new();
+    for (index, name) in re.capture_names().enumerate() {
+        let value = caps.get(index).map_or(Value::Null, |m| Value::String(m.as_str().to_string()));
+        if let Some(name) = name {
+            groups.push((Value::String(name.to_string()), value.clone()));
+        }
+        groups.push((Value::Integer(index as i64), value));
+    }
+    Value::HashMap(vec![
+        (Value::String("text".to_string()), Value::String(whole.as_str().to_string())),
+        (Value::String("start".to_string()), char_offset(text, whole.start())),
+        (Value::String("end".to_string()), char_offset(text, whole.end())),
+        (Value::String("groups".to_string()), Value::HashMap(groups)),
+    ])
+}
+
+fn split(re: &Regex, text: &str) -> Value {
+    Value::Array(re.split(text).map(|part| Value::String(part.to_string())).collect())
+}
+
+
+fn re_is_match(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err("is_match expects 2 arguments: pattern, text".to_string());
     }
 
     if let (Value::String(pat), Value::String(text)) = (&args[0], &args[1]) {
-        Regex::new(pat)
-            .map_err(|e| format!("regex error: {}", e))
-            .map(|re| Value::Bool(re.is_match(text)))
+        compile(pat).map(|re| Value::Bool(re.is_match(text)))
     } else {
         Err("is_match expects two string arguments".to_string())
     }
 }
 
-fn re_match(args: Vec<Value>) -> Result<Value, String> {
+fn re_match(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err("match expects 2 arguments: pattern, text".to_string());
     }
 
     if let (Value::String(pat), Value::String(text)) = (&args[0], &args[1]) {
-        Regex::new(pat)
-            .map_err(|e| format!("regex error: {}", e))
-            .and_then(|re| {
-                if let Some(caps) = re.captures(text) {
-                    let matches = caps
-                        .iter()
-                        .map(|m| match m {
-                            Some(m) => Value::String(m.as_str().to_string()),
-                            None => Value::Null,
-                        })
-                        .collect();
-                    Ok(Value::Array(matches))
-                } else {
-                    Ok(Value::Null)
-                }
-            })
+        compile(pat).map(|re| first_match(&re, text))
     } else {
         Err("match expects two string arguments".to_string())
     }
 }
 
-fn re_findall(args: Vec<Value>) -> Result<Value, String> {
+fn re_findall(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err("findall expects 2 arguments: pattern, text".to_string());
     }
 
     if let (Value::String(pat), Value::String(text)) = (&args[0], &args[1]) {
-        Regex::new(pat)
-            .map_err(|e| format!("regex error: {}", e))
-            .map(|re| {
-                let matches = re
-                    .find_iter(text)
-                    .map(|m| Value::String(m.as_str().to_string()))
-                    .collect();
-                Value::Array(matches)
-            })
+        compile(pat).map(|re| find_all(&re, text))
     } else {
         Err("findall expects two string arguments".to_string())
     }
 }
 
-fn re_replace(args: Vec<Value>) -> Result<Value, String> {
+fn re_replace(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 3 {
         return Err("replace expects 3 arguments: pattern, replacement, text".to_string());
     }
 
     if let (Value::String(pat), Value::String(repl), Value::String(text)) = (&args[0], &args[1], &args[2]) {
-        Regex::new(pat)
-            .map_err(|e| format!("regex error: {}", e))
-            .map(|re| Value::String(re.replace_all(text, repl.as_str()).to_string()))
+        compile(pat).map(|re| Value::String(re.replace_all(text, repl.as_str()).to_string()))
     } else {
         Err("replace expects three string arguments".to_string())
     }
 }
+
+fn re_captures(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("captures expects 2 arguments: pattern, text".to_string());
+    }
+
+    if let (Value::String(pat), Value::String(text)) = (&args[0], &args[1]) {
+        compile(pat).map(|re| captures(&re, text))
+    } else {
+        Err("captures expects two string arguments".to_string())
+    }
+}
+
+fn re_split(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("split expects 2 arguments: pattern, text".to_string());
+    }
+
+    if let (Value::String(pat), Value::String(text)) = (&args[0], &args[1]) {
+        compile(pat).map(|re| split(&re, text))
+    } else {
+        Err("split expects two string arguments".to_string())
+    }
+}
+
+/// Replaces every match with the string returned by `func(m)`, where `m` is a match
+/// hashmap shaped like the result of `captures`
+fn re_replace_fn(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (pat, text, func) = match args.as_slice() {
+        [Value::String(pat), Value::String(text), func] => (pat, text, func),
+        _ => return Err("replace_fn expects 3 arguments: pattern, text, function".to_string()),
+    };
+    let re = compile(pat)?;
+
+    let mut out = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for caps in re.captures_iter(text) {
+        let whole = caps.get(0).expect("group 0 is the whole match");
+        out.push_str(&text[last_end..whole.start()]);
+        match interp.call_function(func.clone(), vec![match_value(&re, &caps, text)])? {
+            Value::String(replacement) => out.push_str(&replacement),
+            other => return Err(format!("replace_fn callback must return a string, got {:?}", other)),
+        }
+        last_end = whole.end();
+    }
+    out.push_str(&text[last_end..]);
+    Ok(Value::String(out))
+}
+
+/// Compiles a pattern once into an object with the module's functions as methods,
+/// minus the pattern argument, e.g. `let re = regex.compile("\d+")` then `re.find_all(text)`
+fn re_compile(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let [Value::String(pat)] = args.as_slice() {
+        let re = compile(pat)?;
+        Ok(Value::Native(Arc::new(CompiledRegex { re })))
+    } else {
+        Err("compile expects a string pattern".to_string())
+    }
+}
+
+
+#[derive(Debug)]
+struct CompiledRegex {
+    re: Regex,
+}
+
+impl NativeObject for CompiledRegex {
+    fn type_name(&self) -> &'static str {
+        "Regex"
+    }
+
+    fn duplicate(&self) -> Option<Arc<dyn NativeObject>> {
+        Some(Arc::new(CompiledRegex { re: self.re.clone() }))
+    }
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        match (method, args.as_slice()) {
+            ("is_match", [Value::String(text)]) => Ok(Value::Bool(self.re.is_match(text))),
+            ("match", [Value::String(text)]) => Ok(first_match(&self.re, text)),
+            ("find_all", [Value::String(text)]) => Ok(find_all(&self.re, text)),
+            ("captures", [Value::String(text)]) => Ok(captures(&self.re, text)),
+            ("split", [Value::String(text)]) => Ok(split(&self.re, text)),
+            ("replace", [Value::String(repl), Value::String(text)]) => {
+                Ok(Value::String(self.re.replace_all(text, repl.as_str()).to_string()))
+            }
+            ("pattern", []) => Ok(Value::String(self.re.as_str().to_string())),
+            ("is_match" | "match" | "find_all" | "captures" | "split", _) => {
+                Err(format!("{}() expects 1 string argument: text", method))
+            }
+            ("replace", _) => Err("replace() expects 2 string arguments: replacement, text".to_string()),
+            _ => Err(format!("Regex has no method '{}'", method)),
+        }
+    }
+
+    fn get_property(&self, name: &str) -> Option<Value> {
+        match name {
+            "pattern" => Some(Value::String(self.re.as_str().to_string())),
+            _ => None,
+        }
+    }
+}
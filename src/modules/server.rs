@@ -0,0 +1,286 @@
+//! `server` internal module: a minimal blocking HTTP/1.1 server driven by a NIKL handler
+//! Each connection serves one request, the handler gets a request hashmap and returns the response
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+use super::convert::to_json;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("listen".to_string()), Value::BuiltinFunction(listen)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// How long a client may take to send its whole request before the connection is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Largest request line and headers together, bigger ones get 431
+const MAX_HEADER_BYTES: u64 = 8192;
+const MAX_HEADERS: usize = 100;
+
+struct ListenOptions {
+    host: String,
+    max_requests: Option<usize>,
+    /// Largest request body accepted, bigger ones get 413
+    max_body: usize,
+}
+
+fn parse_options(value: Option<&Value>) -> Result<ListenOptions, String> {
+    let mut options = ListenOptions { host: "127.0.0.1".to_string(), max_requests: None, max_body: 1024 * 1024 };
+    match value {
+        None => {}
+        Some(Value::HashMap(pairs)) => {
+            for (key, value) in pairs {
+                match (key, value) {
+                    (Value::String(k), Value::String(host)) if k == "host" => options.host = host.clone(),
+                    (Value::String(k), Value::Integer(n)) if k == "max_requests" && *n > 0 => {
+                        options.max_requests = Some(*n as usize)
+                    }
+                    (Value::String(k), Value::Integer(n)) if k == "max_body" && *n >= 0 => options.max_body = *n as usize,
+                    (k, v) => return Err(format!("server.listen option {} has invalid value {:?}", k, v)),
+                }
+            }
+        }
+        Some(other) => return Err(format!("server.listen options must be a hashmap, got {:?}", other)),
+    }
+    Ok(options)
+}
+
+
+/// Serves requests on `port` until the handler calls `exit()`, or until `max_requests` have been handled
+/// Usage: `server.listen(8080, handler)` or `server.listen(8080, handler, {"host": "0.0.0.0", "max_body": 65536})`
+fn listen(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let port = match args.first() {
+        Some(Value::Integer(p)) if (0..=65535).contains(p) => *p as u16,
+        _ => return Err("listen expects a port number between 0 and 65535".to_string()),
+    };
+    let handler = match args.get(1) {
+        Some(f) if f.is_callable() => f.clone(),
+        _ => return Err("listen expects a handler function as its second argument".to_string()),
+    };
+    let options = parse_options(args.get(2))?;
+
+    let listener = TcpListener::bind((options.host.as_str(), port))
+        .map_err(|e| format!("server.listen error: {}", e))?;
+
+    let mut served = 0;
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        // A slow or silent client must not hold up the others, reads have a deadline of their own
+        let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+        // A broken connection only affects that client, keep serving the others
+        let response = match read_request(&mut stream, options.max_body) {
+            Ok(request) => match interp.call_function(handler.clone(), vec![request]) {
+                Ok(value) => build_response(value),
+                // The handler called `exit()`, unwind to the host instead of serving on
+                Err(e) if interp.exit_code().is_some() => return Err(e),
+                Err(e) => {
+                    let _ = interp.with_stderr(|out| writeln!(out, "server handler error: {}", e));
+                    Ok(plain_response(500, "Internal Server Error"))
+                }
+            },
+            Err((status, e)) => Ok(plain_response(status, &e)),
+        };
+        let response = response.unwrap_or_else(|e| {
+            let _ = interp.with_stderr(|out| writeln!(out, "server response error: {}", e));
+            plain_response(500, "Internal Server Error")
+        });
+        let _ = stream.write_all(&response).and_then(|_| stream.flush());
+
+        served += 1;
+        if options.max_requests.is_some_and(|max| served >= max) {
+            break;
+        }
+    }
+    Ok(Value::Null)
+}
+
+
+/// Parses one request into `{"method", "path", "query", "headers", "body"}`
+/// Header names are lowercased so handlers don't have to guess their casing
+/// Fails with the status to answer, 413 for a body over `max_body`, 431 for headers over
+/// `MAX_HEADER_BYTES` or `MAX_HEADERS` and 408 when the client is too slow
+fn read_request(stream: &mut TcpStream, max_body: usize) -> Result<Value, (u16, String)> {
+    let mut reader = BufReader::new(Deadline { stream, deadline: Instant::now() + READ_TIMEOUT });
+    let mut head = reader.by_ref().take(MAX_HEADER_BYTES);
+
+    let request_line = read_head_line(&mut head)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err((400, "Malformed request line".to_string())),
+    };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (target, String::new()),
+    };
+
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let line = read_head_line(&mut head)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err((431, format!("More than {} headers", MAX_HEADERS)));
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err((400, format!("Malformed header line: {}", line)));
+        };
+        let (name, value) = (name.trim().to_lowercase(), value.trim().to_string());
+        if name == "content-length" {
+            content_length = value.parse().map_err(|_| (400, "Invalid Content-Length header".to_string()))?;
+        }
+        headers.push((Value::String(name), Value::String(value)));
+    }
+
+    if content_length > max_body {
+        return Err((413, format!("Request body is larger than {} bytes", max_body)));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+
+    Ok(Value::HashMap(vec![
+        (Value::String("method".to_string()), Value::String(method)),
+        (Value::String("path".to_string()), Value::String(path)),
+        (Value::String("query".to_string()), Value::String(query)),
+        (Value::String("headers".to_string()), Value::HashMap(headers)),
+        (Value::String("body".to_string()), Value::String(String::from_utf8_lossy(&body).to_string())),
+    ]))
+}
+
+/// A line of the request head, empty at the end of the stream
+fn read_head_line<R: BufRead>(head: &mut std::io::Take<R>) -> Result<String, (u16, String)> {
+    let mut line = String::new();
+    head.read_line(&mut line).map_err(read_error)?;
+    if head.limit() == 0 && !line.ends_with('\n') {
+        return Err((431, format!("Request headers are larger than {} bytes", MAX_HEADER_BYTES)));
+    }
+    Ok(line)
+}
+
+/// Reads from a client until a deadline for the whole request, so sending a byte now and
+/// then doesn't keep the connection open
+struct Deadline<'a> {
+    stream: &'a mut TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+fn read_error(e: std::io::Error) -> (u16, String) {
+    match e.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => (408, "Request Timeout".to_string()),
+        _ => (400, e.to_string()),
+    }
+}
+
+
+/// Turns the handler's return value into raw response bytes
+/// A string is sent as text, a hashmap may set `status`, `headers` and `body`,
+/// where a non-string body is sent as JSON, and `None` means 204 No Content.
+/// `Content-Length` and `Connection` are always set by the server, handler values for them are dropped
+fn build_response(value: Value) -> Result<Vec<u8>, String> {
+    match value {
+        Value::Null => Ok(encode_response(204, Vec::new(), "")),
+        Value::String(body) => Ok(encode_response(200, text_headers(), &body)),
+        Value::HashMap(pairs) => {
+            let mut status = 200;
+            let mut headers = Vec::new();
+            let mut body = Value::String(String::new());
+            for (key, value) in pairs {
+                match (key, value) {
+                    (Value::String(k), Value::Integer(code)) if k == "status" && (100..=999).contains(&code) => {
+                        status = code as u16
+                    }
+                    (Value::String(k), Value::HashMap(extra)) if k == "headers" => {
+                        for (name, value) in extra {
+                            let (name, value) = (name.to_string(), value.to_string());
+                            // A line break would end the header early and start one the handler didn't mean
+                            if name.is_empty() || name.contains(['\r', '\n', ':']) || value.contains(['\r', '\n']) {
+                                return Err(format!("Invalid response header {:?}: {:?}", name, value));
+                            }
+                            if !name.eq_ignore_ascii_case("content-length") && !name.eq_ignore_ascii_case("connection") {
+                                headers.push((name, value));
+                            }
+                        }
+                    }
+                    (Value::String(k), value) if k == "body" => body = value,
+                    (k, v) => return Err(format!("Invalid response field {} = {:?}", k, v)),
+                }
+            }
+            let (default_headers, body) = match body {
+                Value::String(s) => (text_headers(), s),
+                Value::Null => (Vec::new(), String::new()),
+                other => {
+                    let json = to_json(&other)?;
+                    (vec![("Content-Type".to_string(), "application/json".to_string())], json.to_string())
+                }
+            };
+            for (name, value) in default_headers {
+                if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(&name)) {
+                    headers.push((name, value));
+                }
+            }
+            Ok(encode_response(status, headers, &body))
+        }
+        other => Err(format!("Handler must return a string, hashmap or None, got {:?}", other)),
+    }
+}
+
+fn text_headers() -> Vec<(String, String)> {
+    vec![("Content-Type".to_string(), "text/plain; charset=utf-8".to_string())]
+}
+
+fn plain_response(status: u16, body: &str) -> Vec<u8> {
+    encode_response(status, text_headers(), body)
+}
+
+fn encode_response(status: u16, headers: Vec<(String, String)>, body: &str) -> Vec<u8> {
+    let mut out = format!("HTTP/1.1 {} {}\r\n", status, reason_phrase(status));
+    for (name, value) in headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    out.push_str(body);
+    out.into_bytes()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
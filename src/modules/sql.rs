@@ -0,0 +1,61 @@
+//! Pieces shared by the SQL modules (`sqlite` and `db`)
+//! Every backend converts the same parameter forms and builds rows the same way
+
+use crate::interpreter::value::Value;
+
+
+/// Query parameters, an array binds placeholders in order, a hashmap binds them by name
+pub enum Params {
+    Positional(Vec<Value>),
+    /// Names without their `:`/`@`/`$` prefix
+    Named(Vec<(String, Value)>),
+}
+
+fn check_param(value: &Value) -> Result<Value, String> {
+    match value {
+        Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) | Value::String(_) => Ok(value.clone()),
+        other => Err(format!("Cannot bind {} as a query parameter", other.type_name())),
+    }
+}
+
+pub fn parse_params(value: Option<&Value>) -> Result<Params, String> {
+    match value {
+        None => Ok(Params::Positional(Vec::new())),
+        Some(Value::Array(items)) | Some(Value::Tuple(items)) => {
+            items.iter().map(check_param).collect::<Result<_, _>>().map(Params::Positional)
+        }
+        Some(Value::HashMap(pairs)) => pairs
+            .iter()
+            .map(|(key, value)| match key {
+                Value::String(name) => Ok((name.trim_start_matches([':', '@', '$']).to_string(), check_param(value)?)),
+                other => Err(format!("Query parameter names must be strings, got {:?}", other)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Params::Named),
+        Some(other) => Err(format!("Query parameters must be an array or hashmap, got {:?}", other)),
+    }
+}
+
+/// Returns the SQL string of an `execute`/`query` call, which may be followed by parameters
+pub fn expect_sql<'a>(method: &str, args: &'a [Value]) -> Result<&'a str, String> {
+    match args.first() {
+        Some(Value::String(sql)) if args.len() <= 2 => Ok(sql),
+        _ => Err(format!("{}() expects an SQL string and optional parameters", method)),
+    }
+}
+
+/// Binary column data, using the same byte array form as the encode module
+pub fn bytes_value(bytes: &[u8]) -> Value {
+    Value::Array(bytes.iter().map(|b| Value::Integer(*b as i64)).collect())
+}
+
+/// Builds a row hashmap keyed by column name, keeping the column order
+pub fn row_value(columns: &[String], values: Vec<Value>) -> Value {
+    Value::HashMap(
+        columns
+            .iter()
+            .zip(values)
+            .map(|(name, value)| (Value::String(name.clone()), value))
+            .collect(),
+    )
+}
@@ -0,0 +1,148 @@
+//! `sqlite` internal module backed by `rusqlite`
+//! `sqlite.open(path)` returns a connection object, use ":memory:" for a throwaway database
+
+use std::sync::{Arc, Mutex};
+
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, ToSql};
+
+use crate::interpreter::value::{NativeObject, Value};
+use super::sql::{bytes_value, expect_sql, parse_params, row_value, Params};
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("open".to_string()), Value::BuiltinFunction(open)),
+    ];
+    Value::HashMap(items)
+}
+
+
+fn open(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.first() {
+        Some(Value::String(path)) => connect(path),
+        _ => Err("open expects a string path".to_string()),
+    }
+}
+
+/// Opens a database file as a connection object, also used by `db.connect("sqlite:...")`
+pub fn connect(path: &str) -> Result<Value, String> {
+    let conn = Connection::open(path).map_err(|e| format!("sqlite.open error: {}", e))?;
+    Ok(Value::Native(Arc::new(SqliteConnection { conn: Mutex::new(Some(conn)) })))
+}
+
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Integer(i) => SqlValue::Integer(*i),
+        Value::Float(f) => SqlValue::Real(*f),
+        Value::String(s) => SqlValue::Text(s.clone()),
+        // `parse_params` only lets scalars through
+        _ => SqlValue::Null,
+    }
+}
+
+fn from_sql_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::Integer(i),
+        ValueRef::Real(f) => Value::Float(f),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).to_string()),
+        ValueRef::Blob(bytes) => bytes_value(bytes),
+    }
+}
+
+/// rusqlite wants the placeholder prefix in parameter names
+fn bind_params(params: &Params) -> (Vec<SqlValue>, Vec<(String, SqlValue)>) {
+    match params {
+        Params::Positional(values) => (values.iter().map(to_sql_value).collect(), Vec::new()),
+        Params::Named(values) => (
+            Vec::new(),
+            values.iter().map(|(name, value)| (format!(":{}", name), to_sql_value(value))).collect(),
+        ),
+    }
+}
+
+fn named(values: &[(String, SqlValue)]) -> Vec<(&str, &dyn ToSql)> {
+    values.iter().map(|(name, value)| (name.as_str(), value as &dyn ToSql)).collect()
+}
+
+
+#[derive(Debug)]
+struct SqliteConnection {
+    /// `None` once the connection has been closed
+    conn: Mutex<Option<Connection>>,
+}
+
+impl SqliteConnection {
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+        let guard = self.conn.lock().map_err(|_| "sqlite connection is poisoned".to_string())?;
+        let conn = guard.as_ref().ok_or_else(|| "sqlite connection is closed".to_string())?;
+        f(conn).map_err(|e| format!("sqlite error: {}", e))
+    }
+
+    fn execute(&self, sql: &str, params: Params) -> Result<Value, String> {
+        let (positional, by_name) = bind_params(&params);
+        let changed = self.with_conn(|conn| {
+            let mut stmt = conn.prepare(sql)?;
+            match params {
+                Params::Positional(_) => stmt.execute(rusqlite::params_from_iter(&positional)),
+                Params::Named(_) => stmt.execute(named(&by_name).as_slice()),
+            }
+        })?;
+        Ok(Value::Integer(changed as i64))
+    }
+
+    /// Runs a query and returns every row as a hashmap keyed by column name
+    fn query(&self, sql: &str, params: Params) -> Result<Value, String> {
+        let (positional, by_name) = bind_params(&params);
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(sql)?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let mut rows = match params {
+                Params::Positional(_) => stmt.query(rusqlite::params_from_iter(&positional))?,
+                Params::Named(_) => stmt.query(named(&by_name).as_slice())?,
+            };
+            let mut result = Vec::new();
+            while let Some(row) = rows.next()? {
+                let values = (0..columns.len())
+                    .map(|index| row.get_ref(index).map(from_sql_value))
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                result.push(row_value(&columns, values));
+            }
+            Ok(Value::Array(result))
+        })
+    }
+
+    fn batch(&self, sql: &str) -> Result<Value, String> {
+        self.with_conn(|conn| conn.execute_batch(sql)).map(|_| Value::Null)
+    }
+}
+
+impl NativeObject for SqliteConnection {
+    fn type_name(&self) -> &'static str {
+        "SqliteConnection"
+    }
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        match method {
+            "execute" => self.execute(expect_sql(method, &args)?, parse_params(args.get(1))?),
+            "query" => self.query(expect_sql(method, &args)?, parse_params(args.get(1))?),
+            "execute_batch" => self.batch(expect_sql(method, &args)?),
+            "begin" => self.batch("BEGIN"),
+            "commit" => self.batch("COMMIT"),
+            "rollback" => self.batch("ROLLBACK"),
+            "last_insert_id" => self.with_conn(|conn| Ok(Value::Integer(conn.last_insert_rowid()))),
+            "close" => {
+                let mut guard = self.conn.lock().map_err(|_| "sqlite connection is poisoned".to_string())?;
+                if let Some(conn) = guard.take() {
+                    conn.close().map_err(|(_, e)| format!("sqlite error: {}", e))?;
+                }
+                Ok(Value::Null)
+            }
+            _ => Err(format!("SqliteConnection has no method '{}'", method)),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! `subprocess` internal module for running external commands
+//! Options are a trailing hashmap with `cwd`, `env` (a hashmap of strings) and `stdin`
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, Output, Stdio};
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("run".to_string()), Value::BuiltinFunction(run)),
+        (Value::String("shell".to_string()), Value::BuiltinFunction(shell)),
+        (Value::String("stream".to_string()), Value::BuiltinFunction(stream)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Builds a command from a program name and an array of string arguments
+pub fn build_command(name: &str, program: Option<&Value>, args: Option<&Value>) -> Result<Command, String> {
+    let program = match program {
+        Some(Value::String(p)) => p,
+        _ => return Err(format!("{} expects a program name string", name)),
+    };
+    let mut command = Command::new(program);
+    match args {
+        None => {}
+        Some(Value::Array(items)) => {
+            for item in items {
+                match item {
+                    Value::String(arg) => command.arg(arg),
+                    other => return Err(format!("{} arguments must be strings, got {:?}", name, other)),
+                };
+            }
+        }
+        Some(other) => return Err(format!("{} expects an array of arguments, got {:?}", name, other)),
+    }
+    Ok(command)
+}
+
+fn shell_command(script: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", script]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+}
+
+/// Applies the options hashmap and returns the text to feed on stdin, if any
+pub fn apply_options(name: &str, command: &mut Command, options: Option<&Value>) -> Result<Option<String>, String> {
+    let pairs = match options {
+        None => return Ok(None),
+        Some(Value::HashMap(pairs)) => pairs,
+        Some(other) => return Err(format!("{} options must be a hashmap, got {:?}", name, other)),
+    };
+    let mut stdin = None;
+    for (key, value) in pairs {
+        match (key, value) {
+            (Value::String(k), Value::String(dir)) if k == "cwd" => {
+                command.current_dir(dir);
+            }
+            (Value::String(k), Value::HashMap(vars)) if k == "env" => {
+                for (var, val) in vars {
+                    match (var, val) {
+                        (Value::String(var), Value::String(val)) => command.env(var, val),
+                        _ => return Err(format!("{} env entries must be strings", name)),
+                    };
+                }
+            }
+            (Value::String(k), Value::String(input)) if k == "stdin" => stdin = Some(input.clone()),
+            (k, v) => return Err(format!("{} option {} has invalid value {:?}", name, k, v)),
+        }
+    }
+    Ok(stdin)
+}
+
+fn spawn(name: &str, command: &mut Command, stdin: Option<String>) -> Result<Child, String> {
+    command
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::inherit() })
+        .stdout(Stdio::piped());
+    let mut child = command.spawn().map_err(|e| format!("{} error: {}", name, e))?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // Written from its own thread while the output is read, a child that fills its output
+        // pipe before it has read all of its input would wait forever otherwise. Dropping the
+        // pipe afterwards closes it so the child sees end of input, a child that stops reading
+        // early only cuts the write short
+        std::thread::spawn(move || pipe.write_all(input.as_bytes()));
+    }
+    Ok(child)
+}
+
+/// Exit code of a finished process, -1 when it was killed by a signal
+fn exit_code(status: std::process::ExitStatus) -> Value {
+    Value::Integer(status.code().unwrap_or(-1) as i64)
+}
+
+fn run_to_completion(name: &str, mut command: Command, options: Option<&Value>) -> Result<Value, String> {
+    let stdin = apply_options(name, &mut command, options)?;
+    let output = spawn(name, command.stderr(Stdio::piped()), stdin)?
+        .wait_with_output()
+        .map_err(|e| format!("{} error: {}", name, e))?;
+    Ok(output_value(output))
+}
+
+/// `{"code", "stdout", "stderr"}` of a finished process
+pub fn output_value(output: Output) -> Value {
+    Value::HashMap(vec![
+        (Value::String("code".to_string()), exit_code(output.status)),
+        (Value::String("stdout".to_string()), Value::String(String::from_utf8_lossy(&output.stdout).to_string())),
+        (Value::String("stderr".to_string()), Value::String(String::from_utf8_lossy(&output.stderr).to_string())),
+    ])
+}
+
+
+/// Runs a program and waits for it, returning `{"code", "stdout", "stderr"}`
+/// Usage: `subprocess.run("git", ["status"])` or `subprocess.run("ls", [], {"cwd": "/tmp"})`
+fn run(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let command = build_command("subprocess.run", args.first(), args.get(1))?;
+    run_to_completion("subprocess.run", command, args.get(2))
+}
+
+/// Runs a command line through the system shell, same result as `run`
+fn shell(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let command = match args.first() {
+        Some(Value::String(script)) => shell_command(script),
+        _ => return Err("shell expects a command string".to_string()),
+    };
+    run_to_completion("subprocess.shell", command, args.get(1))
+}
+
+/// Runs a program and calls `on_line(line)` for each line of its output as it arrives
+/// Stderr is passed through to the script's stderr, the exit code is returned
+/// Usage: `subprocess.stream("ping", ["-c", "3", "host"], fn_name)`
+fn stream(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let mut command = build_command("subprocess.stream", args.first(), args.get(1))?;
+    let callback = match args.get(2) {
+        Some(f) if f.is_callable() => f.clone(),
+        _ => return Err("stream expects a callback function as its third argument".to_string()),
+    };
+    let stdin = apply_options("subprocess.stream", &mut command, args.get(3))?;
+    let mut child = spawn("subprocess.stream", command.stderr(Stdio::piped()), stdin)?;
+    let errors = forward_stderr(interp, child.stderr.take().expect("stderr is piped"));
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    for line in BufReader::new(stdout).lines() {
+        let result = line
+            .map_err(|e| format!("subprocess.stream error: {}", e))
+            .and_then(|line| interp.call_function(callback.clone(), vec![Value::String(line)]));
+        if let Err(e) = result {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = errors.join();
+            return Err(e);
+        }
+    }
+    let status = child.wait().map_err(|e| format!("subprocess.stream error: {}", e))?;
+    let _ = errors.join();
+    Ok(exit_code(status))
+}
+
+/// Copies a child's stderr to the script's stderr as it arrives, on a thread of its own so
+/// neither output pipe fills up while the other is read
+fn forward_stderr(interp: &Interpreter, mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<()> {
+    let interp = interp.detached();
+    std::thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        while let Ok(n) = pipe.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+            let _ = interp.with_stderr(|out| out.write_all(&buffer[..n]).and_then(|_| out.flush()));
+        }
+    })
+}
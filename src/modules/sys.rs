@@ -0,0 +1,21 @@
+//! `sys` internal module with platform and process information
+//! Everything is a plain value captured when the module is imported
+
+use std::env;
+
+use crate::interpreter::value::Value;
+
+
+pub fn make_module() -> Value {
+    let exe_path = env::current_exe()
+        .map_or(Value::Null, |p| Value::String(p.to_string_lossy().to_string()));
+    let items = vec![
+        (Value::String("platform".to_string()), Value::String(env::consts::OS.to_string())),
+        (Value::String("arch".to_string()), Value::String(env::consts::ARCH.to_string())),
+        (Value::String("hostname".to_string()), Value::String(gethostname::gethostname().to_string_lossy().to_string())),
+        (Value::String("pid".to_string()), Value::Integer(std::process::id() as i64)),
+        (Value::String("nikl_version".to_string()), Value::String(env!("CARGO_PKG_VERSION").to_string())),
+        (Value::String("exe_path".to_string()), exe_path),
+    ];
+    Value::HashMap(items)
+}
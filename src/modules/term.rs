@@ -0,0 +1,234 @@
+//! `term` internal module for ANSI colors, text styles and cursor control
+//! Styling functions return the text unchanged when colors are off, which is the default
+//! when the script's stdout is not a terminal or `NO_COLOR` is set (`FORCE_COLOR` turns them back on),
+//! and always when the host asked for no colors, e.g. with `nikl --no-color`
+//! Cursor and clearing functions write to the script's stdout and do nothing outside a terminal
+
+use crate::interpreter::stdio::Stream;
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("color".to_string()), Value::BuiltinFunction(color)),
+        (Value::String("bold".to_string()), Value::BuiltinFunction(bold)),
+        (Value::String("dim".to_string()), Value::BuiltinFunction(dim)),
+        (Value::String("italic".to_string()), Value::BuiltinFunction(italic)),
+        (Value::String("underline".to_string()), Value::BuiltinFunction(underline)),
+        (Value::String("strip".to_string()), Value::BuiltinFunction(strip)),
+        (Value::String("is_tty".to_string()), Value::BuiltinFunction(is_tty)),
+        (Value::String("colors_enabled".to_string()), Value::BuiltinFunction(colors_enabled)),
+        (Value::String("set_colors".to_string()), Value::BuiltinFunction(set_colors)),
+        (Value::String("clear_line".to_string()), Value::BuiltinFunction(clear_line)),
+        (Value::String("clear_screen".to_string()), Value::BuiltinFunction(clear_screen)),
+        (Value::String("move_up".to_string()), Value::BuiltinFunction(move_up)),
+        (Value::String("move_down".to_string()), Value::BuiltinFunction(move_down)),
+        (Value::String("move_to".to_string()), Value::BuiltinFunction(move_to)),
+        (Value::String("hide_cursor".to_string()), Value::BuiltinFunction(hide_cursor)),
+        (Value::String("show_cursor".to_string()), Value::BuiltinFunction(show_cursor)),
+    ];
+    Value::HashMap(items)
+}
+
+
+fn use_colors(interp: &Interpreter) -> bool {
+    match interp.forced_colors() {
+        Some(colors) => colors,
+        // The host's choice, e.g. `nikl --no-color`, wins over the environment
+        None if interp.no_color() => false,
+        None => {
+            if std::env::var_os("FORCE_COLOR").is_some_and(|v| !v.is_empty()) {
+                true
+            } else if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                false
+            } else {
+                interp.is_terminal(Stream::Stdout)
+            }
+        }
+    }
+}
+
+fn color_code(name: &str) -> Option<u8> {
+    let (bright, base) = match name.strip_prefix("bright_") {
+        Some(base) => (true, base),
+        None => (false, name),
+    };
+    let code = match base {
+        "black" => 30,
+        "red" => 31,
+        "green" => 32,
+        "yellow" => 33,
+        "blue" => 34,
+        "magenta" => 35,
+        "cyan" => 36,
+        "white" => 37,
+        _ => return None,
+    };
+    Some(if bright { code + 60 } else { code })
+}
+
+fn paint(interp: &Interpreter, text: &str, codes: &[u8]) -> Value {
+    if !use_colors(interp) {
+        return Value::String(text.to_string());
+    }
+    let codes: Vec<String> = codes.iter().map(u8::to_string).collect();
+    Value::String(format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text))
+}
+
+fn expect_text<'a>(name: &str, args: &'a [Value]) -> Result<&'a str, String> {
+    match args {
+        [Value::String(text)] => Ok(text),
+        _ => Err(format!("{} expects a string", name)),
+    }
+}
+
+/// Writes a control sequence when the script's stdout is a terminal
+fn control(interp: &Interpreter, sequence: &str) -> Result<Value, String> {
+    if interp.is_terminal(Stream::Stdout) {
+        interp
+            .with_stdout(|out| out.write_all(sequence.as_bytes()).and_then(|_| out.flush()))
+            .map_err(|e| format!("term error: {}", e))?;
+    }
+    Ok(Value::Null)
+}
+
+fn count_arg(name: &str, args: &[Value]) -> Result<i64, String> {
+    match args {
+        [] => Ok(1),
+        [Value::Integer(n)] if *n >= 0 => Ok(*n),
+        _ => Err(format!("{} expects an optional non-negative line count", name)),
+    }
+}
+
+
+/// `color(text, fg[, bg])` with names like "red" or "bright_blue"
+fn color(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (text, fg, bg) = match args.as_slice() {
+        [Value::String(text), Value::String(fg)] => (text, fg, None),
+        [Value::String(text), Value::String(fg), Value::String(bg)] => (text, fg, Some(bg)),
+        _ => return Err("color expects a text, a color name and an optional background color".to_string()),
+    };
+    let unknown = |name: &str| format!("Unknown color '{}'", name);
+    let mut codes = vec![color_code(fg).ok_or_else(|| unknown(fg))?];
+    if let Some(bg) = bg {
+        codes.push(color_code(bg).ok_or_else(|| unknown(bg))? + 10);
+    }
+    Ok(paint(interp, text, &codes))
+}
+
+fn bold(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    Ok(paint(interp, expect_text("bold", &args)?, &[1]))
+}
+
+fn dim(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    Ok(paint(interp, expect_text("dim", &args)?, &[2]))
+}
+
+fn italic(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    Ok(paint(interp, expect_text("italic", &args)?, &[3]))
+}
+
+fn underline(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    Ok(paint(interp, expect_text("underline", &args)?, &[4]))
+}
+
+/// Removes ANSI escape sequences, e.g. before measuring or logging styled text
+fn strip(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let text = expect_text("strip", &args)?;
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            if chars.next() == Some('[') {
+                // Parameters and intermediates run until the final byte in '@'..='~'
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    Ok(Value::String(out))
+}
+
+/// `is_tty([stream])` for "stdout" (the default), "stderr" or "stdin"
+fn is_tty(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let stream = match args.as_slice() {
+        [] => "stdout",
+        [Value::String(stream)] => stream.as_str(),
+        _ => return Err("is_tty expects an optional stream name".to_string()),
+    };
+    match stream {
+        "stdout" => Ok(Value::Bool(interp.is_terminal(Stream::Stdout))),
+        "stderr" => Ok(Value::Bool(interp.is_terminal(Stream::Stderr))),
+        "stdin" => Ok(Value::Bool(interp.is_terminal(Stream::Stdin))),
+        other => Err(format!("is_tty expects \"stdout\", \"stderr\" or \"stdin\", got '{}'", other)),
+    }
+}
+
+fn colors_enabled(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("colors_enabled expects no arguments".to_string());
+    }
+    Ok(Value::Bool(use_colors(interp)))
+}
+
+/// `set_colors(True/False)` forces colors on or off, `set_colors("auto")` goes back to detection
+/// The mode belongs to this script, other interpreters in the process keep theirs
+fn set_colors(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let colors = match args.as_slice() {
+        [Value::Bool(colors)] => Some(*colors),
+        [Value::String(mode)] if mode == "auto" => None,
+        _ => return Err("set_colors expects True, False or \"auto\"".to_string()),
+    };
+    interp.force_colors(colors);
+    Ok(Value::Null)
+}
+
+fn clear_line(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("clear_line expects no arguments".to_string());
+    }
+    control(interp, "\r\x1b[2K")
+}
+
+fn clear_screen(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("clear_screen expects no arguments".to_string());
+    }
+    control(interp, "\x1b[2J\x1b[H")
+}
+
+fn move_up(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    control(interp, &format!("\x1b[{}A", count_arg("move_up", &args)?))
+}
+
+fn move_down(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    control(interp, &format!("\x1b[{}B", count_arg("move_down", &args)?))
+}
+
+/// `move_to(row, column)`, both counted from 1
+fn move_to(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.as_slice() {
+        [Value::Integer(row), Value::Integer(col)] if *row >= 1 && *col >= 1 => control(interp, &format!("\x1b[{};{}H", row, col)),
+        _ => Err("move_to expects a row and a column starting at 1".to_string()),
+    }
+}
+
+fn hide_cursor(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("hide_cursor expects no arguments".to_string());
+    }
+    control(interp, "\x1b[?25l")
+}
+
+fn show_cursor(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("show_cursor expects no arguments".to_string());
+    }
+    control(interp, "\x1b[?25h")
+}
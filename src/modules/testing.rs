@@ -0,0 +1,160 @@
+//! `testing` internal module with assertions and test registration
+//! Tests are registered with `testing.test("name", fn)` and executed by `testing.run()`,
+//! which prints one line per test and returns a summary hashmap
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("assert".to_string()), Value::BuiltinFunction(assert)),
+        (Value::String("assert_eq".to_string()), Value::BuiltinFunction(assert_eq)),
+        (Value::String("assert_ne".to_string()), Value::BuiltinFunction(assert_ne)),
+        (Value::String("assert_raises".to_string()), Value::BuiltinFunction(assert_raises)),
+        (Value::String("test".to_string()), Value::BuiltinFunction(test)),
+        (Value::String("run".to_string()), Value::BuiltinFunction(run)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Outcome of running the registered tests
+#[derive(Debug, Default)]
+pub struct TestSummary {
+    pub passed: usize,
+    /// Name and error of every failed test, in run order
+    pub failures: Vec<(String, String)>,
+}
+
+impl TestSummary {
+    pub fn to_value(&self) -> Value {
+        let failures = self
+            .failures
+            .iter()
+            .map(|(name, error)| {
+                Value::HashMap(vec![
+                    (Value::String("name".to_string()), Value::String(name.clone())),
+                    (Value::String("error".to_string()), Value::String(error.clone())),
+                ])
+            })
+            .collect();
+        Value::HashMap(vec![
+            (Value::String("passed".to_string()), Value::Integer(self.passed as i64)),
+            (Value::String("failed".to_string()), Value::Integer(self.failures.len() as i64)),
+            (Value::String("failures".to_string()), Value::Array(failures)),
+        ])
+    }
+}
+
+/// Runs and clears the tests registered on `interp`, printing a line per test and a summary
+pub fn run_registered_tests(interp: &mut Interpreter) -> TestSummary {
+    let mut summary = TestSummary::default();
+    for test in interp.take_registered_tests() {
+        match interp.call_function(test.func, Vec::new()) {
+            Ok(_) => {
+                report(interp, format_args!("test {} ... ok", test.name));
+                summary.passed += 1;
+            }
+            Err(e) => {
+                report(interp, format_args!("test {} ... FAILED", test.name));
+                summary.failures.push((test.name, e));
+            }
+        }
+    }
+
+    for (name, error) in &summary.failures {
+        report(interp, format_args!("\n---- {} ----\n{}", name, error));
+    }
+    let status = if summary.failures.is_empty() { "ok" } else { "FAILED" };
+    report(interp, format_args!("\ntest result: {}. {} passed; {} failed", status, summary.passed, summary.failures.len()));
+    summary
+}
+
+/// Prints a line of the test report to the script's standard output
+fn report(interp: &Interpreter, line: std::fmt::Arguments) {
+    // The report is informational, the returned summary carries the results
+    let _ = interp.with_stdout(|out| writeln!(out, "{}", line));
+}
+
+
+/// Appends the optional message argument to an assertion failure
+fn failure(message: String, extra: Option<&Value>) -> String {
+    match extra {
+        Some(Value::String(extra)) => format!("{}: {}", message, extra),
+        Some(other) => format!("{}: {}", message, other),
+        None => message,
+    }
+}
+
+fn assert(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.first() {
+        Some(Value::Bool(true)) => Ok(Value::Null),
+        Some(Value::Bool(false)) => Err(failure("assertion failed".to_string(), args.get(1))),
+        _ => Err("assert expects a boolean condition and an optional message".to_string()),
+    }
+}
+
+fn assert_eq(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.as_slice() {
+        [left, right, ..] if args.len() <= 3 => {
+            if left == right {
+                Ok(Value::Null)
+            } else {
+                let message = format!("assert_eq failed: {} != {}", left.repr(), right.repr());
+                Err(failure(message, args.get(2)))
+            }
+        }
+        _ => Err("assert_eq expects 2 values and an optional message".to_string()),
+    }
+}
+
+fn assert_ne(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.as_slice() {
+        [left, right, ..] if args.len() <= 3 => {
+            if left != right {
+                Ok(Value::Null)
+            } else {
+                let message = format!("assert_ne failed: both values are {}", left.repr());
+                Err(failure(message, args.get(2)))
+            }
+        }
+        _ => Err("assert_ne expects 2 values and an optional message".to_string()),
+    }
+}
+
+/// `assert_raises(fn[, text])` calls `fn()` and returns its error message,
+/// failing when it succeeds or when the message does not contain `text`
+fn assert_raises(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (func, expected) = match args.as_slice() {
+        [func] => (func.clone(), None),
+        [func, Value::String(text)] => (func.clone(), Some(text)),
+        _ => return Err("assert_raises expects a function and an optional expected message".to_string()),
+    };
+    match interp.call_function(func, Vec::new()) {
+        Ok(value) => Err(format!("assert_raises failed: the function returned {} instead of failing", value.repr())),
+        Err(error) => match expected {
+            Some(text) if !error.contains(text.as_str()) => {
+                Err(format!("assert_raises failed: expected an error containing '{}', got '{}'", text, error))
+            }
+            _ => Ok(Value::String(error)),
+        },
+    }
+}
+
+fn test(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match args.as_slice() {
+        [Value::String(name), func] if func.is_callable() => {
+            interp.register_test(name.clone(), func.clone());
+            Ok(Value::Null)
+        }
+        _ => Err("test expects a name and a function without parameters".to_string()),
+    }
+}
+
+fn run(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("run expects no arguments".to_string());
+    }
+    Ok(run_registered_tests(interp).to_value())
+}
@@ -0,0 +1,65 @@
+//! `thread` internal module, runs NIKL functions on OS threads
+//! The function and its arguments are copied into the new thread, so threads never share
+//! variables and results come back through `join()`
+
+use std::sync::Arc;
+
+use crate::interpreter::tasks::Task;
+use crate::interpreter::value::{NativeObject, Value};
+use crate::interpreter::Interpreter;
+
+
+/// Same as the usual main thread stack, the tree walking interpreter recurses deeply
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("spawn".to_string()), Value::BuiltinFunction(spawn)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// `spawn(fn[, args])` starts `fn(args...)` on a new thread and returns its handle
+fn spawn(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (func, call_args) = match args.as_slice() {
+        [func] => (func.clone(), Vec::new()),
+        [func, Value::Array(items)] | [func, Value::Tuple(items)] => (func.clone(), items.clone()),
+        _ => return Err("spawn expects a function and an optional array of arguments".to_string()),
+    };
+    if !func.is_callable() {
+        return Err(format!("spawn expects a function, got {}", func.type_name()));
+    }
+
+    let mut thread_interp = interp.detached();
+    let builder = std::thread::Builder::new().name("nikl-thread".to_string()).stack_size(STACK_SIZE);
+    let task = Task::spawn_thread(builder, move || thread_interp.call_function(func, call_args))
+        .map_err(|e| format!("thread.spawn error: {}", e))?;
+
+    Ok(Value::Native(Arc::new(ThreadHandle { task })))
+}
+
+
+/// A `Task` under the names threads use, `join()` keeps the result so it can be called again
+#[derive(Debug)]
+struct ThreadHandle {
+    task: Task,
+}
+
+impl NativeObject for ThreadHandle {
+    fn type_name(&self) -> &'static str {
+        "Thread"
+    }
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        if !args.is_empty() {
+            return Err(format!("{}() expects no arguments", method));
+        }
+        match method {
+            "join" => self.task.wait(),
+            "is_finished" => self.task.is_done().map(Value::Bool),
+            _ => Err(format!("Thread has no method '{}'", method)),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+use super::builtin_core::builtin_sleep;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("now".to_string()), Value::BuiltinFunction(now)),
+        (Value::String("monotonic".to_string()), Value::BuiltinFunction(monotonic)),
+        (Value::String("sleep".to_string()), Value::BuiltinFunction(builtin_sleep)),
+        (Value::String("measure".to_string()), Value::BuiltinFunction(measure)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Reference point for `monotonic`, set the first time any clock is read
+fn process_start() -> Instant {
+    static START: OnceLock<Instant> = OnceLock::new();
+    *START.get_or_init(Instant::now)
+}
+
+fn now(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("now expects no arguments".to_string());
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| Value::Float(d.as_secs_f64()))
+        .map_err(|e| format!("time.now error: {}", e))
+}
+
+fn monotonic(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err("monotonic expects no arguments".to_string());
+    }
+    Ok(Value::Float(process_start().elapsed().as_secs_f64()))
+}
+
+fn measure(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("measure expects 1 argument: a function without parameters".to_string());
+    }
+    let start = Instant::now();
+    interp.call_function(args[0].clone(), Vec::new())?;
+    Ok(Value::Float(start.elapsed().as_secs_f64()))
+}
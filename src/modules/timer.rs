@@ -0,0 +1,164 @@
+//! `timer` internal module, runs NIKL functions later or periodically on the async runtime
+//! `timer.after(2, f)` calls `f()` once, `timer.every(0.5, f)` keeps calling it until the
+//! handle is cancelled or `f` returns False
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
+
+use crate::interpreter::tasks::Task;
+use crate::interpreter::value::{NativeObject, Value};
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("after".to_string()), Value::BuiltinFunction(after)),
+        (Value::String("every".to_string()), Value::BuiltinFunction(every)),
+    ];
+    Value::HashMap(items)
+}
+
+
+#[derive(Debug, Default)]
+struct Cancel {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl Cancel {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // `notify_one` keeps a permit, so a timer that is not sleeping yet still sees it
+        self.notify.notify_one();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+fn timer_args(name: &str, args: &[Value]) -> Result<(Duration, Value), String> {
+    let seconds = match args.first() {
+        Some(Value::Integer(i)) if *i >= 0 => *i as f64,
+        Some(Value::Float(f)) if *f >= 0.0 && f.is_finite() => *f,
+        _ => return Err(format!("{} expects a non-negative number of seconds and a function", name)),
+    };
+    match args.get(1) {
+        Some(func) if func.is_callable() && args.len() == 2 => {
+            let delay = Duration::try_from_secs_f64(seconds).map_err(|_| format!("{} delay is too large, got {} seconds", name, seconds))?;
+            Ok((delay, func.clone()))
+        }
+        _ => Err(format!("{} expects a non-negative number of seconds and a function", name)),
+    }
+}
+
+/// Runs the callback on the blocking pool, handing the interpreter back for the next run
+async fn call(mut interp: Interpreter, func: Value) -> Result<(Interpreter, Value), String> {
+    let (interp, result) = tokio::task::spawn_blocking(move || {
+        let result = interp.call_function(func, Vec::new());
+        (interp, result)
+    })
+    .await
+    .map_err(|_| "timer callback panicked".to_string())?;
+    match result {
+        Ok(value) => Ok((interp, value)),
+        Err(e) => {
+            // Nobody may ever wait on the handle of a background timer
+            let _ = interp.with_stderr(|out| writeln!(out, "timer callback error: {}", e));
+            Err(e)
+        }
+    }
+}
+
+
+/// `after(seconds, fn)`, waiting on the handle gives the function's return value,
+/// or None when it was cancelled first
+fn after(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (delay, func) = timer_args("after", &args)?;
+    let cancel = Arc::new(Cancel::default());
+    let timer_interp = interp.detached();
+
+    let token = cancel.clone();
+    let task = Task::spawn(async move {
+        tokio::select! {
+            _ = token.notify.notified() => return Ok(Value::Null),
+            _ = tokio::time::sleep(delay) => {}
+        }
+        if token.is_cancelled() {
+            return Ok(Value::Null);
+        }
+        call(timer_interp, func).await.map(|(_, value)| value)
+    });
+    Ok(Value::Native(Arc::new(Timer { task, cancel })))
+}
+
+/// `every(seconds, fn)`, the first call happens after one period
+fn every(interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (period, func) = timer_args("every", &args)?;
+    if period.is_zero() {
+        return Err("every expects a period above 0 seconds".to_string());
+    }
+    let cancel = Arc::new(Cancel::default());
+    let mut timer_interp = interp.detached();
+
+    let token = cancel.clone();
+    let task = Task::spawn(async move {
+        let mut ticks = interval_at(Instant::now() + period, period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = token.notify.notified() => return Ok(Value::Null),
+                _ = ticks.tick() => {}
+            }
+            if token.is_cancelled() {
+                return Ok(Value::Null);
+            }
+            let (back, value) = call(timer_interp, func.clone()).await?;
+            timer_interp = back;
+            if value == Value::Bool(false) {
+                return Ok(Value::Null);
+            }
+        }
+    });
+    Ok(Value::Native(Arc::new(Timer { task, cancel })))
+}
+
+
+#[derive(Debug)]
+struct Timer {
+    /// The `Task` running the timer loop
+    task: Value,
+    cancel: Arc<Cancel>,
+}
+
+impl NativeObject for Timer {
+    fn type_name(&self) -> &'static str {
+        "Timer"
+    }
+
+    fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        if !args.is_empty() {
+            return Err(format!("{}() expects no arguments", method));
+        }
+        let Value::Native(task) = &self.task else {
+            unreachable!("timers always hold a task")
+        };
+        match method {
+            // A call that is already running finishes, but no new one starts
+            "cancel" => {
+                self.cancel.cancel();
+                Ok(Value::Null)
+            }
+            "is_active" => match task.call_method("is_done", Vec::new())? {
+                Value::Bool(done) => Ok(Value::Bool(!done)),
+                other => Ok(other),
+            },
+            "wait" => task.call_method("wait", Vec::new()),
+            _ => Err(format!("Timer has no method '{}'", method)),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("parse".to_string()), Value::BuiltinFunction(parse)),
+        (Value::String("dump".to_string()), Value::BuiltinFunction(dump)),
+    ];
+    Value::HashMap(items)
+}
+
+
+/// Dates and times have no NIKL type, they are kept as their TOML string form
+/// Floats map straight to `Value::Float`, going through JSON would turn `nan` and `inf` into null
+fn toml_to_value(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Integer(i),
+        toml::Value::Float(f) => Value::Float(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_value).collect()),
+        toml::Value::Table(table) => Value::HashMap(
+            table.into_iter().map(|(k, v)| (Value::String(k), toml_to_value(v))).collect(),
+        ),
+    }
+}
+
+/// Converts straight to TOML so `nan` and `inf` read by `parse` can be written back,
+/// TOML has no null and its tables only have string keys
+fn value_to_toml(value: &Value) -> Result<toml::Value, String> {
+    match value {
+        Value::Bool(b) => Ok(toml::Value::Boolean(*b)),
+        Value::Integer(i) => Ok(toml::Value::Integer(*i)),
+        Value::Float(f) => Ok(toml::Value::Float(*f)),
+        Value::String(s) => Ok(toml::Value::String(s.clone())),
+        Value::Array(items) | Value::Tuple(items) => items.iter().map(value_to_toml).collect::<Result<_, _>>().map(toml::Value::Array),
+        Value::HashMap(pairs) => {
+            let mut table = toml::Table::new();
+            for (key, value) in pairs {
+                match key {
+                    Value::String(k) => table.insert(k.clone(), value_to_toml(value)?),
+                    other => return Err(format!("Cannot convert hashmap key {} to TOML, keys must be strings", other.repr())),
+                };
+            }
+            Ok(toml::Value::Table(table))
+        }
+        Value::Null => Err("TOML has no null value".to_string()),
+        other => Err(format!("Cannot convert {} to TOML", other.type_name())),
+    }
+}
+
+fn parse(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(text)) = args.first() {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| format!("toml.parse error: {}", e))?;
+        Ok(toml_to_value(toml::Value::Table(table)))
+    } else {
+        Err("parse expects a string of TOML".to_string())
+    }
+}
+
+fn dump(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("dump expects 1 argument: a hashmap".to_string());
+    }
+    if !matches!(args[0], Value::HashMap(_)) {
+        return Err("dump expects a hashmap, TOML documents are tables".to_string());
+    }
+    let data = value_to_toml(&args[0]).map_err(|e| format!("toml.dump error: {}", e))?;
+    toml::to_string(&data)
+        .map(Value::String)
+        .map_err(|e| format!("toml.dump error: {}", e))
+}
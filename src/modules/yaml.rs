@@ -0,0 +1,33 @@
+use crate::interpreter::value::Value;
+use super::convert::{from_json, to_json};
+use crate::interpreter::Interpreter;
+
+
+pub fn make_module() -> Value {
+    let items = vec![
+        (Value::String("parse".to_string()), Value::BuiltinFunction(parse)),
+        (Value::String("dump".to_string()), Value::BuiltinFunction(dump)),
+    ];
+    Value::HashMap(items)
+}
+
+
+fn parse(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if let Some(Value::String(text)) = args.first() {
+        serde_yaml_ng::from_str::<serde_json::Value>(text)
+            .map(from_json)
+            .map_err(|e| format!("yaml.parse error: {}", e))
+    } else {
+        Err("parse expects a string of YAML".to_string())
+    }
+}
+
+fn dump(_interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err("dump expects 1 argument: the value to serialize".to_string());
+    }
+    let data = to_json(&args[0]).map_err(|e| format!("yaml.dump error: {}", e))?;
+    serde_yaml_ng::to_string(&data)
+        .map(Value::String)
+        .map_err(|e| format!("yaml.dump error: {}", e))
+}
@@ -5,11 +5,26 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use serde::Deserialize;
 use walkdir::WalkDir;
-use tar::Builder;
+use tar::{Archive, Builder};
+
+
+/// Writer for `.tar.gz` files, also used by the `archive` module
+pub type TarGzBuilder = Builder<GzEncoder<File>>;
+
+pub fn tar_gz_builder(path: &Path) -> io::Result<TarGzBuilder> {
+    let file = File::create(path)?;
+    Ok(Builder::new(GzEncoder::new(file, Compression::default())))
+}
+
+pub fn open_tar_gz(path: &Path) -> io::Result<Archive<GzDecoder<File>>> {
+    let file = File::open(path)?;
+    Ok(Archive::new(GzDecoder::new(file)))
+}
 
 
 #[derive(Deserialize)]
@@ -25,57 +40,73 @@ struct Config {
 }
 
 
-pub fn create_tar_gz() -> io::Result<()> {
-    let current_dir = env::current_dir()?;
-    let config = read_and_validate_config(&current_dir)?;
-    validate_required_files(&current_dir, &config)?;
+/// Writes the package in `dir` to `<name>-<version>.tar.gz` next to its `config.json`
+pub fn create_tar_gz(dir: &Path) -> io::Result<()> {
+    let config = read_and_validate_config(dir)?;
+    validate_required_files(dir, &config)?;
 
-    let tar_gz_name = format!("{}-{}.tar.gz", config.name, config.version);
-    if Path::new(&tar_gz_name).exists() {
-        panic!("File {} already exists. Please remove it before creating a new package.", tar_gz_name);
+    let tar_gz_name = dir.join(format!("{}-{}.tar.gz", config.name, config.version));
+    if tar_gz_name.exists() {
+        return Err(invalid(format!("File {} already exists. Please remove it before creating a new package.", tar_gz_name.display())));
     }
-    println!("Creating {}...", tar_gz_name);
+    println!("Creating {}...", tar_gz_name.display());
+
+    write_archive(&tar_gz_name, dir, &config)?;
+    println!("Created {} successfully.", tar_gz_name.display());
+    Ok(())
+}
 
-    let tar_gz_file = File::create(&tar_gz_name)?;
-    let encoder = GzEncoder::new(tar_gz_file, Compression::default());
-    let mut archive = Builder::new(encoder);
+/// The package in `dir` as a `.tar.gz` in memory, with its name and version
+pub fn build_archive(dir: &Path) -> io::Result<(String, String, Vec<u8>)> {
+    let config = read_and_validate_config(dir)?;
+    validate_required_files(dir, &config)?;
+
+    let path = env::temp_dir().join(format!("nikl-{}-{}-{}.tar.gz", config.name, config.version, std::process::id()));
+    let archive = write_archive(&path, dir, &config).and_then(|()| fs::read(&path));
+    let _ = fs::remove_file(&path);
+    Ok((config.name, config.version, archive?))
+}
 
-    add_nk_files(&mut archive, &config.name)?;
-    add_metadata_files(&mut archive, &config)?;
-    println!("Created {} successfully.", tar_gz_name);
+fn write_archive(path: &Path, dir: &Path, config: &Config) -> io::Result<()> {
+    let mut archive = tar_gz_builder(path)?;
+    add_nk_files(&mut archive, dir, &config.name)?;
+    add_metadata_files(&mut archive, dir, config)?;
+    archive.into_inner()?.finish()?;
     Ok(())
 }
 
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
 
 fn read_and_validate_config(current_dir: &Path) -> io::Result<Config> {
     let config_path = current_dir.join("config.json");
     if !config_path.exists() {
-        panic!("config.json not found");
+        return Err(invalid("config.json not found".to_string()));
     }
 
     let config_text = fs::read_to_string(&config_path)?;
-    let config: Config = serde_json::from_str(&config_text)
-        .expect("Invalid config.json structure or format");
-
-    Ok(config)
+    serde_json::from_str(&config_text).map_err(|e| invalid(format!("Invalid config.json structure or format: {}", e)))
 }
 
 
 fn validate_required_files(current_dir: &Path, config: &Config) -> io::Result<()> {
     let package_src_file = current_dir.join("src").join(format!("{}.nk", config.name));
     if !package_src_file.exists() {
-        panic!("Required file src/{}.nk not found", config.name);
+        return Err(invalid(format!("Required file src/{}.nk not found", config.name)));
     }
 
     Ok(())
 }
 
 
-fn add_nk_files(archive: &mut Builder<GzEncoder<File>>, package_name: &str) -> io::Result<()> {
-    for entry in WalkDir::new("src").into_iter().filter_map(Result::ok) {
+fn add_nk_files(archive: &mut TarGzBuilder, dir: &Path, package_name: &str) -> io::Result<()> {
+    let src = dir.join("src");
+    for entry in WalkDir::new(&src).into_iter().filter_map(Result::ok) {
         let path = entry.path();
         if path.extension().and_then(|e| e.to_str()) == Some("nk") && path.is_file() {
-            let relative_path = path.strip_prefix("src").unwrap();
+            let relative_path = path.strip_prefix(&src).unwrap();
             let mut archive_path = PathBuf::from(package_name);
             archive_path.push(relative_path);
             archive.append_path_with_name(path, archive_path)?;
@@ -86,20 +117,21 @@ fn add_nk_files(archive: &mut Builder<GzEncoder<File>>, package_name: &str) -> i
 
 
 fn add_metadata_files(
-    archive: &mut Builder<GzEncoder<File>>,
+    archive: &mut TarGzBuilder,
+    dir: &Path,
     config: &Config,
 ) -> io::Result<()> {
-    archive.append_path_with_name("config.json", "config.json")?;
+    archive.append_path_with_name(dir.join("config.json"), "config.json")?;
 
     if let Some(readme) = &config.readme_file {
-        if Path::new(readme).exists() {
-            archive.append_path_with_name(readme, readme)?;
+        if dir.join(readme).exists() {
+            archive.append_path_with_name(dir.join(readme), readme)?;
         }
     }
 
     if let Some(license) = &config.license_file {
-        if Path::new(license).exists() {
-            archive.append_path_with_name(license, license)?;
+        if dir.join(license).exists() {
+            archive.append_path_with_name(dir.join(license), license)?;
         }
     }
 
@@ -0,0 +1,102 @@
+//! Downloaded package archives, shared by every project of the user so installing a version
+//! again needs no network. Archives are kept as `packages/<name>/<version>/<sha256>.tar.gz`
+//! and one whose contents don't match its hash any more is dropped when it's read
+
+use std::fs;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use super::registry::compare_versions;
+
+
+/// Where downloads are kept: `NIKL_CACHE_DIR`, otherwise `%LOCALAPPDATA%\nikl\cache` on Windows,
+/// `~/Library/Caches/nikl` on macOS and `$XDG_CACHE_HOME/nikl` or `~/.cache/nikl` elsewhere
+pub fn cache_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if let Some(dir) = var("NIKL_CACHE_DIR") {
+        return Some(dir);
+    }
+    if cfg!(windows) {
+        Some(var("LOCALAPPDATA")?.join("nikl").join("cache"))
+    } else if cfg!(target_os = "macos") {
+        Some(var("HOME")?.join("Library").join("Caches").join("nikl"))
+    } else {
+        Some(var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|home| home.join(".cache")))?.join("nikl"))
+    }
+}
+
+/// The SHA-256 of an archive as lowercase hex
+pub fn checksum(archive: &[u8]) -> String {
+    Sha256::digest(archive).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Where the archives of a version go, `None` for a version that could leave the cache
+fn version_dir(name: &str, version: &str) -> Option<PathBuf> {
+    super::check_version(version).ok()?;
+    Some(cache_dir()?.join("packages").join(name).join(version))
+}
+
+/// The cached archive of a version, if there is an intact one. With `wanted` only
+/// the archive with that hash will do
+pub fn cached(name: &str, version: &str, wanted: Option<&str>) -> Option<Vec<u8>> {
+    for entry in fs::read_dir(version_dir(name, version)?).ok()?.filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(hash) = path.file_name().and_then(|file| file.to_str()).and_then(|file| file.strip_suffix(".tar.gz")) else {
+            continue;
+        };
+        if wanted.is_some_and(|wanted| wanted != hash) {
+            continue;
+        }
+        match fs::read(&path) {
+            Ok(archive) if checksum(&archive) == hash => return Some(archive),
+            _ => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    None
+}
+
+/// Keeps a downloaded archive, once. The cache only saves time, so failing to write it isn't an error
+pub fn store(name: &str, version: &str, archive: &[u8]) {
+    let Some(dir) = version_dir(name, version) else {
+        return;
+    };
+    let hash = checksum(archive);
+    if dir.join(format!("{}.tar.gz", hash)).exists() {
+        return;
+    }
+    // Written next to its place first, so a cut-off write is never read as the archive
+    let partial = dir.join(format!("{}.partial", hash));
+    let stored = fs::create_dir_all(&dir).and_then(|()| fs::write(&partial, archive)).and_then(|()| fs::rename(&partial, dir.join(format!("{}.tar.gz", hash))));
+    if stored.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+}
+
+/// The highest version of a package in the cache
+pub fn latest_cached(name: &str) -> Option<String> {
+    let dir = cache_dir()?.join("packages").join(name);
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|version| cached(name, version, None).is_some())
+        .max_by(|a, b| compare_versions(a, b))
+}
+
+/// `nikl cache clean`, deletes every cached archive and returns how many there were
+pub fn clean_cache() -> Result<usize, String> {
+    let dir = cache_dir().ok_or("Can't find a cache directory, set NIKL_CACHE_DIR")?.join("packages");
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let count = walkdir::WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && entry.file_name().to_string_lossy().ends_with(".tar.gz"))
+        .count();
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove '{}': {}", dir.display(), e))?;
+    Ok(count)
+}
@@ -0,0 +1,94 @@
+//! Registry tokens saved by `nikl login`, in `credentials.json` of the user's config directory
+//! keyed by registry URL. Only the user can read the file. `NIKL_TOKEN` takes precedence
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Credentials {
+    #[serde(default)]
+    tokens: BTreeMap<String, String>,
+}
+
+
+/// Where nikl keeps per-user settings: `NIKL_CONFIG_DIR`, otherwise `%APPDATA%\nikl` on Windows,
+/// `~/Library/Application Support/nikl` on macOS and `$XDG_CONFIG_HOME/nikl` or `~/.config/nikl` elsewhere
+pub fn config_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if let Some(dir) = var("NIKL_CONFIG_DIR") {
+        return Some(dir);
+    }
+    let base = if cfg!(windows) {
+        var("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        var("HOME")?.join("Library").join("Application Support")
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))?
+    };
+    Some(base.join("nikl"))
+}
+
+fn credentials_path() -> Result<PathBuf, String> {
+    config_dir().map(|dir| dir.join("credentials.json")).ok_or_else(|| "Can't find a config directory, set NIKL_CONFIG_DIR".to_string())
+}
+
+fn read() -> Result<Credentials, String> {
+    let path = credentials_path()?;
+    match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("Invalid '{}': {}", path.display(), e)),
+        Err(_) => Ok(Credentials::default()),
+    }
+}
+
+fn write(credentials: &Credentials) -> Result<(), String> {
+    let path = credentials_path()?;
+    let failed = |e: std::io::Error| format!("Failed to write '{}': {}", path.display(), e);
+    let dir = path.parent().unwrap_or(&path);
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir).map_err(failed)?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path).map_err(failed)?;
+    // A file that existed before keeps its mode when opened
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600)).map_err(failed)?;
+    }
+    let text = serde_json::to_string_pretty(credentials).map_err(|e| e.to_string())?;
+    file.write_all((text + "\n").as_bytes()).map_err(failed)
+}
+
+/// The token for `registry`, `NIKL_TOKEN` when set
+pub fn token(registry: &str) -> Option<String> {
+    match std::env::var("NIKL_TOKEN") {
+        Ok(token) if !token.is_empty() => Some(token),
+        _ => read().ok()?.tokens.get(registry).cloned(),
+    }
+}
+
+pub fn save_token(registry: &str, token: &str) -> Result<(), String> {
+    let mut credentials = read()?;
+    credentials.tokens.insert(registry.to_string(), token.to_string());
+    write(&credentials)
+}
+
+/// Forgets the token for `registry`, returns whether there was one
+pub fn remove_token(registry: &str) -> Result<bool, String> {
+    let mut credentials = read()?;
+    if credentials.tokens.remove(registry).is_none() {
+        return Ok(false);
+    }
+    write(&credentials).map(|()| true)
+}
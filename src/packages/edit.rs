@@ -0,0 +1,197 @@
+//! Edits the `dependencies` of a `config.json` in its text, so everything else in the file
+//! stays as it was written. Dependencies may be a `{"name": "version"}` map or a list of
+//! `{"name": .., "version": ..}` objects, new ones are written the same way as the others
+
+use serde_json::Value;
+
+
+/// Where a value is in the text, with its key when it's in an object
+struct Entry {
+    key: Option<String>,
+    /// Where the key starts, or the value in an array
+    start: usize,
+    value: usize,
+    end: usize,
+}
+
+/// A position in the text
+type Span = (usize, usize);
+
+
+/// Adds `name` at `version` to the dependencies, or changes the version it has
+pub fn add_dependency(text: &str, name: &str, version: &str) -> Result<String, String> {
+    let root = scan(text)?;
+    let fields = entries(text, root)?;
+    let Some(dependencies) = fields.iter().find(|field| field.key.as_deref() == Some("dependencies")) else {
+        let field = format!("\"dependencies\": {{{}: {}}}", quote(name), quote(version));
+        return Ok(insert(text, root, &fields, &field));
+    };
+    let span = value_span(text, dependencies)?;
+    let list = text[span.0..].starts_with('[');
+    let items = entries(text, span)?;
+    let existing = items.iter().position(|item| dependency_name(text, item, list).as_deref() == Some(name));
+
+    let entry = if list {
+        format!("{{\"name\": {}, \"version\": {}}}", quote(name), quote(version))
+    } else {
+        format!("{}: {}", quote(name), quote(version))
+    };
+    Ok(match existing {
+        Some(i) => format!("{}{}{}", &text[..items[i].start], entry, &text[items[i].end..]),
+        None => insert(text, span, &items, &entry),
+    })
+}
+
+/// Takes `name` out of the dependencies, `None` when it isn't one
+pub fn remove_dependency(text: &str, name: &str) -> Result<Option<String>, String> {
+    let root = scan(text)?;
+    let fields = entries(text, root)?;
+    let Some(dependencies) = fields.iter().find(|field| field.key.as_deref() == Some("dependencies")) else {
+        return Ok(None);
+    };
+    let span = value_span(text, dependencies)?;
+    let list = text[span.0..].starts_with('[');
+    let items = entries(text, span)?;
+    let Some(i) = items.iter().position(|item| dependency_name(text, item, list).as_deref() == Some(name)) else {
+        return Ok(None);
+    };
+
+    // The separator goes with the entry: the one after it, or before it for the last one
+    let (start, end) = match (i, items.len()) {
+        (_, 1) => (span.0 + 1, span.1 - 1),
+        (i, _) if i + 1 < items.len() => (items[i].start, items[i + 1].start),
+        (i, _) => (items[i - 1].end, items[i].end),
+    };
+    Ok(Some(format!("{}{}", &text[..start], &text[end..])))
+}
+
+
+/// The name of a dependency, its key in a map
+fn dependency_name(text: &str, entry: &Entry, list: bool) -> Option<String> {
+    if !list {
+        return entry.key.clone();
+    }
+    let item: Value = serde_json::from_str(&text[entry.start..entry.end]).ok()?;
+    item["name"].as_str().map(str::to_string)
+}
+
+/// Adds `entry` after the last of `entries` in the object or array at `span`, separated like
+/// the others are, or on a line of its own when there are none
+fn insert(text: &str, span: Span, entries: &[Entry], entry: &str) -> String {
+    if let Some(last) = entries.last() {
+        let before = entries.len().checked_sub(2).map(|i| entries[i].end).unwrap_or(span.0 + 1);
+        let gap = &text[before..last.start];
+        let separator = gap.rsplit(',').next().unwrap_or(gap);
+        return format!("{},{}{}{}", &text[..last.end], separator, entry, &text[last.end..]);
+    }
+    let indent = line_indent(text, span.0);
+    let unit = indent_unit(text);
+    format!("{}\n{}{}{}\n{}{}", &text[..span.0 + 1], indent, unit, entry, indent, &text[span.1 - 1..])
+}
+
+/// The whitespace a line starts with, for the line holding `position`
+fn line_indent(text: &str, position: usize) -> &str {
+    let line = &text[text[..position].rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// How far the first indented line is indented, four spaces when none is
+fn indent_unit(text: &str) -> &str {
+    text.lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("    ")
+}
+
+fn quote(text: &str) -> String {
+    Value::String(text.to_string()).to_string()
+}
+
+
+/// The span of the top-level value, which must be an object
+fn scan(text: &str) -> Result<Span, String> {
+    serde_json::from_str::<serde_json::Map<String, Value>>(text).map_err(|e| format!("Invalid config.json: {}", e))?;
+    let start = skip_space(text, 0);
+    Ok((start, skip_value(text, start)))
+}
+
+fn value_span(text: &str, entry: &Entry) -> Result<Span, String> {
+    match text[entry.value..].chars().next() {
+        Some('{' | '[') => Ok((entry.value, entry.end)),
+        _ => Err("config.json: dependencies must be a list or an object".to_string()),
+    }
+}
+
+/// The entries of the object or array at `span`, which is known to be valid JSON
+fn entries(text: &str, (start, end): Span) -> Result<Vec<Entry>, String> {
+    let object = text[start..].starts_with('{');
+    let mut entries = Vec::new();
+    let mut position = skip_space(text, start + 1);
+    while position < end - 1 {
+        let entry_start = position;
+        let key = if object {
+            let key_end = skip_string(text, position);
+            let key: String = serde_json::from_str(&text[position..key_end]).map_err(|e| e.to_string())?;
+            position = skip_space(text, skip_space(text, key_end) + 1);
+            Some(key)
+        } else {
+            None
+        };
+        let value = position;
+        position = skip_value(text, position);
+        entries.push(Entry { key, start: entry_start, value, end: position });
+        position = skip_space(text, position);
+        if text[position..].starts_with(',') {
+            position = skip_space(text, position + 1);
+        }
+    }
+    Ok(entries)
+}
+
+fn skip_space(text: &str, position: usize) -> usize {
+    position + text[position..].len() - text[position..].trim_start().len()
+}
+
+/// Where the string starting at `position` ends, after its closing quote
+fn skip_string(text: &str, position: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = position + 1;
+    while i < bytes.len() && bytes[i] != b'"' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    i + 1
+}
+
+/// Where the value starting at `position` ends
+fn skip_value(text: &str, position: usize) -> usize {
+    let bytes = text.as_bytes();
+    match bytes[position] {
+        b'"' => skip_string(text, position),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = position;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        i = skip_string(text, i);
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return i + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            i
+        }
+        _ => {
+            let rest = &text[position..];
+            position + rest[..rest.find([',', '}', ']']).unwrap_or(rest.len())].trim_end().len()
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Packages installed from git repositories, `git+<url>#<ref>` where the ref is a branch, a tag
+//! or a commit and defaults to the repository's default branch. Cloning runs the `git` command
+
+use std::path::Path;
+use std::process::Command;
+
+
+/// Splits `git+<url>#<ref>` into the URL and the ref, empty when there is none
+pub fn parse_git(spec: &str) -> Option<(String, String)> {
+    let spec = spec.strip_prefix("git+")?;
+    let (url, reference) = spec.split_once('#').unwrap_or((spec, ""));
+    Some((url.to_string(), reference.to_string()))
+}
+
+/// The name a repository URL suggests, `pkg` for `https://github.com/user/pkg.git`
+pub fn repository_name(url: &str) -> &str {
+    let name = url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or_default();
+    name.strip_suffix(".git").unwrap_or(name)
+}
+
+/// Clones `url` into `dir` and checks out `reference`, returns the commit it points at
+pub fn checkout(url: &str, reference: &str, dir: &Path) -> Result<String, String> {
+    // Specs come from registry manifests too, neither part may pass for an option of git
+    if url.starts_with('-') || reference.starts_with('-') {
+        return Err(format!("Invalid git package 'git+{}#{}'", url, reference));
+    }
+    let dir_arg = dir.to_string_lossy().to_string();
+    // A shallow clone works for branches and tags, commits need the whole history
+    if reference.is_empty() {
+        git(None, &["clone", "--quiet", "--depth", "1", "--", url, &dir_arg])?;
+    } else if git(None, &["clone", "--quiet", "--depth", "1", "--branch", reference, "--", url, &dir_arg]).is_err() {
+        let _ = std::fs::remove_dir_all(dir);
+        git(None, &["clone", "--quiet", "--", url, &dir_arg])?;
+        git(Some(dir), &["checkout", "--quiet", reference, "--"]).map_err(|_| format!("'{}' has no branch, tag or commit '{}'", url, reference))?;
+    }
+    git(Some(dir), &["rev-parse", "HEAD"])
+}
+
+/// Runs git and returns what it printed, its error output when it fails
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command.args(args).output().map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
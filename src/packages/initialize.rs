@@ -73,7 +73,7 @@ fn generate_config(project_name: &str) -> String {
 /// Capitalizes words separated by dashes or underscores
 fn capitalize_words(input: &str) -> String {
     input
-        .split(|c| c == '-' || c == '_')
+        .split(['-', '_'])
         .map(|s| {
             let mut chars = s.chars();
             match chars.next() {
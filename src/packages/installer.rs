@@ -1,43 +1,385 @@
-use std::path::Path;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use tar::Archive;
+
 use super::initialize::create_nikl_environment;
-use super::Package;
+use super::edit;
+use super::registry::Registry;
+use super::workspace::Workspace;
+use super::{Dependency, Manifest, Package};
+
+
+/// A package recorded in `.nikl/info.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    /// `registry`, `file:<path>` for a local archive, `git+<url>#<commit>` for a git repository
+    /// or `path:<dir>` for a path dependency, relative to the project
+    pub source: String,
+    /// SHA-256 of the archive downloaded from the registry, installing the version again
+    /// fails when the archive is different
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// Names of the packages it depends on
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Info {
+    #[serde(default)]
+    packages: Vec<InstalledPackage>,
+}
+
+
+/// The `.nikl` directory of a project: the installed packages and `info.json` listing them
+pub struct PackageEnv {
+    dir: PathBuf,
+    info: Info,
+    quiet: bool,
+    /// Directories of the workspace members by name, dependencies on them are linked
+    members: Vec<(String, PathBuf)>,
+}
+
+impl PackageEnv {
+    /// Opens `<root>/.nikl`, creating it when missing. `quiet` leaves out progress messages
+    pub fn open(root: &Path, quiet: bool) -> Result<Self, String> {
+        let dir = root.join(".nikl");
+        if !dir.exists() {
+            if !quiet {
+                println!("Creating .nikl directory for package management...");
+            }
+            create_nikl_environment(root).map_err(|e| format!("Failed to create the .nikl directory: {}", e))?;
+        }
+        fs::create_dir_all(dir.join("packages")).map_err(|e| format!("Failed to create the packages directory: {}", e))?;
+
+        let info = match fs::read_to_string(dir.join("info.json")) {
+            Ok(text) if !text.trim().is_empty() => serde_json::from_str(&text).map_err(|e| format!("Invalid .nikl/info.json: {}", e))?,
+            _ => Info::default(),
+        };
+        Ok(Self { dir, info, quiet, members: Vec::new() })
+    }
+
+    /// Makes dependencies on the members of `workspace` link to them
+    pub fn with_workspace(self, workspace: &Workspace) -> Self {
+        let members = workspace.members.iter().map(|member| (member.name.clone(), member.dir.clone())).collect();
+        Self { members, ..self }
+    }
+
+    /// The directory of a workspace member, relative to the project
+    pub fn member_dir(&self, name: &str) -> Option<&Path> {
+        self.members.iter().find(|(member, _)| member == name).map(|(_, dir)| dir.as_path())
+    }
+
+    /// The project directory
+    pub fn root(&self) -> &Path {
+        self.dir.parent().unwrap_or(&self.dir)
+    }
+
+    pub fn packages(&self) -> &[InstalledPackage] {
+        &self.info.packages
+    }
+
+    pub fn installed(&self, name: &str) -> Option<&InstalledPackage> {
+        self.info.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Like `installed`, but only when its files are there too, which they aren't in a fresh
+    /// checkout of a project that keeps `info.json`
+    pub fn present(&self, name: &str) -> Option<&InstalledPackage> {
+        self.installed(name).filter(|_| self.package_dir(name).symlink_metadata().is_ok())
+    }
+
+    /// Where a package's files are
+    pub fn package_dir(&self, name: &str) -> PathBuf {
+        self.dir.join("packages").join(name)
+    }
+
+    pub fn progress(&self, message: &str) {
+        if !self.quiet {
+            println!("{}", message);
+        }
+    }
+
+    /// Adds or replaces a package in `info.json`
+    pub fn record(&mut self, package: InstalledPackage) -> Result<(), String> {
+        self.info.packages.retain(|installed| installed.name != package.name);
+        self.info.packages.push(package);
+        self.info.packages.sort_by(|a, b| a.name.cmp(&b.name));
+        self.save()
+    }
+
+    /// Deletes a package's files and its entry in `info.json`
+    pub fn remove(&mut self, name: &str) -> Result<(), String> {
+        let dir = self.package_dir(name);
+        // Linked packages are removed as links, even when what they point at is gone
+        if dir.symlink_metadata().is_ok() {
+            fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove '{}': {}", dir.display(), e))?;
+        }
+        self.info.packages.retain(|package| package.name != name);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(&self.info).map_err(|e| e.to_string())?;
+        fs::write(self.dir.join("info.json"), text + "\n").map_err(|e| format!("Failed to write .nikl/info.json: {}", e))
+    }
+
+    /// Extracts a package archive into `.nikl/packages/<name>`, replacing what was there
+    /// The archive must hold a `config.json` for `name` at `version`. Sources under `<name>/`
+    /// end up next to it
+    pub fn unpack(&self, name: &str, version: &str, archive: &[u8]) -> Result<Manifest, String> {
+        let invalid = |reason: String| format!("Invalid archive for {}@{}: {}", name, version, reason);
+        let staging = self.dir.join("packages").join(format!(".{}.partial", name));
+        let _ = fs::remove_dir_all(&staging);
+        fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+        let result = extract(archive, name, &staging).map_err(invalid).and_then(|()| {
+            let config = fs::read_to_string(staging.join("config.json")).map_err(|_| invalid("no config.json".to_string()))?;
+            let manifest: Manifest = serde_json::from_str(&config).map_err(|e| invalid(format!("config.json: {}", e)))?;
+            if manifest.name != name || manifest.version != version {
+                return Err(invalid(format!("it contains {}@{}", manifest.name, manifest.version)));
+            }
+            Ok(manifest)
+        });
+        let manifest = match result {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+
+        let target = self.package_dir(name);
+        let _ = fs::remove_dir_all(&target);
+        fs::rename(&staging, &target).map_err(|e| format!("Failed to install into '{}': {}", target.display(), e))?;
+        Ok(manifest)
+    }
 
+    /// Makes the package in `dir` available as `.nikl/packages/<name>`. On unix that's a link to its
+    /// `src` directory, so changes show up without installing again, elsewhere a copy of it
+    pub fn link(&self, name: &str, dir: &Path) -> Result<(), String> {
+        let target = self.package_dir(name);
+        let _ = fs::remove_dir_all(&target);
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("src"), &target).map_err(|e| format!("Failed to link '{}': {}", target.display(), e))
+        }
+        #[cfg(not(unix))]
+        {
+            let (_, version, archive) = super::build_archive(dir).map_err(|e| e.to_string())?;
+            self.unpack(name, &version, &archive).map(|_| ())
+        }
+    }
+}
 
+/// Unpacks the entries of a `.tar.gz` into `dir`, dropping the leading `<name>/` of sources
+fn extract(archive: &[u8], name: &str, dir: &Path) -> Result<(), String> {
+    let mut archive = Archive::new(GzDecoder::new(archive));
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        let path = path.strip_prefix(name).unwrap_or(&path).to_path_buf();
+        if path.components().any(|component| !matches!(component, Component::Normal(_))) {
+            return Err(format!("unsafe path '{}'", path.display()));
+        }
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let target = dir.join(&path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        fs::write(&target, bytes).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
 
-fn check_and_create_nikl_directory() {
-    // See if ".nikl" directory exists and in that directory
-    let nikl_dir = Path::new(".nikl");
 
-    if !nikl_dir.exists() {
-        println!("Creating .nikl directory for package management...");
-        
-        // Create the .nikl directory
-        let current_dir = std::env::current_dir().expect("Failed to get current directory");
-        create_nikl_environment(current_dir.as_path()).expect("Failed to create .nikl environment");
-    } else {
-        let packages_dir = nikl_dir.join("packages");
-        if !packages_dir.exists() {
-            std::fs::create_dir(&packages_dir).expect("Failed to create packages directory");
+/// The project the current directory belongs to: the root of the workspace it's in, or itself
+fn project_root() -> Result<(PathBuf, Option<Workspace>), String> {
+    let dir = std::env::current_dir().map_err(|e| e.to_string())?;
+    match Workspace::find(&dir)? {
+        Some(workspace) => Ok((workspace.root.clone(), Some(workspace))),
+        None => Ok((dir, None)),
+    }
+}
+
+/// The `.nikl` directory of the current directory's project
+fn project_env(quiet: bool) -> Result<PackageEnv, String> {
+    let (root, workspace) = project_root()?;
+    let env = PackageEnv::open(&root, quiet)?;
+    Ok(match workspace {
+        Some(workspace) => env.with_workspace(&workspace),
+        None => env,
+    })
+}
+
+
+/// `nikl install <package>`, installs `name`, `name@version`, a local `.tar.gz` or a
+/// `git+<url>#<ref>` repository and its dependencies into `.nikl/packages` of the project
+pub fn install_package(full_package_name: &str, quiet: bool) -> Result<(), String> {
+    let package = Package::new(full_package_name.to_string())?;
+    let mut env = project_env(quiet)?;
+    package.install_package(&mut env, &Registry::from_env())
+}
+
+/// `nikl install` without a package, installs the dependencies in `config.json` of the
+/// current directory, or of every member in a workspace
+pub fn install_project(quiet: bool) -> Result<(), String> {
+    let (root, workspace) = project_root()?;
+    // Each member's path dependencies are relative to it
+    let projects: Vec<(PathBuf, Vec<Dependency>)> = match &workspace {
+        Some(workspace) => workspace.members.iter().map(|member| (member.dir.clone(), member.dependencies.clone())).collect(),
+        None => vec![(PathBuf::new(), Manifest::read(&root)?.dependencies)],
+    };
+    let mut env = PackageEnv::open(&root, quiet)?;
+    if let Some(workspace) = &workspace {
+        env = env.with_workspace(workspace);
+    }
+    if projects.iter().all(|(_, dependencies)| dependencies.is_empty()) {
+        env.progress("No dependencies to install");
+        return Ok(());
+    }
+    let registry = Registry::from_env();
+    let mut linked = Vec::new();
+    for (base, dependencies) in &projects {
+        install_dependencies(&mut env, &registry, dependencies, Some(base), &mut linked)?;
+    }
+    Ok(())
+}
+
+/// `nikl add <package>`, installs `name` or `name@version` and adds it to the dependencies in
+/// `config.json` of the current directory, at the latest version when none is given
+pub fn add_dependency(full_package_name: &str, quiet: bool) -> Result<(), String> {
+    let mut package = Package::new(full_package_name.to_string())?;
+    if package.is_local || package.git.is_some() {
+        return Err("nikl add takes a package from the registry, 'name' or 'name@version'".to_string());
+    }
+    let root = std::env::current_dir().map_err(|e| e.to_string())?;
+    let path = root.join("config.json");
+    let text = fs::read_to_string(&path).map_err(|_| "No config.json in the current directory".to_string())?;
+    let registry = Registry::from_env();
+    if matches!(package.version.as_str(), "" | "*" | "latest") {
+        package.version = registry.latest(&package.name)?;
+    }
+    // config.json is only written once the package is installed
+    let edited = edit::add_dependency(&text, &package.name, &package.version)?;
+    let mut env = project_env(quiet)?;
+    package.install_package(&mut env, &registry)?;
+    fs::write(&path, edited).map_err(|e| format!("Failed to write config.json: {}", e))?;
+    env.progress(&format!("Added {}@{} to config.json", package.name, package.version));
+    Ok(())
+}
+
+/// `nikl remove <package> [--force]`, takes a package out of the dependencies in `config.json`
+/// of the current directory and uninstalls it, see `uninstall_package` for `force`
+pub fn remove_dependency(name: &str, force: bool, quiet: bool) -> Result<(), String> {
+    let package = Package::new(name.to_string())?;
+    let root = std::env::current_dir().map_err(|e| e.to_string())?;
+    let path = root.join("config.json");
+    let text = fs::read_to_string(&path).map_err(|_| "No config.json in the current directory".to_string())?;
+    let edited = edit::remove_dependency(&text, &package.name)?.ok_or_else(|| format!("'{}' is not a dependency in config.json", package.name))?;
+    if project_root()?.0.join(".nikl").exists() {
+        let mut env = project_env(quiet)?;
+        if env.installed(&package.name).is_some() {
+            package.uninstall_package(&mut env, force)?;
         }
+    }
+    fs::write(&path, edited).map_err(|e| format!("Failed to write config.json: {}", e))?;
+    if !quiet {
+        println!("Removed {} from config.json", package.name);
+    }
+    Ok(())
+}
 
-        let info_file = nikl_dir.join("info.json");
-        if !info_file.exists() {
-            std::fs::File::create(&info_file).expect("Failed to create info.json file");
-        } else {
-            // TODO: Check if it has the correct structure
-            // For now, we assume it has the correct structure
-            // TODO: In future, we can add more checks to validate the structure
-            // For now, we just print a message
-            // This is a placeholder for future validation logic
+/// Installs what `dependencies` names that isn't installed yet. `base` is where the
+/// `config.json` listing them is, relative to the project, and `None` for packages that were
+/// unpacked, which can't have path dependencies. `linked` are the path dependencies done so far
+pub(super) fn install_dependencies(env: &mut PackageEnv, registry: &Registry, dependencies: &[Dependency], base: Option<&Path>, linked: &mut Vec<PathBuf>) -> Result<(), String> {
+    for dependency in dependencies {
+        if let Some(path) = &dependency.path {
+            let base = base.ok_or_else(|| format!("'{}' is a path dependency, only a project's config.json can have those", dependency.name))?;
+            link_package(env, registry, &dependency.name, &join(base, Path::new(path)), linked)?;
+            continue;
+        }
+        if let Some(dir) = env.member_dir(&dependency.name).map(Path::to_path_buf) {
+            link_package(env, registry, &dependency.name, &dir, linked)?;
+            continue;
+        }
+        let installed = env.present(&dependency.name);
+        let satisfied = match dependency.version.as_str() {
+            "" | "*" | "latest" => installed.is_some(),
+            version if version.starts_with("git+") => installed.is_some(),
+            version => installed.is_some_and(|package| package.version == version),
+        };
+        if !satisfied {
+            // A dependency's version may be a git package of its own
+            let spec = if dependency.version.starts_with("git+") {
+                dependency.version.clone()
+            } else {
+                super::check_name(&dependency.name)?;
+                super::check_version(&dependency.version).map_err(|e| format!("{} of dependency '{}'", e, dependency.name))?;
+                format!("{}@{}", dependency.name, dependency.version)
+            };
+            Package::new(spec)?.install_package(env, registry)?;
         }
     }
+    Ok(())
 }
 
+/// Installs the package in the directory `path` of the project, and its dependencies
+fn link_package(env: &mut PackageEnv, registry: &Registry, name: &str, path: &Path, linked: &mut Vec<PathBuf>) -> Result<(), String> {
+    let dir = env.root().join(path).canonicalize().map_err(|_| format!("Path dependency '{}' not found at '{}'", name, path.display()))?;
+    // Packages depending on each other by path are only linked once
+    if linked.contains(&dir) {
+        return Ok(());
+    }
+    linked.push(dir.clone());
+
+    let manifest = Manifest::read(&dir)?;
+    if manifest.name != name {
+        return Err(format!("'{}' holds the package '{}', not '{}'", path.display(), manifest.name, name));
+    }
+    if !dir.join("src").join(format!("{}.nk", name)).is_file() {
+        return Err(format!("Required file src/{}.nk not found in '{}'", name, path.display()));
+    }
+    env.link(name, &dir)?;
+    let dependencies = manifest.dependencies.iter().map(|dependency| dependency.name.clone()).collect();
+    env.record(InstalledPackage { name: name.to_string(), version: manifest.version.clone(), source: format!("path:{}", path.display()), checksum: None, dependencies })?;
+    env.progress(&format!("Linked {}@{} from {}", name, manifest.version, path.display()));
+    install_dependencies(env, registry, &manifest.dependencies, Some(path), linked)
+}
 
-pub fn install_package(full_package_name: &str) {
-    // Check if virtual environment is valid
-    check_and_create_nikl_directory();
+/// `base` joined with `path`, with `..` dropping what's before it when it can
+fn join(base: &Path, path: &Path) -> PathBuf {
+    let mut joined = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(joined.components().next_back(), Some(Component::Normal(_))) => {
+                joined.pop();
+            }
+            component => joined.push(component),
+        }
+    }
+    joined
+}
 
-    // Parse and install the package
-    Package::new(full_package_name.to_string()).install_package();
+/// `nikl uninstall <package> [--force]`, removes a package from `.nikl/packages` of the
+/// project, refusing while installed packages depend on it unless `force` is set
+pub fn uninstall_package(full_package_name: &str, force: bool, quiet: bool) -> Result<(), String> {
+    let package = Package::new(full_package_name.to_string())?;
+    if !project_root()?.0.join(".nikl").exists() {
+        return Err(format!("Package '{}' is not installed", package.name));
+    }
+    let mut env = project_env(quiet)?;
+    package.uninstall_package(&mut env, force)
 }
@@ -1,25 +1,83 @@
 mod initialize;
 mod installer;
 mod builder;
+mod cache;
+mod credentials;
+mod edit;
+mod git;
+mod registry;
+mod workspace;
 
 pub use initialize::create_package_structure;
-pub use installer::install_package;
-pub use builder::create_tar_gz;
+pub use installer::{add_dependency, install_package, install_project, remove_dependency, uninstall_package, InstalledPackage, PackageEnv};
+pub use registry::{compare_versions, PackageInfo, PackageSummary, Registry, VersionInfo};
+pub use builder::{build_archive, create_tar_gz};
+pub use cache::{cache_dir, clean_cache};
+pub use credentials::{config_dir, remove_token, save_token};
+pub use workspace::{Member, Workspace, WORKSPACE_FILE};
+pub(crate) use builder::{open_tar_gz, tar_gz_builder};
 
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+use installer::install_dependencies;
 
-#[derive(Deserialize, Serialize, Debug)]
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Author {
     pub name: String,
     pub email: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Dependency {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    /// A directory holding the package, relative to the `config.json` naming it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Reads `dependencies` written as a list of `{"name": .., "version": ..}` or as a
+/// `{"name": "version"}` map, like `nikl init` writes them. In a map a path dependency
+/// is `{"name": {"path": ..}}`
+pub(crate) fn dependencies<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<Dependency>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Dependencies {
+        List(Vec<Dependency>),
+        Map(serde_json::Map<String, serde_json::Value>),
+    }
+    Ok(match Dependencies::deserialize(deserializer)? {
+        Dependencies::List(list) => list,
+        Dependencies::Map(map) => map
+            .into_iter()
+            .map(|(name, value)| {
+                let field = |key: &str| value.get(key).and_then(|field| field.as_str()).map(str::to_string);
+                let version = value.as_str().map(str::to_string).or_else(|| field("version")).unwrap_or_default();
+                Dependency { name, version, path: field("path") }
+            })
+            .collect(),
+    })
+}
+
+/// The parts of a package's `config.json` installing it needs
+#[derive(Deserialize, Debug)]
+pub struct Manifest {
     pub name: String,
     pub version: String,
+    #[serde(default, deserialize_with = "dependencies")]
+    pub dependencies: Vec<Dependency>,
+}
+
+impl Manifest {
+    /// The `config.json` in `dir`
+    pub fn read(dir: &Path) -> Result<Self, String> {
+        let path = dir.join("config.json");
+        let text = std::fs::read_to_string(&path).map_err(|_| format!("No config.json in '{}'", dir.display()))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid '{}': {}", path.display(), e))
+    }
 }
 
 
@@ -37,41 +95,67 @@ pub struct PkgConfig {
     pub license_file: Option<String>,
     pub repository: Option<String>,
     pub homepage: Option<String>,
+    #[serde(deserialize_with = "dependencies")]
     pub dependencies: Vec<Dependency>,
     pub keywords: Vec<String>,
+    /// Rule name to enabled, read by `nikl lint`
+    #[serde(default)]
+    pub lint: std::collections::HashMap<String, bool>,
 }
 
 
+/// A package to install: `name`, `name@version`, a local `name-version.tar.gz` or a git
+/// repository as `git+<url>#<ref>`
 #[derive(Debug, Clone)]
 pub struct Package {
+    /// For a git package the repository's name, until its `config.json` is read
     pub name: String,
+    /// Empty for the latest version, the ref of a git package
     pub version: String,
     pub is_local: bool,
+    /// The archive of a local package
+    pub path: Option<String>,
+    /// The repository URL of a git package
+    pub git: Option<String>,
     pub dependencies: Vec<String>,
 }
 
 impl Package {
-    pub fn new(raw: String) -> Self {
+    pub fn new(raw: String) -> Result<Self, String> {
         let trimmed = raw.trim().to_string();
 
-        if Self::is_local_path(&trimmed) {
-            let (name, version) = Self::parse_local(&trimmed)
-                .expect("Failed to parse local package name, version, or dependencies");
-            Self {
+        if let Some((url, reference)) = git::parse_git(&trimmed) {
+            if url.is_empty() {
+                return Err(format!("Invalid git package '{}', use 'git+<url>#<ref>'", trimmed));
+            }
+            Ok(Self {
+                name: git::repository_name(&url).to_string(),
+                version: reference,
+                is_local: false,
+                path: None,
+                git: Some(url),
+                dependencies: Vec::new(),
+            })
+        } else if Self::is_local_path(&trimmed) {
+            let (name, version) = Self::parse_local(&trimmed)?;
+            Ok(Self {
                 name,
                 version,
                 is_local: true,
+                path: Some(trimmed),
+                git: None,
                 dependencies: Vec::new(),
-            }
+            })
         } else {
-            let (name, version) = Self::parse_remote(&trimmed)
-                .expect("Failed to parse remote package name and version");
-            Self {
+            let (name, version) = Self::parse_remote(&trimmed)?;
+            Ok(Self {
                 name,
                 version,
                 is_local: false,
+                path: None,
+                git: None,
                 dependencies: Vec::new(),
-            }
+            })
         }
     }
 
@@ -92,11 +176,14 @@ impl Package {
     /// Parses remote package name and version from formats like `name@version` or just `name`
     fn parse_remote(s: &str) -> Result<(String, String), String> {
         let parts: Vec<&str> = s.split('@').collect();
-        match parts.len() {
-            2 => Ok((parts[0].to_string(), parts[1].to_string())),
-            1 => Ok((parts[0].to_string(), "".to_string())),
-            _ => Err("Invalid remote package format. Use 'name' or 'name@version'.".to_string()),
-        }
+        let (name, version) = match parts.len() {
+            2 => (parts[0].to_string(), parts[1].to_string()),
+            1 => (parts[0].to_string(), "".to_string()),
+            _ => return Err("Invalid remote package format. Use 'name' or 'name@version'.".to_string()),
+        };
+        check_name(&name)?;
+        check_version(&version)?;
+        Ok((name, version))
     }
 
     fn parse_local_name_version(file_name: &str) -> Result<(String, String), String> {
@@ -130,49 +217,146 @@ impl Package {
             .and_then(|s| s.to_str())
             .ok_or("Failed to extract file name from path")?;
     
-        let (name, version) = Self::parse_local_name_version(&file_name)
+        let (name, version) = Self::parse_local_name_version(file_name)
             .map_err(|e| format!("Failed to parse local package name and version: {}", e))?;
 
         if name.is_empty() || version.is_empty() {
             return Err("Package name or version cannot be empty.".to_string());
         }
+        check_name(&name)?;
 
         Ok((name, version))
     }
 
 
+    /// Unpacks the package into `env` and records it, then installs its dependencies that
+    /// aren't installed yet. Remote packages come from the cache or `registry`, the latest
+    /// version when none is given. Git packages are named by their `config.json`
+    pub fn install_package(&self, env: &mut PackageEnv, registry: &Registry) -> Result<(), String> {
+        let (name, version, archive, source) = match (&self.path, &self.git) {
+            (Some(path), _) => {
+                let archive = std::fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+                (self.name.clone(), self.version.clone(), archive, format!("file:{}", path))
+            }
+            (None, Some(url)) => {
+                env.progress(&format!("Cloning {}", url));
+                let (name, version, archive, commit) = self.clone_package(url)?;
+                (name, version, archive, format!("git+{}#{}", url, commit))
+            }
+            (None, None) => {
+                let version = match self.version.as_str() {
+                    // Offline the newest cached version does
+                    "" | "*" | "latest" => match registry.latest(&self.name) {
+                        Ok(version) => version,
+                        Err(e) => cache::latest_cached(&self.name).ok_or(e)?,
+                    },
+                    version => version.to_string(),
+                };
+                // The registry's answer names a cache directory too
+                check_version(&version)?;
+                if env.present(&self.name).is_some_and(|package| package.version == version) {
+                    env.progress(&format!("{}@{} is already installed", self.name, version));
+                    return Ok(());
+                }
+                let archive = match cache::cached(&self.name, &version, locked_checksum(env, &self.name, &version)) {
+                    Some(archive) => {
+                        env.progress(&format!("Using cached {}@{}", self.name, version));
+                        archive
+                    }
+                    None => {
+                        env.progress(&format!("Downloading {}@{}", self.name, version));
+                        registry.download(&self.name, &version)?
+                    }
+                };
+                (self.name.clone(), version.clone(), archive, "registry".to_string())
+            }
+        };
 
-    fn is_already_installed(&self) -> bool {
-        // Check if the package is already installed
-        // This could involve checking a local database, file system, or other means
-        // For now, we will just return false to indicate that the package is not installed
-        false
-    }
+        let checksum = (source == "registry").then(|| cache::checksum(&archive));
+        if let (Some(expected), Some(actual)) = (locked_checksum(env, &name, &version), &checksum) {
+            if expected != actual {
+                return Err(format!(
+                    "Checksum mismatch for {}@{}: .nikl/info.json has {} but the downloaded archive is {}, it may have been tampered with",
+                    name, version, expected, actual
+                ));
+            }
+        }
 
-    pub fn install_package(&self) {
-        // Check if the package is already installed
-        if self.is_already_installed() {
-            println!("Package '{}' is already installed. Skipping installation.", self.name);
-            return;
+        let manifest = env.unpack(&name, &version, &archive)?;
+        // Only archives that turned out to be the package are cached
+        if source == "registry" {
+            cache::store(&name, &version, &archive);
         }
+        let dependencies: Vec<String> = manifest.dependencies.iter().map(|dependency| dependency.name.clone()).collect();
+        env.record(InstalledPackage { name: name.clone(), version: version.clone(), source, checksum, dependencies })?;
+        env.progress(&format!("Installed {}@{}", name, version));
+        install_dependencies(env, registry, &manifest.dependencies, None, &mut Vec::new())
+    }
 
-        if self.is_local {
-            // If it's a local package, install it directly
-            println!("Installing local package: {} (version: {})", self.name, self.version);
-            // Here you would implement the logic to install the local package
-            // For example, extracting the tar.gz file and copying files to the appropriate directories
-        } else {
-            // If it's not a local package, handle remote package installation
-            println!("Installing remote package: {} (version: {})", self.name, self.version);
-            // Here you would implement the logic to download and install the remote package (No login required)
-            // This could involve fetching from a remote repository or server
+    /// Clones a git package at its ref and packs it like `nikl build` would, returns its name,
+    /// version, archive and the commit it was built from
+    fn clone_package(&self, url: &str) -> Result<(String, String, Vec<u8>, String), String> {
+        let dir = std::env::temp_dir().join(format!("nikl-git-{}-{}", self.name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let result = git::checkout(url, &self.version, &dir).and_then(|commit| {
+            // The name comes from the repository's config.json and becomes a directory, so it's
+            // checked before anything is built or written under it
+            if let Ok(manifest) = Manifest::read(&dir) {
+                check_name(&manifest.name).map_err(|e| format!("Invalid package in '{}': {}", url, e))?;
+            }
+            let (name, version, archive) = build_archive(&dir).map_err(|e| format!("Invalid package in '{}': {}", url, e))?;
+            Ok((name, version, archive, commit))
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// Removes the package from `env`, which fails while other installed packages depend on
+    /// it unless `force` is set
+    pub fn uninstall_package(&self, env: &mut PackageEnv, force: bool) -> Result<(), String> {
+        let installed = env.installed(&self.name).ok_or_else(|| format!("Package '{}' is not installed", self.name))?;
+        if !self.version.is_empty() && installed.version != self.version {
+            return Err(format!("{}@{} is installed, not {}", self.name, installed.version, self.version));
+        }
+        let version = installed.version.clone();
+        let dependents: Vec<&str> = env
+            .packages()
+            .iter()
+            .filter(|package| package.dependencies.contains(&self.name))
+            .map(|package| package.name.as_str())
+            .collect();
+        if !dependents.is_empty() && !force {
+            return Err(format!("'{}' is needed by {}, use --force to remove it anyway", self.name, dependents.join(", ")));
         }
+
+        env.remove(&self.name)?;
+        env.progress(&format!("Uninstalled {}@{}", self.name, version));
+        Ok(())
     }
+}
 
+/// The checksum `info.json` has for a version of a package from the registry
+fn locked_checksum<'a>(env: &'a PackageEnv, name: &str, version: &str) -> Option<&'a str> {
+    let package = env.installed(name).filter(|package| package.version == version && package.source == "registry")?;
+    package.checksum.as_deref()
+}
 
-    pub fn uninstall_package(&self) {
-        // Logic to uninstall the package
-        // This could involve removing files, directories, or entries from a database
-        println!("Uninstalling package: {} (version: {})", self.name, self.version);
+/// Package names become directory names, so only letters, digits, `-` and `_` are allowed
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(|c: char| !c.is_alphanumeric() && c != '-' && c != '_') {
+        return Err(format!("Invalid package name '{}'", name));
+    }
+    Ok(())
+}
+
+/// Versions become directory names in the cache, so only letters, digits, `.`, `+` and `-`
+/// are allowed, and `*` for any version
+fn check_version(version: &str) -> Result<(), String> {
+    if version == "*" {
+        return Ok(());
+    }
+    if version.contains("..") || version.contains(|c: char| !c.is_ascii_alphanumeric() && !matches!(c, '.' | '+' | '-')) {
+        return Err(format!("Invalid package version '{}'", version));
     }
+    Ok(())
 }
@@ -0,0 +1,207 @@
+//! The package registry's HTTP API
+//!
+//! - `GET /api/v1/packages/<name>` describes a package, its `config.json` fields and
+//!   `"versions": [{"version": .., "dependencies": ..}, ..]`
+//! - `GET /api/v1/packages/<name>/<version>/download` is the `.tar.gz` made by `nikl build`
+//! - `GET /api/v1/search?q=<query>` finds packages by name, description and keywords:
+//!   `{"packages": [{"name": .., "version": .., "description": ..}, ..]}`
+//!
+//! - `GET /api/v1/me` is the account a token belongs to: `{"name": ..}`
+//! - `PUT /api/v1/packages/<name>/<version>` publishes an archive
+//!
+//! Requests carry the token saved by `nikl login` as `Authorization: Bearer <token>`, which
+//! also gives access to private packages. `NIKL_REGISTRY` points the client at another
+//! registry, e.g. a mirror
+
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+use super::credentials;
+use super::{dependencies, Author, Dependency};
+
+
+pub const DEFAULT_REGISTRY: &str = "https://nikl-pkg.nekonik.org";
+/// Largest archive a download reads
+const MAX_ARCHIVE: u64 = 100 * 1024 * 1024;
+
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub authors: Vec<Author>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub versions: Vec<VersionInfo>,
+}
+
+impl PackageInfo {
+    /// The highest published version
+    pub fn latest(&self) -> Option<&VersionInfo> {
+        self.versions.iter().max_by(|a, b| compare_versions(&a.version, &b.version))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    #[serde(default, deserialize_with = "dependencies")]
+    pub dependencies: Vec<Dependency>,
+}
+
+/// A search result
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageSummary {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    url: String,
+    token: Option<String>,
+}
+
+impl Registry {
+    /// A registry used without a token
+    pub fn new(url: &str) -> Self {
+        Self { url: url.trim_end_matches('/').to_string(), token: None }
+    }
+
+    /// `NIKL_REGISTRY` when set, otherwise the official registry, with the saved token
+    pub fn from_env() -> Self {
+        let registry = match std::env::var("NIKL_REGISTRY") {
+            Ok(url) if !url.is_empty() => Self::new(&url),
+            _ => Self::new(DEFAULT_REGISTRY),
+        };
+        let token = credentials::token(&registry.url);
+        Self { token, ..registry }
+    }
+
+    pub fn with_token(self, token: &str) -> Self {
+        Self { token: Some(token.to_string()), ..self }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// The name of the account the token belongs to
+    pub fn whoami(&self) -> Result<String, String> {
+        #[derive(Deserialize)]
+        struct Account {
+            name: String,
+        }
+        let mut response = self.get("/api/v1/me", "The registry doesn't support accounts")?;
+        let body = response.body_mut().read_to_string().map_err(|e| self.error(e))?;
+        let account: Account = serde_json::from_str(&body).map_err(|e| format!("Invalid response from {}: {}", self.url, e))?;
+        Ok(account.name)
+    }
+
+    /// Uploads the archive of a version, which needs a token
+    pub fn publish(&self, name: &str, version: &str, archive: &[u8]) -> Result<(), String> {
+        let token = self.token.as_deref().ok_or("Not logged in, run `nikl login` first")?;
+        let url = format!("{}/api/v1/packages/{}/{}", self.url, encode(name), encode(version));
+        let request = ureq::put(&url).header("Authorization", &format!("Bearer {}", token)).header("Content-Type", "application/gzip");
+        match request.send(archive) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::StatusCode(409)) => Err(format!("{}@{} is already published", name, version)),
+            Err(e) => Err(self.error(e)),
+        }
+    }
+
+    pub fn package(&self, name: &str) -> Result<PackageInfo, String> {
+        let body = self.get(&format!("/api/v1/packages/{}", encode(name)), &not_found(name))?.body_mut().read_to_string().map_err(|e| self.error(e))?;
+        serde_json::from_str(&body).map_err(|e| format!("Invalid response from {} for '{}': {}", self.url, name, e))
+    }
+
+    /// The highest published version of a package
+    pub fn latest(&self, name: &str) -> Result<String, String> {
+        let package = self.package(name)?;
+        let latest = package.latest().map(|info| info.version.clone());
+        latest.ok_or_else(|| format!("Package '{}' has no published versions", name))
+    }
+
+    /// Packages whose name, description or keywords match `query`
+    pub fn search(&self, query: &str) -> Result<Vec<PackageSummary>, String> {
+        #[derive(Deserialize)]
+        struct Results {
+            packages: Vec<PackageSummary>,
+        }
+        let mut response = self.get(&format!("/api/v1/search?q={}", encode(query)), "The registry doesn't support searching")?;
+        let body = response.body_mut().read_to_string().map_err(|e| self.error(e))?;
+        let results: Results = serde_json::from_str(&body).map_err(|e| format!("Invalid search response from {}: {}", self.url, e))?;
+        Ok(results.packages)
+    }
+
+    /// The archive of one version
+    pub fn download(&self, name: &str, version: &str) -> Result<Vec<u8>, String> {
+        let path = format!("/api/v1/packages/{}/{}/download", encode(name), encode(version));
+        let mut response = self.get(&path, &not_found(&format!("{}@{}", name, version)))?;
+        response.body_mut().with_config().limit(MAX_ARCHIVE).read_to_vec().map_err(|e| self.error(e))
+    }
+
+    /// `not_found` is the error when the registry answers 404
+    fn get(&self, path: &str, not_found: &str) -> Result<ureq::http::Response<ureq::Body>, String> {
+        let mut request = ureq::get(&format!("{}{}", self.url, path));
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", &format!("Bearer {}", token));
+        }
+        match request.call() {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::StatusCode(404)) => Err(not_found.to_string()),
+            Err(e) => Err(self.error(e)),
+        }
+    }
+
+    fn error(&self, error: ureq::Error) -> String {
+        match error {
+            ureq::Error::StatusCode(401 | 403) if self.token.is_some() => format!("{} rejected the token, run `nikl login` again", self.url),
+            ureq::Error::StatusCode(401 | 403) => format!("{} needs a login, run `nikl login` first", self.url),
+            error => format!("Registry request to {} failed: {}", self.url, error),
+        }
+    }
+}
+
+fn not_found(package: &str) -> String {
+    format!("Package '{}' not found in the registry", package)
+}
+
+/// Percent-encodes everything but unreserved characters, for paths and query values
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Orders versions like `1.2.10` after `1.2.9`, parts that aren't numbers compare as text
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| version.split(['.', '-']).map(str::to_string).collect::<Vec<_>>();
+    for (a, b) in parts(a).iter().zip(parts(b).iter()) {
+        let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    parts(a).len().cmp(&parts(b).len())
+}
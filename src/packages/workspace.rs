@@ -0,0 +1,104 @@
+//! Workspaces, packages developed together. A `workspace.json` at the root lists the member
+//! directories, `*` patterns included:
+//!
+//! ```json
+//! {"members": ["packages/*", "tools/cli"]}
+//! ```
+//!
+//! Members share the root's `.nikl` directory, and a dependency on a member is linked to it
+//! like a path dependency
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::{Dependency, Manifest};
+
+
+pub const WORKSPACE_FILE: &str = "workspace.json";
+
+
+#[derive(Deserialize)]
+struct WorkspaceFile {
+    members: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub version: String,
+    /// Relative to the workspace root
+    pub dir: PathBuf,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub members: Vec<Member>,
+}
+
+impl Workspace {
+    /// The workspace `dir` is the root or in a member of, looking through its parents
+    pub fn find(dir: &Path) -> Result<Option<Self>, String> {
+        let dir = dir.canonicalize().map_err(|e| format!("Can't read '{}': {}", dir.display(), e))?;
+        let Some(root) = dir.ancestors().find(|root| root.join(WORKSPACE_FILE).is_file()) else {
+            return Ok(None);
+        };
+        let workspace = Self::load(root)?;
+        let inside = root == dir || workspace.members.iter().any(|member| dir.starts_with(root.join(&member.dir)));
+        Ok(inside.then_some(workspace))
+    }
+
+    /// Reads the `workspace.json` in `root` and the `config.json` of every member
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let root = root.canonicalize().map_err(|e| format!("Can't read '{}': {}", root.display(), e))?;
+        let path = root.join(WORKSPACE_FILE);
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        let file: WorkspaceFile = serde_json::from_str(&text).map_err(|e| format!("Invalid '{}': {}", path.display(), e))?;
+
+        let mut members: Vec<Member> = Vec::new();
+        for pattern in &file.members {
+            for dir in member_dirs(&root, pattern)? {
+                if members.iter().any(|member| member.dir == dir) {
+                    continue;
+                }
+                let manifest = Manifest::read(&root.join(&dir))?;
+                if let Some(other) = members.iter().find(|member| member.name == manifest.name) {
+                    return Err(format!("Workspace members '{}' and '{}' are both named '{}'", other.dir.display(), dir.display(), manifest.name));
+                }
+                members.push(Member { name: manifest.name, version: manifest.version, dir, dependencies: manifest.dependencies });
+            }
+        }
+        Ok(Self { root, members })
+    }
+
+    /// Whether `dir` is the root of the workspace
+    pub fn is_root(&self, dir: &Path) -> bool {
+        dir.canonicalize().is_ok_and(|dir| dir == self.root)
+    }
+
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+/// The directories a `members` entry names, relative to `root`. Patterns only match
+/// directories with a `config.json`, a plain path must have one
+fn member_dirs(root: &Path, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    if !pattern.contains(['*', '?', '[']) {
+        if !root.join(pattern).join("config.json").is_file() {
+            return Err(format!("Workspace member '{}' has no config.json", pattern));
+        }
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+    let full = format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern);
+    let paths = glob::glob(&full).map_err(|e| format!("Invalid workspace member '{}': {}", pattern, e))?;
+    let mut dirs: Vec<PathBuf> = paths
+        .filter_map(Result::ok)
+        .filter(|path| path.join("config.json").is_file())
+        .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
 use crate::lexer::{Token, TokenKind};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expr {
     Identifier(String),
     Integer(i64),
@@ -31,9 +35,13 @@ pub enum Expr {
         object: Box<Expr>,
         property: String,
     },
+    /// `spawn f(args)` runs the call in the background and evaluates to its task
+    Spawn(Box<Expr>),
+    /// `wait task` blocks until the task (or every task in an array) has finished
+    Wait(Box<Expr>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Stmt {
     Let { name: String, value: Expr },
     Const { name: String, value: Expr },
@@ -67,16 +75,27 @@ pub enum Stmt {
     Delete(String),
     Break,
     Continue,
+    /// File and line the next statement starts on, only emitted by `Parser::with_lines` so
+    /// execution hooks can follow the source, running it does nothing else
+    Line { file: Arc<str>, line: usize },
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// Put a `Stmt::Line` naming this file before every statement
+    lines: Option<Arc<str>>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+        Parser { tokens, pos: 0, lines: None }
+    }
+
+    /// A parser that marks the line of every statement with `Stmt::Line`, for debuggers and
+    /// other `ExecutionHook`s that need to know where the program is, `file` names the source
+    pub fn with_lines(tokens: Vec<Token>, file: &str) -> Self {
+        Parser { tokens, pos: 0, lines: Some(file.into()) }
     }
 
     fn current(&self) -> &Token {
@@ -94,21 +113,43 @@ impl Parser {
             self.advance();
             Ok(())
         } else {
-            Err(format!(
-                "Expected {:?}, found {:?} at line {}, column {}",
-                expected, self.current().kind, self.current().line, self.current().column
-            ))
+            Err(format!("Expected {:?}, found {:?}", expected, self.current().kind))
         }
     }
 
+    /// Parses a whole program, errors end with ` at line <line>, column <column>` of the token
+    /// the parser stopped at
     pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
         let mut stmts = Vec::new();
         while self.current().kind != TokenKind::Eof {
-            stmts.push(self.parse_stmt()?);
+            self.parse_stmt_into(&mut stmts).map_err(|e| self.located(e))?;
         }
         Ok(stmts)
     }
 
+    /// Parses the whole input as a single expression, e.g. `a + b * 2`
+    pub fn parse_expression(&mut self) -> Result<Expr, String> {
+        let expr = self.parse_expr().map_err(|e| self.located(e))?;
+        if self.current().kind != TokenKind::Eof {
+            return Err(self.located(format!("Unexpected {:?} after expression", self.current().kind)));
+        }
+        Ok(expr)
+    }
+
+    /// Adds the position of the current token to an error, the token the parse failed on
+    fn located(&self, error: String) -> String {
+        format!("{} at line {}, column {}", error, self.current().line, self.current().column)
+    }
+
+    /// Parses a statement onto the end of a program or block, after its line marker if enabled
+    fn parse_stmt_into(&mut self, stmts: &mut Vec<Stmt>) -> Result<(), String> {
+        if let Some(file) = &self.lines {
+            stmts.push(Stmt::Line { file: file.clone(), line: self.current().line });
+        }
+        stmts.push(self.parse_stmt()?);
+        Ok(())
+    }
+
     fn parse_stmt(&mut self) -> Result<Stmt, String> {
         match &self.current().kind {
             TokenKind::Let => self.parse_var_decl(true),
@@ -189,7 +230,7 @@ impl Parser {
 
         let mut body = Vec::new();
         while self.current().kind != TokenKind::RightBrace {
-            body.push(self.parse_stmt()?);
+            self.parse_stmt_into(&mut body)?;
         }
         self.expect(&TokenKind::RightBrace)?;
 
@@ -202,7 +243,7 @@ impl Parser {
 
             let mut elif_body = Vec::new();
             while self.current().kind != TokenKind::RightBrace {
-                elif_body.push(self.parse_stmt()?);
+                self.parse_stmt_into(&mut elif_body)?;
             }
             self.expect(&TokenKind::RightBrace)?;
             else_if_branches.push((elif_cond, elif_body));
@@ -214,7 +255,7 @@ impl Parser {
             self.expect(&TokenKind::LeftBrace)?;
             let mut stmts = Vec::new();
             while self.current().kind != TokenKind::RightBrace {
-                stmts.push(self.parse_stmt()?);
+                self.parse_stmt_into(&mut stmts)?;
             }
             self.expect(&TokenKind::RightBrace)?;
             Some(stmts)
@@ -236,7 +277,7 @@ impl Parser {
         self.expect(&TokenKind::LeftBrace)?;
         let mut body = Vec::new();
         while self.current().kind != TokenKind::RightBrace {
-            body.push(self.parse_stmt()?);
+            self.parse_stmt_into(&mut body)?;
         }
         self.expect(&TokenKind::RightBrace)?;
 
@@ -259,7 +300,7 @@ impl Parser {
         let mut body = Vec::new();
         
         while self.current().kind != TokenKind::RightBrace {
-            body.push(self.parse_stmt()?);
+            self.parse_stmt_into(&mut body)?;
         }
         self.expect(&TokenKind::RightBrace)?;
 
@@ -269,7 +310,7 @@ impl Parser {
     fn parse_for(&mut self) -> Result<Stmt, String> {
         self.advance(); // Consume 'for'
 
-        // Parse one or two variable names
+        // Parse one or more comma separated variable names, e.g. `for dir, subdirs, files in ...`
         let mut names = Vec::new();
         if let TokenKind::Identifier(name) = &self.current().kind {
             names.push(name.clone());
@@ -278,13 +319,13 @@ impl Parser {
             return Err("Expected identifier after 'for'".to_string());
         }
 
-        if matches!(self.current().kind, TokenKind::Comma) {
+        while matches!(self.current().kind, TokenKind::Comma) {
             self.advance();
             if let TokenKind::Identifier(name) = &self.current().kind {
                 names.push(name.clone());
                 self.advance();
             } else {
-                return Err("Expected second identifier after comma".to_string());
+                return Err("Expected identifier after comma".to_string());
             }
         }
 
@@ -296,7 +337,7 @@ impl Parser {
 
         let mut body = Vec::new();
         while self.current().kind != TokenKind::RightBrace {
-            body.push(self.parse_stmt()?);
+            self.parse_stmt_into(&mut body)?;
         }
 
         self.expect(&TokenKind::RightBrace)?;
@@ -384,7 +425,7 @@ impl Parser {
 
         let mut body = Vec::new();
         while self.current().kind != TokenKind::RightBrace {
-            body.push(self.parse_stmt()?);
+            self.parse_stmt_into(&mut body)?;
         }
 
         self.expect(&TokenKind::RightBrace)?;
@@ -537,6 +578,18 @@ impl Parser {
     }
 
     fn parse_unary(&mut self) -> Result<Expr, String> {
+        if matches!(self.current().kind, TokenKind::Spawn) {
+            self.advance();
+            let call = self.parse_unary()?;
+            if !matches!(call, Expr::Call { .. }) {
+                return Err("Expected a function call after 'spawn'".to_string());
+            }
+            return Ok(Expr::Spawn(Box::new(call)));
+        }
+        if matches!(self.current().kind, TokenKind::Wait) {
+            self.advance();
+            return Ok(Expr::Wait(Box::new(self.parse_unary()?)));
+        }
         if matches!(self.current().kind, TokenKind::Subtract | TokenKind::Not) {
             let op = self.current().kind.clone();
             self.advance();
@@ -557,16 +610,18 @@ impl Parser {
             match self.current().kind.clone() {
                 TokenKind::Dot => {
                     self.advance();
-                    if let TokenKind::Identifier(name) = &self.current().kind {
-                        let prop = name.clone();
-                        self.advance();
-                        expr = Expr::DotAccess {
-                            object: Box::new(expr),
-                            property: prop,
-                        };
-                    } else {
-                        return Err("Expected identifier after '.'".to_string());
-                    }
+                    // `spawn` and `wait` are reserved, but still usable as members like `thread.spawn`
+                    let prop = match &self.current().kind {
+                        TokenKind::Identifier(name) => name.clone(),
+                        TokenKind::Spawn => "spawn".to_string(),
+                        TokenKind::Wait => "wait".to_string(),
+                        _ => return Err("Expected identifier after '.'".to_string()),
+                    };
+                    self.advance();
+                    expr = Expr::DotAccess {
+                        object: Box::new(expr),
+                        property: prop,
+                    };
                 }
                 TokenKind::LeftParen => {
                     self.advance();
@@ -670,10 +725,7 @@ impl Parser {
                 self.expect(&TokenKind::RightBrace)?;
                 Ok(Expr::HashMap(pairs))
             }
-            _ => Err(format!(
-                "Unexpected token: {:?} at line {}, column {}",
-                token.kind, token.line, token.column
-            )),
+            _ => Err(format!("Unexpected token: {:?}", token.kind)),
         }
     }
 }
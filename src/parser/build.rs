@@ -0,0 +1,196 @@
+//! Constructors for building programs as syntax trees instead of source text,
+//! e.g. for code generators, the results run with `Interpreter::run_ast`
+//!
+//! ```
+//! use std::path::PathBuf;
+//! use nikl::{Expr, Interpreter, Stmt, TokenKind, Value};
+//!
+//! // fn double(n) { return n * 2 }
+//! // double(21)
+//! let program = vec![
+//!     Stmt::function("double", ["n"], vec![
+//!         Stmt::ret(Expr::binary(Expr::ident("n"), TokenKind::Multiply, Expr::int(2))),
+//!     ]),
+//!     Stmt::expr(Expr::call(Expr::ident("double"), vec![Expr::int(21)])),
+//! ];
+//! let mut interp = Interpreter::new(PathBuf::from("."));
+//! assert_eq!(interp.run_ast(&program), Ok(Value::Integer(42)));
+//! ```
+
+use crate::lexer::TokenKind;
+use super::ast::{Expr, Stmt};
+
+
+fn names<I: IntoIterator<Item = S>, S: Into<String>>(items: I) -> Vec<String> {
+    items.into_iter().map(Into::into).collect()
+}
+
+
+impl Expr {
+    pub fn ident(name: impl Into<String>) -> Self {
+        Expr::Identifier(name.into())
+    }
+
+    pub fn int(value: i64) -> Self {
+        Expr::Integer(value)
+    }
+
+    pub fn float(value: f64) -> Self {
+        Expr::Float(value)
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Expr::Bool(value)
+    }
+
+    pub fn string(value: impl Into<String>) -> Self {
+        Expr::String(value.into())
+    }
+
+    pub fn array(items: Vec<Expr>) -> Self {
+        Expr::Array(items)
+    }
+
+    pub fn hashmap(pairs: Vec<(Expr, Expr)>) -> Self {
+        Expr::HashMap(pairs)
+    }
+
+    pub fn tuple(items: Vec<Expr>) -> Self {
+        Expr::Tuple(items)
+    }
+
+    /// `name = value`
+    pub fn assign(name: impl Into<String>, value: Expr) -> Self {
+        Expr::Assign { name: name.into(), value: Box::new(value) }
+    }
+
+    /// `left op right`, where `op` is an operator such as `TokenKind::Add` or `TokenKind::And`
+    pub fn binary(left: Expr, op: TokenKind, right: Expr) -> Self {
+        Expr::BinaryOp { left: Box::new(left), op, right: Box::new(right) }
+    }
+
+    /// `function(args)`
+    pub fn call(function: Expr, args: Vec<Expr>) -> Self {
+        Expr::Call { function: Box::new(function), args }
+    }
+
+    /// `object.property`
+    pub fn dot(object: Expr, property: impl Into<String>) -> Self {
+        Expr::DotAccess { object: Box::new(object), property: property.into() }
+    }
+
+    /// `object.method(args)`
+    pub fn method(object: Expr, method: impl Into<String>, args: Vec<Expr>) -> Self {
+        Expr::call(Expr::dot(object, method), args)
+    }
+
+    /// `spawn call`, `call` must be an `Expr::Call`
+    pub fn spawn(call: Expr) -> Self {
+        Expr::Spawn(Box::new(call))
+    }
+
+    /// `wait task`
+    pub fn wait(task: Expr) -> Self {
+        Expr::Wait(Box::new(task))
+    }
+}
+
+
+/// `-expr`
+impl std::ops::Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        Expr::UnaryOp { op: TokenKind::Subtract, expr: Box::new(self) }
+    }
+}
+
+/// `not expr`
+impl std::ops::Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::UnaryOp { op: TokenKind::Not, expr: Box::new(self) }
+    }
+}
+
+
+impl Stmt {
+    /// `let name = value`
+    pub fn var(name: impl Into<String>, value: Expr) -> Self {
+        Stmt::Let { name: name.into(), value }
+    }
+
+    /// `const name = value`
+    pub fn constant(name: impl Into<String>, value: Expr) -> Self {
+        Stmt::Const { name: name.into(), value }
+    }
+
+    pub fn expr(expr: Expr) -> Self {
+        Stmt::Expr(expr)
+    }
+
+    /// `return value`
+    pub fn ret(value: Expr) -> Self {
+        Stmt::Return(value)
+    }
+
+    /// `fn name(params) { body }`
+    pub fn function<I, S>(name: impl Into<String>, params: I, body: Vec<Stmt>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Stmt::Function { name: name.into(), params: names(params), body }
+    }
+
+    /// `if condition { body }`, chain `else if`/`else` with `else_if` and `otherwise`
+    pub fn if_then(condition: Expr, body: Vec<Stmt>) -> Self {
+        Stmt::If { condition, body, else_if_branches: Vec::new(), else_body: None }
+    }
+
+    /// Adds an `else if` branch to an `if` statement, other statements are returned unchanged
+    pub fn else_if(mut self, condition: Expr, body: Vec<Stmt>) -> Self {
+        if let Stmt::If { else_if_branches, .. } = &mut self {
+            else_if_branches.push((condition, body));
+        }
+        self
+    }
+
+    /// Sets the `else` body of an `if` statement, other statements are returned unchanged
+    pub fn otherwise(mut self, body: Vec<Stmt>) -> Self {
+        if let Stmt::If { else_body, .. } = &mut self {
+            *else_body = Some(body);
+        }
+        self
+    }
+
+    /// `loop { body }`
+    pub fn loop_forever(body: Vec<Stmt>) -> Self {
+        Stmt::Loop(body)
+    }
+
+    /// `while condition { body }`
+    pub fn while_loop(condition: Expr, body: Vec<Stmt>) -> Self {
+        Stmt::While { condition, body }
+    }
+
+    /// `for names in iterable { body }`
+    pub fn for_each<I, S>(loop_names: I, iterable: Expr, body: Vec<Stmt>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Stmt::For { names: names(loop_names), iterable: Box::new(iterable), body }
+    }
+
+    /// `import "path" as alias`
+    pub fn import(path: impl Into<String>, alias: impl Into<String>) -> Self {
+        Stmt::Import { path: path.into(), alias: alias.into() }
+    }
+
+    /// `del name`
+    pub fn delete(name: impl Into<String>) -> Self {
+        Stmt::Delete(name.into())
+    }
+}
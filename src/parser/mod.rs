@@ -1,3 +1,4 @@
 pub mod ast;
+mod build;
 
 pub use ast::{Parser, Expr, Stmt};
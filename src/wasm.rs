@@ -0,0 +1,162 @@
+//! JavaScript API for running scripts in the browser, enabled by the `wasm` feature
+//!
+//! Values cross the boundary through JSON, so JS gets plain numbers, strings, arrays and objects.
+//! Output written with `print`/`eprint` is captured rather than sent to the console.
+//!
+//! ```js
+//! import init, { NiklInterpreter, run_script } from "./pkg/nikl.js";
+//!
+//! await init();
+//! const interp = new NiklInterpreter();
+//! interp.set_global("name", "web");
+//! interp.run('print("hello " + name)\n40 + 2');   // 42
+//! interp.take_stdout();                           // "hello web\n"
+//! ```
+
+use std::path::PathBuf;
+
+use js_sys::JSON;
+use wasm_bindgen::prelude::*;
+
+use crate::interpreter::{CaptureBuffer, InputSource, Interpreter, NiklError};
+use crate::interpreter::value::Value;
+use crate::modules::convert::{from_json, to_json};
+
+
+fn to_js(value: &Value) -> Result<JsValue, JsError> {
+    let json = to_json(value).map_err(|e| JsError::new(&e))?;
+    JSON::parse(&json.to_string()).map_err(|_| JsError::new("Failed to convert the result to JavaScript"))
+}
+
+fn from_js(value: &JsValue) -> Result<Value, JsError> {
+    if value.is_undefined() {
+        return Ok(Value::Null);
+    }
+    let text = JSON::stringify(value)
+        .ok()
+        .and_then(|text| text.as_string())
+        .ok_or_else(|| JsError::new("Value can't be converted to JSON"))?;
+    serde_json::from_str(&text).map(from_json).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn parse(source: &str) -> Result<Vec<crate::parser::ast::Stmt>, NiklError> {
+    let tokens = crate::lexer::Lexer::new(source).tokenize()?;
+    crate::parser::Parser::new(tokens).parse().map_err(NiklError::Parse)
+}
+
+
+/// An interpreter whose globals persist between `run` calls, with captured output
+#[wasm_bindgen]
+pub struct NiklInterpreter {
+    interp: Interpreter,
+    stdout: CaptureBuffer,
+    stderr: CaptureBuffer,
+}
+
+#[wasm_bindgen]
+impl NiklInterpreter {
+    /// `input()` has no source in the browser and fails
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> NiklInterpreter {
+        let stdout = CaptureBuffer::new();
+        let stderr = CaptureBuffer::new();
+        let interp = Interpreter::builder(PathBuf::from("/"))
+            .stdin(InputSource::None)
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .build();
+        NiklInterpreter { interp, stdout, stderr }
+    }
+
+    /// Runs a script and returns its result, results that aren't data come back as `null`
+    pub fn run(&mut self, source: &str) -> Result<JsValue, JsError> {
+        let result = self.run_source(source).map_err(|e| JsError::new(&e))?;
+        Ok(to_js(&result).unwrap_or(JsValue::NULL))
+    }
+
+    /// Evaluates a single expression against the globals
+    pub fn eval(&mut self, source: &str) -> Result<JsValue, JsError> {
+        let result = self.interp.eval(source).map_err(|e| JsError::new(&e.to_string()))?;
+        to_js(&result)
+    }
+
+    /// Returns a global variable, `undefined` when it isn't defined
+    pub fn get_global(&self, name: &str) -> Result<JsValue, JsError> {
+        match self.interp.get_global(name) {
+            Some(value) => to_js(&value),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    pub fn set_global(&mut self, name: &str, value: JsValue) -> Result<(), JsError> {
+        self.interp.set_global(name, from_js(&value)?);
+        Ok(())
+    }
+
+    /// Returns what the script printed since the last call and clears it
+    pub fn take_stdout(&self) -> String {
+        self.stdout.take()
+    }
+
+    pub fn take_stderr(&self) -> String {
+        self.stderr.take()
+    }
+}
+
+impl NiklInterpreter {
+    fn run_source(&mut self, source: &str) -> Result<Value, String> {
+        let stmts = parse(source).map_err(|e| e.to_string())?;
+        self.interp.run_program(&stmts).map_err(|e| NiklError::Runtime(e).to_string())
+    }
+}
+
+impl Default for NiklInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// What a one-off `run_script` produced
+#[wasm_bindgen]
+pub struct ScriptOutput {
+    value: JsValue,
+    stdout: String,
+    stderr: String,
+    error: Option<String>,
+}
+
+#[wasm_bindgen]
+impl ScriptOutput {
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> JsValue {
+        self.value.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn stdout(&self) -> String {
+        self.stdout.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn stderr(&self) -> String {
+        self.stderr.clone()
+    }
+
+    /// The error message when the script failed, its output up to that point is still kept
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Runs a script in a fresh interpreter, collecting its result and output
+#[wasm_bindgen]
+pub fn run_script(source: &str) -> ScriptOutput {
+    let mut interp = NiklInterpreter::new();
+    let (value, error) = match interp.run_source(source) {
+        Ok(value) => (to_js(&value).unwrap_or(JsValue::NULL), None),
+        Err(e) => (JsValue::NULL, Some(e)),
+    };
+    ScriptOutput { value, stdout: interp.take_stdout(), stderr: interp.take_stderr(), error }
+}
@@ -0,0 +1,383 @@
+use nikl::run_script;
+
+
+#[test]
+fn test_sorted_returns_new_array() {
+    let input = r#"
+        let arr = [3, 1, 2]
+        let result = sorted(arr)
+        if result.join(",") != "1,2,3" { fail() }
+        if arr.join(",") != "3,1,2" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_sorted_with_key_function() {
+    let input = r#"
+        fn by_length(s) {
+            return len(s)
+        }
+        let words = ["ccc", "a", "bb", "d"]
+        if sorted(words, by_length).join(",") != "a,d,bb,ccc" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_sorted_with_incomparable_keys_errors() {
+    let input = r#"
+        fn mixed(x) {
+            if x > 1 {
+                return "big"
+            }
+            return x
+        }
+        sorted([1, 2, 3], mixed)
+    "#;
+    assert!(run_script(input).is_err());
+}
+
+#[test]
+fn test_map_and_filter() {
+    let input = r#"
+        fn double(x) {
+            return x * 2
+        }
+        fn is_big(x) {
+            return x > 2
+        }
+        let doubled = map(double, [1, 2, 3])
+        if doubled.join(",") != "2,4,6" { fail() }
+        if filter(is_big, doubled).join(",") != "4,6" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_reduce_with_and_without_initial() {
+    let input = r#"
+        fn add(a, b) {
+            return a + b
+        }
+        if reduce(add, [1, 2, 3]) != 6 { fail() }
+        if reduce(add, [1, 2, 3], 10) != 16 { fail() }
+        if reduce(add, [], 5) != 5 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_filter_requires_boolean_result() {
+    let input = r#"
+        fn identity(x) {
+            return x
+        }
+        filter(identity, [1, 2])
+    "#;
+    assert!(run_script(input).is_err());
+}
+
+#[test]
+fn test_enumerate_in_for_loop() {
+    let input = r#"
+        let total = 0
+        let seen = ""
+        for i, letter in enumerate(["a", "b", "c"]) {
+            total = total + i
+            seen = seen + letter
+        }
+        if total != 3 { fail() }
+        if seen != "abc" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_zip_truncates_to_shortest() {
+    let input = r#"
+        let pairs = zip([1, 2, 3], ["x", "y"])
+        if len(pairs) != 2 { fail() }
+        let out = ""
+        for n, s in pairs {
+            out = out + s + str(n)
+        }
+        if out != "x1y2" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_for_unpack_length_mismatch_errors() {
+    let input = r#"
+        for a, b in [(1, 2, 3)] {
+            print(a)
+        }
+    "#;
+    assert!(run_script(input).is_err());
+}
+
+#[test]
+fn test_min_max_over_args_and_arrays() {
+    let input = r#"
+        if min(3, 1, 2) != 1 { fail() }
+        if max([3, 1.5, 2]) != 3 { fail() }
+        if min(["pear", "apple"]) != "apple" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_sum_abs_round_pow() {
+    let input = r#"
+        if sum([1, 2, 3]) != 6 { fail() }
+        if sum(1, 2.5) != 3.5 { fail() }
+        if abs(-4) != 4 { fail() }
+        if abs(-1.5) != 1.5 { fail() }
+        if round(2.5) != 3 { fail() }
+        if round(3.14159, 2) != 3.14 { fail() }
+        if pow(2, 10) != 1024 { fail() }
+        if pow(2, -1) != 0.5 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_abs_round_pow_on_arrays() {
+    let input = r#"
+        let values = [-1, 2.5, [-3]]
+        let absolute = abs(values)
+        let rounded = round([1.4, -2.6, 7])
+        let digits = round([3.14159, 2.71828], 2)
+        let squares = pow([1, 2, 3], 2)
+        let powers = pow(2, [0, 1, 10])
+        let pairs = pow([2, 3], [3, 2])
+        [absolute, rounded, digits, squares, powers, pairs]
+    "#;
+    assert_eq!(nikl::eval_script(input).unwrap().to_string(), "[[1, 2.5, [3]], [1, -3, 7], [3.14, 2.72], [1, 4, 9], [1, 2, 1024], [8, 9]]");
+    assert!(run_script("pow([1, 2], [1])").is_err());
+    assert!(run_script(r#"abs([1, "a"])"#).is_err());
+    assert_eq!(nikl::eval_script("abs((-1, -2.5))").unwrap().to_string(), "(1, 2.5)");
+    assert_eq!(nikl::eval_script("round([(1.4, 2.6)])").unwrap().to_string(), "[(1, 3)]");
+}
+
+#[test]
+fn test_round_rejects_values_without_an_integer() {
+    for value in ["nan", "inf", "-inf", "1e300"] {
+        let error = run_script(&format!("round(float(\"{}\"))", value)).unwrap_err();
+        assert!(error.contains("round() can't turn"), "{}", error);
+    }
+    // With digits the result stays a float, NaN included
+    assert!(matches!(nikl::eval_script("round(float(\"nan\"), 2)"), Ok(nikl::Value::Float(f)) if f.is_nan()));
+    // Digits beyond what a float can scale by don't overflow into NaN
+    assert_eq!(nikl::eval_script("round(1.5, 400)"), Ok(nikl::Value::Float(1.5)));
+    assert_eq!(nikl::eval_script(r#"round(float("1e300"), 300)"#), Ok(nikl::Value::Float(1e300)));
+    assert_eq!(nikl::eval_script("round(1.5, -400)"), Ok(nikl::Value::Float(0.0)));
+    assert_eq!(nikl::eval_script("round(1234.5, -2)"), Ok(nikl::Value::Float(1200.0)));
+}
+
+#[test]
+fn test_numeric_builtins_reject_mixed_types() {
+    assert!(run_script(r#"min(1, "a")"#).is_err());
+    assert!(run_script(r#"sum([1, "a"])"#).is_err());
+    assert!(run_script(r#"abs("a")"#).is_err());
+    assert!(run_script(r#"pow(10, 100)"#).is_err());
+    assert!(run_script(r#"max([])"#).is_err());
+}
+
+#[test]
+fn test_builtins_can_be_shadowed() {
+    let input = r#"
+        let sum = 0
+        for x in [1, 2, 3] {
+            sum = sum + x
+        }
+        if sum != 6 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_ord_and_chr_round_trip() {
+    let input = r#"
+        if ord("A") != 65 { fail() }
+        if ord("é") != 233 { fail() }
+        if chr(97) != "a" { fail() }
+        if chr(ord("z") - 1) != "y" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_ord_and_chr_invalid_input() {
+    assert!(run_script(r#"ord("ab")"#).is_err());
+    assert!(run_script(r#"ord("")"#).is_err());
+    assert!(run_script("chr(-1)").is_err());
+    assert!(run_script("chr(55296)").is_err());
+}
+
+#[test]
+fn test_type_names_for_all_values() {
+    let input = r#"
+        fn f() {
+            return 1
+        }
+        if type(1) != "Integer" { fail() }
+        if type(1.5) != "Float" { fail() }
+        if type(True) != "Boolean" { fail() }
+        if type("s") != "String" { fail() }
+        if type([1]) != "Array" { fail() }
+        if type((1, 2)) != "Tuple" { fail() }
+        if type({"a": 1}) != "HashMap" { fail() }
+        if type(f) != "Function" { fail() }
+        if type(print) != "BuiltinFunction" { fail() }
+        if type(sorted) != "BuiltinFunction" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_format_positional_and_precision() {
+    let input = r#"
+        if format("{}: {:.2}", "price", 3.14159) != "price: 3.14" { fail() }
+        if format("{1} {0} {1}", "a", "b") != "b a b" { fail() }
+        if format("{{}} {}", 1) != "{} 1" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_format_width_alignment_and_padding() {
+    let input = r#"
+        if format("[{:>5}]", 42) != "[   42]" { fail() }
+        if format("[{:<5}]", "ab") != "[ab   ]" { fail() }
+        if format("[{:*^6}]", "ab") != "[**ab**]" { fail() }
+        if format("[{:05}]", -42) != "[-0042]" { fail() }
+        if format("[{:8.3}]", 2.5) != "[   2.500]" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_format_arity_mismatch_errors() {
+    assert!(run_script(r#"format("{} {}", 1)"#).is_err());
+    assert!(run_script(r#"format("{}", 1, 2)"#).is_err());
+    assert!(run_script(r#"format("{:.}", 1.0)"#).is_err());
+    assert!(run_script(r#"format("{", 1)"#).is_err());
+}
+
+#[test]
+fn test_print_with_options_map() {
+    let input = r#"
+        print("loading", {"end": ""})
+        print(".", ".", {"sep": "", "end": "", "flush": True})
+        print(" done")
+        print({"not": "options"})
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_print_lone_map_is_printed() {
+    use nikl::{CaptureBuffer, InterpreterBuilder};
+
+    let out = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from(".")).stdout(out.clone()).build();
+    let source = "let config = {\"end\": 5}\nprint({\"end\": \"done\"})\nprint(config)";
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    interp.run_program(&stmts).unwrap();
+    assert_eq!(out.contents(), "{\"end\": \"done\"}\n{\"end\": 5}\n");
+}
+
+#[test]
+fn test_print_invalid_option_value_errors() {
+    assert!(run_script(r#"print("x", {"end": 1})"#).is_err());
+    assert!(run_script(r#"print("x", {"flush": "yes"})"#).is_err());
+}
+
+#[test]
+fn test_eprint_accepts_print_options() {
+    let input = r#"
+        eprint("warning:", "disk almost full")
+        eprint("a", "b", {"sep": "-"})
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script(r#"eprint("x", {"sep": 0})"#).is_err());
+}
+
+#[test]
+fn test_sleep_accepts_int_and_float_seconds() {
+    let start = std::time::Instant::now();
+    assert!(run_script("sleep(0.05)\nsleep(0)").is_ok());
+    assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+}
+
+#[test]
+fn test_sleep_rejects_invalid_durations() {
+    assert!(run_script("sleep(-1)").is_err());
+    assert!(run_script(r#"sleep("1")"#).is_err());
+}
+
+#[test]
+fn test_copy_and_deepcopy_are_independent() {
+    let input = r#"
+        let original = {"items": [1, 2]}
+        let shallow = copy(original)
+        let deep = deepcopy(original)
+        original.items.push(3)
+        if len(original.items) != 3 { fail() }
+        if len(shallow.items) != 2 { fail() }
+        if len(deep.items) != 2 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_copy_shares_native_objects_and_deepcopy_duplicates_them() {
+    let input = r#"
+        import "regex" as regex
+        let digits = regex.compile("[0-9]+")
+        let original = {"patterns": [digits, digits]}
+        let shallow = copy(original)
+        let deep = deepcopy(original)
+        for pattern in shallow.get("patterns") {
+            if pattern != digits { fail() }
+        }
+        // Both entries were the same object, so both are the same copy
+        let seen = []
+        for pattern in deep.get("patterns") {
+            if pattern == digits { fail() }
+            if pattern.pattern() != "[0-9]+" { fail() }
+            for other in seen {
+                if other != pattern { fail() }
+            }
+            seen.push(pattern)
+        }
+    "#;
+    assert!(run_script(input).is_ok());
+
+    let path = std::env::temp_dir().join("nikl_test_deepcopy_handle.txt");
+    let handles = format!("import \"os\" as os\nlet files = [os.open({:?}, \"w\")]\nlet shallow = copy(files)\n", path.display().to_string());
+    assert!(run_script(&handles).is_ok());
+    let error = run_script(&format!("{}deepcopy(files)", handles)).unwrap_err();
+    assert!(error.contains("deepcopy() can't copy a File object"), "{}", error);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_len_of_collections_and_unicode_strings() {
+    let input = r#"
+        if len([1, 2, 3]) != 3 { fail() }
+        if len((1, 2)) != 2 { fail() }
+        if len({"a": 1}) != 1 { fail() }
+        if len("héllo") != 5 { fail() }
+        let count = 0
+        for c in "日本語" {
+            count = count + 1
+        }
+        if len("日本語") != count { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("len(5)").is_err());
+}
@@ -0,0 +1,1082 @@
+#![cfg(feature = "repl")]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nikl_test_cli_{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, source) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+    dir
+}
+
+fn nikl(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nikl")).args(args).output().unwrap()
+}
+
+fn nikl_with_stdin(args: &[&str], input: &str) -> Output {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nikl"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_check_reports_every_invalid_file() {
+    let dir = project("check", &[("ok.nk", "let x = 1\n"), ("bad.nk", "let = 1\n"), ("lib/worse.nk", "let x = (\n")]);
+    let output = nikl(&["check", dir.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("bad.nk: Parse error"), "{}", stderr);
+    assert!(stderr.contains("worse.nk: Parse error"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Checked 3 file(s), found 2 error(s)"));
+
+    assert_eq!(nikl(&["check", dir.join("ok.nk").to_str().unwrap()]).status.code(), Some(0));
+    assert_eq!(nikl(&["check", dir.join("missing").to_str().unwrap()]).status.code(), Some(2));
+}
+
+#[test]
+fn test_commands_return_their_exit_code() {
+    // Embedders call these directly, they must not exit the process
+    let dir = project("exit_codes", &[("bad.nk", "let = 1\n"), ("tests/fails.nk", "fn test_fails() { fail() }\n")]);
+    let bad = dir.join("bad.nk").to_string_lossy().to_string();
+    let missing = dir.join("missing").to_string_lossy().to_string();
+    assert_eq!(nikl::cli::check_files(std::slice::from_ref(&bad), false), 1);
+    assert_eq!(nikl::cli::format_files(std::slice::from_ref(&bad), true), 1);
+    assert_eq!(nikl::cli::lint_files(std::slice::from_ref(&missing)), 2);
+    assert_eq!(nikl::cli::run_tests(&[dir.join("tests").to_string_lossy().to_string()], false, None), 1);
+    assert_eq!(nikl::cli::init_package(&dir.to_string_lossy()), 1);
+    assert_eq!(nikl::cli::dump_ast(&bad, false), 1);
+    assert_eq!(nikl::cli::dump_tokens(&missing, false), 2);
+
+    let output = nikl(&["init", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Directory is not empty"));
+}
+
+#[test]
+fn test_check_imports() {
+    let main = "import \"lib/util.nk\" as util\nimport \"regex\" as re\nimport \"nope\" as nope\n";
+    let dir = project("check_imports", &[("main.nk", main), ("lib/util.nk", "fn f( {\n")]);
+    let main = dir.join("main.nk");
+
+    assert_eq!(nikl(&["check", main.to_str().unwrap()]).status.code(), Some(0));
+    let output = nikl(&["check", "--imports", main.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("util.nk': Parse error"), "{}", stderr);
+    assert!(stderr.contains("Unknown module 'nope'"), "{}", stderr);
+    assert!(!stderr.contains("regex"), "{}", stderr);
+}
+
+
+#[test]
+fn test_lint_uses_package_config() {
+    let dir = project("lint", &[("main.nk", "let len = 1\nwhile False { print(len) }\n"), ("clean.nk", "print(1)\n")]);
+    let lint = |dir: &PathBuf| Command::new(env!("CARGO_BIN_EXE_nikl")).arg("lint").current_dir(dir).output().unwrap();
+
+    let output = lint(&dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("main.nk:1: [shadowed-builtin] variable 'len' shadows the builtin of the same name"), "{}", stderr);
+    assert!(stderr.contains("main.nk:2: [constant-condition] 'while' condition is always the same"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Linted 2 file(s), found 2 problem(s)"));
+
+    std::fs::write(dir.join("config.json"), "{\n    // quiet\n    \"lint\": {\"shadowed-builtin\": false, \"constant-condition\": false}\n}").unwrap();
+    assert_eq!(lint(&dir).status.code(), Some(0));
+
+    std::fs::write(dir.join("config.json"), "{\"lint\": {\"typo\": false}}").unwrap();
+    assert_eq!(lint(&dir).status.code(), Some(2));
+}
+
+#[test]
+fn test_test_discovers_and_runs_test_files() {
+    let math = r#"import "testing" as t
+fn add(a, b) { return a + b }
+fn fn_adds() { t.assert_eq(add(1, 2), 3) }
+t.test("adds", fn_adds)
+fn test_fails() { t.assert_eq(add(2, 2), 5, "bad math") }
+fn helper() { return 1 }
+"#;
+    let dir = project("test", &[("tests/math.nk", math), ("tests/empty.nk", "let x = 1\n"), ("tests/broken.nk", "let = 1\n")]);
+    let output = Command::new(env!("CARGO_BIN_EXE_nikl")).arg("test").current_dir(&dir).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("test adds ... ok"), "{}", stdout);
+    assert!(stdout.contains("test test_fails ... FAILED"), "{}", stdout);
+    assert!(stdout.contains("assert_eq failed: 4 != 5: bad math"), "{}", stdout);
+    assert!(!stdout.contains("helper"), "{}", stdout);
+    assert!(stdout.contains("no tests found"), "{}", stdout);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("broken.nk: Parse error"));
+    assert!(stdout.contains("FAILED. 1 passed; 2 failed in 3 file(s)"), "{}", stdout);
+
+    let no_tests = nikl(&["test", dir.join("tests/empty.nk").to_str().unwrap()]);
+    assert_eq!(no_tests.status.code(), Some(0));
+    assert_eq!(nikl(&["test", dir.join("missing").to_str().unwrap()]).status.code(), Some(2));
+}
+
+#[test]
+fn test_eval_runs_one_liners() {
+    let output = nikl(&["-e", "import \"sys\" as sys\nprint(1 + 2, sys.argv.join(\",\"))", "a", "b"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3 -e,a,b\n");
+
+    let output = nikl(&["--eval", "print(missing)"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error[E0301]: Undefined variable 'missing'"));
+    assert_eq!(nikl(&["--eval", "let = 1"]).status.code(), Some(1));
+    assert_eq!(nikl(&["-e", "exit(7)"]).status.code(), Some(7));
+    assert_eq!(nikl(&["-e"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_script_from_stdin() {
+    let output = nikl_with_stdin(&["-", "--", "--flag"], "import \"sys\" as sys\nprint(sys.argv.join(\" \"))\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "- --flag\n");
+    assert_eq!(nikl_with_stdin(&["-"], "let = 1\n").status.code(), Some(1));
+}
+
+#[test]
+fn test_dump_tokens_and_ast() {
+    let dir = project("dump", &[("main.nk", "let x = 1\n"), ("bad.nk", "let = 1\n")]);
+    let main = dir.join("main.nk");
+    let main = main.to_str().unwrap();
+
+    let output = nikl(&["--dump-tokens", main]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1:1\tLet\n1:5\tIdentifier(\"x\")\n1:7\tAssign\n1:9\tIntegerLiteral(1)\n2:1\tEof\n");
+
+    let output = nikl(&["--dump-tokens", "--json", main]);
+    let tokens: Vec<nikl::Token> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tokens.len(), 5);
+
+    let output = nikl(&["--dump-ast", "--json", main]);
+    let stmts: Vec<nikl::Stmt> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stmts, vec![nikl::Stmt::Let { name: "x".to_string(), value: nikl::Expr::Integer(1) }]);
+    assert!(String::from_utf8_lossy(&nikl(&["--dump-ast", main]).stdout).starts_with("Let {\n    name: \"x\","));
+
+    assert_eq!(nikl(&["--dump-ast", dir.join("bad.nk").to_str().unwrap()]).status.code(), Some(1));
+    assert_eq!(nikl(&["--dump-ast"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = project("exit_codes", &[
+        ("ok.nk", "print(1)\n"),
+        ("exits.nk", "fn quit() { exit(3) }\nquit()\nprint(\"after\")\n"),
+        ("fails.nk", "print(missing)\n"),
+        ("bad.nk", "let = 1\n"),
+    ]);
+    let run = |name: &str| nikl(&[dir.join(name).to_str().unwrap()]);
+
+    assert_eq!(run("ok.nk").status.code(), Some(0));
+    let exits = run("exits.nk");
+    assert_eq!(exits.status.code(), Some(3));
+    assert!(exits.stdout.is_empty() && exits.stderr.is_empty());
+    assert_eq!(run("fails.nk").status.code(), Some(1));
+    assert_eq!(run("bad.nk").status.code(), Some(1));
+    assert_eq!(run("missing.nk").status.code(), Some(2));
+    assert_eq!(nikl(&["no-such-command"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_debugger_breakpoints_and_inspection() {
+    let source = "fn square(n) {\n    let result = n * n\n    return result\n}\nlet total = 0\nfor i in [1, 2] {\n    total = total + square(i)\n}\nprint(total)\n";
+    let dir = project("debug", &[("main.nk", source)]);
+    let main = dir.join("main.nk");
+    let main = main.to_str().unwrap();
+
+    let output = nikl_with_stdin(&["debug", main], "b 2\nc\nl\np n * 10\nw\nn\nn\nd 2\nc\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    let expected = [
+        "   1 | fn square(n) {",
+        "Breakpoint set on line 2",
+        "   2 | let result = n * n",
+        "n = 1",
+        "10",
+        "main.nk:2, 1 call(s) deep",
+        "   3 | return result",
+        "   7 | total = total + square(i)",
+        "Breakpoint on line 2 removed",
+        "5\nProgram finished",
+    ];
+    let mut rest = stdout.as_ref();
+    for text in expected {
+        let at = rest.find(text).unwrap_or_else(|| panic!("missing {:?} in {}", text, stdout));
+        rest = &rest[at + text.len()..];
+    }
+
+    // Breakpoints in imported modules take the module's path
+    std::fs::write(dir.join("util.nk"), "fn twice(n) {\n    return n * 2\n}\n").unwrap();
+    std::fs::write(dir.join("uses.nk"), "import \"util.nk\" as util\nprint(util.twice(4))\n").unwrap();
+    let util = dir.join("util.nk");
+    let commands = format!("b {}:2\nb\nc\np n\nc\n", util.display());
+    let output = nikl_with_stdin(&["debug", dir.join("uses.nk").to_str().unwrap()], &commands);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("util.nk:2 | return n * 2\n(debug) 4\n(debug) 8\nProgram finished"), "{}", stdout);
+
+    let output = nikl_with_stdin(&["debug", main], "s\nq\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("   5 | let total = 0"));
+    assert!(output.stderr.is_empty());
+    assert_eq!(nikl(&["debug"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_profile_reports_functions() {
+    let source = "fn square(n) { return n * n }\nfn total(items) {\n    let sum = 0\n    for i in items { sum = sum + square(i) }\n    return sum\n}\nprint(total([1, 2, 3]))\n";
+    let dir = project("profile", &[("main.nk", source)]);
+    let folded = dir.join("out.folded");
+    let output = nikl(&["--profile", "--folded", folded.to_str().unwrap(), dir.join("main.nk").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "14\n");
+
+    let report = String::from_utf8_lossy(&output.stderr);
+    let rows: Vec<(&str, &str)> = report
+        .lines()
+        .skip(2)
+        .map(|row| {
+            let columns: Vec<&str> = row.split_whitespace().collect();
+            (columns[0], columns[3])
+        })
+        .collect();
+    assert_eq!(rows, vec![("1", "<main>"), ("1", "total"), ("3", "square")], "{}", report);
+
+    let folded = std::fs::read_to_string(folded).unwrap();
+    let stacks: Vec<&str> = folded.lines().map(|line| line.rsplit_once(' ').unwrap().0).collect();
+    assert_eq!(stacks, vec!["<main>", "<main>;total", "<main>;total;square"]);
+    assert_eq!(nikl(&["--profile"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_coverage_of_tests_and_scripts() {
+    let util = "fn double(n) {\n    return n * 2\n}\nfn clamp(n) {\n    if n > 1 {\n        return 1\n    }\n    return n\n}\n";
+    let test = "import \"testing\" as t\nimport \"../lib/util.nk\" as util\nfn test_double() {\n    t.assert_eq(util.double(2), 4)\n}\n";
+    let dir = project("coverage", &[("lib/util.nk", util), ("tests/util.nk", test)]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nikl")).args(["test", "--lcov", "out.info"]).current_dir(&dir).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(stdout.contains("Coverage:\n   50.0%        3/6  lib/util.nk\n  100.0%        4/4  tests/util.nk\n   70.0%       7/10  total\n"), "{}", stdout);
+
+    let lcov = std::fs::read_to_string(dir.join("out.info")).unwrap();
+    let util_record = lcov.split("end_of_record\n").find(|record| record.contains("lib/util.nk")).unwrap();
+    let hits: Vec<&str> = util_record.lines().filter(|line| line.starts_with("DA:")).collect();
+    assert_eq!(hits, vec!["DA:1,1", "DA:2,1", "DA:4,1", "DA:5,0", "DA:6,0", "DA:8,0"]);
+    assert!(util_record.ends_with("LF:6\nLH:3\n"));
+
+    let output = nikl(&["--coverage", dir.join("lib/util.nk").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).ends_with("   33.3%        2/6  total\n"));
+}
+
+#[test]
+fn test_watch_reruns_on_changes_to_imports() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let dir = project("watch", &[("main.nk", "import \"lib.nk\" as lib\nprint(lib.name)\n"), ("lib.nk", "let name = \"first\"\n")]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nikl"))
+        .args(["watch", dir.join("main.nk").to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let (sender, lines) = mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let _ = sender.send(line.unwrap());
+        }
+    });
+    let wait_for = |text: &str| loop {
+        let line = lines.recv_timeout(Duration::from_secs(10)).unwrap_or_else(|_| panic!("no {:?} line", text));
+        if line.contains(text) {
+            break;
+        }
+    };
+
+    wait_for("first");
+    wait_for("[watch] exited with 0");
+    std::fs::write(dir.join("lib.nk"), "let name = \"second\"\n").unwrap();
+    wait_for("second");
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(nikl(&["watch"]).status.code(), Some(2));
+    assert_eq!(nikl(&["watch", dir.join("missing.nk").to_str().unwrap()]).status.code(), Some(2));
+}
+
+#[test]
+fn test_interactive_keeps_script_globals() {
+    let dir = project("interactive", &[
+        ("main.nk", "let total = 21\nfn twice(n) { return n * 2 }\n"),
+        ("fails.nk", "let seen = 1\nprint(missing)\n"),
+        ("exits.nk", "exit(5)\n"),
+    ]);
+    let output = nikl_with_stdin(&["-i", dir.join("main.nk").to_str().unwrap()], "twice(total)\nexit(4)\n");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("42\n"));
+
+    // A failing script still opens the REPL with what it defined
+    let output = nikl_with_stdin(&["-i", dir.join("fails.nk").to_str().unwrap()], "seen + 1\nexit\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Undefined variable 'missing'"));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("2\n"));
+
+    let output = nikl_with_stdin(&["-i", dir.join("exits.nk").to_str().unwrap()], "print(1)\n");
+    assert_eq!(output.status.code(), Some(5));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_usage_and_global_flags() {
+    let help = nikl(&["--help"]);
+    assert_eq!(help.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&help.stdout).contains("Usage: nikl [OPTIONS] [SCRIPT [ARGS]...]"));
+    assert!(String::from_utf8_lossy(&nikl(&["fmt", "--help"]).stdout).contains("--check"));
+    let version = nikl(&["--version"]);
+    assert_eq!(String::from_utf8_lossy(&version.stdout), format!("nikl {}\n", env!("CARGO_PKG_VERSION")));
+
+    // Misused flags are usage errors
+    assert_eq!(nikl(&["--json", "main.nk"]).status.code(), Some(2));
+    assert_eq!(nikl(&["--profile", "-i", "main.nk"]).status.code(), Some(2));
+    assert_eq!(nikl(&["check", "--no-such-flag"]).status.code(), Some(2));
+    assert_eq!(nikl(&["--verbose", "--quiet", "check"]).status.code(), Some(2));
+
+    let dir = project("global_flags", &[("ok.nk", "import \"sys\" as sys\nprint(sys.argv.slice(1).join(\",\"))\n")]);
+    let script = dir.join("ok.nk");
+    let quiet = nikl(&["check", "--quiet", script.to_str().unwrap()]);
+    assert_eq!(quiet.status.code(), Some(0));
+    assert!(quiet.stdout.is_empty());
+    let verbose = nikl(&["-v", "check", script.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&verbose.stdout).contains("Checking "));
+    let package = dir.join("quiet_package");
+    let init = nikl(&["--quiet", "init", package.to_str().unwrap()]);
+    assert_eq!(init.status.code(), Some(0));
+    assert!(init.stdout.is_empty(), "{}", String::from_utf8_lossy(&init.stdout));
+
+    // --no-color reaches the term module, even over FORCE_COLOR
+    let colored = project("no_color", &[("main.nk", "import \"term\" as term\nprint(term.color(\"hi\", \"red\"))\n")]);
+    let main = colored.join("main.nk");
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_nikl")).args(args).env("FORCE_COLOR", "1").output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&run(&[main.to_str().unwrap()]).stdout), "\x1b[31mhi\x1b[0m\n");
+    assert_eq!(String::from_utf8_lossy(&run(&["--no-color", main.to_str().unwrap()]).stdout), "hi\n");
+
+    // Flags after the script are its own
+    let output = nikl(&["-q", script.to_str().unwrap(), "--quiet", "check"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "--quiet,check\n");
+}
+
+#[test]
+fn test_errors_show_the_source_line() {
+    let dir = project("diagnostics", &[
+        ("runtime.nk", "let x = 1\nfn f(a) {\n    return a + missing\n}\nprint(f(x))\n"),
+        ("parse.nk", "let x = (1\n"),
+        ("lex.nk", "let x = 1 @ 2\n"),
+        ("imports.nk", "import \"runtime.nk\" as runtime\n"),
+    ]);
+    let stderr = |name: &str| String::from_utf8_lossy(&nikl(&[dir.join(name).to_str().unwrap()]).stderr).to_string();
+
+    let runtime = stderr("runtime.nk");
+    assert!(runtime.starts_with("error[E0301]: Undefined variable 'missing'\n --> "), "{}", runtime);
+    assert!(runtime.ends_with("runtime.nk:3\n  |\n3 |     return a + missing\n  |     ^^^^^^^^^^^^^^^^^^\n"), "{}", runtime);
+    // Modules point into their own file
+    assert!(stderr("imports.nk").contains("runtime.nk:3\n"));
+
+    let parse = stderr("parse.nk");
+    assert!(parse.starts_with("error[E0201]: Expected RightParen, found Eof\n"), "{}", parse);
+    assert!(parse.ends_with("parse.nk:1:11\n  |\n1 | let x = (1\n  |           ^\n"), "{}", parse);
+    assert!(stderr("lex.nk").ends_with("1 | let x = 1 @ 2\n  |           ^\n"));
+
+    // The REPL names every entry, piped output has no colors
+    let output = nikl_with_stdin(&["-q"], "fn f() { return nope }\nf()\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error[E0301]: Undefined variable 'nope'\n --> <input 1>:1\n"), "{}", stderr);
+    assert!(!stderr.contains('\x1b'));
+}
+
+#[test]
+fn test_language_server() {
+    use serde_json::{json, Value};
+
+    let dir = project("lsp", &[("util.nk", "fn double(n: Int) -> Int {\n    return n * 2\n}\n")]);
+    let uri = format!("file://{}", dir.join("main.nk").display());
+    let text = "import \"util.nk\" as util\nimport \"path\" as p\nimport \"nope\" as bad\n\nfn add(a: Int, b: Int) -> Int {\n    return a + b\n}\nlet total: Int = add(1, util.double(2))\np.\n";
+    let document = json!({ "uri": uri });
+    let at = |line: u32, character: u32| json!({ "textDocument": document, "position": { "line": line, "character": character } });
+    let messages = [
+        json!({ "id": 1, "method": "initialize", "params": {} }),
+        json!({ "method": "textDocument/didOpen", "params": { "textDocument": { "uri": uri, "languageId": "nikl", "version": 1, "text": text } } }),
+        json!({ "id": 2, "method": "textDocument/definition", "params": at(7, 18) }),
+        json!({ "id": 3, "method": "textDocument/definition", "params": at(7, 30) }),
+        json!({ "id": 4, "method": "textDocument/hover", "params": at(7, 30) }),
+        json!({ "id": 5, "method": "textDocument/hover", "params": at(5, 12) }),
+        json!({ "id": 6, "method": "textDocument/completion", "params": at(8, 2) }),
+        json!({ "method": "textDocument/didChange", "params": { "textDocument": document, "contentChanges": [{ "text": "let x = (1\n" }] } }),
+        json!({ "id": 7, "method": "shutdown" }),
+        json!({ "method": "exit" }),
+    ];
+    let input: String = messages
+        .iter()
+        .map(|message| {
+            let mut message = message.clone();
+            message["jsonrpc"] = json!("2.0");
+            let body = message.to_string();
+            format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+        })
+        .collect();
+
+    let output = nikl_with_stdin(&["lsp"], &input);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let replies: Vec<Value> = stdout.split("Content-Length: ").skip(1).map(|reply| serde_json::from_str(reply.split_once("\r\n\r\n").unwrap().1).unwrap()).collect();
+    let reply = |id: u32| replies.iter().find(|reply| reply["id"] == id).unwrap()["result"].clone();
+    let diagnostics: Vec<&Value> = replies.iter().filter(|reply| reply["method"] == "textDocument/publishDiagnostics").collect();
+
+    assert_eq!(reply(1)["capabilities"]["completionProvider"]["triggerCharacters"], json!(["."]));
+    // The unfinished `p.` doesn't parse, and one module doesn't exist
+    let problems = &diagnostics[0]["params"]["diagnostics"];
+    assert_eq!(problems.as_array().unwrap().len(), 2, "{}", problems);
+    assert_eq!(problems[1]["message"], "Unknown module 'nope'");
+    assert_eq!(problems[1]["range"]["start"], json!({ "line": 2, "character": 7 }));
+
+    assert_eq!(reply(2), json!({ "uri": uri, "range": { "start": { "line": 4, "character": 3 }, "end": { "line": 4, "character": 6 } } }));
+    assert!(reply(3)["uri"].as_str().unwrap().ends_with("/util.nk"));
+    assert_eq!(reply(3)["range"]["start"], json!({ "line": 0, "character": 3 }));
+    assert!(reply(4)["contents"]["value"].as_str().unwrap().contains("fn double(n: Int) -> Int"));
+    assert_eq!(reply(5)["contents"]["value"], "```nikl\na: Int\n```");
+    let members: Vec<String> = reply(6).as_array().unwrap().iter().map(|item| item["label"].as_str().unwrap().to_string()).collect();
+    assert!(members.contains(&"join".to_string()) && members.contains(&"sep".to_string()), "{:?}", members);
+
+    let changed = &diagnostics[1]["params"]["diagnostics"][0];
+    assert_eq!(changed["message"], "Expected RightParen, found Eof");
+    assert_eq!(changed["range"]["start"], json!({ "line": 0, "character": 10 }));
+}
+
+#[test]
+fn test_bundle() {
+    let dir = project("bundle", &[
+        ("app/main.nk", "import \"lib/util.nk\" as util\nimport \"../shared/greet.nk\" as greet\nimport \"sys\" as sys\nprint(greet.hello(util.double(21)))\nprint(sys.argv.slice(1).join(\",\"))\n"),
+        ("app/lib/util.nk", "import \"../../shared/greet.nk\" as g\n//@main evil.nk 0\nfn double(n) { return n * 2 }\n"),
+        ("shared/greet.nk", "fn hello(x) { return \"hello \" + str(x) }\n"),
+        ("clash/main.nk", "import \"x.nk\" as a\nimport \"../x.nk\" as b\n"),
+        ("clash/x.nk", ""),
+        ("x.nk", ""),
+    ]);
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+    let output = nikl(&["bundle", &path("app/main.nk"), "-o", &path("app.nk")]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let source = std::fs::read_to_string(dir.join("app.nk")).unwrap();
+    assert!(source.starts_with("//@bundle nikl\n//@module lib/util.nk 84\n"), "{}", source);
+    assert!(source.contains("//@module shared/greet.nk 41\n") && source.contains("//@main main.nk 157\n"));
+    // The bundle runs without the sources next to it
+    std::fs::rename(dir.join("shared"), dir.join("moved")).unwrap();
+    let output = nikl(&[&path("app.nk"), "a", "b"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello 42\na,b\n");
+
+    let output = nikl(&["-q", "bundle", &path("app.nk"), "--exe", "-o", &path("app-exe")]);
+    assert!(output.status.success() && output.stdout.is_empty());
+    let output = Command::new(dir.join("app-exe")).args(["--help", "x"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello 42\n--help,x\n");
+
+    let output = nikl(&["bundle", &path("clash/main.nk"), "-o", &path("clash.nk")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("would both be bundled as 'x.nk'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shebang_scripts() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = project("shebang", &[("tool", "#!/usr/bin/env nikl\nimport \"sys\" as sys\nprint(sys.argv.slice(1).join(\",\"))\n"), ("plain", "print(1)\n")]);
+    std::fs::set_permissions(dir.join("tool"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let nikl_dir = std::path::Path::new(env!("CARGO_BIN_EXE_nikl")).parent().unwrap();
+    let path = format!("{}:{}", nikl_dir.display(), std::env::var("PATH").unwrap_or_default());
+
+    let output = Command::new(dir.join("tool")).args(["a", "b"]).env("PATH", path).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a,b\n", "{}", String::from_utf8_lossy(&output.stderr));
+    // Files without `.nk` need the shebang
+    let output = nikl(&[dir.join("plain").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// Serves `routes` as a package registry on a local port, unknown paths get a 404
+/// Tokens other than `secret` are refused, and paths with `private` in them need it
+/// Every request is logged as `METHOD path token body-length`
+fn registry(routes: Vec<(String, Vec<u8>)>) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let requests = log.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let (mut token, mut length) = (String::from("-"), 0);
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let Some((name, value)) = header.trim_end().split_once(": ") else { break };
+                match name.to_ascii_lowercase().as_str() {
+                    "authorization" => token = value.trim_start_matches("Bearer ").to_string(),
+                    "content-length" => length = value.parse().unwrap(),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut parts = request.split(' ');
+            let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+            requests.lock().unwrap().push(format!("{} {} {} {}", method, path, token, length));
+            let authorized = token == "secret" || (token == "-" && !path.contains("private"));
+            let (status, body) = match routes.iter().find(|(route, _)| route == path) {
+                _ if !authorized => ("401 Unauthorized", Vec::new()),
+                Some((_, body)) => ("200 OK", body.clone()),
+                None => ("404 Not Found", Vec::new()),
+            };
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len()).unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+    (url, log)
+}
+
+/// Builds the package in `dir` with `nikl build` and returns its archive
+fn sha256(bytes: &[u8]) -> String {
+    use sha2::Digest;
+    sha2::Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn build_package(dir: &std::path::Path, name: &str, version: &str) -> Vec<u8> {
+    let output = Command::new(env!("CARGO_BIN_EXE_nikl")).arg("build").current_dir(dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    std::fs::read(dir.join(format!("{}-{}.tar.gz", name, version))).unwrap()
+}
+
+#[test]
+fn test_install_from_registry() {
+    let dir = project("install", &[
+        ("greet/config.json", r#"{"name": "greet", "version": "1.0.0", "dependencies": [{"name": "color", "version": "1.2.0"}]}"#),
+        ("greet/src/greet.nk", "fn hello(name) { return \"hello \" + name }\n"),
+        ("color/config.json", r#"{"name": "color", "version": "1.2.0", "dependencies": {}}"#),
+        ("color/src/color.nk", "let red = \"red\"\n"),
+        ("color/src/lib/util.nk", "let x = 1\n"),
+        ("app/main.nk", ""),
+    ]);
+    let greet = build_package(&dir.join("greet"), "greet", "1.0.0");
+    let color = build_package(&dir.join("color"), "color", "1.2.0");
+    let checksum = sha256(&greet);
+    let (url, _) = registry(vec![
+        ("/api/v1/packages/greet".to_string(), br#"{"name": "greet", "versions": [{"version": "1.0.0"}, {"version": "0.9.0"}, {"version": "2.0.0"}]}"#.to_vec()),
+        ("/api/v1/packages/greet/1.0.0/download".to_string(), greet.clone()),
+        // Claims to be another version than the archive holds
+        ("/api/v1/packages/greet/2.0.0/download".to_string(), greet),
+        ("/api/v1/packages/color/1.2.0/download".to_string(), color),
+    ]);
+    let install = |package: &str| Command::new(env!("CARGO_BIN_EXE_nikl")).args(["install", package]).current_dir(dir.join("app")).env("NIKL_REGISTRY", &url).env("NIKL_CACHE_DIR", dir.join("cache")).output().unwrap();
+
+    let output = install("greet@1.0.0");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Downloading greet@1.0.0\nInstalled greet@1.0.0\nDownloading color@1.2.0\nInstalled color@1.2.0\n"), "{}", stdout);
+    let packages = dir.join("app/.nikl/packages");
+    assert!(packages.join("greet/greet.nk").is_file() && packages.join("greet/config.json").is_file());
+    assert!(packages.join("color/lib/util.nk").is_file());
+    let info: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("app/.nikl/info.json")).unwrap()).unwrap();
+    assert_eq!(info["packages"][1], serde_json::json!({ "name": "greet", "version": "1.0.0", "source": "registry", "checksum": checksum, "dependencies": ["color"] }));
+    assert_eq!(info["packages"][0]["name"], "color");
+
+    assert!(String::from_utf8_lossy(&install("greet@1.0.0").stdout).contains("greet@1.0.0 is already installed"));
+
+    // The latest version is 2.0.0, whose archive is wrong
+    let output = install("greet");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid archive for greet@2.0.0: it contains greet@1.0.0"));
+    assert!(packages.join("greet/greet.nk").is_file());
+
+    let output = install("missing");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: Package 'missing' not found in the registry\n");
+
+    // Local archives install without the registry
+    let local = dir.join("color/color-1.2.0.tar.gz");
+    std::fs::remove_dir_all(dir.join("app/.nikl")).unwrap();
+    let output = install(local.to_str().unwrap());
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(packages.join("color/color.nk").is_file());
+}
+
+#[test]
+fn test_uninstall() {
+    let info = r#"{"packages": [
+        {"name": "color", "version": "1.2.0", "source": "registry", "dependencies": []},
+        {"name": "greet", "version": "1.0.0", "source": "registry", "dependencies": ["color"]}
+    ]}"#;
+    let dir = project("uninstall", &[
+        (".nikl/info.json", info),
+        (".nikl/packages/color/color.nk", ""),
+        (".nikl/packages/greet/greet.nk", ""),
+    ]);
+    let uninstall = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_nikl")).arg("uninstall").args(args).current_dir(&dir).output().unwrap();
+    let installed = || {
+        let info: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join(".nikl/info.json")).unwrap()).unwrap();
+        info["packages"].as_array().unwrap().iter().map(|package| package["name"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+
+    let output = uninstall(&["color"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: 'color' is needed by greet, use --force to remove it anyway\n");
+    assert!(dir.join(".nikl/packages/color").exists());
+
+    assert_eq!(String::from_utf8_lossy(&uninstall(&["greet@2.0.0"]).stderr), "Error: greet@1.0.0 is installed, not 2.0.0\n");
+    let output = uninstall(&["greet"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Uninstalled greet@1.0.0\n");
+    assert!(!dir.join(".nikl/packages/greet").exists());
+    assert_eq!(installed(), ["color"]);
+
+    // Nothing depends on it anymore
+    assert!(uninstall(&["-q", "color"]).stdout.is_empty());
+    assert!(installed().is_empty());
+    assert_eq!(String::from_utf8_lossy(&uninstall(&["color"]).stderr), "Error: Package 'color' is not installed\n");
+
+    std::fs::write(dir.join(".nikl/info.json"), info).unwrap();
+    assert!(uninstall(&["--force", "color"]).status.success());
+    assert_eq!(installed(), ["greet"]);
+}
+
+#[test]
+fn test_search_and_info() {
+    let greet = r#"{
+        "name": "greet", "description": "Friendly greetings", "license": "MIT",
+        "authors": [{"name": "Neko Nik", "email": "admin@nekonik.com"}], "keywords": ["text"],
+        "versions": [
+            {"version": "1.10.0", "dependencies": [{"name": "color", "version": "1.2.0"}]},
+            {"version": "1.9.0", "dependencies": {}}
+        ]
+    }"#;
+    let search = r#"{"packages": [{"name": "greet", "version": "1.10.0", "description": "Friendly greetings"}, {"name": "hello-world", "version": "0.1.0"}]}"#;
+    let (url, _) = registry(vec![
+        ("/api/v1/packages/greet".to_string(), greet.as_bytes().to_vec()),
+        ("/api/v1/search?q=hello%20there".to_string(), search.as_bytes().to_vec()),
+        ("/api/v1/search?q=nothing".to_string(), br#"{"packages": []}"#.to_vec()),
+    ]);
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_nikl")).args(args).env("NIKL_REGISTRY", &url).output().unwrap();
+
+    let output = run(&["search", "hello", "there"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "greet@1.10.0       Friendly greetings\nhello-world@0.1.0\n");
+    assert_eq!(String::from_utf8_lossy(&run(&["search", "nothing"]).stdout), "No packages found for 'nothing'\n");
+
+    let output = run(&["info", "greet"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\
+greet@1.10.0
+Friendly greetings
+
+authors:      Neko Nik <admin@nekonik.com>
+license:      MIT
+keywords:     text
+versions:     1.10.0, 1.9.0
+dependencies: color 1.2.0
+");
+    assert!(!String::from_utf8_lossy(&run(&["info", "greet@1.9.0"]).stdout).contains("dependencies:"));
+    assert_eq!(String::from_utf8_lossy(&run(&["info", "greet@3.0.0"]).stderr), "Error: greet has no version '3.0.0'\n");
+    assert_eq!(String::from_utf8_lossy(&run(&["info", "missing"]).stderr), "Error: Package 'missing' not found in the registry\n");
+}
+
+#[test]
+fn test_login_and_publish() {
+    let dir = project("login", &[
+        ("greet/config.json", r#"{"name": "greet", "version": "1.0.0", "dependencies": []}"#),
+        ("greet/src/greet.nk", "fn hello(name) { return \"hello \" + name }\n"),
+        ("app/main.nk", ""),
+    ]);
+    let archive = build_package(&dir.join("greet"), "greet", "1.0.0");
+    let (url, log) = registry(vec![
+        ("/api/v1/me".to_string(), br#"{"name": "neko"}"#.to_vec()),
+        ("/api/v1/packages/greet/1.0.0".to_string(), Vec::new()),
+        ("/api/v1/packages/private-greet/1.0.0/download".to_string(), archive),
+    ]);
+    let config = dir.join("config");
+    let run = |args: &[&str], cwd: &str| {
+        Command::new(env!("CARGO_BIN_EXE_nikl"))
+            .args(args)
+            .current_dir(dir.join(cwd))
+            .env("NIKL_REGISTRY", &url)
+            .env("NIKL_CONFIG_DIR", &config)
+            .env("NIKL_CACHE_DIR", dir.join("cache"))
+            .env_remove("NIKL_TOKEN")
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["publish"], "greet");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: Not logged in, run `nikl login` first\n");
+
+    let output = run(&["login", "--token", "wrong"], "app");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), format!("Error: {} rejected the token, run `nikl login` again\n", url));
+    assert!(!config.join("credentials.json").exists());
+
+    let output = run(&["login", "--token", "secret"], "app");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("Logged in to {} as neko\n", url));
+    let credentials: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(config.join("credentials.json")).unwrap()).unwrap();
+    assert_eq!(credentials["tokens"][&url], "secret");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(config.join("credentials.json")).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    let output = run(&["publish"], "greet");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("Publishing greet@1.0.0 to {}\nPublished greet@1.0.0\n", url));
+    let published = log.lock().unwrap().last().unwrap().clone();
+    assert!(published.starts_with("PUT /api/v1/packages/greet/1.0.0 secret ") && !published.ends_with(" 0"), "{}", published);
+
+    // Private packages are downloaded with the token, and fail without it
+    let output = run(&["install", "private-greet@1.0.0"], "app");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid archive for private-greet@1.0.0: it contains greet@1.0.0"), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(String::from_utf8_lossy(&run(&["logout"], "app").stdout), format!("Logged out of {}\n", url));
+    assert_eq!(String::from_utf8_lossy(&run(&["logout"], "app").stdout), format!("Not logged in to {}\n", url));
+    let output = run(&["install", "private-greet@1.0.0"], "app");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), format!("Error: {} needs a login, run `nikl login` first\n", url));
+}
+
+#[test]
+fn test_install_from_git() {
+    let dir = project("install_git", &[
+        ("greet/config.json", r#"{"name": "greet", "version": "1.0.0", "dependencies": []}"#),
+        ("greet/src/greet.nk", "fn hello(name) { return \"hello \" + name }\n"),
+        ("empty/README.md", "nothing here\n"),
+        ("escape/config.json", r#"{"name": "../escape", "version": "1.0.0", "dependencies": []}"#),
+        ("escape/src/escape.nk", ""),
+        ("app/main.nk", ""),
+    ]);
+    let git = |repo: &str, args: &[&str]| {
+        let output = Command::new("git")
+            .args(["-c", "user.name=nikl", "-c", "user.email=nikl@example.com", "-c", "init.defaultBranch=main"])
+            .args(args)
+            .current_dir(dir.join(repo))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    for repo in ["greet", "empty", "escape"] {
+        git(repo, &["init", "--quiet"]);
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "--quiet", "-m", "first"]);
+    }
+    git("greet", &["tag", "v1.0.0"]);
+    let first = git("greet", &["rev-parse", "HEAD"]);
+    std::fs::write(dir.join("greet/config.json"), r#"{"name": "greet", "version": "1.1.0", "dependencies": []}"#).unwrap();
+    git("greet", &["commit", "--quiet", "-am", "second"]);
+    let second = git("greet", &["rev-parse", "HEAD"]);
+
+    let url = format!("file://{}", dir.join("greet").display());
+    let install = |package: &str| Command::new(env!("CARGO_BIN_EXE_nikl")).args(["install", package]).current_dir(dir.join("app")).output().unwrap();
+    let info = || -> serde_json::Value { serde_json::from_str(&std::fs::read_to_string(dir.join("app/.nikl/info.json")).unwrap()).unwrap() };
+
+    let output = install(&format!("git+{}#v1.0.0", url));
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("Cloning {}\nInstalled greet@1.0.0\n", url)));
+    assert!(dir.join("app/.nikl/packages/greet/greet.nk").is_file());
+    assert_eq!(info()["packages"][0], serde_json::json!({ "name": "greet", "version": "1.0.0", "source": format!("git+{}#{}", url, first), "dependencies": [] }));
+
+    // Without a ref the default branch is installed
+    assert!(install(&format!("git+{}", url)).status.success());
+    assert_eq!(info()["packages"][0]["source"], format!("git+{}#{}", url, second));
+    assert_eq!(info()["packages"][0]["version"], "1.1.0");
+
+    // Commits are found in the whole history
+    assert!(install(&format!("git+{}#{}", url, first)).status.success());
+    assert_eq!(info()["packages"][0]["version"], "1.0.0");
+
+    let output = install(&format!("git+{}#v9", url));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), format!("Error: '{}' has no branch, tag or commit 'v9'\n", url));
+    let output = install(&format!("git+file://{}", dir.join("empty").display()));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid package in"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("config.json not found"));
+    assert_eq!(info()["packages"].as_array().unwrap().len(), 1);
+
+    // Names from config.json become directories and specs may come from a registry, neither is trusted
+    let output = install(&format!("git+file://{}", dir.join("escape").display()));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid package name '../escape'"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!dir.join("app/.nikl/escape").exists());
+    let output = install("git+--upload-pack=touch injected#main");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid git package"), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = install(&format!("git+{}#--orphan=x", url));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid git package"));
+    assert_eq!(info()["packages"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_path_dependencies() {
+    let dir = project("path_deps", &[
+        ("mylib/config.json", r#"{"name": "mylib", "version": "0.1.0", "dependencies": {"util": {"path": "../util"}}}"#),
+        ("mylib/src/mylib.nk", "let version = 1\n"),
+        ("util/config.json", r#"{"name": "util", "version": "0.2.0", "dependencies": [{"name": "mylib", "path": "../mylib"}]}"#),
+        ("util/src/util.nk", ""),
+        ("app/config.json", r#"{"name": "app", "version": "1.0.0", "dependencies": [{"name": "mylib", "path": "../mylib"}]}"#),
+        ("wrong/config.json", r#"{"name": "app", "version": "1.0.0", "dependencies": [{"name": "other", "path": "../mylib"}]}"#),
+    ]);
+    let install = |cwd: &str| Command::new(env!("CARGO_BIN_EXE_nikl")).arg("install").current_dir(dir.join(cwd)).output().unwrap();
+
+    let output = install("app");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("Linked mylib@0.1.0 from ../mylib\nLinked util@0.2.0 from ../util\n"));
+    let info: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("app/.nikl/info.json")).unwrap()).unwrap();
+    assert_eq!(info["packages"][0], serde_json::json!({ "name": "mylib", "version": "0.1.0", "source": "path:../mylib", "dependencies": ["util"] }));
+    assert_eq!(info["packages"][1]["source"], "path:../util");
+    let installed = dir.join("app/.nikl/packages/mylib/mylib.nk");
+    assert_eq!(std::fs::read_to_string(&installed).unwrap(), "let version = 1\n");
+
+    // Linked sources follow the package without installing again
+    #[cfg(unix)]
+    {
+        std::fs::write(dir.join("mylib/src/mylib.nk"), "let version = 2\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&installed).unwrap(), "let version = 2\n");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nikl")).args(["uninstall", "--force", "util"]).current_dir(dir.join("app")).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!dir.join("app/.nikl/packages/util").exists());
+    assert!(dir.join("util/src/util.nk").is_file());
+
+    assert_eq!(String::from_utf8_lossy(&install("wrong").stderr), "Error: '../mylib' holds the package 'mylib', not 'other'\n");
+    std::fs::write(dir.join("wrong/config.json"), r#"{"name": "app", "version": "1.0.0", "dependencies": [{"name": "gone", "path": "../gone"}]}"#).unwrap();
+    assert_eq!(String::from_utf8_lossy(&install("wrong").stderr), "Error: Path dependency 'gone' not found at '../gone'\n");
+}
+
+#[test]
+fn test_package_cache() {
+    let dir = project("cache", &[
+        ("color/config.json", r#"{"name": "color", "version": "1.2.0", "dependencies": []}"#),
+        ("color/src/color.nk", "let red = \"red\"\n"),
+        ("one/main.nk", ""),
+        ("two/main.nk", ""),
+    ]);
+    let archive = build_package(&dir.join("color"), "color", "1.2.0");
+    let (url, log) = registry(vec![
+        ("/api/v1/packages/color".to_string(), br#"{"name": "color", "versions": [{"version": "1.2.0"}]}"#.to_vec()),
+        ("/api/v1/packages/color/1.2.0/download".to_string(), archive),
+    ]);
+    let cache = dir.join("cache");
+    let run = |args: &[&str], cwd: &str, registry: &str| {
+        Command::new(env!("CARGO_BIN_EXE_nikl")).args(args).current_dir(dir.join(cwd)).env("NIKL_REGISTRY", registry).env("NIKL_CACHE_DIR", &cache).output().unwrap()
+    };
+    let downloads = || log.lock().unwrap().iter().filter(|request| request.contains("/download")).count();
+
+    let output = run(&["install", "color@1.2.0"], "one", &url);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Downloading color@1.2.0\n"));
+    assert_eq!(std::fs::read_dir(cache.join("packages/color/1.2.0")).unwrap().count(), 1);
+
+    // Other projects install it from the cache, even without the registry
+    let output = run(&["install", "color@1.2.0"], "two", &url);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Using cached color@1.2.0\nInstalled color@1.2.0\n"));
+    assert_eq!(downloads(), 1);
+    std::fs::remove_dir_all(dir.join("two/.nikl")).unwrap();
+    let output = run(&["install", "color"], "two", "http://127.0.0.1:1");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(dir.join("two/.nikl/packages/color/color.nk").is_file());
+
+    // A damaged archive is dropped and downloaded again
+    let cached = std::fs::read_dir(cache.join("packages/color/1.2.0")).unwrap().next().unwrap().unwrap().path();
+    std::fs::write(&cached, "damaged").unwrap();
+    std::fs::remove_dir_all(dir.join("two/.nikl")).unwrap();
+    let output = run(&["install", "color@1.2.0"], "two", &url);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Downloading color@1.2.0\n"));
+    assert_eq!(downloads(), 2);
+    assert_ne!(std::fs::read(&cached).unwrap(), b"damaged");
+
+    assert_eq!(String::from_utf8_lossy(&run(&["cache", "dir"], "one", &url).stdout), format!("{}\n", cache.display()));
+    assert_eq!(String::from_utf8_lossy(&run(&["cache", "clean"], "one", &url).stdout), format!("Removed 1 cached archive(s) from {}\n", cache.display()));
+    assert!(!cache.join("packages").exists());
+
+    // Versions name cache directories, they can't point out of it
+    let output = run(&["install", "color@../../escape"], "one", &url);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: Invalid package version '../../escape'\n");
+    std::fs::write(dir.join("two/config.json"), r#"{"name": "two", "version": "0.1.0", "dependencies": {"color": "1.2.0/../.."}}"#).unwrap();
+    let output = run(&["install"], "two", &url);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid package version '1.2.0/../..' of dependency 'color'"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!cache.join("escape").exists());
+}
+
+#[test]
+fn test_checksums_are_verified() {
+    let dir = project("checksums", &[
+        ("color/config.json", r#"{"name": "color", "version": "1.2.0", "dependencies": []}"#),
+        ("color/src/color.nk", "let red = \"red\"\n"),
+        ("app/main.nk", ""),
+    ]);
+    let archive = build_package(&dir.join("color"), "color", "1.2.0");
+    std::fs::write(dir.join("color/src/color.nk"), "let red = \"evil\"\n").unwrap();
+    std::fs::remove_file(dir.join("color/color-1.2.0.tar.gz")).unwrap();
+    let tampered = build_package(&dir.join("color"), "color", "1.2.0");
+    let (url, _) = registry(vec![("/api/v1/packages/color/1.2.0/download".to_string(), archive.clone())]);
+    let (tampered_url, _) = registry(vec![("/api/v1/packages/color/1.2.0/download".to_string(), tampered.clone())]);
+    let install = |registry: &str, cache: &str| {
+        Command::new(env!("CARGO_BIN_EXE_nikl")).args(["install", "color@1.2.0"]).current_dir(dir.join("app")).env("NIKL_REGISTRY", registry).env("NIKL_CACHE_DIR", dir.join(cache)).output().unwrap()
+    };
+    let packages = dir.join("app/.nikl/packages");
+
+    assert!(install(&url, "cache").status.success());
+    let info: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("app/.nikl/info.json")).unwrap()).unwrap();
+    assert_eq!(info["packages"][0]["checksum"], sha256(&archive));
+
+    // info.json is kept, the files are installed again and must be the same
+    std::fs::remove_dir_all(&packages).unwrap();
+    let output = install(&tampered_url, "other-cache");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("Error: Checksum mismatch for color@1.2.0: .nikl/info.json has {} but the downloaded archive is {}, it may have been tampered with\n", sha256(&archive), sha256(&tampered))
+    );
+    assert!(!packages.join("color").exists());
+
+    // Cached archives with another checksum aren't used either
+    let cached = dir.join("other-cache/packages/color/1.2.0");
+    std::fs::create_dir_all(&cached).unwrap();
+    std::fs::write(cached.join(format!("{}.tar.gz", sha256(&tampered))), &tampered).unwrap();
+    let output = install(&url, "other-cache");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Downloading color@1.2.0\n"));
+    assert_eq!(std::fs::read_to_string(packages.join("color/color.nk")).unwrap(), "let red = \"red\"\n");
+}
+
+#[test]
+fn test_add_and_remove() {
+    let map = "{\n    \"name\": \"app\",\n    \"version\": \"1.0.0\",\n    \"dependencies\": {\n        \"os\": \"0.0.1\"\n    },\n    \"keywords\": []\n}\n";
+    let list = "{\"name\": \"lib\", \"version\": \"1.0.0\",\n  \"dependencies\": [\n    {\"name\": \"greet\", \"version\": \"0.1.0\"} ,\n    {\"name\": \"os\", \"version\": \"0.0.1\"}\n  ]\n}";
+    let dir = project("add_remove", &[
+        ("greet/config.json", r#"{"name": "greet", "version": "1.1.0", "dependencies": []}"#),
+        ("greet/src/greet.nk", ""),
+        ("map/config.json", map),
+        ("list/config.json", list),
+        ("empty/config.json", "{\n\t\"name\": \"empty\",\n\t\"dependencies\": {}\n}\n"),
+        ("bare/config.json", "{\n  \"name\": \"bare\"\n}\n"),
+    ]);
+    let archive = build_package(&dir.join("greet"), "greet", "1.1.0");
+    let (url, _) = registry(vec![
+        ("/api/v1/packages/greet".to_string(), br#"{"name": "greet", "versions": [{"version": "1.1.0"}, {"version": "0.1.0"}]}"#.to_vec()),
+        ("/api/v1/packages/greet/1.1.0/download".to_string(), archive),
+    ]);
+    let run = |args: &[&str], cwd: &str| {
+        Command::new(env!("CARGO_BIN_EXE_nikl")).args(args).current_dir(dir.join(cwd)).env("NIKL_REGISTRY", &url).env("NIKL_CACHE_DIR", dir.join("cache")).output().unwrap()
+    };
+    let config = |project: &str| std::fs::read_to_string(dir.join(project).join("config.json")).unwrap();
+
+    let output = run(&["add", "greet"], "map");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("Installed greet@1.1.0\nAdded greet@1.1.0 to config.json\n"));
+    assert_eq!(config("map"), map.replace("\"0.0.1\"\n", "\"0.0.1\",\n        \"greet\": \"1.1.0\"\n"));
+    assert!(dir.join("map/.nikl/packages/greet/greet.nk").is_file());
+
+    let output = run(&["remove", "greet"], "map");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Uninstalled greet@1.1.0\nRemoved greet from config.json\n");
+    assert_eq!(config("map"), map);
+    assert!(!dir.join("map/.nikl/packages/greet").exists());
+    assert_eq!(String::from_utf8_lossy(&run(&["remove", "greet"], "map").stderr), "Error: 'greet' is not a dependency in config.json\n");
+
+    // A version that's there is changed in place, and list entries are written as lists have them
+    assert!(run(&["add", "greet"], "list").status.success());
+    assert_eq!(config("list"), list.replace("0.1.0", "1.1.0"));
+    assert!(run(&["remove", "os"], "list").status.success());
+    assert_eq!(config("list"), "{\"name\": \"lib\", \"version\": \"1.0.0\",\n  \"dependencies\": [\n    {\"name\": \"greet\", \"version\": \"1.1.0\"}\n  ]\n}");
+    assert!(run(&["remove", "greet"], "list").status.success());
+    assert_eq!(config("list"), "{\"name\": \"lib\", \"version\": \"1.0.0\",\n  \"dependencies\": []\n}");
+
+    assert!(run(&["add", "greet@1.1.0"], "empty").status.success());
+    assert_eq!(config("empty"), "{\n\t\"name\": \"empty\",\n\t\"dependencies\": {\n\t\t\"greet\": \"1.1.0\"\n\t}\n}\n");
+
+    assert!(run(&["add", "greet"], "bare").status.success());
+    assert_eq!(config("bare"), "{\n  \"name\": \"bare\",\n  \"dependencies\": {\"greet\": \"1.1.0\"}\n}\n");
+
+    // Nothing is written when the install fails
+    let output = run(&["add", "greet@0.1.0"], "empty");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(config("empty").contains("\"greet\": \"1.1.0\""));
+    assert_eq!(String::from_utf8_lossy(&run(&["add", "x.tar.gz"], "empty").stderr), "Error: Local package 'x.tar.gz' does not exist.\n");
+}
+
+#[test]
+fn test_workspace() {
+    let dir = project("workspace", &[
+        ("workspace.json", r#"{"members": ["packages/*", "tools/cli"]}"#),
+        ("packages/core/config.json", r#"{"name": "core", "version": "1.0.0", "dependencies": []}"#),
+        ("packages/core/src/core.nk", "fn one() { return 1 }\n"),
+        ("packages/core/tests/core.nk", "import \"testing\" as t\nfn test_one() { t.assert_eq(1, 1) }\n"),
+        ("packages/util/config.json", r#"{"name": "util", "version": "0.1.0", "dependencies": [{"name": "core", "version": "1.0.0"}]}"#),
+        ("packages/util/src/util.nk", ""),
+        ("packages/util/tests/util.nk", "import \"testing\" as t\nfn test_two() { t.assert_eq(1 + 1, 2) }\n"),
+        ("packages/notes/README.md", "not a package\n"),
+        ("tools/cli/config.json", r#"{"name": "cli", "version": "0.0.1", "dependencies": {"util": "0.1.0"}}"#),
+        ("tools/cli/src/cli.nk", ""),
+    ]);
+    let run = |args: &[&str], cwd: &str| Command::new(env!("CARGO_BIN_EXE_nikl")).args(args).current_dir(dir.join(cwd)).output().unwrap();
+
+    // Dependencies on members are linked, into the root's .nikl
+    let output = run(&["install"], "");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("Linked core@1.0.0 from packages/core\nLinked util@0.1.0 from packages/util\n"));
+    let info: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join(".nikl/info.json")).unwrap()).unwrap();
+    assert_eq!(info["packages"][1], serde_json::json!({ "name": "util", "version": "0.1.0", "source": "path:packages/util", "dependencies": ["core"] }));
+    assert!(dir.join(".nikl/packages/core/core.nk").is_file());
+
+    let output = run(&["install"], "tools/cli");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!dir.join("tools/cli/.nikl").exists());
+    let output = run(&["uninstall", "core", "--force"], "tools/cli");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!dir.join(".nikl/packages/core").exists());
+
+    let output = run(&["build"], "");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Building core in packages/core...\n"));
+    for archive in ["packages/core/core-1.0.0.tar.gz", "packages/util/util-0.1.0.tar.gz", "tools/cli/cli-0.0.1.tar.gz"] {
+        assert!(dir.join(archive).is_file(), "{}", archive);
+    }
+
+    let output = run(&["test"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("test test_one ... ok") && stdout.contains("test test_two ... ok"), "{}", stdout);
+    assert!(stdout.contains("ok. 2 passed; 0 failed in 2 file(s)"), "{}", stdout);
+
+    // A member that fails to build fails the build, the others are still built
+    std::fs::remove_file(dir.join("packages/util/src/util.nk")).unwrap();
+    for archive in ["packages/core/core-1.0.0.tar.gz", "tools/cli/cli-0.0.1.tar.gz"] {
+        std::fs::remove_file(dir.join(archive)).unwrap();
+    }
+    let output = run(&["build"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Failed to create package util: Required file src/util.nk not found\n");
+    assert!(dir.join("tools/cli/cli-0.0.1.tar.gz").is_file());
+
+    std::fs::write(dir.join("workspace.json"), r#"{"members": ["packages/*", "tools/missing"]}"#).unwrap();
+    assert_eq!(String::from_utf8_lossy(&run(&["install"], "").stderr), "Error: Workspace member 'tools/missing' has no config.json\n");
+}
@@ -0,0 +1,27 @@
+#![cfg(feature = "repl")]
+
+use nikl::cli::highlight_line;
+
+
+#[test]
+fn test_highlight_tokens() {
+    assert_eq!(
+        highlight_line("let x: Int = 1 // one", None),
+        "\x1b[35mlet\x1b[0m x: \x1b[36mInt\x1b[0m = \x1b[33m1\x1b[0m \x1b[90m// one\x1b[0m"
+    );
+    assert_eq!(
+        highlight_line("print(\"a(b\") and True", None),
+        "print(\x1b[32m\"a(b\"\x1b[0m) \x1b[35mand\x1b[0m \x1b[33mTrue\x1b[0m"
+    );
+    // Unterminated strings are still colored while being typed
+    assert_eq!(highlight_line("\"open", None), "\x1b[32m\"open\x1b[0m");
+}
+
+#[test]
+fn test_highlight_matching_brackets() {
+    let outer = "f\x1b[1;4m(\x1b[0m[a, (b)]\x1b[1;4m)\x1b[0m";
+    assert_eq!(highlight_line("f([a, (b)])", Some(1)), outer);
+    assert_eq!(highlight_line("f([a, (b)])", Some(11)), outer);
+    assert_eq!(highlight_line("f([a, (b)])", Some(9)), "f(\x1b[1;4m[\x1b[0ma, (b)\x1b[1;4m]\x1b[0m)");
+    assert_eq!(highlight_line("f((", Some(3)), "f((");
+}
@@ -0,0 +1,470 @@
+use nikl::{eval_script, Value};
+
+#[test]
+fn test_eval_script_returns_last_expression() {
+    assert_eq!(eval_script("let x = 20\nx * 2 + 2"), Ok(Value::Integer(42)));
+    assert_eq!(eval_script("\"a\" + \"b\""), Ok(Value::String("ab".to_string())));
+    assert_eq!(eval_script("let x = 1"), Ok(Value::Null));
+    assert_eq!(eval_script(""), Ok(Value::Null));
+}
+
+#[test]
+fn test_eval_script_returns_top_level_return() {
+    let source = r#"
+        fn square(n) {
+            return n * n
+        }
+        return [square(3), square(4)]
+        print("not reached")
+    "#;
+    assert_eq!(eval_script(source), Ok(Value::Array(vec![Value::Integer(9), Value::Integer(16)])));
+    assert!(eval_script("missing_variable").is_err());
+}
+
+fn parse(source: &str) -> Vec<nikl::Stmt> {
+    let tokens = nikl::lexer::Lexer::new(source).tokenize().unwrap();
+    nikl::parser::Parser::new(tokens).parse().unwrap()
+}
+
+#[test]
+fn test_register_fn_closure_captures_state() {
+    use std::sync::{Arc, Mutex};
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let seen = calls.clone();
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    interp
+        .register_fn("record", move |_, args| {
+            seen.lock().unwrap().extend(args);
+            Ok::<_, String>(Value::Integer(seen.lock().unwrap().len() as i64))
+        })
+        .unwrap();
+
+    let result = interp.run_program(&parse(r#"
+        fn twice(x) {
+            record(x)
+            return record(x)
+        }
+        twice("hi")
+    "#));
+    assert_eq!(result, Ok(Value::Integer(2)));
+    assert_eq!(calls.lock().unwrap().len(), 2);
+    assert_eq!(interp.run_program(&parse("type(record)")), Ok(Value::String("BuiltinFunction".to_string())));
+}
+
+#[test]
+fn test_register_fn_returns_nikl_errors() {
+    use nikl::NiklError;
+
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    interp.register_fn("check", |_, args| match args.as_slice() {
+        [Value::Integer(n)] if *n > 0 => Ok(Value::Integer(*n)),
+        _ => Err(NiklError::Runtime("check expects a positive integer".to_string())),
+    }).unwrap();
+    interp.register_fn("parse_config", |_, _| Err::<Value, _>(NiklError::Parse("unexpected '}'".to_string()))).unwrap();
+
+    assert_eq!(interp.eval("check(2)"), Ok(Value::Integer(2)));
+    assert_eq!(interp.eval("check(0)"), Err(NiklError::Runtime("check expects a positive integer".to_string())));
+    assert_eq!(interp.eval("parse_config()"), Err(NiklError::Runtime("Parse error: unexpected '}'".to_string())));
+}
+
+#[test]
+fn test_set_const() {
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    interp.set_const("limit", 10);
+    interp.set_global("count", 1);
+
+    let err = interp.run_program(&parse("limit = 20")).unwrap_err();
+    assert!(err.contains("Cannot assign to constant 'limit'"), "{}", err);
+    assert_eq!(interp.get_global("limit"), Some(Value::Integer(10)));
+    assert_eq!(interp.run_program(&parse("count = limit + 1\ncount")), Ok(Value::Integer(11)));
+}
+
+#[test]
+fn test_register_module() {
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    let config = nikl::HostModule::new()
+        .value("name", Value::String("demo".to_string()))
+        .function("add", |_, args| match args.as_slice() {
+            [Value::Integer(a), Value::Integer(b)] => Ok(Value::Integer(a + b)),
+            _ => Err("add expects 2 integers".to_string()),
+        });
+    interp.register_module("host", config);
+
+    let result = interp.run_program(&parse(r#"
+        import "host" as host
+        host.name + ":" + str(host.add(2, 3))
+    "#));
+    assert_eq!(result, Ok(Value::String("demo:5".to_string())));
+    assert!(interp.run_program(&parse("host.add(1)")).is_err());
+}
+
+#[test]
+fn test_builder_sandbox_disables_modules_and_builtins() {
+    use nikl::InterpreterBuilder;
+
+    let build = || InterpreterBuilder::new(std::path::PathBuf::from(".")).sandboxed().build();
+
+    let err = build().run_program(&parse("import \"os\" as os")).unwrap_err();
+    assert_eq!(err, "Module 'os' is disabled");
+    assert!(build().run_program(&parse("exit(1)")).unwrap_err().contains("Undefined variable 'exit'"));
+    assert_eq!(build().run_program(&parse("import \"regex\" as re\nre.is_match(\"a+\", \"aa\")")), Ok(Value::Bool(true)));
+    // Module functions that are the same as a disabled builtin go with it
+    let err = build().run_program(&parse("import \"time\" as t\nt.sleep(1000000000)")).unwrap_err();
+    assert!(err.contains("sleep"), "{}", err);
+    assert_eq!(build().run_program(&parse("import \"time\" as t\ntype(t.now())")), Ok(Value::String("Float".to_string())));
+
+    // Function bodies run in their own interpreter with the same restrictions
+    let source = "fn f() { return exit }\nf()";
+    assert!(build().run_program(&parse(source)).is_err());
+
+    let mut custom = InterpreterBuilder::new(std::path::PathBuf::from(".")).disable_module("regex").build();
+    assert!(custom.run_program(&parse("import \"regex\" as re")).is_err());
+    assert!(custom.run_program(&parse("import \"path\" as path")).is_ok());
+}
+
+#[test]
+fn test_builder_limits() {
+    use nikl::InterpreterBuilder;
+
+    let mut steps = InterpreterBuilder::new(std::path::PathBuf::from(".")).max_steps(100).build();
+    assert_eq!(steps.run_program(&parse("loop { }")).unwrap_err(), "Step limit of 100 exceeded");
+
+    // Line markers from `Parser::with_lines` don't use up steps
+    let source = "let a = 1\nlet b = 2\nlet c = a + b";
+    let with_lines = nikl::parser::Parser::with_lines(nikl::lexer::Lexer::new(source).tokenize().unwrap(), "main.nk").parse().unwrap();
+    let limited = || InterpreterBuilder::new(std::path::PathBuf::from(".")).max_steps(3).build();
+    assert!(limited().run_program(&parse(source)).is_ok());
+    assert!(limited().run_program(&with_lines).is_ok());
+
+    // Functions can't see their own name, so recursion passes the function along
+    let source = "fn down(self, n) {\n if n == 0 { return 0 }\n return self(self, n - 1)\n}\ndown(down, N)";
+    let depth = || InterpreterBuilder::new(std::path::PathBuf::from(".")).max_call_depth(10).build();
+    assert_eq!(depth().run_program(&parse(&source.replace('N', "5"))), Ok(Value::Integer(0)));
+    assert_eq!(depth().run_program(&parse(&source.replace('N', "50"))).unwrap_err(), "Maximum call depth of 10 exceeded");
+
+    let mut timed = InterpreterBuilder::new(std::path::PathBuf::from("."))
+        .timeout(std::time::Duration::from_millis(50))
+        .build();
+    assert!(timed.run_program(&parse("while True { }")).unwrap_err().starts_with("Time limit"));
+}
+
+#[test]
+fn test_builder_deterministic_mode() {
+    let mut interp = nikl::Interpreter::builder(std::path::PathBuf::from(".")).deterministic(true).build();
+    assert!(interp.run_program(&parse("import \"time\" as time")).is_err());
+    assert!(interp.run_program(&parse("input()")).is_err());
+    assert_eq!(interp.run_program(&parse("import \"crypto\" as crypto\nlen(crypto.sha256(\"x\"))")), Ok(Value::Integer(64)));
+}
+
+#[test]
+fn test_output_capture() {
+    use nikl::{CaptureBuffer, InterpreterBuilder};
+
+    let out = CaptureBuffer::new();
+    let err = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from("."))
+        .stdout(out.clone())
+        .stderr(err.clone())
+        .build();
+    let source = r#"
+        fn shout(text) {
+            print(text, "!", {"sep": ""})
+        }
+        shout("hi")
+        eprint("oops")
+        fn passes() { }
+        import "testing" as testing
+        testing.test("passes", passes)
+        testing.run()
+    "#;
+    interp.run_program(&parse(source)).unwrap();
+    assert_eq!(out.take(), "hi!\ntest passes ... ok\n\ntest result: ok. 1 passed; 0 failed\n");
+    assert_eq!(err.contents(), "oops\n");
+
+    // Replacing a writer later affects the interpreter and everything it runs
+    let later = CaptureBuffer::new();
+    interp.set_stdout(later.clone());
+    interp.run_program(&parse("print(1, 2)")).unwrap();
+    assert_eq!(later.contents(), "1 2\n");
+    assert_eq!(out.contents(), "");
+}
+
+#[test]
+fn test_input_sources() {
+    use nikl::{CaptureBuffer, InputSource, InterpreterBuilder};
+
+    let out = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from("."))
+        .stdin(InputSource::reader(std::io::Cursor::new("Ada\n42\n")))
+        .stdout(out.clone())
+        .build();
+    let source = "let name = input(\"name: \")\nlet age = int(input())\n[name, age, input()]";
+    assert_eq!(
+        interp.run_program(&parse(source)),
+        Ok(Value::from(vec![Value::from("Ada"), Value::from(42), Value::from("")]))
+    );
+    assert_eq!(out.contents(), "name: > > ");
+
+    let prompts = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = prompts.clone();
+    interp.set_stdin(InputSource::callback(move |prompt| {
+        seen.lock().unwrap().push(prompt.to_string());
+        Ok("yes".to_string())
+    }));
+    assert_eq!(interp.run_program(&parse("input(\"sure? \")")), Ok(Value::from("yes")));
+    assert_eq!(*prompts.lock().unwrap(), vec!["sure? ".to_string()]);
+
+    interp.set_stdin(InputSource::None);
+    assert!(interp.run_program(&parse("input()")).unwrap_err().contains("no input source"));
+}
+
+#[test]
+fn test_globals_from_rust() {
+    use std::collections::HashMap;
+
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    interp.set_global("config", HashMap::from([("retries", 3)]));
+    interp.set_global("name", "job");
+    interp.run_program(&parse("let attempts = config.retries + 1\nname = name + \"-1\"")).unwrap();
+
+    assert_eq!(interp.get_global("attempts"), Some(Value::Integer(4)));
+    assert_eq!(interp.get_global("name"), Some(Value::from("job-1")));
+    assert_eq!(interp.get_global("missing"), None);
+
+    // Seeding again replaces the previous value
+    interp.set_global("name", Value::Null);
+    assert_eq!(interp.get_global("name"), Some(Value::Null));
+}
+
+#[test]
+fn test_custom_module_resolver() {
+    use std::path::{Path, PathBuf};
+    use nikl::{ModuleResolver, ResolvedModule};
+
+    struct Memory;
+
+    impl ModuleResolver for Memory {
+        fn resolve(&self, base_path: &Path, path: &str) -> Result<ResolvedModule, String> {
+            let full = base_path.join(path);
+            let source = match full.to_str() {
+                Some("/app/main.nk") => "import \"lib/math.nk\" as math\nlet answer = math.double(21)",
+                Some("/app/lib/math.nk") => "fn double(n) { return n * 2 }",
+                _ => return Err(format!("No module '{}'", full.display())),
+            };
+            Ok(ResolvedModule {
+                id: full.to_string_lossy().to_string(),
+                source: source.to_string(),
+                base_path: full.parent().unwrap().to_path_buf(),
+            })
+        }
+    }
+
+    let mut interp = nikl::Interpreter::builder(PathBuf::from("/app")).resolver(Memory).build();
+    assert_eq!(interp.run_program(&parse("import \"main.nk\" as app\napp.answer")), Ok(Value::Integer(42)));
+    assert_eq!(interp.run_program(&parse("import \"other.nk\" as other")).unwrap_err(), "No module '/app/other.nk'");
+}
+
+#[test]
+fn test_embedded_module_bundle() {
+    use nikl::module_bundle;
+
+    let bundle = || module_bundle! {
+        "lib/shapes.nk" => include_str!("bundle/lib/shapes.nk"),
+        "util/num.nk" => include_str!("bundle/util/num.nk"),
+    };
+    let build = |bundle| nikl::Interpreter::builder(std::env::temp_dir()).resolver(bundle).build();
+
+    let mut interp = build(bundle());
+    assert_eq!(interp.run_program(&parse("import \"lib/shapes.nk\" as shapes\nshapes.area(3, 4)")), Ok(Value::Integer(12)));
+
+    // Files are still importable unless the bundle is exclusive
+    let dir = std::env::temp_dir().join(format!("nikl_bundle_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("local.nk"), "let x = 1").unwrap();
+    let import_local = format!("import \"nikl_bundle_{}/local.nk\" as local\nlocal.x", std::process::id());
+    assert_eq!(build(bundle()).run_program(&parse(&import_local)), Ok(Value::Integer(1)));
+    assert!(build(bundle().exclusive()).run_program(&parse(&import_local)).unwrap_err().contains("not in the bundle"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_eval_expression() {
+    use nikl::NiklError;
+
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    interp.run_program(&parse("let items = [3, 1, 2]\nfn total(xs) { return sum(xs) }")).unwrap();
+
+    assert_eq!(interp.eval("total(items) * 10"), Ok(Value::Integer(60)));
+    assert_eq!(interp.eval("sorted(items)").unwrap(), Value::from(vec![1, 2, 3]));
+    assert_eq!(interp.eval("items = []"), Ok(Value::Array(vec![])));
+    assert_eq!(interp.get_global("items"), Some(Value::Array(vec![])));
+
+    assert!(matches!(interp.eval("1 2"), Err(NiklError::Parse(_))));
+    assert!(matches!(interp.eval("let x = 1"), Err(NiklError::Parse(_))));
+    assert!(matches!(interp.eval("1 @ 2"), Err(NiklError::Lex(_))));
+    let err = interp.eval("1 / 0").unwrap_err();
+    assert_eq!(err.to_string(), "Runtime error: Division by zero");
+}
+
+#[test]
+fn test_snapshot_and_restore() {
+    let mut session = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    let source = r#"
+        import "regex" as re
+        let history = [1, (2, "two"), {"k": False}]
+        const rate = 1.5
+        fn scale(n) { return n * rate }
+        fn scale_all(xs) { return map(scale, xs) }
+    "#;
+    session.run_program(&parse(source)).unwrap();
+    let saved = session.snapshot().unwrap();
+
+    let mut resumed = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    resumed.restore(&saved).unwrap();
+    assert_eq!(resumed.get_global("history"), session.get_global("history"));
+    assert_eq!(resumed.eval("scale_all([2, 4])").unwrap(), Value::from(vec![3.0, 6.0]));
+    // Constants stay constant, modules have to be imported again
+    assert!(resumed.eval("rate = 2").is_err());
+    assert_eq!(resumed.get_global("re"), None);
+
+    assert!(resumed.restore(b"not a snapshot").unwrap_err().starts_with("Invalid snapshot"));
+}
+
+#[test]
+fn test_snapshot_writes_each_function_once() {
+    let mut session = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    let mut source = String::from("let step = 1\nfn f0(n) { return n }\n");
+    for i in 1..30 {
+        source.push_str(&format!("fn f{}(n) {{ return f{}(n + step) }}\n", i, i - 1));
+    }
+    // Closures keep the values from when the function was defined
+    source.push_str("step = 2\n");
+    session.run_program(&parse(&source)).unwrap();
+    assert_eq!(session.eval("f29(0)").unwrap(), Value::Integer(29));
+
+    let saved = session.snapshot().unwrap();
+    assert!(saved.len() < 64 * 1024, "snapshot is {} bytes", saved.len());
+
+    let mut resumed = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    resumed.restore(&saved).unwrap();
+    assert_eq!(resumed.eval("f29(0)").unwrap(), Value::Integer(29));
+    assert_eq!(resumed.get_global("step"), Some(Value::Integer(2)));
+}
+
+#[test]
+fn test_isolates_share_prelude() {
+    use std::sync::Arc;
+    use nikl::CaptureBuffer;
+
+    let mut setup = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    setup.run_program(&parse("import \"regex\" as re\nlet greeting = \"hi\"\nfn greet(name) { return greeting + \" \" + name }")).unwrap();
+    let prelude = Arc::new(setup.into_prelude());
+
+    let handles: Vec<_> = (0..4)
+        .map(|tenant| {
+            let prelude = prelude.clone();
+            std::thread::spawn(move || {
+                let out = CaptureBuffer::new();
+                let mut isolate = prelude.isolate();
+                isolate.set_stdout(out.clone());
+                isolate.set_global("tenant", tenant);
+                isolate.run_program(&parse("let count = tenant * 10\nprint(greet(str(tenant)))")).unwrap();
+                (isolate.get_global("count"), out.contents())
+            })
+        })
+        .collect();
+    for (tenant, handle) in handles.into_iter().enumerate() {
+        let (count, output) = handle.join().unwrap();
+        assert_eq!(count, Some(Value::Integer(tenant as i64 * 10)));
+        assert_eq!(output, format!("hi {}\n", tenant));
+    }
+
+    let mut isolate = prelude.isolate();
+    assert_eq!(isolate.get_global("count"), None);
+    assert_eq!(isolate.eval("re.is_match(\"h\", greeting)"), Ok(Value::Bool(true)));
+    assert!(isolate.eval("greeting = \"bye\"").unwrap_err().to_string().contains("shared prelude"));
+    // Shadowing a prelude variable only affects this isolate
+    isolate.run_program(&parse("let greeting = \"yo\"")).unwrap();
+    assert_eq!(isolate.eval("greeting"), Ok(Value::from("yo")));
+    assert_eq!(prelude.isolate().eval("greeting"), Ok(Value::from("hi")));
+}
+
+#[test]
+fn test_native_host_objects() {
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use nikl::interpreter::value::NativeObject;
+
+    #[derive(Debug)]
+    struct Counter {
+        step: i64,
+        total: AtomicI64,
+    }
+
+    impl NativeObject for Counter {
+        fn type_name(&self) -> &'static str {
+            "Counter"
+        }
+
+        fn call_method(&self, method: &str, args: Vec<Value>) -> Result<Value, String> {
+            match (method, args.as_slice()) {
+                ("bump", []) => Ok(Value::Integer(self.total.fetch_add(self.step, Ordering::SeqCst) + self.step)),
+                _ => Err(format!("Counter has no method '{}'", method)),
+            }
+        }
+
+        fn get_property(&self, name: &str) -> Option<Value> {
+            match name {
+                "step" => Some(Value::Integer(self.step)),
+                "total" => Some(Value::Integer(self.total.load(Ordering::SeqCst))),
+                _ => None,
+            }
+        }
+    }
+
+    let mut interp = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    interp.set_global("counter", Value::native(Counter { step: 5, total: AtomicI64::new(0) }));
+    let source = "let alias = counter\nalias.bump()\ncounter.bump()\n[type(counter), counter.step, counter.total, alias == counter]";
+    assert_eq!(
+        interp.run_program(&parse(source)),
+        Ok(Value::from(vec![Value::from("Counter"), Value::from(5), Value::from(10), Value::from(true)]))
+    );
+    assert_eq!(interp.eval("counter.missing").unwrap_err().to_string(), "Runtime error: Property 'missing' not found on Counter");
+}
+
+#[test]
+fn test_execution_hook_sees_lines_and_calls() {
+    use std::sync::{Arc, Mutex};
+    use nikl::interpreter::ExecutionHook;
+
+    #[derive(Default)]
+    struct Trace(Mutex<Vec<String>>);
+
+    impl ExecutionHook for Trace {
+        fn on_line(&self, interp: &mut nikl::Interpreter, file: &str, line: usize) -> Result<(), String> {
+            self.0.lock().unwrap().push(format!("{}:{} depth {}", file, line, interp.call_depth()));
+            Ok(())
+        }
+        fn on_call(&self, function: &str) {
+            self.0.lock().unwrap().push(format!("call {}", function));
+        }
+        fn on_return(&self, function: &str) {
+            self.0.lock().unwrap().push(format!("return {}", function));
+        }
+    }
+
+    let trace = Arc::new(Trace::default());
+    let mut interp = nikl::Interpreter::builder(std::path::PathBuf::from(".")).hook(trace.clone()).build();
+    let tokens = nikl::lexer::Lexer::new("fn fail(x) {\n    return x + missing\n}\nfail(1)").tokenize().unwrap();
+    let program = nikl::parser::Parser::with_lines(tokens, "main.nk").parse().unwrap();
+    assert!(interp.run(&program).is_err());
+    assert_eq!(*trace.0.lock().unwrap(), vec![
+        "main.nk:1 depth 0", "main.nk:4 depth 0", "call fail", "main.nk:2 depth 1", "return fail",
+    ]);
+
+    // Programs parsed without lines only report calls
+    trace.0.lock().unwrap().clear();
+    interp.run(&parse("fn id(x) { return x }\nid(2)")).unwrap();
+    assert_eq!(*trace.0.lock().unwrap(), vec!["call id", "return id"]);
+    assert_eq!(interp.variables().keys().collect::<Vec<_>>(), vec!["fail", "id"]);
+}
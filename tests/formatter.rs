@@ -0,0 +1,46 @@
+use nikl::formatter::format_source;
+
+
+#[test]
+fn test_format_normalizes_spacing_and_indentation() {
+    let source = "fn add(a:Int,b : Int)->Int{\n  let x=a+b*-2\n\tif x>1{return x}elif not x { return -x }\nreturn (1,2)\n}\n";
+    let expected = "fn add(a: Int, b: Int) -> Int {\n    let x = a + b * -2\n    if x > 1 { return x } elif not x { return -x }\n    return (1, 2)\n}\n";
+    assert_eq!(format_source(source).unwrap(), expected);
+}
+
+#[test]
+fn test_format_keeps_comments_line_breaks_and_literals() {
+    let source = r#"// header
+
+
+import "os"   as os   // trailing
+let m={"a":1,"b":[1,2.50,-3]}
+let total = sum(1,
+2)
+let t = thread.spawn(work, (1, 2))
+"#;
+    let expected = r#"// header
+
+import "os" as os // trailing
+let m = {"a": 1, "b": [1, 2.50, -3]}
+let total = sum(1,
+    2)
+let t = thread.spawn(work, (1, 2))
+"#;
+    assert_eq!(format_source(source).unwrap(), expected);
+}
+
+#[test]
+fn test_format_is_idempotent() {
+    let source = "while(x<3){x=x+1}\nfor k,v in m.items() {print(k)}\nlet s = \"two\nlines\"\n\n\n";
+    let formatted = format_source(source).unwrap();
+    assert_eq!(formatted, "while (x < 3) { x = x + 1 }\nfor k, v in m.items() { print(k) }\nlet s = \"two\nlines\"\n");
+    assert_eq!(format_source(&formatted).unwrap(), formatted);
+    assert_eq!(format_source("").unwrap(), "");
+}
+
+#[test]
+fn test_format_rejects_invalid_source() {
+    assert!(format_source("let x = (").unwrap_err().starts_with("Parse error"));
+    assert!(format_source("let x = 1 @ 2").unwrap_err().starts_with("Unexpected character '@'"));
+}
@@ -367,18 +367,23 @@ fn test_bool() {
 
 #[test]
 fn test_exit() {
-    // Since exit terminates the process, you might want to mock or test the error case only.
-    // let input = r#"
-    //     exit(0)
-    // "#;
-    // This will terminate the test runner if actually called,
-    // so you may want to skip or mock exit in tests.
-    // Here we just check for argument errors:
+    // exit() stops the script and records the code instead of ending the test runner
+    let mut interpreter = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("fn stop() { exit(0) }\nstop()\nprint(\"unreachable\")").tokenize().unwrap()).parse().unwrap();
+    assert_eq!(interpreter.run(&stmts).unwrap_err(), "exit(0)");
+    assert_eq!(interpreter.exit_code(), Some(0));
+
     let bad_input = r#"
         exit("not an int")
     "#;
     let bad_result = run_script(bad_input);
     assert!(bad_result.is_err());
+
+    // A code that doesn't fit is an error, not a different code
+    let mut interpreter = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new("exit(4294967296)").tokenize().unwrap()).parse().unwrap();
+    assert_eq!(interpreter.run(&stmts).unwrap_err(), "exit() code must be between -2147483648 and 2147483647, got 4294967296");
+    assert_eq!(interpreter.exit_code(), None);
 }
 
 #[test]
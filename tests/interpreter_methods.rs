@@ -0,0 +1,196 @@
+use nikl::run_script;
+
+
+#[test]
+fn test_string_case_and_trim_methods() {
+    let input = r#"
+        let s = "  Hello World  "
+        if s.trim() != "Hello World" { fail() }
+        if s.trim().upper() != "HELLO WORLD" { fail() }
+        if s.trim().lower() != "hello world" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_string_split_and_join() {
+    let input = r#"
+        let parts = "a,b,c".split(",")
+        if len(parts) != 3 { fail() }
+        if "-".join(parts) != "a-b-c" { fail() }
+        if len("one  two three".split()) != 3 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_string_search_methods() {
+    let input = r#"
+        let s = "héllo world"
+        if not s.starts_with("hé") { fail() }
+        if not s.contains("world") { fail() }
+        if s.find("world") != 6 { fail() }
+        if s.find("xyz") != -1 { fail() }
+        if s.replace("world", "nikl") != "héllo nikl" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_string_unknown_method_errors() {
+    let input = r#"
+        let s = "abc"
+        s.explode()
+    "#;
+    assert!(run_script(input).is_err());
+}
+
+#[test]
+fn test_array_push_pop_mutate_in_place() {
+    let input = r#"
+        let arr = [1, 2]
+        arr.push(3)
+        if len(arr) != 3 { fail() }
+        let last = arr.pop()
+        if last != 3 { fail() }
+        if len(arr) != 2 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_array_insert_remove_and_search() {
+    let input = r#"
+        let arr = ["a", "c"]
+        arr.insert(1, "b")
+        if arr.join(",") != "a,b,c" { fail() }
+        if arr.index_of("c") != 2 { fail() }
+        if not arr.contains("a") { fail() }
+        if arr.remove(0) != "a" { fail() }
+        if arr.join(",") != "b,c" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_array_sort_reverse_slice() {
+    let input = r#"
+        let arr = [3, 1.5, 2]
+        arr.sort()
+        if arr.join(" ") != "1.5 2 3" { fail() }
+        arr.reverse()
+        if arr.join(" ") != "3 2 1.5" { fail() }
+        if arr.slice(1).join(" ") != "2 1.5" { fail() }
+        if arr.slice(0, -1).join(" ") != "3 2" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_array_sort_mixed_types_errors() {
+    let input = r#"
+        let arr = [1, "two"]
+        arr.sort()
+    "#;
+    assert!(run_script(input).is_err());
+}
+
+#[test]
+fn test_array_mutation_on_constant_errors() {
+    let input = r#"
+        const arr = [1]
+        arr.push(2)
+    "#;
+    assert!(run_script(input).is_err());
+}
+
+#[test]
+fn test_mutation_through_map_entries() {
+    let input = r#"
+        let m = {"xs": [1], "inner": {"ys": []}}
+        m.get("xs").push(2)
+        m.xs.push(3)
+        m.get("inner").get("ys").push("a")
+        m.inner.ys.push("b")
+        m.get("missing", []).push(1)
+        [m.xs, m.inner.ys, m.has("missing")]
+    "#;
+    assert_eq!(nikl::eval_script(input).unwrap().to_string(), r#"[[1, 2, 3], ["a", "b"], False]"#);
+}
+
+#[test]
+fn test_method_receiver_is_evaluated_once() {
+    use nikl::{CaptureBuffer, InterpreterBuilder};
+
+    let out = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from(".")).stdout(out.clone()).build();
+    let source = r#"
+        fn make() {
+            print("make")
+            return {"xs": []}
+        }
+        fn key() {
+            print("key")
+            return "xs"
+        }
+        let m = {"xs": []}
+        make().xs.push(1)
+        m.get(key()).push(1)
+        m.xs
+    "#;
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    assert_eq!(interp.run_program(&stmts).unwrap().to_string(), "[1]");
+    assert_eq!(out.contents(), "make\nkey\n");
+}
+
+#[test]
+fn test_hashmap_get_set_has_remove() {
+    let input = r#"
+        let m = {"a": 1}
+        m.set("b", 2)
+        m.set("a", 10)
+        if m.len() != 2 { fail() }
+        if m.get("a") != 10 { fail() }
+        if m.get("zzz", 0) != 0 { fail() }
+        if not m.has("b") { fail() }
+        if m.remove("b") != 2 { fail() }
+        if m.has("b") { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_hashmap_keys_values_items_merge() {
+    let input = r#"
+        let m = {"x": 1, "y": 2}
+        m.merge({"y": 3, "z": 4})
+        if m.keys().join(",") != "x,y,z" { fail() }
+        if m.values().join(",") != "1,3,4" { fail() }
+        if len(m.items()) != 3 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_hashmap_property_wins_over_method() {
+    let input = r#"
+        fn custom() {
+            return "custom"
+        }
+        let m = {"keys": custom}
+        if m.keys() != "custom" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_hashmap_data_keys_keep_methods() {
+    let input = r#"
+        let m = {"keys": 1, "items": [2], "len": "three"}
+        if m.keys().join(",") != "keys,items,len" { fail() }
+        if len(m.items()) != 3 { fail() }
+        if m.len() != 3 { fail() }
+        if m.get("keys") != 1 { fail() }
+    "#;
+    assert_eq!(run_script(input), Ok(()));
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "serde")]
+
+use nikl::interpreter::value::Value;
+
+
+fn s(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+#[test]
+fn test_value_from_json() {
+    let value: Value = serde_json::from_str(r#"{"name": "nikl", "tags": [1, 2.5, null], "ok": true}"#).unwrap();
+    assert_eq!(
+        value,
+        Value::HashMap(vec![
+            (s("name"), s("nikl")),
+            (s("tags"), Value::Array(vec![Value::Integer(1), Value::Float(2.5), Value::Null])),
+            (s("ok"), Value::Bool(true)),
+        ])
+    );
+}
+
+#[test]
+fn test_value_round_trips_through_toml() {
+    let value: Value = toml::from_str("title = \"x\"\n[server]\nport = 8080\n").unwrap();
+    let text = toml::to_string(&value).unwrap();
+    assert_eq!(toml::from_str::<Value>(&text).unwrap(), value);
+}
+
+#[test]
+fn test_script_result_to_json() {
+    let value = nikl::eval_script("let t = (1, \"a\")\n{\"pair\": t, \"score\": 0.5}").unwrap();
+    assert_eq!(serde_json::to_string(&value).unwrap(), r#"{"pair":[1,"a"],"score":0.5}"#);
+
+    let func = nikl::eval_script("fn f() { return 1 }\nf").unwrap();
+    assert!(serde_json::to_string(&func).unwrap_err().to_string().contains("Cannot serialize Function"));
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "native")]
+
+use std::path::PathBuf;
+
+use nikl::lexer::Lexer;
+use nikl::parser::{Parser, Stmt};
+use nikl::{Interpreter, SharedInterpreter};
+
+
+fn parse_input(source: &str) -> Vec<Stmt> {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    Parser::new(tokens).parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_interpreter_driven_from_tokio_tasks() {
+    let shared: SharedInterpreter = Interpreter::new(PathBuf::from(".")).into_shared();
+    shared.lock().unwrap().run(&parse_input("let counter = 0")).unwrap();
+
+    let mut handles = Vec::new();
+    for _ in 0..4 {
+        let interp = shared.clone();
+        handles.push(tokio::spawn(async move {
+            let stmts = parse_input("counter = counter + 1");
+            interp.lock().unwrap().run(&stmts).map(|_| ())
+        }));
+    }
+    for handle in handles {
+        assert!(handle.await.unwrap().is_ok());
+    }
+
+    let check = parse_input(r#"
+        if counter != 4 {
+            undefined_function()
+        }
+    "#);
+    assert!(shared.lock().unwrap().run(&check).is_ok());
+}
+
+#[test]
+fn test_interpreter_moved_to_thread() {
+    let mut interp = Interpreter::new(PathBuf::from("."));
+    let handle = std::thread::spawn(move || {
+        interp.run(&parse_input("let x = 1 + 2")).map(|_| ())
+    });
+    assert!(handle.join().unwrap().is_ok());
+}
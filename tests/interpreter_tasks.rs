@@ -0,0 +1,31 @@
+use nikl::run_script;
+
+#[test]
+fn test_spawn_and_wait() {
+    let input = r#"
+        fn add(a, b) {
+            return a + b
+        }
+        let task = spawn add(1, 2)
+        if wait task != 3 { fail() }
+        if task.wait() != 3 { fail() }
+        if not task.is_done() { fail() }
+
+        let results = wait [spawn add(1, 1), spawn add(2, 2), 7]
+        if results.join(",") != "2,4,7" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_spawned_errors_surface_on_wait() {
+    let input = r#"
+        fn broken() {
+            return missing_variable
+        }
+        let task = spawn broken()
+        wait task
+    "#;
+    assert!(run_script(input).is_err());
+    assert!(run_script("let x = spawn 1").is_err());
+}
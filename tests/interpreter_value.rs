@@ -0,0 +1,98 @@
+use nikl::interpreter::value::Value;
+use nikl::run_script;
+
+
+fn s(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+#[test]
+fn test_repr_quotes_and_escapes_strings() {
+    assert_eq!(s("plain").repr(), r#""plain""#);
+    assert_eq!(s("say \"hi\"\n").repr(), r#""say \"hi\"\n""#);
+    assert_eq!(s(r"C:\dir").repr(), r#""C:\\dir""#);
+    assert_eq!(Value::Float(1.0).repr(), "1.0");
+    assert_eq!(Value::Integer(7).repr(), "7");
+}
+
+#[test]
+fn test_collection_display_uses_repr_for_elements() {
+    let ambiguous = Value::Array(vec![s("a, b"), s("c")]);
+    let split = Value::Array(vec![s("a"), s("b"), s("c")]);
+    assert_eq!(ambiguous.to_string(), r#"["a, b", "c"]"#);
+    assert_eq!(split.to_string(), r#"["a", "b", "c"]"#);
+
+    let map = Value::HashMap(vec![(s("k"), Value::Tuple(vec![Value::Integer(1), Value::Float(2.0)]))]);
+    assert_eq!(map.to_string(), r#"{"k": (1, 2.0)}"#);
+}
+
+#[test]
+fn test_repr_builtin() {
+    let input = r#"
+        if len(repr("ab")) != 4 { fail() }
+        if repr(1) != "1" { fail() }
+        if str([1, 2]) != "[1, 2]" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_stable_hash_is_deterministic() {
+    let tuple = Value::Tuple(vec![s("a"), Value::Integer(1), Value::Bool(true)]);
+    assert_eq!(tuple.stable_hash(), tuple.clone().stable_hash());
+    assert_ne!(s("ab").stable_hash(), s("ba").stable_hash());
+    // Fixed FNV-1a output, must never change between releases
+    assert_eq!(Value::Null.stable_hash(), Some(0xaf63_b94c_8601_b113));
+}
+
+#[test]
+fn test_unhashable_values() {
+    assert!(Value::Array(vec![]).stable_hash().is_none());
+    assert!(Value::Float(1.5).stable_hash().is_none());
+    assert!(Value::Tuple(vec![Value::HashMap(vec![])]).stable_hash().is_none());
+}
+
+#[test]
+fn test_hash_builtin() {
+    let input = r#"
+        if hash("abc") != hash("abc") { fail() }
+        if hash((1, "x")) == hash((1, "y")) { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("hash([1, 2])").is_err());
+}
+
+#[test]
+fn test_from_rust_values() {
+    use std::collections::HashMap;
+
+    assert_eq!(Value::from(7), Value::Integer(7));
+    assert_eq!(Value::from(2.5), Value::Float(2.5));
+    assert_eq!(Value::from("hi"), s("hi"));
+    assert_eq!(Value::from(None::<i64>), Value::Null);
+    assert_eq!(Value::from(vec![1, 2]), Value::Array(vec![Value::Integer(1), Value::Integer(2)]));
+    assert_eq!(Value::from(("a", true)), Value::Tuple(vec![s("a"), Value::Bool(true)]));
+    assert_eq!(
+        Value::from(HashMap::from([("b", 2), ("a", 1)])),
+        Value::HashMap(vec![(s("a"), Value::Integer(1)), (s("b"), Value::Integer(2))])
+    );
+}
+
+#[test]
+fn test_extracting_rust_values() {
+    use std::collections::HashMap;
+    use nikl::FromNikl;
+
+    assert_eq!(i64::try_from(Value::Integer(3)), Ok(3));
+    assert_eq!(f64::from_nikl(Value::Integer(3)), Ok(3.0));
+    assert_eq!(Value::from(vec!["x", "y"]).extract::<Vec<String>>(), Ok(vec!["x".to_string(), "y".to_string()]));
+    assert_eq!(Value::Null.extract::<Option<bool>>(), Ok(None));
+    assert_eq!(Value::from(("k", 1)).extract::<(String, u8)>(), Ok(("k".to_string(), 1)));
+
+    let map: HashMap<String, Vec<i64>> = Value::HashMap(vec![(s("n"), Value::from(vec![1, 2]))]).extract().unwrap();
+    assert_eq!(map["n"], vec![1, 2]);
+
+    assert_eq!(String::try_from(Value::Integer(1)), Err("Expected String, got Integer".to_string()));
+    assert!(Value::Integer(300).extract::<u8>().is_err());
+    assert!(Value::from(vec![Value::Integer(1), s("two")]).extract::<Vec<i64>>().is_err());
+}
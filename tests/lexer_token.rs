@@ -51,3 +51,14 @@ fn test_lexer() {
         println!("{:?}", token);
     }
 }
+
+#[test]
+fn test_shebang_line_is_skipped() {
+    use nikl::lexer::TokenKind;
+
+    let (tokens, comments) = Lexer::new("#!/usr/bin/env nikl\nlet x = 1\n").tokenize_with_comments().unwrap();
+    assert_eq!(comments[0].text, "#!/usr/bin/env nikl");
+    assert_eq!((&tokens[0].kind, tokens[0].line, tokens[0].column), (&TokenKind::Let, 2, 1));
+    // Only the first line can be one
+    assert!(Lexer::new("let x = 1\n#!/usr/bin/env nikl\n").tokenize().is_err());
+}
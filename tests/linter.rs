@@ -0,0 +1,99 @@
+use nikl::linter::{lint_source, LintConfig, Rule};
+
+
+fn messages(source: &str, config: &LintConfig) -> Vec<String> {
+    lint_source(source, config).unwrap().iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_lint_reports_each_rule() {
+    let source = r#"
+fn area(len, _scale) {
+    let unused = 2
+    let used = len * len
+    if used == 0.5 { print("half") }
+    return used
+    print("done")
+}
+while True { break }
+if 1 > 2 { print("never") }
+"#;
+    assert_eq!(messages(source, &LintConfig::default()), vec![
+        "2: [shadowed-builtin] in function 'area': parameter 'len' shadows the builtin of the same name",
+        "3: [unused-variable] in function 'area': variable 'unused' is never used",
+        "5: [float-equality] in function 'area': '==' against a float is unreliable, compare the difference to a tolerance instead",
+        "7: [unreachable-code] in function 'area': code after 'return' is never run",
+        "9: [constant-condition] 'while' condition is always the same",
+        "10: [constant-condition] 'if' condition is always the same",
+    ]);
+}
+
+#[test]
+fn test_lint_counts_reads_from_nested_functions() {
+    let source = r#"
+fn outer() {
+    let captured = 1
+    fn inner() { return captured }
+    let x = 0
+    x = 1
+    return inner
+}
+"#;
+    assert_eq!(messages(source, &LintConfig::default()), vec![
+        "5: [unused-variable] in function 'outer': variable 'x' is never used",
+    ]);
+}
+
+#[test]
+fn test_lint_unused_variables_at_the_top_level() {
+    let source = r#"let top = 1
+let shared = 2
+let _ignored = 3
+fn read() { return shared }
+for item in [1, 2] {
+    let doubled = item * 2
+}
+if read() > 1 {
+    let kept = 4
+    print(kept)
+}
+while False {
+    let inside = 5
+}
+"#;
+    let config = LintConfig::default().disable(Rule::ConstantCondition);
+    assert_eq!(messages(source, &config), vec![
+        "1: [unused-variable] variable 'top' is never used",
+        "6: [unused-variable] variable 'doubled' is never used",
+        "13: [unused-variable] variable 'inside' is never used",
+    ]);
+}
+
+#[test]
+fn test_lint_config_disables_rules() {
+    let source = "let print = 1\nif True { print(print) }";
+    let config = LintConfig::default().disable(Rule::ConstantCondition);
+    assert_eq!(messages(source, &config), vec!["1: [shadowed-builtin] variable 'print' shadows the builtin of the same name"]);
+    assert!(config.clone().enable(Rule::ConstantCondition).is_enabled(Rule::ConstantCondition));
+
+    let text = r#"{
+    "name": "demo", // package name
+    "homepage": "https://example.com",
+    "lint": { "shadowed-builtin": false, "constant-condition": true }
+}"#;
+    let config = LintConfig::from_package_config(text).unwrap();
+    assert!(!config.is_enabled(Rule::ShadowedBuiltin));
+    assert!(config.is_enabled(Rule::ConstantCondition));
+    assert_eq!(LintConfig::from_package_config("{}").unwrap(), LintConfig::default());
+
+    let err = LintConfig::from_package_config(r#"{"lint": {"no-such-rule": false}}"#).unwrap_err();
+    assert_eq!(err, "Unknown lint rule 'no-such-rule'");
+}
+
+#[test]
+fn test_lint_rule_names_round_trip_and_errors_pass_through() {
+    for rule in Rule::ALL {
+        assert_eq!(Rule::from_name(rule.name()), Some(rule));
+    }
+    assert!(lint_source("let = 1", &LintConfig::default()).is_err());
+}
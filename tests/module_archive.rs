@@ -0,0 +1,47 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+fn archive_round_trip(name: &str, create: &str) {
+    let dir = std::env::temp_dir().join(format!("nikl_test_archive_{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    let input = format!(r#"
+        import "os" as os
+        import "archive" as archive
+        let root = "{}"
+        os.make_dir(root + "/src/nested")
+        os.write_file(root + "/src/main.nk", "println(1)")
+        os.write_file(root + "/src/nested/util.nk", "let x = 2")
+
+        let out = root + "/{}"
+        archive.{}(root + "/src", out)
+        let paths = []
+        for entry in archive.list(out) {{
+            if not entry.get("is_dir") {{
+                paths.push(entry.get("path") + "=" + str(entry.get("size")))
+            }}
+        }}
+        if paths.join(",") != "main.nk=10,nested/util.nk=9" {{ fail() }}
+
+        archive.extract(out, root + "/dest")
+        if os.read_file(root + "/dest/nested/util.nk") != "let x = 2" {{ fail() }}
+        os.remove_dir(root)
+    "#, dir.display(), name, create);
+    assert!(run_script(&input).is_ok());
+}
+
+#[test]
+fn test_tar_gz_round_trip() {
+    archive_round_trip("pkg.tar.gz", "create_tar_gz");
+}
+
+#[test]
+fn test_zip_round_trip() {
+    archive_round_trip("pkg.zip", "create_zip");
+}
+
+#[test]
+fn test_unknown_format() {
+    assert!(run_script("import \"archive\" as a\na.list(\"file.rar\")").is_err());
+    assert!(run_script("import \"archive\" as a\na.extract(\"missing.tar\", \"out\")").is_err());
+}
@@ -0,0 +1,78 @@
+use nikl::run_script;
+
+const SPEC: &str = r#"
+    import "argparse" as argparse
+    let spec = {
+        "prog": "greet",
+        "description": "Greets people",
+        "args": [
+            {"name": "who", "help": "Person to greet"},
+            {"name": "--times", "short": "-t", "type": "int", "default": 1},
+            {"name": "--loud", "type": "bool", "help": "Shout"},
+            {"name": "--tag", "multiple": True},
+            {"name": "--ratio", "type": "float"}
+        ]
+    }
+"#;
+
+#[test]
+fn test_argparse_parses_options_and_positionals() {
+    let input = format!(r#"{}
+        let opts = argparse.parse(spec, ["--loud", "world", "-t", "3", "--tag=a", "--tag", "b", "--ratio", "0.5"])
+        if opts.get("who") != "world" {{ fail() }}
+        if opts.get("times") != 3 {{ fail() }}
+        if not opts.get("loud") {{ fail() }}
+        if opts.get("tag").join(",") != "a,b" {{ fail() }}
+        if opts.get("ratio") != 0.5 {{ fail() }}
+
+        let defaults = argparse.parse(spec, ["you"])
+        if defaults.get("times") != 1 {{ fail() }}
+        if defaults.get("loud") {{ fail() }}
+        if len(defaults.get("tag")) != 0 {{ fail() }}
+        if type(defaults.get("ratio")) != "None" {{ fail() }}
+
+        if argparse.parse(spec, ["--", "--loud"]).get("who") != "--loud" {{ fail() }}
+        if argparse.parse(spec).get("who") != "never" {{ fail() }}
+    "#, SPEC);
+    // No script arguments, so the last parse fails on the missing positional
+    let err = run_script(&input).unwrap_err();
+    assert!(err.contains("the following argument is required: who"), "{}", err);
+    assert!(err.starts_with("usage: greet [--times TIMES] [--loud] [--tag TAG] [--ratio RATIO] who"), "{}", err);
+}
+
+#[test]
+fn test_argparse_errors() {
+    for argv in [r#"["x", "--times", "many"]"#, r#"["x", "--bogus"]"#, r#"["x", "y"]"#, r#"["x", "--times"]"#] {
+        let input = format!("{}\nargparse.parse(spec, {})", SPEC, argv);
+        assert!(run_script(&input).is_err(), "{}", argv);
+    }
+}
+
+#[test]
+fn test_argparse_help_text() {
+    let input = format!(r#"{}
+        let text = argparse.help(spec)
+        if not text.contains("Greets people") {{ fail() }}
+        if not text.contains("-t, --times TIMES") {{ fail() }}
+        if not text.contains("(default: 1)") {{ fail() }}
+    "#, SPEC);
+    assert!(run_script(&input).is_ok());
+}
+
+#[test]
+fn test_argparse_help_requests_exit_instead_of_exiting() {
+    use nikl::{CaptureBuffer, InterpreterBuilder};
+
+    let out = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from("."))
+        .argv(vec!["greet.nk".to_string(), "--help".to_string()])
+        .stdout(out.clone())
+        .build();
+    let source = format!("{}\nargparse.parse(spec)\nprint(\"after\")", SPEC);
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new(&source).tokenize().unwrap()).parse().unwrap();
+    assert_eq!(interp.run(&stmts).unwrap_err(), "exit(0)");
+    assert_eq!(interp.exit_code(), Some(0));
+    let printed = out.contents();
+    assert!(printed.starts_with("usage: greet"), "{}", printed);
+    assert!(!printed.contains("after"), "{}", printed);
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_sleeps_run_concurrently() {
+    let input = r#"
+        import "async" as aio
+        import "time" as time
+        let start = time.monotonic()
+        wait [aio.sleep(0.3), aio.sleep(0.3), aio.sleep(0.3)]
+        let elapsed = time.monotonic() - start
+        if elapsed < 0.3 { fail() }
+        if elapsed > 0.8 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_async_files() {
+    let path = std::env::temp_dir().join("nikl_test_async_file.txt");
+    let input = format!(r#"
+        import "async" as aio
+        let path = "{}"
+        wait aio.write_file(path, "hello")
+        if wait aio.read_file(path) != "hello" {{ fail() }}
+    "#, path.display());
+    assert!(run_script(&input).is_ok());
+    let _ = std::fs::remove_file(path);
+
+    assert!(run_script("import \"async\" as aio\nwait aio.read_file(\"/no/such/file\")").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_async_run() {
+    let input = r#"
+        import "async" as aio
+        let result = wait aio.run("cat", [], {"stdin": "piped"})
+        if result.get("code") != 0 { fail() }
+        if result.get("stdout") != "piped" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+/// Answers each connection on a local port with `reply(request)`, for `count` connections
+fn local_server(count: usize, reply: fn(&str) -> String) -> u16 {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(count) {
+            let mut stream = stream.unwrap();
+            // The request may arrive in pieces, an HTTP body is complete once Content-Length bytes follow the headers
+            let mut request = Vec::new();
+            let mut chunk = [0; 4096];
+            loop {
+                let n = stream.read(&mut chunk).unwrap();
+                request.extend_from_slice(&chunk[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                let complete = match text.split_once("\r\n\r\n") {
+                    Some((head, body)) => {
+                        let length = head.lines().find_map(|line| line.to_lowercase().strip_prefix("content-length: ").map(|n| n.parse().unwrap()));
+                        body.len() >= length.unwrap_or(0)
+                    }
+                    None => !text.starts_with("GET") && !text.starts_with("POST"),
+                };
+                if n == 0 || complete {
+                    break;
+                }
+            }
+            let response = reply(&String::from_utf8_lossy(&request));
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    port
+}
+
+#[test]
+fn test_async_http() {
+    let port = local_server(2, |request| {
+        let (status, body) = if request.starts_with("POST /echo ") && request.ends_with("\r\n\r\nping") {
+            ("200 OK", "pong")
+        } else {
+            ("404 Not Found", "missing")
+        };
+        format!("HTTP/1.1 {}\r\nX-Test: 1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body)
+    });
+    let input = format!(r#"
+        import "async" as aio
+        let url = "http://127.0.0.1:{}"
+        let responses = wait [aio.http("post", url + "/echo", {{"body": "ping", "headers": {{"X-Client": "nikl"}}}}), aio.http("GET", url + "/nope")]
+        let found = True
+        for response in responses {{
+            if response.get("headers").get("x-test") != "1" {{ fail() }}
+            if found {{
+                if response.get("status") != 200 {{ fail() }}
+                if response.get("body") != "pong" {{ fail() }}
+            }} else {{
+                if response.get("status") != 404 {{ fail() }}
+            }}
+            found = False
+        }}
+    "#, port);
+    assert_eq!(nikl::run_script(&input), Ok(()));
+
+    assert!(run_script("import \"async\" as aio\nwait aio.http(\"GET\", \"http://127.0.0.1:1/\", {\"timeout\": 1})").is_err());
+    assert!(run_script("import \"async\" as aio\naio.http(\"GET\")").is_err());
+}
+
+#[test]
+fn test_async_tcp() {
+    let port = local_server(1, |request| request.to_uppercase());
+    let input = format!(r#"
+        import "async" as aio
+        if wait aio.tcp("127.0.0.1", {}, "hello") != "HELLO" {{ fail() }}
+    "#, port);
+    assert_eq!(run_script(&input), Ok(()));
+    assert!(run_script("import \"async\" as aio\naio.tcp(\"127.0.0.1\", 70000, \"x\")").is_err());
+}
+
+#[test]
+fn test_async_rejects_durations_too_large() {
+    let err = run_script("import \"async\" as aio\naio.sleep(100000000000000000000.0)").unwrap_err();
+    assert!(err.contains("sleep duration must be"), "{}", err);
+    let err = run_script("import \"async\" as aio\naio.tcp(\"127.0.0.1\", 1, \"x\", 100000000000000000000.0)").unwrap_err();
+    assert!(err.contains("async.tcp timeout is too large"), "{}", err);
+    assert!(run_script("import \"async\" as aio\naio.sleep(-1)").is_err());
+}
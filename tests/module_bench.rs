@@ -0,0 +1,51 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_bench_time() {
+    let input = r#"
+        import "bench" as bench
+        fn work() {
+            let total = 0
+            for i in [1, 2, 3, 4, 5] {
+                total = total + i
+            }
+            return total
+        }
+        let timing = bench.time(work, 20)
+        if timing.get("iterations") != 20 { fail() }
+        if timing.get("min") > timing.get("mean") { fail() }
+        if timing.get("mean") > timing.get("max") { fail() }
+        if timing.get("total") <= 0.0 { fail() }
+        if bench.time(work).get("iterations") != 100 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_bench_compare() {
+    let input = r#"
+        import "bench" as bench
+        fn quick() {
+            return 1
+        }
+        fn slow() {
+            let items = []
+            for i in [1, 2, 3, 4, 5, 6, 7, 8, 9, 10] {
+                items.push(str(i))
+            }
+            return items.join(",")
+        }
+        let results = bench.compare([("slow", slow), quick], 50)
+        if len(results) != 2 { fail() }
+        let names = []
+        for result in results {
+            names.push(result.get("name"))
+        }
+        if names.join(",") != "quick,slow" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("import \"bench\" as bench\nbench.compare([])").is_err());
+    assert!(run_script("import \"bench\" as bench\nbench.time(print, 0)").is_err());
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_round_trips() {
+    let input = r#"
+        import "compress" as compress
+        let text = "hello hello hello hello hello"
+        if compress.gunzip(compress.gzip(text), {"text": True}) != text { fail() }
+        if compress.unzlib(compress.zlib(text, 9), {"text": True}) != text { fail() }
+        if compress.unzstd(compress.zstd(text, 19), {"text": True}) != text { fail() }
+
+        let raw = compress.gunzip(compress.gzip([0, 255, 7]))
+        if raw.join(",") != "0,255,7" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_gzip_header_and_errors() {
+    let input = r#"
+        import "compress" as compress
+        let data = compress.gzip("x")
+        for byte in data {
+            if byte != 31 { fail() }
+            break
+        }
+    "#;
+    assert!(run_script(input).is_ok());
+
+    assert!(run_script("import \"compress\" as c\nc.gunzip(\"not gzip\")").is_err());
+    assert!(run_script("import \"compress\" as c\nc.gzip(\"x\", 42)").is_err());
+}
@@ -0,0 +1,25 @@
+use nikl::run_script;
+
+#[test]
+fn test_crypto_digests() {
+    let input = r#"
+        import "crypto" as crypto
+        if crypto.sha256("abc") != "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad" { fail() }
+        if crypto.sha1("abc") != "a9993e364706816aba3e25717850c26c9cd0d89d" { fail() }
+        if crypto.md5("abc") != "900150983cd24fb0d6963f7d28e17f72" { fail() }
+        if crypto.sha256([0, 255]) != "06eb7d6a69ee19e5fbdf749018d3d2abfa04bcbd1365db312eb86dc7169389b8" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_crypto_hmac_sha256() {
+    let input = r#"
+        import "crypto" as crypto
+        let sig = crypto.hmac_sha256("key", "The quick brown fox jumps over the lazy dog")
+        if sig != "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("import \"crypto\" as crypto\ncrypto.hmac_sha256(\"key\")").is_err());
+    assert!(run_script("import \"crypto\" as crypto\ncrypto.sha256(42)").is_err());
+}
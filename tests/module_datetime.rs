@@ -0,0 +1,51 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_format_and_parse() {
+    let input = r#"
+        import "datetime" as datetime
+        let ts = 1714566600
+        if datetime.format(ts, "%Y-%m-%d %H:%M") != "2024-05-01 12:30" { fail() }
+        if datetime.format(ts, "%H:%M", "+05:30") != "18:00" { fail() }
+        if datetime.format(ts, "%H:%M", -3600) != "11:30" { fail() }
+
+        if datetime.parse("2024-05-01 12:30", "%Y-%m-%d %H:%M") != 1714566600.0 { fail() }
+        if datetime.parse("2024-05-01 18:00", "%Y-%m-%d %H:%M", "+05:30") != 1714566600.0 { fail() }
+        if datetime.parse("2024-05-01 18:00 +0530", "%Y-%m-%d %H:%M %z") != 1714566600.0 { fail() }
+        if datetime.parse("2024-05-01", "%Y-%m-%d") != 1714521600.0 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_iso_and_components() {
+    let input = r#"
+        import "datetime" as datetime
+        let ts = datetime.parse_iso("2024-05-01T12:30:00.250+02:00")
+        if datetime.iso(ts) != "2024-05-01T10:30:00.250+00:00" { fail() }
+        if datetime.iso(ts, "+02:00") != "2024-05-01T12:30:00.250+02:00" { fail() }
+
+        let parts = datetime.components(ts, "+02:00")
+        if parts.get("hour") != 12 { fail() }
+        if parts.get("microsecond") != 250000 { fail() }
+        if parts.get("weekday") != 2 { fail() }
+        if parts.get("offset") != 7200 { fail() }
+        if datetime.utc_offset("utc") != 0 { fail() }
+        if datetime.utc_offset("-08:00") != -28800 { fail() }
+        if type(datetime.utc_offset("local")) != "Integer" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_datetime_errors() {
+    assert!(run_script("import \"datetime\" as d\nd.parse(\"May 1\", \"%Y-%m-%d\")").is_err());
+    assert!(run_script("import \"datetime\" as d\nd.format(0, \"%Y\", \"mars\")").is_err());
+    assert!(run_script("import \"datetime\" as d\nd.format(0, \"%Q\")").is_err());
+    for value in ["nan", "inf", "-inf"] {
+        let error = run_script(&format!("import \"datetime\" as d\nd.format(float(\"{}\"), \"%Y\")", value)).unwrap_err();
+        assert!(error.contains("not a finite number"), "{}", error);
+    }
+}
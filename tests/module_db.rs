@@ -0,0 +1,33 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_db_connect_sqlite_url_and_transactions() {
+    let input = r#"
+        import "db" as db
+        let conn = db.connect("sqlite::memory:")
+        conn.execute("CREATE TABLE t (n INTEGER)")
+        conn.begin()
+        conn.execute("INSERT INTO t VALUES (?)", [1])
+        conn.rollback()
+        if len(conn.query("SELECT * FROM t")) != 0 { fail() }
+        conn.begin()
+        conn.execute("INSERT INTO t VALUES (:n)", {"n": 2})
+        conn.commit()
+        for row in conn.query("SELECT n FROM t") {
+            if row.get("n") != 2 { fail() }
+        }
+        conn.close()
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_db_connect_errors() {
+    assert!(run_script("import \"db\" as db\ndb.connect(\"oracle://x\")").is_err());
+    assert!(run_script("import \"db\" as db\ndb.connect(42)").is_err());
+    // Nothing listens on port 1, so this fails either way, without the feature it fails early
+    assert!(run_script("import \"db\" as db\ndb.connect(\"postgres://u:p@127.0.0.1:1/x\")").is_err());
+    assert!(run_script("import \"db\" as db\ndb.connect(\"mysql://u:p@127.0.0.1:1/x\")").is_err());
+}
@@ -0,0 +1,37 @@
+use nikl::run_script;
+
+#[test]
+fn test_base64_round_trip() {
+    let input = r#"
+        import "encode" as encode
+        import "decode" as decode
+        if encode.base64("hello") != "aGVsbG8=" { fail() }
+        if decode.base64("aGVsbG8=") != "hello" { fail() }
+        if decode.base64_url(encode.base64_url("??>>")) != "??>>" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_hex_and_binary_bytes() {
+    let input = r#"
+        import "encode" as encode
+        import "decode" as decode
+        if encode.hex("AB") != "4142" { fail() }
+        if encode.hex([0, 255, 16]) != "00ff10" { fail() }
+        let raw = decode.hex_bytes("00ff10")
+        if raw.join(",") != "0,255,16" { fail() }
+        if decode.base64_bytes(encode.base64(raw)).join(",") != "0,255,16" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_decode_errors() {
+    assert!(run_script("import \"decode\" as decode\ndecode.hex(\"abc\")").is_err());
+    assert!(run_script("import \"decode\" as decode\ndecode.hex_bytes(\"+f\")").is_err());
+    assert!(run_script("import \"decode\" as decode\ndecode.hex_bytes(\"+f+f\")").is_err());
+    assert!(run_script("import \"decode\" as decode\ndecode.hex(\"ff\")").is_err());
+    assert!(run_script("import \"decode\" as decode\ndecode.base64(\"***\")").is_err());
+    assert!(run_script("import \"encode\" as encode\nencode.hex([256])").is_err());
+}
@@ -0,0 +1,43 @@
+use nikl::run_script;
+
+#[test]
+fn test_utf8_round_trip() {
+    let input = r#"
+        import "encoding" as encoding
+        let bytes = encoding.to_utf8_bytes("hé")
+        if bytes.join(",") != "104,195,169" { fail() }
+        if encoding.from_utf8(bytes) != "hé" { fail() }
+        if not encoding.is_utf8(bytes) { fail() }
+        if encoding.is_utf8([255]) { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_strict_and_lossy_decoding() {
+    let lossy = r#"
+        import "encoding" as encoding
+        if encoding.from_utf8([104, 255, 105], {"lossy": True}) != "h�i" { fail() }
+        if encoding.from_hex("68ff69", {"text": True, "lossy": True}) != "h�i" { fail() }
+    "#;
+    assert!(run_script(lossy).is_ok());
+
+    let strict = r#"
+        import "encoding" as encoding
+        encoding.from_utf8([104, 255, 105])
+    "#;
+    assert!(run_script(strict).is_err());
+}
+
+#[test]
+fn test_hex() {
+    let input = r#"
+        import "encoding" as encoding
+        if encoding.to_hex("hi") != "6869" { fail() }
+        if encoding.to_hex([0, 255]) != "00ff" { fail() }
+        if encoding.from_hex("00ff").join(",") != "0,255" { fail() }
+        if encoding.from_hex("6869", {"text": True}) != "hi" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("import \"encoding\" as e\ne.from_hex(\"abc\")").is_err());
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_lookup_and_interfaces() {
+    let input = r#"
+        import "network" as net
+        let addrs = net.lookup("localhost")
+        if len(addrs) == 0 { fail() }
+        if net.lookup("127.0.0.1").join(",") != "127.0.0.1" { fail() }
+
+        let loopback = False
+        for entry in net.interfaces() {
+            if entry.get("is_loopback") {
+                loopback = True
+            }
+        }
+        if not loopback { fail() }
+        let ip = net.local_ip()
+        if type(ip) != "None" and type(ip) != "String" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_port_open() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let closed = {
+        let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().port()
+    };
+    let input = format!(r#"
+        import "network" as net
+        if not net.port_open("127.0.0.1", {}) {{ fail() }}
+        if net.port_open("127.0.0.1", {}, 0.5) {{ fail() }}
+    "#, port, closed);
+    assert!(run_script(&input).is_ok());
+    assert!(run_script("import \"network\" as net\nnet.port_open(\"127.0.0.1\", 70000)").is_err());
+    assert!(run_script("import \"network\" as net\nnet.lookup(\"\")").is_err());
+    let err = run_script("import \"network\" as net\nnet.port_open(\"127.0.0.1\", 1, 100000000000000000000.0)").unwrap_err();
+    assert!(err.contains("port_open timeout is too large"), "{}", err);
+    let err = run_script("import \"network\" as net\nnet.ping(\"127.0.0.1\", 100000000000000000000.0)").unwrap_err();
+    assert!(err.contains("ping timeout is too large"), "{}", err);
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "native")]
+
 use nikl::run_script;
 
 #[test]
@@ -103,3 +105,218 @@ fn test_os_env_get_set() {
     "#;
     assert!(run_script(input).is_ok());
 }
+
+#[test]
+fn test_os_glob_and_fnmatch() {
+    let dir = std::env::temp_dir().join("nikl_test_os_glob");
+    let _ = std::fs::remove_dir_all(&dir);
+    let input = format!(r#"
+        import "os" as os
+        let root = "{}"
+        os.make_dir(root + "/src/nested")
+        os.write_file(root + "/src/main.nk", "")
+        os.write_file(root + "/src/nested/util.nk", "")
+        os.write_file(root + "/src/notes.txt", "")
+        let found = os.glob(root + "/src/**/*.nk")
+        if len(found) != 2 {{ fail() }}
+        if not found.join(",").contains("src/main.nk") {{ fail() }}
+        if len(os.glob(root + "/missing/*")) != 0 {{ fail() }}
+        if not os.fnmatch("*.nk", "main.nk") {{ fail() }}
+        if os.fnmatch("*.nk", "notes.txt") {{ fail() }}
+        if not os.fnmatch("file?.[ch]", "file1.c") {{ fail() }}
+        os.remove_dir(root)
+    "#, dir.display());
+    assert!(run_script(&input).is_ok());
+    assert!(run_script("import \"os\" as os\nos.glob(\"[\")").is_err());
+}
+
+#[test]
+fn test_os_walk() {
+    let dir = std::env::temp_dir().join("nikl_test_os_walk");
+    let _ = std::fs::remove_dir_all(&dir);
+    let input = format!(r#"
+        import "os" as os
+        let root = "{}"
+        os.make_dir(root + "/a/b")
+        os.write_file(root + "/top.txt", "")
+        os.write_file(root + "/a/b/deep.txt", "")
+        let seen = []
+        for dir, subdirs, files in os.walk(root) {{
+            seen.push(dir.replace(root, "") + ":" + subdirs.join(",") + ":" + files.join(","))
+        }}
+        if seen.join("|") != ":a:top.txt|/a:b:|/a/b::deep.txt" {{ fail() }}
+        if len(os.walk(root, {{"max_depth": 1}})) != 2 {{ fail() }}
+        os.remove_dir(root)
+    "#, dir.display());
+    assert!(run_script(&input).is_ok());
+    assert!(run_script("import \"os\" as os\nos.walk(\".\", {\"max_depth\": -1})").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_os_stat_and_chmod() {
+    let dir = std::env::temp_dir().join("nikl_test_os_stat");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::os::unix::fs::symlink(dir.join("data.txt"), dir.join("link")).unwrap();
+    let input = format!(r#"
+        import "os" as os
+        let root = "{}"
+        os.write_file(root + "/data.txt", "12345")
+        let info = os.stat(root + "/data.txt")
+        if info.get("size") != 5 {{ fail() }}
+        if not info.get("is_file") {{ fail() }}
+        if info.get("is_symlink") {{ fail() }}
+        if info.get("modified") <= 0 {{ fail() }}
+        if not os.stat(root + "/link").get("is_symlink") {{ fail() }}
+        os.chmod(root + "/data.txt", "640")
+        if os.stat(root + "/data.txt").get("mode") != "640" {{ fail() }}
+        os.chmod(root + "/data.txt", 420)
+        if os.stat(root + "/data.txt").get("mode") != "644" {{ fail() }}
+        os.remove_dir(root)
+    "#, dir.display());
+    assert!(run_script(&input).is_ok());
+    assert!(run_script("import \"os\" as os\nos.stat(\"/definitely/missing/nikl\")").is_err());
+    assert!(run_script("import \"os\" as os\nos.chmod(\".\", \"9\")").is_err());
+}
+
+#[test]
+fn test_os_copy_file_and_dir() {
+    let dir = std::env::temp_dir().join("nikl_test_os_copy");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src/inner")).unwrap();
+    std::fs::write(dir.join("src/inner/blob.bin"), [0u8, 159, 146, 150, 255]).unwrap();
+    let input = format!(r#"
+        import "os" as os
+        let root = "{}"
+        os.copy_file(root + "/src/inner/blob.bin", root + "/single.bin")
+        os.copy_dir(root + "/src", root + "/dst")
+        if not os.is_file(root + "/dst/inner/blob.bin") {{ fail() }}
+        os.copy_dir(root + "/src", root + "/dst", True)
+    "#, dir.display());
+    assert!(run_script(&input).is_ok());
+    assert_eq!(std::fs::read(dir.join("single.bin")).unwrap(), [0u8, 159, 146, 150, 255]);
+    assert_eq!(std::fs::read(dir.join("dst/inner/blob.bin")).unwrap(), [0u8, 159, 146, 150, 255]);
+
+    let again = format!("import \"os\" as os\nos.copy_dir(\"{0}/src\", \"{0}/dst\")", dir.display());
+    assert!(run_script(&again).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_os_copy_dir_into_itself() {
+    let dir = std::env::temp_dir().join("nikl_test_os_copy_into_itself");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/file.txt"), "x").unwrap();
+    for dst in ["src/copy", "src/./copy/deeper", "src", "missing/../src/x"] {
+        let input = format!("import \"os\" as os\nos.copy_dir(\"{0}/src\", \"{0}/{1}\")", dir.display(), dst);
+        let err = run_script(&input).unwrap_err();
+        assert!(err.contains("into itself"), "{}: {}", dst, err);
+    }
+    assert!(!dir.join("src/copy").exists());
+    assert!(!dir.join("src/x").exists());
+
+    // `..` after a symlink leaves the directory it points to, not the link's
+    #[cfg(unix)]
+    {
+        std::fs::create_dir_all(dir.join("src/inner")).unwrap();
+        std::os::unix::fs::symlink(dir.join("src/inner"), dir.join("link")).unwrap();
+        let input = format!("import \"os\" as os\nos.copy_dir(\"{0}/src\", \"{0}/link/../x\")", dir.display());
+        assert!(run_script(&input).unwrap_err().contains("into itself"));
+    }
+
+    // A sibling sharing the name prefix isn't inside the source
+    let sibling = format!("import \"os\" as os\nos.copy_dir(\"{0}/src\", \"{0}/src2\")", dir.display());
+    assert!(run_script(&sibling).is_ok());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_os_load_env() {
+    let path = std::env::temp_dir().join("nikl_test_os_load_env.env");
+    std::fs::write(&path, concat!(
+        "# settings\n",
+        "NIKL_DOTENV_PLAIN=value # trailing comment\n",
+        "export NIKL_DOTENV_SINGLE='keep $raw #text'\n",
+        "NIKL_DOTENV_DOUBLE=\"a\\tb\"\n",
+        "\n",
+        "NIKL_DOTENV_EXISTING=from-file\n",
+    )).unwrap();
+    let input = format!(r#"
+        import "os" as os
+        let file = "{}"
+        let preview = os.load_env(file, {{"apply": False}})
+        if preview.get("NIKL_DOTENV_PLAIN") != "value" {{ fail() }}
+        if preview.get("NIKL_DOTENV_SINGLE") != "keep $raw #text" {{ fail() }}
+        if len(preview.get("NIKL_DOTENV_DOUBLE")) != 3 {{ fail() }}
+        if type(os.env_get("NIKL_DOTENV_PLAIN")) != "None" {{ fail() }}
+
+        os.env_set("NIKL_DOTENV_EXISTING", "from-env")
+        os.load_env(file)
+        if os.env_get("NIKL_DOTENV_PLAIN") != "value" {{ fail() }}
+        if os.env_get("NIKL_DOTENV_EXISTING") != "from-env" {{ fail() }}
+        os.load_env(file, {{"override": True}})
+        if os.env_get("NIKL_DOTENV_EXISTING") != "from-file" {{ fail() }}
+    "#, path.display());
+    let result = run_script(&input);
+    std::fs::write(&path, "NOT VALID LINE\n").unwrap();
+    let invalid = run_script(&format!("import \"os\" as os\nos.load_env(\"{}\")", path.display()));
+    std::fs::write(&path, "NIKL_DOTENV_NUL=b\0c\n").unwrap();
+    let nul = run_script(&format!("import \"os\" as os\nos.load_env(\"{}\")", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_ok());
+    assert!(invalid.is_err());
+    assert!(nul.unwrap_err().contains("contains a NUL byte"));
+}
+
+#[test]
+fn test_os_open_file_handles() {
+    let dir = std::env::temp_dir().join("nikl_test_os_open");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = format!(r#"
+        import "os" as os
+        let path = "{}/log.txt"
+        let out = os.open(path, "w")
+        if out.write("first") != 5 {{ fail() }}
+        out.write("
+second
+")
+        if out.closed or out.binary {{ fail() }}
+        out.close()
+        if not out.is_closed() {{ fail() }}
+        if not out.closed {{ fail() }}
+
+        let log = os.open(path, "a")
+        log.write("thé end")
+        log.close()
+
+        let lines = []
+        for line in os.open(path) {{
+            lines.push(line)
+        }}
+        if lines.join("|") != "first|second|thé end" {{ fail() }}
+
+        let f = os.open(path, "r+")
+        if f.read_line() != "first" {{ fail() }}
+        if f.tell() != 6 {{ fail() }}
+        f.seek(-8, "end")
+        if f.read(3) != "thé" {{ fail() }}
+        f.seek(0)
+        f.write("FIRST")
+        f.seek(0)
+        if f.read(5) != "FIRST" {{ fail() }}
+        f.read_lines()
+        if type(f.read_line()) != "None" {{ fail() }}
+        f.close()
+
+        let bin = os.open(path, "rb")
+        if bin.read(2).join(",") != "70,73" {{ fail() }}
+    "#, dir.display());
+    assert!(run_script(&input).is_ok());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(run_script("import \"os\" as os\nos.open(\"x.txt\", \"rw\")").is_err());
+    assert!(run_script("import \"os\" as os\nos.open(\"/no/such/dir/x.txt\")").is_err());
+}
@@ -0,0 +1,43 @@
+use nikl::run_script;
+
+#[cfg(unix)]
+#[test]
+fn test_path_components() {
+    let input = r#"
+        import "path" as path
+        if path.join("a", "b", "c.nk") != "a/b/c.nk" { fail() }
+        if path.join("a", "/etc") != "/etc" { fail() }
+        if path.basename("/tmp/dir/file.tar.gz") != "file.tar.gz" { fail() }
+        if path.dirname("/tmp/dir/file.tar.gz") != "/tmp/dir" { fail() }
+        if path.dirname("file") != "" { fail() }
+        if path.extension("file.tar.gz") != "gz" { fail() }
+        if path.extension("Makefile") != "" { fail() }
+        for dir, base in [path.split("/tmp/x.nk")] {
+            if dir != "/tmp" { fail() }
+            if base != "x.nk" { fail() }
+        }
+        if path.sep != "/" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[cfg(all(unix, feature = "native"))]
+#[test]
+fn test_path_normalize_and_absolute() {
+    let input = r#"
+        import "path" as path
+        import "os" as os
+        if path.normalize("a/./b/../c") != "a/c" { fail() }
+        if path.normalize("../a/..") != ".." { fail() }
+        if path.normalize("/../x") != "/x" { fail() }
+        if path.normalize("a/..") != "." { fail() }
+        if path.absolute("sub/../f") != path.join(os.get_cwd(), "f") { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_path_errors() {
+    assert!(run_script("import \"path\" as path\npath.basename(1)").is_err());
+    assert!(run_script("import \"path\" as path\npath.join()").is_err());
+}
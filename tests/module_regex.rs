@@ -39,3 +39,73 @@ fn test_regex_replace() {
     "#;
     assert!(run_script(input).is_ok());
 }
+
+#[test]
+fn test_regex_captures_named_groups() {
+    let input = r#"
+        import "regex" as regex
+        let m = regex.captures("(?P<year>\d{4})-(?P<month>\d{2})(-(\d{2}))?", "date: 2024-05 ok")
+        if m.get("text") != "2024-05" { fail() }
+        if m.get("start") != 6 { fail() }
+        if m.get("end") != 13 { fail() }
+        let groups = m.get("groups")
+        if groups.get("year") != "2024" { fail() }
+        if groups.get(2) != "05" { fail() }
+        if groups.get(0) != "2024-05" { fail() }
+        if type(groups.get(4)) != "None" { fail() }
+        if type(regex.captures("x", "abc")) != "None" { fail() }
+        if regex.captures("b", "äb").get("start") != 1 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_regex_compile_and_split() {
+    let input = r#"
+        import "regex" as regex
+        let re = regex.compile("\d+")
+        if type(re) != "Regex" { fail() }
+        if re.pattern() != "\d+" { fail() }
+        if re.pattern != "\d+" { fail() }
+        if not re.is_match("a1") { fail() }
+        if re.find_all("a1b22c333").join(",") != "1,22,333" { fail() }
+        if re.replace("N", "a1b22") != "aNbN" { fail() }
+        if re.split("a1b22c").join("|") != "a|b|c" { fail() }
+        if regex.split("\s*,\s*", "x , y,z").join("|") != "x|y|z" { fail() }
+        let words = regex.compile("(?P<w>[a-z]+)")
+        for line in ["one", "two"] {
+            if words.captures(line).get("groups").get("w") != line { fail() }
+        }
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("import \"regex\" as regex\nregex.compile(\"(\")").is_err());
+    assert!(run_script("import \"regex\" as regex\nregex.compile(\"a\").replace(\"x\")").is_err());
+}
+
+#[test]
+fn test_regex_replace_fn() {
+    let input = r#"
+        import "regex" as regex
+        fn double(m) {
+            return str(int(m.get("text")) * 2)
+        }
+        if regex.replace_fn("\d+", "a1 b20 c", double) != "a2 b40 c" { fail() }
+
+        fn swap(m) {
+            let g = m.get("groups")
+            return g.get("last") + " " + g.get("first")
+        }
+        if regex.replace_fn("(?P<first>\w+) (?P<last>\w+)", "ada lovelace", swap) != "lovelace ada" { fail() }
+        if regex.replace_fn("x", "abc", double) != "abc" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+
+    let bad_return = r#"
+        import "regex" as regex
+        fn nothing(m) {
+            return 1
+        }
+        regex.replace_fn("a", "a", nothing)
+    "#;
+    assert!(run_script(bad_return).is_err());
+}
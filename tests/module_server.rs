@@ -0,0 +1,161 @@
+#![cfg(feature = "native")]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use nikl::run_script;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// Sends a raw request once the server is up and returns the raw response
+fn send(port: u16, request: &str) -> String {
+    for _ in 0..100 {
+        if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) {
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            return response;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("server on port {} never came up", port);
+}
+
+#[test]
+fn test_server_handles_requests() {
+    let port = free_port();
+    let client = thread::spawn(move || {
+        let first = send(port, "GET /health?verbose=1 HTTP/1.1\r\nHost: x\r\n\r\n");
+        let second = send(port, "POST /echo HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello");
+        let third = send(port, "GET /missing HTTP/1.1\r\n\r\n");
+        (first, second, third)
+    });
+
+    let script = format!(r#"
+        import "server" as server
+        fn handle(req) {{
+            if req.get("path") == "/health" {{
+                return {{"body": {{"ok": True, "query": req.get("query")}}}}
+            }}
+            if req.get("method") == "POST" {{
+                return req.get("body").upper()
+            }}
+            return {{"status": 404, "body": "nope", "headers": {{"X-Test": "1"}}}}
+        }}
+        server.listen({}, handle, {{"max_requests": 3}})
+    "#, port);
+    assert!(run_script(&script).is_ok());
+
+    let (first, second, third) = client.join().unwrap();
+    assert!(first.starts_with("HTTP/1.1 200 OK"));
+    assert!(first.contains("application/json"));
+    assert!(first.ends_with(r#"{"ok":true,"query":"verbose=1"}"#));
+    assert!(second.ends_with("\r\n\r\nHELLO"));
+    assert!(third.starts_with("HTTP/1.1 404 Not Found"));
+    assert!(third.contains("X-Test: 1"));
+}
+
+#[test]
+fn test_server_rejects_bad_arguments() {
+    assert!(run_script("import \"server\" as server\nserver.listen(\"80\", print)").is_err());
+    assert!(run_script("import \"server\" as server\nserver.listen(8080, 1)").is_err());
+}
+
+#[test]
+fn test_server_rejects_large_bodies() {
+    let port = free_port();
+    let client = thread::spawn(move || {
+        let large = send(port, "POST /upload HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n");
+        let small = send(port, "POST /upload HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc");
+        (large, small)
+    });
+
+    let script = format!(r#"
+        import "server" as server
+        fn handle(req) {{
+            return req.get("body")
+        }}
+        server.listen({}, handle, {{"max_requests": 2, "max_body": 16}})
+    "#, port);
+    assert!(run_script(&script).is_ok());
+
+    let (large, small) = client.join().unwrap();
+    assert!(large.starts_with("HTTP/1.1 413 Payload Too Large"));
+    assert!(small.ends_with("\r\n\r\nabc"));
+}
+
+#[test]
+fn test_server_stops_when_handler_exits() {
+    let port = free_port();
+    let client = thread::spawn(move || send(port, "GET /stop HTTP/1.1\r\n\r\n"));
+
+    let script = format!(r#"
+        import "server" as server
+        fn handle(req) {{
+            exit(4)
+        }}
+        server.listen({}, handle)
+    "#, port);
+    assert_eq!(run_script(&script).unwrap_err(), "exit(4)");
+    client.join().unwrap();
+}
+
+#[test]
+fn test_server_limits_request_headers() {
+    let port = free_port();
+    let client = thread::spawn(move || {
+        let long = send(port, &format!("GET /{}", "a".repeat(8187)));
+        let many: String = (0..101).map(|i| format!("X-{}: 1\r\n", i)).collect();
+        let many = send(port, &format!("GET / HTTP/1.1\r\n{}\r\n", many));
+        let fine = send(port, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        (long, many, fine)
+    });
+
+    let script = format!(r#"
+        import "server" as server
+        fn handle(req) {{
+            return "ok"
+        }}
+        server.listen({}, handle, {{"max_requests": 3}})
+    "#, port);
+    assert!(run_script(&script).is_ok());
+
+    let (long, many, fine) = client.join().unwrap();
+    assert!(long.starts_with("HTTP/1.1 431 Request Header Fields Too Large"), "{}", long);
+    assert!(many.starts_with("HTTP/1.1 431 Request Header Fields Too Large"), "{}", many);
+    assert!(fine.ends_with("\r\n\r\nok"));
+}
+
+#[test]
+fn test_server_checks_response_headers() {
+    let port = free_port();
+    let client = thread::spawn(move || {
+        let injected = send(port, "GET /inject HTTP/1.1\r\n\r\n");
+        let length = send(port, "GET /length HTTP/1.1\r\n\r\n");
+        (injected, length)
+    });
+
+    let script = format!(r#"
+        import "server" as server
+        fn handle(req) {{
+            if req.get("path") == "/inject" {{
+                return {{"body": "x", "headers": {{"X-Name": "a" + chr(13) + chr(10) + "Set-Cookie: admin=1"}}}}
+            }}
+            return {{"body": "abc", "headers": {{"Content-Length": "999", "connection": "keep-alive"}}}}
+        }}
+        server.listen({}, handle, {{"max_requests": 2}})
+    "#, port);
+    assert!(run_script(&script).is_ok());
+
+    let (injected, length) = client.join().unwrap();
+    assert!(injected.starts_with("HTTP/1.1 500 Internal Server Error"), "{}", injected);
+    assert!(!injected.contains("Set-Cookie"));
+    assert_eq!(length.matches("Content-Length").count(), 1, "{}", length);
+    assert!(length.contains("Content-Length: 3\r\n"));
+    assert!(!length.contains("keep-alive"));
+    assert!(length.ends_with("\r\n\r\nabc"));
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_sqlite_execute_and_query() {
+    let input = r#"
+        import "sqlite" as sqlite
+        let db = sqlite.open(":memory:")
+        if type(db) != "SqliteConnection" { fail() }
+        db.execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL, active INTEGER)")
+        db.execute("INSERT INTO users (name, score, active) VALUES (?, ?, ?)", ["ada", 9.5, True])
+        if db.last_insert_id() != 1 { fail() }
+        db.execute("INSERT INTO users (name, score, active) VALUES (:name, :score, :active)", {"name": "bob", "score": 7, "active": False})
+        if db.execute("UPDATE users SET score = score + 1") != 2 { fail() }
+
+        let rows = db.query("SELECT id, name, score FROM users WHERE active = ? ORDER BY id", [1])
+        if len(rows) != 1 { fail() }
+        for row in rows {
+            if row.get("name") != "ada" { fail() }
+            if row.get("score") != 10.5 { fail() }
+        }
+        let names = []
+        for row in db.query("SELECT name FROM users ORDER BY name") {
+            names.push(row.get("name"))
+        }
+        if names.join(",") != "ada,bob" { fail() }
+        let nulls = db.query("SELECT NULL AS empty, x'00ff' AS blob")
+        for row in nulls {
+            if type(row.get("empty")) != "None" { fail() }
+            if row.get("blob").join(",") != "0,255" { fail() }
+        }
+        db.close()
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_sqlite_file_persistence_and_errors() {
+    let path = std::env::temp_dir().join("nikl_test_sqlite.db");
+    let _ = std::fs::remove_file(&path);
+    let write = format!(r#"
+        import "sqlite" as sqlite
+        let db = sqlite.open("{}")
+        db.execute("CREATE TABLE kv (k TEXT, v TEXT)")
+        db.execute("INSERT INTO kv VALUES (?, ?)", ["a", "1"])
+    "#, path.display());
+    assert!(run_script(&write).is_ok());
+    let read = format!(r#"
+        import "sqlite" as sqlite
+        let db = sqlite.open("{}")
+        if len(db.query("SELECT * FROM kv")) != 1 {{ fail() }}
+    "#, path.display());
+    assert!(run_script(&read).is_ok());
+    std::fs::remove_file(&path).unwrap();
+
+    let base = "import \"sqlite\" as sqlite\nlet db = sqlite.open(\":memory:\")\n";
+    assert!(run_script(&format!("{}db.query(\"SELECT * FROM missing\")", base)).is_err());
+    assert!(run_script(&format!("{}db.execute(\"SELECT ?\", [[1]])", base)).is_err());
+    assert!(run_script(&format!("{}db.close()\ndb.query(\"SELECT 1\")", base)).is_err());
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[cfg(unix)]
+#[test]
+fn test_subprocess_run_and_shell() {
+    let input = r#"
+        import "subprocess" as proc
+        let result = proc.run("echo", ["hello", "world"])
+        if result.get("code") != 0 { fail() }
+        if result.get("stdout").trim() != "hello world" { fail() }
+
+        let failed = proc.shell("echo oops >&2; exit 3")
+        if failed.get("code") != 3 { fail() }
+        if failed.get("stderr").trim() != "oops" { fail() }
+
+        let piped = proc.run("cat", [], {"stdin": "from stdin"})
+        if piped.get("stdout") != "from stdin" { fail() }
+
+        let opts = {"cwd": "/", "env": {"NIKL_TEST_VAR": "42"}}
+        if proc.shell("pwd; echo $NIKL_TEST_VAR", opts).get("stdout").split().join(",") != "/,42" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_subprocess_stream_lines() {
+    let input = r#"
+        import "subprocess" as proc
+        fn check(line) {
+            if len(line) != 1 { fail() }
+        }
+        let code = proc.stream("printf", ["a\nb\nc\n"], check)
+        if code != 0 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+
+    let stop_on_b = r#"
+        import "subprocess" as proc
+        fn check(line) {
+            if line == "b" { fail() }
+        }
+        proc.stream("printf", ["a\nb\nc\n"], check)
+    "#;
+    assert!(run_script(stop_on_b).is_err());
+}
+
+#[test]
+fn test_subprocess_errors() {
+    assert!(run_script("import \"subprocess\" as proc\nproc.run(\"definitely-not-a-real-program-nikl\", [])").is_err());
+    assert!(run_script("import \"subprocess\" as proc\nproc.run(\"echo\", [1])").is_err());
+    assert!(run_script("import \"subprocess\" as proc\nproc.shell(\"echo\", {\"bogus\": 1})").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_subprocess_large_stdin() {
+    // More than a pipe holds both ways, the child writes output before it has read all its input
+    let input = r#"
+        import "subprocess" as proc
+        let text = "0123456789abcdef"
+        let i = 0
+        while i < 16 {
+            text = text + text
+            i = i + 1
+        }
+        let piped = proc.run("cat", [], {"stdin": text})
+        len(piped.get("stdout"))
+    "#;
+    assert_eq!(nikl::eval_script(input), Ok(nikl::Value::Integer(16 * 65536)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_subprocess_stream_stderr_goes_to_script_stderr() {
+    use nikl::{CaptureBuffer, InterpreterBuilder};
+
+    let err = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from(".")).stderr(err.clone()).build();
+    let source = r#"
+        import "subprocess" as proc
+        fn ignore(line) { }
+        proc.stream("sh", ["-c", "echo out; echo problem >&2"], ignore)
+    "#;
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    assert_eq!(interp.run_program(&stmts), Ok(nikl::Value::Integer(0)));
+    assert_eq!(err.contents(), "problem\n");
+}
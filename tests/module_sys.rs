@@ -0,0 +1,58 @@
+#![cfg(feature = "native")]
+
+use std::path::PathBuf;
+
+use nikl::lexer::Lexer;
+use nikl::parser::Parser;
+use nikl::{run_script, Interpreter};
+
+
+#[test]
+fn test_sys_platform_info() {
+    let input = format!(r#"
+        import "sys" as sys
+        if sys.platform != "{}" {{ fail() }}
+        if sys.arch != "{}" {{ fail() }}
+        if sys.pid != {} {{ fail() }}
+        if sys.nikl_version != "{}" {{ fail() }}
+        if type(sys.hostname) != "String" {{ fail() }}
+        if type(sys.exe_path) != "String" {{ fail() }}
+    "#, std::env::consts::OS, std::env::consts::ARCH, std::process::id(), env!("CARGO_PKG_VERSION"));
+    assert!(run_script(&input).is_ok());
+}
+
+#[test]
+fn test_sys_argv() {
+    let source = r#"
+        import "sys" as sys
+        if len(sys.argv) != 3 { fail() }
+        if sys.argv.join(" ") != "script.nk --name nikl" { fail() }
+    "#;
+    let stmts = Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    let mut interpreter = Interpreter::new(PathBuf::from("."));
+    interpreter.set_argv(vec!["script.nk".to_string(), "--name".to_string(), "nikl".to_string()]);
+    assert!(interpreter.run(&stmts).is_ok());
+
+    assert!(run_script("import \"sys\" as sys\nif len(sys.argv) != 0 { fail() }").is_ok());
+}
+
+#[test]
+fn test_script_receives_cli_arguments() {
+    let script = std::env::temp_dir().join("nikl_test_sys_argv.nk");
+    std::fs::write(&script, "import \"sys\" as sys\nprint(sys.argv.slice(1).join(\",\"))\n").unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_nikl"))
+        .arg(&script)
+        .args(["a", "b c"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a,b c\n");
+
+    // Only the first `--` separates the script's arguments
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_nikl"))
+        .arg(&script)
+        .args(["--", "--verbose", "--"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&script).unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "--verbose,--\n");
+}
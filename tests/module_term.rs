@@ -0,0 +1,72 @@
+use nikl::run_script;
+
+#[test]
+fn test_styles_follow_color_mode() {
+    let input = r#"
+        import "term" as term
+        term.set_colors(True)
+        let red = term.color("hi", "red")
+        if red == "hi" { fail() }
+        if not red.contains("[31m") { fail() }
+        if not term.color("hi", "bright_white", "blue").contains("[97;44m") { fail() }
+        if term.strip(red) != "hi" { fail() }
+        if term.strip(term.bold(term.underline("x"))) != "x" { fail() }
+
+        term.set_colors(False)
+        if term.color("hi", "red") != "hi" { fail() }
+        if term.bold("hi") != "hi" { fail() }
+        if term.colors_enabled() { fail() }
+        term.set_colors("auto")
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_tty_and_cursor_functions() {
+    let input = r#"
+        import "term" as term
+        if type(term.is_tty()) != "Boolean" { fail() }
+        if type(term.is_tty("stderr")) != "Boolean" { fail() }
+        term.clear_line()
+        term.move_up(2)
+        term.move_to(1, 1)
+    "#;
+    assert!(run_script(input).is_ok());
+    assert!(run_script("import \"term\" as term\nterm.color(\"x\", \"mauve\")").is_err());
+    assert!(run_script("import \"term\" as term\nterm.is_tty(\"printer\")").is_err());
+    assert!(run_script("import \"term\" as term\nterm.move_to(0, 1)").is_err());
+    assert!(run_script("import \"term\" as term\nterm.hide_cursor(1)").is_err());
+    assert!(run_script("import \"term\" as term\nterm.show_cursor(True)").is_err());
+}
+
+#[test]
+fn test_captured_output_is_not_a_terminal() {
+    use nikl::{CaptureBuffer, InterpreterBuilder};
+
+    let out = CaptureBuffer::new();
+    let mut interp = InterpreterBuilder::new(std::path::PathBuf::from("."))
+        .stdout(out.clone())
+        .stderr(CaptureBuffer::new())
+        .build();
+    let source = r#"
+        import "term" as term
+        term.clear_screen()
+        term.move_to(1, 1)
+        term.hide_cursor()
+        [term.is_tty(), term.is_tty("stderr")]
+    "#;
+    let stmts = nikl::parser::Parser::new(nikl::lexer::Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    assert_eq!(interp.run_program(&stmts).unwrap().to_string(), "[False, False]");
+    assert_eq!(out.contents(), "");
+}
+
+#[test]
+fn test_color_mode_is_per_interpreter() {
+    let parse = |source: &str| nikl::parser::Parser::new(nikl::lexer::Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    let mut forced = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    let mut other = nikl::Interpreter::new(std::path::PathBuf::from("."));
+    forced.run_program(&parse("import \"term\" as term\nterm.set_colors(False)")).unwrap();
+    other.run_program(&parse("import \"term\" as term\nterm.set_colors(True)")).unwrap();
+    assert_eq!(forced.run_program(&parse("term.colors_enabled()")), Ok(nikl::Value::Bool(false)));
+    assert_eq!(other.run_program(&parse("term.colors_enabled()")), Ok(nikl::Value::Bool(true)));
+}
@@ -0,0 +1,47 @@
+use nikl::run_script;
+
+#[test]
+fn test_assertions() {
+    let input = r#"
+        import "testing" as t
+        t.assert(1 < 2)
+        t.assert_eq([1, 2], [1, 2])
+        t.assert_ne("a", "b")
+        fn broken() {
+            return missing_variable
+        }
+        let message = t.assert_raises(broken, "missing_variable")
+        if not message.contains("Undefined variable") { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+
+    assert!(run_script("import \"testing\" as t\nt.assert_eq(1, 2, \"numbers differ\")").is_err());
+    assert!(run_script("import \"testing\" as t\nt.assert_ne(1, 1)").is_err());
+    assert!(run_script("import \"testing\" as t\nt.assert(1 > 2)").is_err());
+    assert!(run_script("import \"testing\" as t\nt.assert_raises(print)").is_err());
+}
+
+#[test]
+fn test_registered_tests_report_results() {
+    let input = r#"
+        import "testing" as t
+        fn passes() {
+            t.assert_eq(1 + 1, 2)
+        }
+        fn fails() {
+            t.assert_eq("left", "right")
+        }
+        t.test("adds", passes)
+        t.test("compares strings", fails)
+
+        let summary = t.run()
+        if summary.get("passed") != 1 { fail() }
+        if summary.get("failed") != 1 { fail() }
+        for failure in summary.get("failures") {
+            if failure.get("name") != "compares strings" { fail() }
+            if not failure.get("error").contains("assert_eq failed") { fail() }
+        }
+        if t.run().get("passed") != 0 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_spawn_and_join() {
+    let input = r#"
+        import "thread" as thread
+        fn square(n) {
+            return n * n
+        }
+        let handles = []
+        for n in [1, 2, 3, 4] {
+            handles.push(thread.spawn(square, [n]))
+        }
+        let results = []
+        for handle in handles {
+            results.push(handle.join())
+        }
+        if results.join(",") != "1,4,9,16" { fail() }
+
+        let single = thread.spawn(square, [5])
+        if single.join() != 25 { fail() }
+        if single.join() != 25 { fail() }
+        if not single.is_finished() { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_arguments_are_copied() {
+    let input = r#"
+        import "thread" as thread
+        fn grow(items) {
+            items.push(4)
+            return len(items)
+        }
+        let items = [1, 2, 3]
+        if thread.spawn(grow, [items]).join() != 4 { fail() }
+        if len(items) != 3 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_thread_errors_surface_on_join() {
+    let input = r#"
+        import "thread" as thread
+        fn broken() {
+            return missing_variable
+        }
+        thread.spawn(broken).join()
+    "#;
+    assert!(run_script(input).is_err());
+    assert!(run_script("import \"thread\" as thread\nthread.spawn(1)").is_err());
+}
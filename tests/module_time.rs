@@ -0,0 +1,37 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_time_now_and_monotonic() {
+    let input = r#"
+        import "time" as time
+        if time.now() < 1600000000 { fail() }
+        let a = time.monotonic()
+        time.sleep(0.01)
+        if time.monotonic() - a < 0.01 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_time_measure() {
+    let input = r#"
+        import "time" as time
+        fn work() {
+            time.sleep(0.02)
+        }
+        let elapsed = time.measure(work)
+        if elapsed < 0.02 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_time_measure_requires_function() {
+    let input = r#"
+        import "time" as time
+        time.measure(5)
+    "#;
+    assert!(run_script(input).is_err());
+}
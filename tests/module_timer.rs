@@ -0,0 +1,60 @@
+#![cfg(feature = "native")]
+
+use nikl::run_script;
+
+#[test]
+fn test_after_and_cancel() {
+    let input = r#"
+        import "timer" as timer
+        fn answer() {
+            return 42
+        }
+        let handle = timer.after(0.05, answer)
+        if handle.wait() != 42 { fail() }
+        if handle.is_active() { fail() }
+
+        let cancelled = timer.after(10, answer)
+        if not cancelled.is_active() { fail() }
+        cancelled.cancel()
+        if type(cancelled.wait()) != "None" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_every_until_false() {
+    let path = std::env::temp_dir().join("nikl_test_timer_every.txt");
+    let _ = std::fs::remove_file(&path);
+    // Timer callbacks run on their own interpreter, so they count through a file
+    let input = format!(r#"
+        import "os" as os
+        import "timer" as timer
+        let path = "{}"
+        os.write_file(path, "")
+        fn tick() {{
+            let seen = os.read_file(path) + "x"
+            os.write_file(path, seen)
+            return len(seen) < 3
+        }}
+        timer.every(0.02, tick).wait()
+        if os.read_file(path) != "xxx" {{ fail() }}
+        os.remove_file(path)
+    "#, path.display());
+    assert!(run_script(&input).is_ok());
+}
+
+#[test]
+fn test_timer_errors() {
+    let input = r#"
+        import "timer" as timer
+        fn broken() {
+            return missing_variable
+        }
+        timer.every(0.01, broken).wait()
+    "#;
+    assert!(run_script(input).is_err());
+    assert!(run_script("import \"timer\" as timer\ntimer.every(0, print)").is_err());
+    assert!(run_script("import \"timer\" as timer\ntimer.after(1)").is_err());
+    let err = run_script("import \"timer\" as timer\ntimer.after(100000000000000000000.0, print)").unwrap_err();
+    assert!(err.contains("after delay is too large"), "{}", err);
+}
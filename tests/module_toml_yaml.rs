@@ -0,0 +1,83 @@
+use nikl::run_script;
+
+#[test]
+fn test_toml_parse_and_dump() {
+    let input = r#"
+        import "toml" as toml
+        let config = toml.parse("
+title = 'demo'
+ports = [80, 443]
+[owner]
+name = 'neko'
+")
+        if config.title != "demo" { fail() }
+        if config.ports.join(",") != "80,443" { fail() }
+        if config.owner.name != "neko" { fail() }
+
+        let back = toml.parse(toml.dump(config))
+        if back.owner.name != "neko" { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_toml_keeps_special_floats() {
+    let input = r#"
+        import "toml" as toml
+        toml.parse("
+a = nan
+b = inf
+c = -inf
+d = 1.5
+")
+    "#;
+    let Ok(nikl::Value::HashMap(pairs)) = nikl::eval_script(input) else { panic!("expected a table") };
+    let floats: Vec<f64> = pairs.iter().map(|(_, v)| match v { nikl::Value::Float(f) => *f, other => panic!("{:?}", other) }).collect();
+    assert!(floats[0].is_nan());
+    assert_eq!(&floats[1..], [f64::INFINITY, f64::NEG_INFINITY, 1.5]);
+}
+
+#[test]
+fn test_toml_special_floats_round_trip() {
+    let input = r#"
+        import "toml" as toml
+        toml.dump(toml.parse("
+a = nan
+b = inf
+c = -inf
+"))
+    "#;
+    let dumped = nikl::eval_script(input).unwrap().to_string();
+    assert_eq!(dumped, "a = nan\nb = inf\nc = -inf\n");
+}
+
+#[test]
+fn test_toml_errors() {
+    assert!(run_script("import \"toml\" as toml\ntoml.parse(\"= broken\")").is_err());
+    assert!(run_script("import \"toml\" as toml\ntoml.dump([1, 2])").is_err());
+}
+
+#[test]
+fn test_yaml_parse_and_dump() {
+    let input = r#"
+        import "yaml" as yaml
+        let data = yaml.parse("
+name: nikl
+version: 1.5
+tags: [fast, small]
+")
+        if data.name != "nikl" { fail() }
+        if data.version != 1.5 { fail() }
+        if data.tags.join(" ") != "fast small" { fail() }
+
+        let back = yaml.parse(yaml.dump({"list": [1, 2], "nested": {"ok": True}}))
+        if not back.nested.ok { fail() }
+        if len(back.list) != 2 { fail() }
+    "#;
+    assert!(run_script(input).is_ok());
+}
+
+#[test]
+fn test_yaml_dump_rejects_functions() {
+    assert!(run_script("import \"yaml\" as yaml\nyaml.dump({\"f\": print})").is_err());
+}
@@ -219,3 +219,37 @@ fn test_single_print_statement() {
     assert_eq!(ast.len(), 1);
     assert!(matches!(ast[0], Stmt::Expr(Expr::Call { .. })));
 }
+
+#[test]
+fn test_built_ast_matches_parsed_source() {
+    use nikl::TokenKind;
+
+    let source = r#"
+        import "regex" as re
+        fn label(items) {
+            for i, item in enumerate(items) {
+                if item.count > 1 {
+                    print(item.name)
+                } else {
+                    print(not True, -i)
+                }
+            }
+        }
+    "#;
+    let built = vec![
+        Stmt::import("regex", "re"),
+        Stmt::function("label", ["items"], vec![Stmt::for_each(
+            ["i", "item"],
+            Expr::call(Expr::ident("enumerate"), vec![Expr::ident("items")]),
+            vec![Stmt::if_then(
+                Expr::binary(Expr::dot(Expr::ident("item"), "count"), TokenKind::GreaterThan, Expr::int(1)),
+                vec![Stmt::expr(Expr::call(Expr::ident("print"), vec![Expr::dot(Expr::ident("item"), "name")]))],
+            )
+            .otherwise(vec![Stmt::expr(Expr::call(
+                Expr::ident("print"),
+                vec![!Expr::bool(true), -Expr::ident("i")],
+            ))])],
+        )]),
+    ];
+    assert_eq!(parse_input(source).unwrap(), built);
+}